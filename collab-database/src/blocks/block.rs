@@ -1,7 +1,7 @@
 use crate::error::DatabaseError;
 use crate::rows::{
-  Cell, CreateRowParams, DatabaseRow, Row, RowChangeSender, RowDetail, RowId, RowMeta, RowMetaKey,
-  RowMetaUpdate, RowUpdate, meta_id_from_row_id,
+  Cell, Cells, CreateRowParams, DatabaseRow, Row, RowChangeSender, RowDetail, RowHistoryRetention,
+  RowId, RowMeta, RowMetaKey, RowMetaUpdate, RowUpdate, meta_id_from_row_id,
 };
 use crate::views::RowOrder;
 
@@ -76,16 +76,40 @@ impl Block {
     Ok(())
   }
 
-  pub async fn create_rows<T>(&self, rows: Vec<T>, client_id: ClientID) -> Vec<RowOrder>
+  /// Creates the collabs of the rows in a batch, see
+  /// [DatabaseRowCollabService::batch_create_arc_database_row]. Returns the [RowOrder]s of the
+  /// created rows in the order of the given rows, the rows that fail to be created are skipped.
+  pub async fn create_rows<T>(&self, rows: Vec<T>) -> Vec<RowOrder>
   where
     T: Into<CreateRowParams> + Send,
   {
-    let mut row_orders = Vec::with_capacity(rows.len());
-    for row in rows {
-      if let Ok(row_order) = self.create_new_row(row, client_id).await {
-        row_orders.push(row_order);
+    let mut row_orders = vec![];
+    let mut row_metas = vec![];
+    let mut rows_data = vec![];
+    for params in rows {
+      let params = params.into();
+      let row: Row = params.clone().into();
+      row_orders.push(RowOrder {
+        id: row.id.clone(),
+        height: row.height,
+      });
+      if let Some(row_meta) = params.row_meta {
+        row_metas.push((row.id.clone(), row_meta));
       }
+      rows_data.push((row.id.clone(), DatabaseRowDataVariant::Row(row)));
     }
+
+    trace!("creating {} new database rows", rows_data.len());
+    let created_rows = self
+      .collab_service
+      .batch_create_arc_database_row(rows_data, self.row_change_tx.clone())
+      .await;
+    for (row_id, row_meta) in row_metas {
+      if let Some(database_row) = created_rows.get(&row_id) {
+        set_row_meta(&mut *database_row.write().await, row_meta);
+      }
+    }
+    row_orders.retain(|row_order| created_rows.contains_key(&row_order.id));
     row_orders
   }
 
@@ -113,14 +137,7 @@ impl Block {
       .await?;
 
     if let Some(row_meta) = params.row_meta {
-      let mut write_guard = database_row.write().await;
-      write_guard.update_meta(|update| {
-        update
-          .insert_icon_if_not_none(row_meta.icon_url)
-          .insert_cover_if_not_none(row_meta.cover)
-          .update_is_document_empty_if_not_none(Some(row_meta.is_document_empty))
-          .update_attachment_count_if_not_none(Some(row_meta.attachment_count));
-      });
+      set_row_meta(&mut *database_row.write().await, row_meta);
     }
 
     trace!("created new database row: {}", row_id);
@@ -184,6 +201,62 @@ impl Block {
     }
   }
 
+  /// Updates the row and records the changes of its cells in its history, see
+  /// [DatabaseRow::update_with_history]. Returns false if the row can't be loaded.
+  pub async fn update_row_with_history<F>(
+    &mut self,
+    row_id: &RowId,
+    f: F,
+    retention: &RowHistoryRetention,
+  ) -> bool
+  where
+    F: FnOnce(RowUpdate),
+  {
+    match self.get_or_init_database_row(row_id).await {
+      Ok(database_row) => {
+        database_row.write().await.update_with_history(f, retention);
+        true
+      },
+      Err(_) => false,
+    }
+  }
+
+  /// Updates the row without writing the changes of the cells of the `held_field_ids`, see
+  /// [DatabaseRow::update_holding_cells]. Returns `None` if the row can't be loaded.
+  pub async fn update_row_holding_cells<F>(
+    &mut self,
+    row_id: &RowId,
+    f: F,
+    held_field_ids: &[&str],
+    retention: &RowHistoryRetention,
+  ) -> Option<(Cells, Cells)>
+  where
+    F: FnOnce(RowUpdate),
+  {
+    let database_row = self.get_or_init_database_row(row_id).await.ok()?;
+    let cells = database_row
+      .write()
+      .await
+      .update_holding_cells(f, held_field_ids, retention);
+    Some(cells)
+  }
+
+  /// Writes the cells of the fields to the row, see [DatabaseRow::write_cells].
+  pub async fn write_row_cells(
+    &mut self,
+    row_id: &RowId,
+    field_ids: &[&str],
+    cells: &Cells,
+    retention: &RowHistoryRetention,
+  ) {
+    if let Ok(database_row) = self.get_or_init_database_row(row_id).await {
+      database_row
+        .write()
+        .await
+        .write_cells(field_ids, cells, retention);
+    }
+  }
+
   pub async fn update_row_meta<F>(&mut self, row_id: &RowId, f: F)
   where
     F: FnOnce(RowMetaUpdate),
@@ -242,3 +315,13 @@ impl Block {
     Ok(database_rows)
   }
 }
+
+fn set_row_meta(database_row: &mut DatabaseRow, row_meta: RowMeta) {
+  database_row.update_meta(|update| {
+    update
+      .insert_icon_if_not_none(row_meta.icon_url)
+      .insert_cover_if_not_none(row_meta.cover)
+      .update_is_document_empty_if_not_none(Some(row_meta.is_document_empty))
+      .update_attachment_count_if_not_none(Some(row_meta.attachment_count));
+  });
+}
@@ -4,7 +4,9 @@ use std::fmt::Debug;
 use std::ops::{Deref, DerefMut};
 
 use crate::blocks::{Block, BlockEvent, InitRowChan};
+use crate::database_relation::{RelatedDatabases, RowCellsChange, TwoWayRelation};
 use crate::database_state::DatabaseNotify;
+use crate::database_validation::{CellViolation, ConstrainedField, UniqueValueIndex};
 use crate::error::DatabaseError;
 use crate::fields::{
   Field, FieldChangeReceiver, FieldMap, FieldUpdate, TypeOptionCellReader, TypeOptionCellWriter,
@@ -12,15 +14,16 @@ use crate::fields::{
 };
 use crate::meta::MetaMap;
 use crate::rows::{
-  CreateRowParams, CreateRowParamsValidator, DatabaseRow, Row, RowCell, RowChangeReceiver,
-  RowDetail, RowId, RowMeta, RowMetaKey, RowMetaUpdate, RowUpdate, meta_id_from_row_id,
+  Cells, CreateRowParams, CreateRowParamsValidator, DatabaseRow, Row, RowCell, RowChange,
+  RowChangeReceiver, RowCommentThread, RowDetail, RowHistoryRetention, RowId, RowMeta, RowMetaKey,
+  RowMetaUpdate, RowUpdate, meta_id_from_row_id,
 };
 use crate::util::encoded_collab;
 use crate::views::define::DATABASE_VIEW_ROW_ORDERS;
 use crate::views::{
   CalculationMap, DatabaseLayout, DatabaseViewUpdate, DatabaseViews, FieldOrder,
   FieldSettingsByFieldIdMap, FieldSettingsMap, FilterMap, GroupSettingMap, LayoutSetting,
-  OrderArray, OrderObjectPosition, RowOrder, RowOrderArray, SortMap, ViewChangeReceiver,
+  OrderArray, OrderObjectPosition, RowFilter, RowOrder, RowOrderArray, SortMap, ViewChangeReceiver,
 };
 use crate::workspace_database::DatabaseMeta;
 
@@ -51,7 +54,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 pub use tokio_stream::wrappers::WatchStream;
 use tokio_util::sync::CancellationToken;
-use tracing::{error, info, instrument, trace};
+use tracing::{error, info, instrument, trace, warn};
 use uuid::Uuid;
 use yrs::block::ClientID;
 
@@ -383,18 +386,103 @@ impl Database {
   /// This row will be inserted to the end of rows of each view that
   /// reference the given database. Return the row order if the row is
   /// created successfully. Otherwise, return None.
+  ///
+  /// Returns [DatabaseError::ConstraintViolation] if a cell violates the constraints of a field
+  /// in [crate::database_validation::ConstraintMode::Enforce] mode.
   pub async fn create_row(&mut self, params: CreateRowParams) -> Result<RowOrder, DatabaseError> {
+    let (row_order, _) = self.create_row_with_violations(params).await?;
+    Ok(row_order)
+  }
+
+  /// Same as [Database::create_row], and returns the cells that violate the constraints of fields
+  /// in [crate::database_validation::ConstraintMode::Report] mode along with the row order.
+  pub async fn create_row_with_violations(
+    &mut self,
+    params: CreateRowParams,
+  ) -> Result<(RowOrder, Vec<CellViolation>), DatabaseError> {
     let client_id = self.collab_service.database_client_id().await;
     let params = CreateRowParamsValidator::validate(params)?;
+    let fields = self.constrained_fields();
+    let violations = self
+      .check_new_rows(std::slice::from_ref(&params), &fields)
+      .await?;
+    let cells = params.cells.clone();
     let row_order = self.body.block.create_new_row(params, client_id).await?;
-    let mut txn = self.collab.transact_mut();
-    self
-      .body
-      .views
-      .update_all_views(&mut txn, |_view_id, update| {
-        update.insert_row_order(&row_order, &OrderObjectPosition::default());
-      });
-    Ok(row_order)
+    {
+      let mut txn = self.collab.transact_mut();
+      self
+        .body
+        .views
+        .update_all_views(&mut txn, |_view_id, update| {
+          update.insert_row_order(&row_order, &OrderObjectPosition::default());
+        });
+    }
+    self.index_unique_values(&row_order.id, &cells, &fields);
+    self.add_back_references(&row_order.id, &cells).await;
+    Ok((row_order, violations))
+  }
+
+  /// Create the rows from the given params. The rows are created concurrently and their
+  /// [RowOrder]s are inserted to each view in a single transaction at the position of their
+  /// params, so the views receive one [crate::views::DatabaseViewChange::DidUpdateRowOrders]
+  /// for all the rows. Returns the row orders of the created rows, the rows that fail to be
+  /// created are skipped, and the cells that violate the constraints of fields in
+  /// [crate::database_validation::ConstraintMode::Report] mode.
+  ///
+  /// Returns [DatabaseError::ConstraintViolation] without creating any row if a cell violates the
+  /// constraints of a field in [crate::database_validation::ConstraintMode::Enforce] mode.
+  pub async fn create_rows(
+    &mut self,
+    params: Vec<CreateRowParams>,
+  ) -> Result<(Vec<RowOrder>, Vec<CellViolation>), DatabaseError> {
+    let params = params
+      .into_iter()
+      .map(CreateRowParamsValidator::validate)
+      .collect::<Result<Vec<_>, _>>()?;
+    let fields = self.constrained_fields();
+    let violations = self.check_new_rows(&params, &fields).await?;
+    let mut new_rows = params
+      .iter()
+      .map(|params| {
+        (
+          params.id.clone(),
+          (params.row_position.clone(), params.cells.clone()),
+        )
+      })
+      .collect::<HashMap<_, _>>();
+    let row_orders = self.body.block.create_rows(params).await;
+    let new_rows = row_orders
+      .iter()
+      .filter_map(|row_order| Some((row_order, new_rows.remove(&row_order.id)?)))
+      .collect::<Vec<_>>();
+    {
+      let mut txn = self.collab.transact_mut();
+      self
+        .body
+        .views
+        .update_all_views(&mut txn, |_view_id, mut update| {
+          for (row_order, (row_position, _)) in new_rows.iter() {
+            update = update.insert_row_order(*row_order, row_position);
+          }
+        });
+    }
+    for (row_order, (_, cells)) in new_rows.iter() {
+      self.index_unique_values(&row_order.id, cells, &fields);
+    }
+
+    let relations = self.two_way_relations();
+    if !relations.is_empty() {
+      let changes = new_rows
+        .into_iter()
+        .map(|(row_order, (_, cells))| RowCellsChange {
+          row_id: row_order.id.clone(),
+          previous_cells: Cells::new(),
+          cells,
+        })
+        .collect::<Vec<_>>();
+      self.update_back_references(&relations, &changes).await;
+    }
+    Ok((row_orders, violations))
   }
 
   pub fn update_database_view<F>(&mut self, view_id: &str, f: F)
@@ -420,6 +508,9 @@ impl Database {
   /// Create a new row from the given view.
   /// This row will be inserted into corresponding [Block]. The [RowOrder] of this row will
   /// be inserted to each view.
+  ///
+  /// Returns the index of the row in the view, or [DatabaseError::ConstraintViolation] like
+  /// [Database::create_row].
   pub async fn create_row_in_view(
     &mut self,
     view_id: &str,
@@ -427,24 +518,34 @@ impl Database {
   ) -> Result<(usize, RowOrder), DatabaseError> {
     let client_id = self.collab_service.database_client_id().await;
     let row_position = params.row_position.clone();
+    let fields = self.constrained_fields();
+    self
+      .check_new_rows(std::slice::from_ref(&params), &fields)
+      .await?;
+    let cells = params.cells.clone();
     let row_order = self.body.create_row(params, client_id).await?;
 
-    let mut txn = self.collab.transact_mut();
-    self
-      .body
-      .views
-      .update_all_views(&mut txn, |_view_id, update| {
-        update.insert_row_order(&row_order, &row_position);
-      });
-    let index = self
-      .body
-      .index_of_row(&txn, view_id, &row_order.id)
-      .unwrap_or_default();
+    let index = {
+      let mut txn = self.collab.transact_mut();
+      self
+        .body
+        .views
+        .update_all_views(&mut txn, |_view_id, update| {
+          update.insert_row_order(&row_order, &row_position);
+        });
+      self
+        .body
+        .index_of_row(&txn, view_id, &row_order.id)
+        .unwrap_or_default()
+    };
+    self.index_unique_values(&row_order.id, &cells, &fields);
+    self.add_back_references(&row_order.id, &cells).await;
     Ok((index, row_order))
   }
 
   /// Remove the row
-  /// The [RowOrder] of each view representing this row will be removed.
+  /// The [RowOrder] of each view representing this row will be removed, and the back-references
+  /// of the row are removed from the rows it links with a two-way relation.
   pub async fn remove_row(&mut self, row_id: &RowId) {
     {
       let mut txn = self.collab.transact_mut();
@@ -452,6 +553,8 @@ impl Database {
         update.remove_row_order(row_id);
       });
     };
+    self.unindex_rows(std::slice::from_ref(row_id));
+    self.remove_back_references(row_id).await;
   }
 
   pub async fn move_row(&mut self, from_row_id: &str, to_row_id: &str) {
@@ -461,6 +564,9 @@ impl Database {
     });
   }
 
+  /// Remove the rows
+  /// The [RowOrder]s of the rows are removed from each view in a single transaction. The rows
+  /// are loaded in a batch when their back-references need to be removed.
   pub async fn remove_rows(&mut self, row_ids: &[RowId]) {
     {
       let mut txn = self.collab.transact_mut();
@@ -470,14 +576,244 @@ impl Database {
         }
       });
     };
+    self.unindex_rows(row_ids);
+    let relations = self.two_way_relations();
+    if relations.is_empty() {
+      return;
+    }
+    if let Err(err) = self
+      .body
+      .block
+      .init_database_rows(row_ids.to_vec(), true)
+      .await
+    {
+      error!("Failed to load the removed rows: {}", err);
+    }
+    let mut changes = vec![];
+    for row_id in row_ids {
+      changes.push(RowCellsChange {
+        row_id: row_id.clone(),
+        previous_cells: self.get_row(row_id).await.cells,
+        cells: Cells::new(),
+      });
+    }
+    self.update_back_references(&relations, &changes).await;
   }
 
   /// Update the row
-  pub async fn update_row<F>(&mut self, row_id: RowId, f: F)
+  ///
+  /// Returns the changed cells that violate the constraints of their fields. The changes of the
+  /// cells of the fields in [crate::database_validation::ConstraintMode::Enforce] mode are held
+  /// back until they are checked, the cells that violate their constraints are not written and
+  /// keep their previous value. The back-references of the changed two-way relation cells are
+  /// updated in the linked rows, and the changes are recorded in the history of the row in the
+  /// same transaction as the update.
+  pub async fn update_row<F>(&mut self, row_id: RowId, f: F) -> Vec<CellViolation>
   where
     F: FnOnce(RowUpdate),
   {
-    self.body.block.update_row(row_id, f).await;
+    let fields = self.constrained_fields();
+    let relations = self.two_way_relations();
+    let retention = self.get_row_history_retention();
+    let Some((violations, change)) = self
+      .write_row_update(row_id, f, &fields, &relations, &retention)
+      .await
+    else {
+      return vec![];
+    };
+    self
+      .update_back_references(&relations, change.as_slice())
+      .await;
+    violations
+  }
+
+  /// Update the rows, each row with its own closure. The rows are loaded in a batch before being
+  /// updated, see [Database::update_row] for how each row is updated. The back-references of the
+  /// rows are written once all the rows are updated, with one write per linked row.
+  ///
+  /// Each row still sends its own changes, such as [RowChange::DidUpdateCell]. A
+  /// [RowChange::DidUpdateRows] with the rows that were written is sent in addition, once the
+  /// batch is done, so that the batch can be handled as a whole.
+  ///
+  /// Returns the changed cells that violate the constraints of their fields.
+  pub async fn update_rows<F>(&mut self, updates: Vec<(RowId, F)>) -> Vec<CellViolation>
+  where
+    F: FnOnce(RowUpdate),
+  {
+    let row_ids = updates
+      .iter()
+      .map(|(row_id, _)| row_id.clone())
+      .collect::<Vec<_>>();
+    if let Err(err) = self
+      .body
+      .block
+      .init_database_rows(row_ids.clone(), true)
+      .await
+    {
+      error!("Failed to load the updated rows: {}", err);
+    }
+
+    let fields = self.constrained_fields();
+    let relations = self.two_way_relations();
+    let retention = self.get_row_history_retention();
+    let mut violations = vec![];
+    let mut changes = vec![];
+    let mut written_row_ids = vec![];
+    for (row_id, f) in updates {
+      let Some((row_violations, change)) = self
+        .write_row_update(row_id.clone(), f, &fields, &relations, &retention)
+        .await
+      else {
+        continue;
+      };
+      violations.extend(row_violations);
+      changes.extend(change);
+      written_row_ids.push(row_id);
+    }
+    self.update_back_references(&relations, &changes).await;
+    if let Some(notifier) = &self.body.notifier {
+      if !written_row_ids.is_empty() {
+        let _ = notifier.row_change_tx.send(RowChange::DidUpdateRows {
+          row_ids: written_row_ids,
+        });
+      }
+    }
+    violations
+  }
+
+  /// Writes the update of the row without updating the back-references of its relations, see
+  /// [Database::update_row]. Returns the change of the cells of the row when there are
+  /// `relations`, so that their back-references can be updated, or `None` if the row can't be
+  /// loaded.
+  pub(crate) async fn write_row_update<F>(
+    &mut self,
+    row_id: RowId,
+    f: F,
+    fields: &[ConstrainedField],
+    relations: &[TwoWayRelation],
+    retention: &RowHistoryRetention,
+  ) -> Option<(Vec<CellViolation>, Option<RowCellsChange>)>
+  where
+    F: FnOnce(RowUpdate),
+  {
+    if fields.is_empty() && relations.is_empty() {
+      let written = self
+        .body
+        .block
+        .update_row_with_history(&row_id, f, retention)
+        .await;
+      return written.then_some((vec![], None));
+    }
+
+    let held_field_ids = fields
+      .iter()
+      .filter(|field| field.is_enforced())
+      .map(|field| field.field_id.as_str())
+      .collect::<Vec<_>>();
+    let (previous_cells, mut cells) = self
+      .body
+      .block
+      .update_row_holding_cells(&row_id, f, &held_field_ids, retention)
+      .await?;
+    let violations = self
+      .check_changed_cells(&row_id, fields, &previous_cells, &cells)
+      .await;
+    for violation in violations.iter().filter(|violation| violation.rejected) {
+      match previous_cells.get(&violation.field_id) {
+        Some(cell) => cells.insert(violation.field_id.clone(), cell.clone()),
+        None => cells.remove(&violation.field_id),
+      };
+    }
+    let accepted_field_ids = held_field_ids
+      .into_iter()
+      .filter(|field_id| previous_cells.get(*field_id) != cells.get(*field_id))
+      .collect::<Vec<_>>();
+    if !accepted_field_ids.is_empty() {
+      self
+        .body
+        .block
+        .write_row_cells(&row_id, &accepted_field_ids, &cells, retention)
+        .await;
+    }
+    self.index_unique_values(&row_id, &cells, fields);
+    let change = (!relations.is_empty()).then_some(RowCellsChange {
+      row_id,
+      previous_cells,
+      cells,
+    });
+    Some((violations, change))
+  }
+
+  /// Checks the cells of the constrained fields that are changed by an update of the row.
+  async fn check_changed_cells(
+    &mut self,
+    row_id: &RowId,
+    fields: &[ConstrainedField],
+    previous_cells: &Cells,
+    cells: &Cells,
+  ) -> Vec<CellViolation> {
+    let changed_fields = fields
+      .iter()
+      .filter(|field| previous_cells.get(&field.field_id) != cells.get(&field.field_id))
+      .collect::<Vec<_>>();
+    if changed_fields.is_empty() {
+      return vec![];
+    }
+    match self.check_row_cells(row_id, cells, &changed_fields).await {
+      Ok(violations) => violations,
+      Err(err) => {
+        error!("Failed to check the constraints of row {}: {}", row_id, err);
+        vec![]
+      },
+    }
+  }
+
+  async fn add_back_references(&mut self, row_id: &RowId, cells: &Cells) {
+    let relations = self.two_way_relations();
+    if !relations.is_empty() {
+      let change = RowCellsChange {
+        row_id: row_id.clone(),
+        previous_cells: Cells::new(),
+        cells: cells.clone(),
+      };
+      self.update_back_references(&relations, &[change]).await;
+    }
+  }
+
+  async fn remove_back_references(&mut self, row_id: &RowId) {
+    let relations = self.two_way_relations();
+    if !relations.is_empty() {
+      let change = RowCellsChange {
+        row_id: row_id.clone(),
+        previous_cells: self.get_row(row_id).await.cells,
+        cells: Cells::new(),
+      };
+      self.update_back_references(&relations, &[change]).await;
+    }
+  }
+
+  /// Checks the cells of the new rows. Returns the violations of the fields in
+  /// [crate::database_validation::ConstraintMode::Report] mode, or
+  /// [DatabaseError::ConstraintViolation] if a cell violates the constraints of a field in
+  /// [crate::database_validation::ConstraintMode::Enforce] mode.
+  pub(crate) async fn check_new_rows(
+    &mut self,
+    params: &[CreateRowParams],
+    fields: &[ConstrainedField],
+  ) -> Result<Vec<CellViolation>, DatabaseError> {
+    if fields.is_empty() {
+      return Ok(vec![]);
+    }
+    let fields = fields.iter().collect::<Vec<_>>();
+    let rows = params
+      .iter()
+      .map(|params| (&params.id, &params.cells))
+      .collect::<Vec<_>>();
+    let violations = self.check_batch_cells(&rows, &fields).await?;
+    if violations.iter().any(|violation| violation.rejected) {
+      return Err(DatabaseError::ConstraintViolation(violations));
+    }
+    Ok(violations)
   }
 
   /// Update the meta of the row
@@ -601,6 +937,15 @@ impl Database {
     read_guard.get_row_detail()
   }
 
+  /// Returns the comments of the row with their replies. The comments are changed through the
+  /// [DatabaseRow] returned by [Database::get_database_row].
+  pub async fn get_row_comment_threads(&self, row_id: &RowId) -> Vec<RowCommentThread> {
+    match self.body.block.get_database_row(row_id).await {
+      Some(row) => row.read().await.get_comment_threads(),
+      None => vec![],
+    }
+  }
+
   pub fn get_row_document_id(&self, row_id: &RowId) -> Option<String> {
     self.body.block.get_row_document_id(row_id)
   }
@@ -748,17 +1093,27 @@ impl Database {
     (index, field)
   }
 
+  /// Deletes the field. When it's a two-way relation field, its paired field in the related
+  /// database becomes a one-way relation, see [Database::delete_two_way_relation].
+  /// Deletes the field from the database and its views. The paired field of a deleted two-way
+  /// relation field becomes a one-way relation, see [Database::unpair_deleted_relation].
   pub fn delete_field(&mut self, field_id: &str) {
-    let mut txn = self.collab.transact_mut();
-    self
-      .body
-      .views
-      .update_all_views(&mut txn, |_view_id, update| {
-        update
-          .remove_field_order(field_id)
-          .remove_field_setting(field_id);
-      });
-    self.body.fields.delete_field(&mut txn, field_id);
+    let relation = self.two_way_relation(field_id);
+    {
+      let mut txn = self.collab.transact_mut();
+      self
+        .body
+        .views
+        .update_all_views(&mut txn, |_view_id, update| {
+          update
+            .remove_field_order(field_id)
+            .remove_field_setting(field_id);
+        });
+      self.body.fields.delete_field(&mut txn, field_id);
+    }
+    if let Some(relation) = relation {
+      self.unpair_deleted_relation(relation);
+    }
   }
 
   pub fn get_all_group_setting<T: TryFrom<GroupSettingMap>>(&self, view_id: &str) -> Vec<T> {
@@ -1030,6 +1385,12 @@ impl Database {
       .collect()
   }
 
+  /// Returns the [RowFilter] that checks the rows against the filters of the view.
+  pub fn get_row_filter(&self, view_id: &str) -> RowFilter {
+    let filters = self.get_all_filters::<FilterMap>(view_id);
+    RowFilter::new(&filters, self.get_all_fields())
+  }
+
   pub fn get_filter<T>(&self, view_id: &str, filter_id: &str) -> Option<T>
   where
     T: TryFrom<FilterMap>,
@@ -1681,6 +2042,10 @@ pub struct DatabaseBody {
   /// A database rows will be stored in multiple blocks.
   pub block: Block,
   pub notifier: Option<DatabaseNotify>,
+  /// The values of the unique fields, see [UniqueValueIndex].
+  pub(crate) unique_values: UniqueValueIndex,
+  /// The databases of the two-way relations, see [Database::add_related_database].
+  pub(crate) related_databases: RelatedDatabases,
 }
 
 impl DatabaseBody {
@@ -1724,12 +2089,7 @@ impl DatabaseBody {
     let inline_view_id = database_inline_view_id(&database_id_uuid);
 
     // create rows
-    let row_orders = block
-      .create_rows(
-        new_rows,
-        context.database_collab_service.database_client_id().await,
-      )
-      .await;
+    let row_orders = block.create_rows(new_rows).await;
 
     // create field orders
     let field_orders: Vec<FieldOrder> = new_fields.iter().map(FieldOrder::from).collect();
@@ -1761,6 +2121,8 @@ impl DatabaseBody {
       metas: metas.into(),
       block,
       notifier: Some(context.notifier),
+      unique_values: UniqueValueIndex::default(),
+      related_databases: RelatedDatabases::default(),
     };
 
     let mut txn = collab.context.transact_mut();
@@ -1812,6 +2174,8 @@ impl DatabaseBody {
       metas: metas.into(),
       block,
       notifier,
+      unique_values: UniqueValueIndex::default(),
+      related_databases: RelatedDatabases::default(),
     })
   }
 
@@ -0,0 +1,415 @@
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use futures::StreamExt;
+use tracing::warn;
+
+use crate::database::Database;
+use crate::entity::{FieldType, default_type_option_data_from_type};
+use crate::error::DatabaseError;
+use crate::fields::date_type_option::{DateCellData, DateTypeOption};
+use crate::fields::type_option_cell_reader;
+use crate::rows::RowId;
+use crate::views::{CalendarLayoutSetting, DatabaseLayout};
+
+const CALENDAR_CHUNK_SIZE: usize = 100;
+/// The number of periods of a recurrence that are looked at, so that a rule that never matches a
+/// date, like the 30th of February, ends.
+const MAX_RECURRENCE_PERIODS: u32 = 100_000;
+
+/// An occurrence of the date of a row in a calendar view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarEvent {
+  pub row_id: RowId,
+  /// The text of the primary field of the row.
+  pub title: String,
+  /// The timestamp of the start of the event, in seconds.
+  pub start: i64,
+  /// The timestamp of the end of the event, only set for date ranges.
+  pub end: Option<i64>,
+  /// True if the date has no time. The event lasts from the start of its first day to the end of
+  /// its last day, in the timezone of the date.
+  pub is_all_day: bool,
+  /// The index of the occurrence when the date repeats, the first occurrence is 0.
+  pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecurrenceFrequency {
+  Daily,
+  Weekly,
+  Monthly,
+  Yearly,
+}
+
+/// The subset of the RRULE of RFC 5545 supported by the calendar events: `FREQ`, `INTERVAL`,
+/// `COUNT`, `UNTIL`, `BYDAY` for the weekly rules and `WKST`. The dates are repeated in the
+/// timezone of the date, see [RecurrenceRule::occurrences_in_timezone].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurrenceRule {
+  pub frequency: RecurrenceFrequency,
+  pub interval: u32,
+  /// The number of occurrences, including the first one.
+  pub count: Option<u32>,
+  /// The timestamp after which the date doesn't repeat.
+  pub until: Option<i64>,
+  /// The days of the week of a weekly rule. The day of the first occurrence is used when it's
+  /// empty.
+  pub by_day: Vec<Weekday>,
+  pub week_start: Weekday,
+}
+
+impl FromStr for RecurrenceRule {
+  type Err = DatabaseError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid = |reason: &str| DatabaseError::InvalidRecurrenceRule(format!("{}: {}", reason, s));
+    let rule = s.trim();
+    let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+    let mut frequency = None;
+    let mut interval = 1;
+    let mut count = None;
+    let mut until = None;
+    let mut by_day = vec![];
+    let mut week_start = Weekday::Mon;
+    for part in rule.split(';').filter(|part| !part.is_empty()) {
+      let (key, value) = part
+        .split_once('=')
+        .ok_or_else(|| invalid("missing value"))?;
+      match key.to_uppercase().as_str() {
+        "FREQ" => {
+          frequency = Some(match value.to_uppercase().as_str() {
+            "DAILY" => RecurrenceFrequency::Daily,
+            "WEEKLY" => RecurrenceFrequency::Weekly,
+            "MONTHLY" => RecurrenceFrequency::Monthly,
+            "YEARLY" => RecurrenceFrequency::Yearly,
+            _ => return Err(invalid("unsupported frequency")),
+          })
+        },
+        "INTERVAL" => {
+          interval = value
+            .parse::<u32>()
+            .ok()
+            .filter(|interval| *interval > 0)
+            .ok_or_else(|| invalid("invalid interval"))?;
+        },
+        "COUNT" => count = Some(value.parse::<u32>().map_err(|_| invalid("invalid count"))?),
+        "UNTIL" => until = Some(parse_until(value).ok_or_else(|| invalid("invalid until"))?),
+        "BYDAY" => {
+          by_day = value
+            .split(',')
+            .map(parse_weekday)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid("invalid day"))?;
+        },
+        "WKST" => week_start = parse_weekday(value).ok_or_else(|| invalid("invalid day"))?,
+        _ => return Err(invalid("unsupported part")),
+      }
+    }
+    let frequency = frequency.ok_or_else(|| invalid("missing frequency"))?;
+    if !by_day.is_empty() && frequency != RecurrenceFrequency::Weekly {
+      return Err(invalid("days are only supported by weekly rules"));
+    }
+    Ok(Self {
+      frequency,
+      interval,
+      count,
+      until,
+      by_day,
+      week_start,
+    })
+  }
+}
+
+impl RecurrenceRule {
+  /// Returns the timestamps of the occurrences of a date starting at the timestamp, in order. The
+  /// date is repeated in UTC.
+  pub fn occurrences(&self, start: i64) -> impl Iterator<Item = i64> + '_ {
+    self.occurrences_in_timezone(start, DateTypeOption::default_utc())
+  }
+
+  /// Returns the timestamps of the occurrences of a date starting at the timestamp, in order. The
+  /// date is repeated in the timezone of the type option, so the occurrences keep their local
+  /// time across daylight saving changes.
+  pub fn occurrences_in_timezone(
+    &self,
+    start: i64,
+    type_option: DateTypeOption,
+  ) -> impl Iterator<Item = i64> + '_ {
+    let start = type_option
+      .local_date_time_from_timestamp(start)
+      .unwrap_or_default();
+    Occurrences {
+      rule: self,
+      type_option,
+      start,
+      period: 0,
+      pending: VecDeque::new(),
+      emitted: 0,
+    }
+  }
+
+  /// The occurrences in the `period`-th period of the rule after the first one, in order.
+  fn period_occurrences(&self, start: NaiveDateTime, period: u32) -> Vec<NaiveDateTime> {
+    let step = period.saturating_mul(self.interval);
+    let time = start.time();
+    match self.frequency {
+      RecurrenceFrequency::Daily => start
+        .checked_add_days(Days::new(step as u64))
+        .into_iter()
+        .collect(),
+      RecurrenceFrequency::Weekly if self.by_day.is_empty() => start
+        .checked_add_days(Days::new(step as u64 * 7))
+        .into_iter()
+        .collect(),
+      RecurrenceFrequency::Weekly => {
+        let days_from_week_start = start.weekday().days_since(self.week_start);
+        let week_start = start.date() - Days::new(days_from_week_start as u64);
+        let Some(week_start) = week_start.checked_add_days(Days::new(step as u64 * 7)) else {
+          return vec![];
+        };
+        let mut offsets = self
+          .by_day
+          .iter()
+          .map(|day| day.days_since(self.week_start))
+          .collect::<Vec<_>>();
+        offsets.sort_unstable();
+        offsets.dedup();
+        offsets
+          .into_iter()
+          .filter_map(|offset| week_start.checked_add_days(Days::new(offset as u64)))
+          .map(|date| date.and_time(time))
+          .collect()
+      },
+      RecurrenceFrequency::Monthly => {
+        let first_day = start
+          .date()
+          .with_day(1)
+          .and_then(|date| date.checked_add_months(Months::new(step)));
+        first_day
+          .and_then(|date| date.with_day(start.day()))
+          .map(|date| date.and_time(time))
+          .into_iter()
+          .collect()
+      },
+      RecurrenceFrequency::Yearly => i32::try_from(step)
+        .ok()
+        .and_then(|step| start.year().checked_add(step))
+        .and_then(|year| NaiveDate::from_ymd_opt(year, start.month(), start.day()))
+        .map(|date| date.and_time(time))
+        .into_iter()
+        .collect(),
+    }
+  }
+}
+
+struct Occurrences<'a> {
+  rule: &'a RecurrenceRule,
+  type_option: DateTypeOption,
+  start: NaiveDateTime,
+  period: u32,
+  pending: VecDeque<NaiveDateTime>,
+  emitted: u32,
+}
+
+impl Iterator for Occurrences<'_> {
+  type Item = i64;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.rule.count.is_some_and(|count| self.emitted >= count) {
+      return None;
+    }
+    let occurrence = if self.emitted == 0 {
+      // the first occurrence is the date itself, even if it doesn't match the rule.
+      self.start
+    } else {
+      loop {
+        if let Some(occurrence) = self.pending.pop_front() {
+          if occurrence > self.start {
+            break occurrence;
+          }
+          continue;
+        }
+        if self.period >= MAX_RECURRENCE_PERIODS {
+          return None;
+        }
+        self.pending = self.rule.period_occurrences(self.start, self.period).into();
+        self.period += 1;
+      }
+    };
+    let timestamp = self
+      .type_option
+      .timestamp_from_local_date_time(occurrence)?;
+    if self.rule.until.is_some_and(|until| timestamp > until) {
+      return None;
+    }
+    self.emitted += 1;
+    Some(timestamp)
+  }
+}
+
+impl Database {
+  /// Returns the events of the calendar view that overlap the period from `from` included to `to`
+  /// excluded, ordered by their start. The timestamps are in seconds.
+  ///
+  /// Each row whose cell in the date field of the [CalendarLayoutSetting] is set gives an event,
+  /// or an event per occurrence when the date has a [DateCellData::recurrence]. The rows hidden by
+  /// the filters of the view and the rows that can't be read are skipped.
+  ///
+  /// The days of the all-day events and the occurrences of the dates are in the timezone of the
+  /// cell, or of the date field if the cell has none.
+  pub async fn get_calendar_events(
+    &self,
+    view_id: &str,
+    from: i64,
+    to: i64,
+  ) -> Result<Vec<CalendarEvent>, DatabaseError> {
+    if self.get_view(view_id).is_none() {
+      return Err(DatabaseError::DatabaseViewNotExist);
+    }
+    let setting = self
+      .get_layout_setting::<CalendarLayoutSetting>(view_id, &DatabaseLayout::Calendar)
+      .ok_or_else(|| DatabaseError::NoRequiredData("calendar layout setting".to_string()))?;
+    let fields = self.get_all_fields();
+    let date_field = fields
+      .iter()
+      .find(|field| field.id == setting.field_id)
+      .filter(|field| FieldType::from(field.field_type) == FieldType::DateTime)
+      .ok_or_else(|| DatabaseError::NoRequiredData("calendar date field".to_string()))?;
+    let date_type_option = DateTypeOption::from(
+      date_field
+        .get_any_type_option(FieldType::DateTime.type_id())
+        .unwrap_or_else(|| default_type_option_data_from_type(FieldType::DateTime)),
+    );
+    let title_reader = fields.iter().find(|field| field.is_primary).map(|field| {
+      let field_type = FieldType::from(field.field_type);
+      let reader = self.get_cell_reader(&field.id).unwrap_or_else(|| {
+        type_option_cell_reader(default_type_option_data_from_type(field_type), &field_type)
+      });
+      (field.id.clone(), reader)
+    });
+    let row_filter = self.get_row_filter(view_id);
+
+    let mut events = vec![];
+    let mut rows = Box::pin(
+      self
+        .get_rows_for_view(view_id, CALENDAR_CHUNK_SIZE, None, false)
+        .await,
+    );
+    while let Some(row) = rows.next().await {
+      let row = match row {
+        Ok(row) => row,
+        Err(err) => {
+          warn!("Skip a row of the calendar view {}: {}", view_id, err);
+          continue;
+        },
+      };
+      let Some(cell) = row.cells.get(&date_field.id) else {
+        continue;
+      };
+      let date = DateCellData::from(cell);
+      let Some(start) = date.timestamp else {
+        continue;
+      };
+      if !row_filter.matches(&row) {
+        continue;
+      }
+      let type_option = date_type_option.for_cell(&date);
+      let title = title_reader
+        .as_ref()
+        .and_then(|(field_id, reader)| {
+          row
+            .cells
+            .get(field_id)
+            .map(|cell| reader.stringify_cell(cell))
+        })
+        .unwrap_or_default();
+      let end = date
+        .end_timestamp
+        .filter(|end| date.is_range && *end >= start);
+      let event = CalendarEvent {
+        row_id: row.id.clone(),
+        title,
+        start,
+        end,
+        is_all_day: !date.include_time,
+        occurrence: None,
+      };
+
+      let rule = date.recurrence.as_deref().and_then(|recurrence| {
+        RecurrenceRule::from_str(recurrence)
+          .map_err(|err| warn!("Ignore the recurrence of row {}: {}", row.id, err))
+          .ok()
+      });
+      match rule {
+        None => {
+          if event.overlaps(from, to, &type_option) {
+            events.push(event);
+          }
+        },
+        Some(rule) => {
+          let occurrences = rule.occurrences_in_timezone(start, type_option.clone());
+          for (index, occurrence) in occurrences.enumerate() {
+            if occurrence >= to {
+              break;
+            }
+            let shift = occurrence - start;
+            let event = CalendarEvent {
+              start: occurrence,
+              end: event.end.map(|end| end + shift),
+              occurrence: Some(index as u32),
+              ..event.clone()
+            };
+            if event.overlaps(from, to, &type_option) {
+              events.push(event);
+            }
+          }
+        },
+      }
+    }
+    events.sort_by_key(|event| event.start);
+    Ok(events)
+  }
+}
+
+impl CalendarEvent {
+  fn overlaps(&self, from: i64, to: i64, type_option: &DateTypeOption) -> bool {
+    let end = self.end.unwrap_or(self.start);
+    let end = if self.is_all_day {
+      // the end of the last day, in the timezone of the date.
+      type_option
+        .local_date_from_timestamp(end)
+        .and_then(|date| date.succ_opt())
+        .and_then(|date| type_option.timestamp_from_local_date_time(date.and_time(NaiveTime::MIN)))
+        .unwrap_or(end)
+    } else {
+      end
+    };
+    self.start < to && (end > from || self.start >= from)
+  }
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+  match value.trim().to_uppercase().as_str() {
+    "MO" => Some(Weekday::Mon),
+    "TU" => Some(Weekday::Tue),
+    "WE" => Some(Weekday::Wed),
+    "TH" => Some(Weekday::Thu),
+    "FR" => Some(Weekday::Fri),
+    "SA" => Some(Weekday::Sat),
+    "SU" => Some(Weekday::Sun),
+    _ => None,
+  }
+}
+
+/// `YYYYMMDD` is the end of the day, `YYYYMMDDTHHMMSS` and `YYYYMMDDTHHMMSSZ` are in UTC.
+fn parse_until(value: &str) -> Option<i64> {
+  let value = value.trim_end_matches('Z');
+  if let Ok(date_time) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+    return Some(Utc.from_utc_datetime(&date_time).timestamp());
+  }
+  let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+  let end_of_day = date.and_time(NaiveTime::from_hms_opt(23, 59, 59)?);
+  Some(Utc.from_utc_datetime(&end_of_day).timestamp())
+}
@@ -0,0 +1,311 @@
+use std::collections::{HashMap, HashSet};
+
+use collab::preclude::Any;
+use collab_entity::CollabType;
+use futures::StreamExt;
+use tracing::warn;
+
+use crate::database::{
+  Database, DatabaseContext, gen_database_id, gen_database_view_id, gen_field_id, gen_option_id,
+  gen_row_id, get_row_document_id, timestamp,
+};
+use crate::entity::{CreateDatabaseParams, CreateViewParams, FieldType};
+use crate::error::DatabaseError;
+use crate::fields::relation_type_option::RelationTypeOption;
+use crate::fields::select_type_option::{SelectOptionIds, SelectTypeOption};
+use crate::fields::{Field, FieldVisibility, field_visibility_in_view};
+use crate::rows::{Cell, Cells, CreateRowParams, RowId};
+use crate::template::relation_parse::RelationCellData;
+use crate::views::{FieldSettingsMap, GroupSetting, GroupSettingMap, OrderObjectPosition};
+
+const FIELD_ID: &str = "field_id";
+const DUPLICATE_ROWS_CHUNK_SIZE: usize = 20;
+
+/// The ids of the database created by [Database::duplicate_view_as_database], with the ids of
+/// the duplicated view mapped to the new ones.
+#[derive(Debug, Clone, Default)]
+pub struct DuplicatedDatabaseIds {
+  pub database_id: String,
+  pub view_id: String,
+  pub field_ids: HashMap<String, String>,
+  pub row_ids: HashMap<RowId, RowId>,
+  /// The ids of the options of the select fields.
+  pub option_ids: HashMap<String, String>,
+  /// The rows whose document was not copied because this database or the new one has no
+  /// persistence.
+  pub skipped_document_row_ids: Vec<RowId>,
+  /// The rows of the view that couldn't be loaded, they are not duplicated.
+  pub skipped_row_ids: Vec<RowId>,
+}
+
+impl DuplicatedDatabaseIds {
+  fn map_id(&self, id: &str) -> Option<String> {
+    self
+      .field_ids
+      .get(id)
+      .or_else(|| self.option_ids.get(id))
+      .cloned()
+  }
+}
+
+impl Database {
+  /// Creates a standalone database from the view. The new database only contains the fields
+  /// that are not hidden in the view and the rows that pass its filters, and its only view
+  /// keeps the layout, field settings, sorts and groups of the view. The filters are not
+  /// copied because all the rows of the new database pass them.
+  ///
+  /// The fields, rows and select options get new ids. The metas and the documents of the rows
+  /// are copied, the rows whose document can't be copied are reported in
+  /// [DuplicatedDatabaseIds::skipped_document_row_ids], and the rows that can't be loaded in
+  /// [DuplicatedDatabaseIds::skipped_row_ids]. The relations to the rows of this database are
+  /// mapped to the duplicated rows, and the two-way relations become one-way relations in the new
+  /// database.
+  pub async fn duplicate_view_as_database(
+    &self,
+    view_id: &str,
+    context: DatabaseContext,
+  ) -> Result<(Database, DuplicatedDatabaseIds), DatabaseError> {
+    let view = self
+      .get_view(view_id)
+      .ok_or(DatabaseError::DatabaseViewNotExist)?;
+    let mut ids = DuplicatedDatabaseIds {
+      database_id: gen_database_id(),
+      view_id: gen_database_view_id(),
+      ..Default::default()
+    };
+
+    let field_settings = view.field_settings.clone().into_inner();
+    let fields = self
+      .get_fields_in_view(view_id, None)
+      .into_iter()
+      .filter(|field| {
+        field_visibility_in_view(field, view.layout, field_settings.get(&field.id))
+          != FieldVisibility::AlwaysHidden
+      })
+      .collect::<Vec<_>>();
+    for field in &fields {
+      ids.field_ids.insert(field.id.clone(), gen_field_id());
+    }
+    let fields = fields
+      .into_iter()
+      .map(|field| self.duplicate_field_with_ids(field, &mut ids))
+      .collect::<Vec<_>>();
+
+    let row_orders = self.get_row_orders_for_view(view_id);
+    let results = self
+      .get_rows_from_row_orders(row_orders.clone(), DUPLICATE_ROWS_CHUNK_SIZE, None, true)
+      .await
+      .collect::<Vec<_>>()
+      .await;
+    let mut rows = Vec::with_capacity(results.len());
+    for result in results {
+      match result {
+        Ok(row) => rows.push(row),
+        Err(err) => warn!("Failed to load a row of view {}: {}", view_id, err),
+      }
+    }
+    let loaded_row_ids = rows.iter().map(|row| &row.id).collect::<HashSet<_>>();
+    ids.skipped_row_ids = row_orders
+      .into_iter()
+      .map(|row_order| row_order.id)
+      .filter(|row_id| !loaded_row_ids.contains(row_id))
+      .collect();
+    let rows = {
+      let row_filter = self.get_row_filter(view_id);
+      rows
+        .into_iter()
+        .filter(|row| row_filter.matches(row))
+        .collect::<Vec<_>>()
+    };
+    for row in &rows {
+      ids.row_ids.insert(row.id.clone(), gen_row_id());
+    }
+
+    let source_persistence = self.collab_service.persistence();
+    let target_persistence = context.database_collab_service.persistence();
+    let timestamp = timestamp();
+    let mut create_row_params = Vec::with_capacity(rows.len());
+    for row in rows {
+      let new_row_id = ids.row_ids[&row.id].clone();
+      let row_meta = self.get_row_meta(&row.id).await;
+      let has_document = row_meta
+        .as_ref()
+        .is_some_and(|meta| !meta.is_document_empty);
+      match (&source_persistence, &target_persistence) {
+        (Some(source), Some(target)) => {
+          let document_id = get_row_document_id(&row.id)?;
+          if source.is_collab_exist(&document_id) {
+            if let Some(encoded_collab) =
+              source.get_encoded_collab(&document_id, CollabType::Document)
+            {
+              target.upsert_collab(&get_row_document_id(&new_row_id)?, encoded_collab)?;
+            }
+          }
+        },
+        _ if has_document => ids.skipped_document_row_ids.push(row.id.clone()),
+        _ => {},
+      }
+
+      create_row_params.push(CreateRowParams {
+        id: new_row_id,
+        database_id: ids.database_id.clone(),
+        cells: duplicate_cells(&row.cells, &fields, &ids),
+        height: row.height,
+        visibility: row.visibility,
+        row_position: OrderObjectPosition::End,
+        created_at: timestamp,
+        modified_at: timestamp,
+        row_meta,
+      });
+    }
+
+    let mut layout_settings = view.layout_settings;
+    for setting in layout_settings.values_mut() {
+      map_field_id(setting, &ids);
+    }
+    let field_settings = field_settings
+      .into_iter()
+      .filter_map(|(field_id, settings)| Some((ids.field_ids.get(&field_id)?.clone(), settings)))
+      .collect::<HashMap<String, FieldSettingsMap>>();
+    let sorts = view
+      .sorts
+      .into_iter()
+      .filter_map(|mut sort| map_field_id(&mut sort, &ids).then_some(sort))
+      .collect();
+    let group_settings = view
+      .group_settings
+      .into_iter()
+      .filter_map(|setting| {
+        let mut setting = GroupSetting::try_from(setting).ok()?;
+        setting.field_id = ids.field_ids.get(&setting.field_id)?.clone();
+        for group in setting.groups.iter_mut() {
+          if let Some(group_id) = ids.map_id(&group.id) {
+            group.id = group_id;
+          }
+        }
+        Some(GroupSettingMap::from(setting))
+      })
+      .collect();
+
+    let params = CreateDatabaseParams {
+      database_id: ids.database_id.clone(),
+      fields: fields.into_iter().map(|(field, _)| field).collect(),
+      rows: create_row_params,
+      views: vec![CreateViewParams {
+        database_id: ids.database_id.clone(),
+        view_id: ids.view_id.clone(),
+        name: view.name,
+        layout: view.layout,
+        layout_settings,
+        group_settings,
+        sorts,
+        field_settings: field_settings.into(),
+        created_at: timestamp,
+        modified_at: timestamp,
+        ..Default::default()
+      }],
+    };
+    let database = Database::create_with_view(params, context).await?;
+    Ok((database, ids))
+  }
+
+  /// Returns the field with its new id and new option ids, along with the id of the field it
+  /// was duplicated from.
+  fn duplicate_field_with_ids(
+    &self,
+    mut field: Field,
+    ids: &mut DuplicatedDatabaseIds,
+  ) -> (Field, String) {
+    let field_id = field.id.clone();
+    let field_type = FieldType::from(field.field_type);
+    field.id = ids.field_ids[&field_id].clone();
+    match field_type {
+      FieldType::SingleSelect | FieldType::MultiSelect => {
+        if let Some(type_option) = field.get_any_type_option(field_type.type_id()) {
+          let mut type_option = SelectTypeOption::from(type_option);
+          for option in type_option.options.iter_mut() {
+            let option_id = gen_option_id();
+            ids.option_ids.insert(
+              std::mem::replace(&mut option.id, option_id.clone()),
+              option_id,
+            );
+          }
+          field
+            .type_options
+            .insert(field_type.type_id().to_string(), type_option.into());
+        }
+      },
+      FieldType::Relation => {
+        if let Some(type_option) = field.get_any_type_option(field_type.type_id()) {
+          let mut type_option = RelationTypeOption::from(type_option);
+          if type_option.database_id == self.get_database_id() {
+            type_option.database_id = ids.database_id.clone();
+          }
+          type_option.paired_field_id = None;
+          field
+            .type_options
+            .insert(field_type.type_id().to_string(), type_option.into());
+        }
+      },
+      _ => {},
+    }
+    (field, field_id)
+  }
+}
+
+/// Returns the cells of the duplicated fields, keyed by their new ids and with the ids of the
+/// options and rows in the cells mapped to the new ones.
+fn duplicate_cells(
+  cells: &Cells,
+  fields: &[(Field, String)],
+  ids: &DuplicatedDatabaseIds,
+) -> Cells {
+  fields
+    .iter()
+    .filter_map(|(field, old_field_id)| {
+      let mut cell = cells.get(old_field_id)?.clone();
+      match FieldType::from(field.field_type) {
+        FieldType::SingleSelect | FieldType::MultiSelect => {
+          let option_ids = SelectOptionIds::from(&cell)
+            .into_inner()
+            .into_iter()
+            .filter_map(|option_id| ids.option_ids.get(&option_id).cloned())
+            .collect::<Vec<_>>();
+          cell.extend(SelectOptionIds::from(option_ids).to_cell(field.field_type));
+        },
+        FieldType::Relation if is_relation_to(field, &ids.database_id) => {
+          let row_ids = RelationCellData::from(&cell)
+            .row_ids
+            .into_iter()
+            .filter_map(|row_id| ids.row_ids.get(&row_id).cloned())
+            .collect();
+          cell.extend(Cell::from(RelationCellData { row_ids }));
+        },
+        _ => {},
+      }
+      Some((field.id.clone(), cell))
+    })
+    .collect()
+}
+
+fn is_relation_to(field: &Field, database_id: &str) -> bool {
+  field
+    .get_any_type_option(FieldType::Relation.type_id())
+    .is_some_and(|type_option| RelationTypeOption::from(type_option).database_id == database_id)
+}
+
+/// Maps the field id of the setting to the new id, returns false if the field was not
+/// duplicated.
+fn map_field_id(setting: &mut HashMap<String, Any>, ids: &DuplicatedDatabaseIds) -> bool {
+  let field_id = match setting.get(FIELD_ID) {
+    Some(Any::String(field_id)) => field_id.to_string(),
+    _ => return false,
+  };
+  match ids.field_ids.get(&field_id) {
+    Some(new_field_id) => {
+      setting.insert(FIELD_ID.to_string(), new_field_id.as_str().into());
+      true
+    },
+    None => false,
+  }
+}
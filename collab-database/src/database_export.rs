@@ -0,0 +1,233 @@
+use std::io;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use csv::{Terminator, WriterBuilder};
+use futures::StreamExt;
+
+use crate::database::Database;
+use crate::entity::{FieldType, default_type_option_data_from_type};
+use crate::error::DatabaseError;
+use crate::fields::date_type_option::DateCellData;
+use crate::fields::media_type_option::MediaCellData;
+use crate::fields::{
+  Field, FieldVisibility, TypeOptionCellReader, field_visibility_in_view, type_option_cell_reader,
+};
+use crate::rows::{Cell, Row};
+use crate::template::check_list_parse::ChecklistCellData;
+use crate::template::number_parse::NumberCellData;
+use crate::template::timestamp_parse::TimestampCellData;
+use crate::views::FieldSettingsMap;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const DEFAULT_EXPORT_CHUNK_SIZE: usize = 100;
+
+/// How the numbers and dates are written by [Database::export_view_csv].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CellValueFormat {
+  /// The text displayed in the app, for example `$1,000.50` or `Aug 22, 2024`.
+  #[default]
+  Formatted,
+  /// The stored value. Numbers are written as they were entered, dates and timestamps as RFC 3339
+  /// date times in UTC. A date range is written as `start/end`.
+  Raw,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvExportOptions {
+  pub number_format: CellValueFormat,
+  pub date_format: CellValueFormat,
+  /// Writes a checklist as `x/y`, the number of checked items over the number of items, instead
+  /// of the names of its items.
+  pub checklist_as_progress: bool,
+  /// Writes a media cell as the urls of its files instead of their names.
+  pub media_as_urls: bool,
+  /// Exports the fields that are hidden in the view.
+  pub include_hidden_fields: bool,
+  /// Starts the file with a UTF-8 byte order mark and ends the lines with `\r\n`, so that
+  /// spreadsheet applications like Excel decode the non ASCII characters.
+  pub excel_compatible: bool,
+  pub delimiter: u8,
+  /// The number of rows loaded at a time.
+  pub chunk_size: usize,
+}
+
+impl Default for CsvExportOptions {
+  fn default() -> Self {
+    Self {
+      number_format: CellValueFormat::Formatted,
+      date_format: CellValueFormat::Formatted,
+      checklist_as_progress: false,
+      media_as_urls: false,
+      include_hidden_fields: false,
+      excel_compatible: false,
+      delimiter: b',',
+      chunk_size: DEFAULT_EXPORT_CHUNK_SIZE,
+    }
+  }
+}
+
+impl Database {
+  /// Writes the rows of the view as CSV, in the order of the view. The first line is the names
+  /// of the fields, in the field order of the view.
+  ///
+  /// The rows are loaded [CsvExportOptions::chunk_size] at a time and written as soon as they are
+  /// loaded, the CSV is never built in memory. Returns the number of rows written.
+  pub async fn export_view_csv<W: io::Write>(
+    &self,
+    view_id: &str,
+    writer: W,
+    options: &CsvExportOptions,
+  ) -> Result<usize, DatabaseError> {
+    let columns = self.export_columns(view_id, options)?;
+    let mut writer = writer;
+    if options.excel_compatible {
+      writer.write_all(UTF8_BOM).map_err(export_error)?;
+    }
+    let terminator = if options.excel_compatible {
+      Terminator::CRLF
+    } else {
+      Terminator::Any(b'\n')
+    };
+    let mut writer = WriterBuilder::new()
+      .delimiter(options.delimiter)
+      .terminator(terminator)
+      .from_writer(writer);
+
+    writer
+      .write_record(columns.iter().map(|column| column.field.name.as_str()))
+      .map_err(export_error)?;
+
+    let mut num_rows = 0;
+    let mut rows = Box::pin(
+      self
+        .get_rows_for_view(view_id, options.chunk_size.max(1), None, false)
+        .await,
+    );
+    while let Some(row) = rows.next().await {
+      let row = row?;
+      let record = columns
+        .iter()
+        .map(|column| column.export_cell(&row, options))
+        .collect::<Vec<_>>();
+      writer.write_record(&record).map_err(export_error)?;
+      num_rows += 1;
+    }
+    writer.flush().map_err(export_error)?;
+    Ok(num_rows)
+  }
+
+  fn export_columns(
+    &self,
+    view_id: &str,
+    options: &CsvExportOptions,
+  ) -> Result<Vec<ExportColumn>, DatabaseError> {
+    let view = self
+      .get_view(view_id)
+      .ok_or(DatabaseError::DatabaseViewNotExist)?;
+    let field_settings = self.get_field_settings::<FieldSettingsMap>(view_id, None);
+    let columns = self
+      .get_fields_in_view(view_id, None)
+      .into_iter()
+      .filter(|field| {
+        if options.include_hidden_fields {
+          return true;
+        }
+        let visibility =
+          field_visibility_in_view(field, view.layout, field_settings.get(&field.id));
+        // the hidden when empty fields are exported, finding the empty ones would require loading
+        // all the rows first.
+        visibility != FieldVisibility::AlwaysHidden
+      })
+      .map(|field| {
+        let field_type = FieldType::from(field.field_type);
+        // a field created without type option is read with the default one.
+        let reader = self.get_cell_reader(&field.id).unwrap_or_else(|| {
+          type_option_cell_reader(default_type_option_data_from_type(field_type), &field_type)
+        });
+        ExportColumn {
+          field,
+          field_type,
+          reader,
+        }
+      })
+      .collect();
+    Ok(columns)
+  }
+}
+
+struct ExportColumn {
+  field: Field,
+  field_type: FieldType,
+  reader: Box<dyn TypeOptionCellReader>,
+}
+
+impl ExportColumn {
+  fn export_cell(&self, row: &Row, options: &CsvExportOptions) -> String {
+    let Some(cell) = row.cells.get(&self.field.id) else {
+      return String::new();
+    };
+
+    let value = match self.field_type {
+      FieldType::Number if options.number_format == CellValueFormat::Raw => {
+        Some(NumberCellData::from(cell).0)
+      },
+      FieldType::DateTime if options.date_format == CellValueFormat::Raw => Some(raw_date(cell)),
+      FieldType::LastEditedTime | FieldType::CreatedTime
+        if options.date_format == CellValueFormat::Raw =>
+      {
+        Some(
+          TimestampCellData::from(cell)
+            .timestamp
+            .map(rfc3339)
+            .unwrap_or_default(),
+        )
+      },
+      FieldType::Checklist if options.checklist_as_progress => Some(checklist_progress(cell)),
+      FieldType::Media => Some(media_files(cell, options.media_as_urls)),
+      _ => None,
+    };
+
+    value.unwrap_or_else(|| self.reader.stringify_cell(cell))
+  }
+}
+
+fn raw_date(cell: &Cell) -> String {
+  let cell_data = DateCellData::from(cell);
+  let start = cell_data.timestamp.map(rfc3339).unwrap_or_default();
+  match cell_data.end_timestamp {
+    Some(end) if cell_data.is_range && !start.is_empty() => format!("{}/{}", start, rfc3339(end)),
+    _ => start,
+  }
+}
+
+fn checklist_progress(cell: &Cell) -> String {
+  let cell_data = ChecklistCellData::from(cell);
+  if cell_data.options.is_empty() {
+    return String::new();
+  }
+  format!(
+    "{}/{}",
+    cell_data.selected_options().len(),
+    cell_data.options.len()
+  )
+}
+
+/// The media cells store a list of files instead of a string, which the cell reader can't read.
+fn media_files(cell: &Cell, as_urls: bool) -> String {
+  MediaCellData::from(cell)
+    .files
+    .into_iter()
+    .map(|file| if as_urls { file.url } else { file.name })
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+fn rfc3339(timestamp: i64) -> String {
+  DateTime::<Utc>::from_timestamp(timestamp, 0)
+    .map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true))
+    .unwrap_or_default()
+}
+
+fn export_error<E: ToString>(err: E) -> DatabaseError {
+  DatabaseError::ExportData(err.to_string())
+}
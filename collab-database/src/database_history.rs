@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use collab::preclude::{Any, Map, YrsValue};
+use collab::util::AnyMapExt;
+
+use crate::database::Database;
+use crate::entity::{FieldType, default_type_option_data_from_type};
+use crate::fields::{TypeOptionCellReader, type_option_cell_reader};
+use crate::rows::{Cell, RowHistoryRetention, RowId};
+use crate::template::entity::CELL_DATA;
+
+const ROW_HISTORY_RETENTION: &str = "row_history_retention";
+
+/// A change of a cell in the history of a row, with the cells rendered as text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowHistoryEntry {
+  pub field_id: String,
+  pub old_value: String,
+  pub new_value: String,
+  /// The id of the user who changed the cell, None if the change has no client origin.
+  pub uid: Option<i64>,
+  /// The timestamp of the change, in seconds.
+  pub timestamp: i64,
+}
+
+impl Database {
+  /// Returns how many changes are kept in the history of each row.
+  pub fn get_row_history_retention(&self) -> RowHistoryRetention {
+    let txn = self.collab.transact();
+    match self.body.metas.get(&txn, ROW_HISTORY_RETENTION) {
+      Some(YrsValue::Any(Any::Map(map))) => RowHistoryRetention::from(&*map),
+      _ => RowHistoryRetention::default(),
+    }
+  }
+
+  /// Sets how many changes are kept in the history of each row. The histories are trimmed when
+  /// their rows change.
+  pub fn set_row_history_retention(&mut self, retention: RowHistoryRetention) {
+    let mut txn = self.collab.transact_mut();
+    self
+      .body
+      .metas
+      .insert(&mut txn, ROW_HISTORY_RETENTION, Any::from(retention));
+  }
+
+  /// Returns the changes of the cells of the row, from the newest to the oldest. Only the changes
+  /// of the given field are returned when `field_id` is set.
+  ///
+  /// The cells are rendered with the current type option of their field. The cells of deleted
+  /// fields are rendered as their raw data.
+  pub async fn get_row_history(
+    &self,
+    row_id: &RowId,
+    field_id: Option<&str>,
+  ) -> Vec<RowHistoryEntry> {
+    let changes = match self.body.block.get_database_row(row_id).await {
+      Some(row) => row.read().await.get_history(),
+      None => return vec![],
+    };
+
+    let mut readers: HashMap<String, Option<Box<dyn TypeOptionCellReader>>> = HashMap::new();
+    changes
+      .into_iter()
+      .rev()
+      .filter(|change| field_id.is_none_or(|field_id| change.field_id == field_id))
+      .map(|change| {
+        let reader = readers
+          .entry(change.field_id.clone())
+          .or_insert_with(|| {
+            let field = self.get_field(&change.field_id)?;
+            let field_type = FieldType::from(field.field_type);
+            let type_option = field
+              .get_any_type_option(field_type.type_id())
+              .unwrap_or_else(|| default_type_option_data_from_type(field_type));
+            Some(type_option_cell_reader(type_option, &field_type))
+          })
+          .as_deref();
+        let render = |cell: Option<Cell>| match (cell, reader) {
+          (Some(cell), Some(reader)) => reader.stringify_cell(&cell),
+          (Some(cell), None) => cell.get_as::<String>(CELL_DATA).unwrap_or_default(),
+          (None, _) => String::new(),
+        };
+        RowHistoryEntry {
+          old_value: render(change.old_value),
+          new_value: render(change.new_value),
+          field_id: change.field_id,
+          uid: change.uid,
+          timestamp: change.timestamp,
+        }
+      })
+      .collect()
+  }
+}
@@ -0,0 +1,688 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use collab::preclude::Any;
+use futures::StreamExt;
+use uuid::Uuid;
+
+use crate::database::{Database, gen_row_id};
+use crate::database_validation::CellViolation;
+use crate::entity::FieldType;
+use crate::error::DatabaseError;
+use crate::fields::date_type_option::DateCellData;
+use crate::fields::media_type_option::{MediaCellData, MediaFile, MediaFileType, MediaUploadType};
+use crate::fields::relation_type_option::RelationTypeOption;
+use crate::fields::select_type_option::{
+  SelectOption, SelectOptionColor, SelectOptionIds, SelectTypeOption,
+};
+use crate::fields::{Field, default_field_settings_by_layout_map};
+use crate::rows::{Cell, Cells, CreateRowParams, RowId, RowUpdate, new_cell_builder};
+use crate::template::check_list_parse::ChecklistCellData;
+use crate::template::date_parse::cast_string_to_timestamp;
+use crate::template::entity::CELL_DATA;
+use crate::template::number_parse::NumberCellData;
+use crate::template::option_parse::{SELECT_OPTION_COLOR_COUNT, SELECT_OPTION_SEPARATOR};
+use crate::template::relation_parse::RelationCellData;
+use crate::template::time_parse::TimeCellData;
+use crate::views::OrderObjectPosition;
+
+const CHECKED_CHECKLIST_ITEM_PREFIX: &str = "[x]";
+const UNCHECKED_CHECKLIST_ITEM_PREFIX: &str = "[ ]";
+const DEFAULT_IMPORT_CHUNK_SIZE: usize = 100;
+
+/// Where a CSV column is imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvColumnTarget {
+  /// The existing field with this id.
+  Field(String),
+  /// A new field named after the column.
+  NewField(FieldType),
+  /// The column is not imported.
+  Skip,
+}
+
+/// How the text of a CSV column is turned into cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvCellParser {
+  /// The text is kept as is.
+  Text,
+  /// A number, the thousands separators are removed.
+  Number,
+  /// `yes`, `no`, `true`, `false`, `1` or `0`, case insensitive.
+  Checkbox,
+  /// A date in one of the formats recognized when creating a database from a CSV file, or an RFC
+  /// 3339 date time. `start/end` is a date range.
+  Date,
+  /// A date in the given [chrono::format::strftime] format, with or without time.
+  DateWithFormat(String),
+  /// A duration in seconds.
+  Time,
+  /// The names of the options, separated by commas. The missing options are added to the field.
+  Select,
+  /// The names of the items, separated by commas. The checked items start with `[x]`.
+  Checklist,
+  /// The urls of the files, separated by commas.
+  Media,
+  /// The ids of the related rows, separated by commas. The rows must exist when the related
+  /// database is this database or is registered with [Database::add_related_database].
+  Relation,
+}
+
+impl CsvCellParser {
+  /// Returns the default parser of the fields of this type, or `None` if the cells of this type
+  /// can't be imported, like the created time ones.
+  pub fn from_field_type(field_type: FieldType) -> Option<Self> {
+    match field_type {
+      FieldType::RichText | FieldType::URL | FieldType::Summary | FieldType::Translate => {
+        Some(Self::Text)
+      },
+      FieldType::Number => Some(Self::Number),
+      FieldType::DateTime => Some(Self::Date),
+      FieldType::SingleSelect | FieldType::MultiSelect => Some(Self::Select),
+      FieldType::Checkbox => Some(Self::Checkbox),
+      FieldType::Checklist => Some(Self::Checklist),
+      FieldType::Time => Some(Self::Time),
+      FieldType::Media => Some(Self::Media),
+      FieldType::Relation => Some(Self::Relation),
+      FieldType::LastEditedTime | FieldType::CreatedTime => None,
+    }
+  }
+
+  /// Returns true if the parser creates the cells of the fields of this type.
+  pub fn supports(&self, field_type: FieldType) -> bool {
+    match self {
+      // the dates can be parsed with a custom format.
+      Self::DateWithFormat(_) => field_type == FieldType::DateTime,
+      parser => Self::from_field_type(field_type).as_ref() == Some(parser),
+    }
+  }
+}
+
+/// The mapping of a CSV column, identified by its header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvColumnMapping {
+  pub column: String,
+  pub target: CsvColumnTarget,
+  /// The parser of the column, the default parser of the field type when it's `None`.
+  pub parser: Option<CsvCellParser>,
+}
+
+impl CsvColumnMapping {
+  pub fn new(column: &str, target: CsvColumnTarget) -> Self {
+    Self {
+      column: column.to_string(),
+      target,
+      parser: None,
+    }
+  }
+
+  pub fn with_parser(mut self, parser: CsvCellParser) -> Self {
+    self.parser = Some(parser);
+    self
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvImportOptions {
+  /// The mappings of the columns. A column without mapping is imported into the field with the
+  /// same name, or skipped if there is none.
+  pub columns: Vec<CsvColumnMapping>,
+  /// The header of the column used to match the CSV rows with the existing rows. A CSV row
+  /// updates the row whose key cell has the same text, instead of creating a new row.
+  pub key_column: Option<String>,
+  /// Parses the CSV and reports the changes without applying them.
+  pub dry_run: bool,
+  pub delimiter: u8,
+}
+
+impl Default for CsvImportOptions {
+  fn default() -> Self {
+    Self {
+      columns: vec![],
+      key_column: None,
+      dry_run: false,
+      delimiter: b',',
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvRowAction {
+  Create,
+  Update,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvRowImport {
+  /// The line of the row in the CSV, starting at 1 for the header.
+  pub line: u64,
+  /// The id of the created or updated row. In a dry run, the created rows don't exist.
+  pub row_id: RowId,
+  pub action: CsvRowAction,
+}
+
+/// A cell that was not imported because its text couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvCellError {
+  pub line: u64,
+  pub column: String,
+  pub value: String,
+  pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CsvImportReport {
+  pub rows: Vec<CsvRowImport>,
+  /// The rows whose collab couldn't be created, they are not in [CsvImportReport::rows].
+  pub failed_rows: Vec<CsvRowImport>,
+  /// The ids and names of the created fields.
+  pub created_fields: Vec<(String, String)>,
+  /// The headers of the columns that were not imported.
+  pub skipped_columns: Vec<String>,
+  pub cell_errors: Vec<CsvCellError>,
+  /// The imported cells that violate the constraints of their fields. The rejected cells of the
+  /// updated rows are not imported.
+  pub violations: Vec<CellViolation>,
+}
+
+impl CsvImportReport {
+  pub fn num_created_rows(&self) -> usize {
+    self.count_rows(CsvRowAction::Create)
+  }
+
+  pub fn num_updated_rows(&self) -> usize {
+    self.count_rows(CsvRowAction::Update)
+  }
+
+  fn count_rows(&self, action: CsvRowAction) -> usize {
+    self.rows.iter().filter(|row| row.action == action).count()
+  }
+}
+
+impl Database {
+  /// Imports the CSV into the database, see [CsvImportOptions].
+  ///
+  /// The cells whose text can't be parsed are reported in [CsvImportReport::cell_errors] and the
+  /// rest of their row is imported. The empty cells don't replace the existing ones.
+  ///
+  /// The new rows are checked against the constraints of the fields before anything is written,
+  /// the database is left unchanged and [DatabaseError::ConstraintViolation] is returned if a
+  /// cell of a new row violates the constraints of a field in
+  /// [crate::database_validation::ConstraintMode::Enforce] mode.
+  pub async fn import_csv<R: io::Read>(
+    &mut self,
+    reader: R,
+    options: &CsvImportOptions,
+  ) -> Result<CsvImportReport, DatabaseError> {
+    let mut reader = csv::ReaderBuilder::new()
+      .delimiter(options.delimiter)
+      .from_reader(reader);
+    let headers = reader
+      .headers()
+      .map_err(|err| DatabaseError::InvalidCSV(err.to_string()))?
+      .iter()
+      .map(|header| header.to_string())
+      .collect::<Vec<_>>();
+
+    let mut report = CsvImportReport::default();
+    let mut columns = self.import_columns(&headers, options, &mut report)?;
+    let key_column_index = match &options.key_column {
+      Some(key_column) => Some(
+        columns
+          .iter()
+          .position(|column| &column.header == key_column)
+          .ok_or_else(|| {
+            DatabaseError::ImportData(format!("the key column {} is not imported", key_column))
+          })?,
+      ),
+      None => None,
+    };
+    for column in columns.iter_mut() {
+      column.relation_row_ids = self.relation_row_ids(column).await;
+    }
+    let mut row_ids_by_key = match key_column_index {
+      Some(index) => self.row_ids_by_key(&columns[index]).await,
+      None => HashMap::new(),
+    };
+
+    let mut imported_rows = vec![];
+    for record in reader.records() {
+      let record = record.map_err(|err| DatabaseError::InvalidCSV(err.to_string()))?;
+      let line = record
+        .position()
+        .map(|position| position.line())
+        .unwrap_or_default();
+      let mut cells = Cells::new();
+      for column in columns.iter_mut() {
+        let value = record.get(column.index).unwrap_or_default();
+        if value.trim().is_empty() {
+          continue;
+        }
+        match column.parse(value) {
+          Ok(cell) => {
+            cells.insert(column.field.id.clone(), cell);
+          },
+          Err(reason) => report.cell_errors.push(CsvCellError {
+            line,
+            column: column.header.clone(),
+            value: value.to_string(),
+            reason,
+          }),
+        }
+      }
+
+      let key = key_column_index
+        .and_then(|index| record.get(columns[index].index))
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty());
+      let existing_row_id = key
+        .as_ref()
+        .and_then(|key| row_ids_by_key.get(key).cloned());
+      let (row_id, action) = match existing_row_id {
+        Some(row_id) => (row_id, CsvRowAction::Update),
+        None => (gen_row_id(), CsvRowAction::Create),
+      };
+      if let Some(key) = key {
+        // the following rows with the same key update this row.
+        row_ids_by_key.insert(key, row_id.clone());
+      }
+      report.rows.push(CsvRowImport {
+        line,
+        row_id: row_id.clone(),
+        action,
+      });
+      imported_rows.push((row_id, action, cells));
+    }
+
+    if options.dry_run {
+      return Ok(report);
+    }
+
+    let database_id = self.get_database_id();
+    let mut new_rows = vec![];
+    let mut updates = vec![];
+    for (row_id, action, cells) in imported_rows {
+      match action {
+        CsvRowAction::Create => {
+          new_rows.push(CreateRowParams::new(row_id, database_id.clone()).with_cells(cells))
+        },
+        CsvRowAction::Update => updates.push((row_id, move |row: RowUpdate| {
+          row.update_cells(|update| {
+            cells.into_iter().fold(update, |update, (field_id, cell)| {
+              update.insert_cell(&field_id, cell)
+            });
+          });
+        })),
+      }
+    }
+    // checks the new rows before anything is written.
+    let fields = self.constrained_fields();
+    self.check_new_rows(&new_rows, &fields).await?;
+
+    for column in columns.iter_mut() {
+      if column.is_new {
+        // the options added while parsing the cells are only in the select column.
+        if let Some(select) = column.select.as_ref() {
+          let field_type = column.field.field_type;
+          column
+            .field
+            .type_options
+            .insert(field_type.to_string(), select.type_option.clone().into());
+        }
+        self.create_field(
+          None,
+          column.field.clone(),
+          &OrderObjectPosition::End,
+          default_field_settings_by_layout_map(),
+        );
+      } else if let Some(select) = column.select.as_ref().filter(|select| select.changed) {
+        let field_type = column.field.field_type;
+        let type_option = select.type_option.clone();
+        self.update_field(&column.field.id, |update| {
+          update.set_type_option(field_type, Some(type_option.into()));
+        });
+      }
+    }
+
+    let (row_orders, violations) = self.create_rows(new_rows).await?;
+    report.violations.extend(violations);
+    let created_row_ids = row_orders
+      .into_iter()
+      .map(|row_order| row_order.id)
+      .collect::<HashSet<_>>();
+    let (rows, failed_rows) = report
+      .rows
+      .drain(..)
+      .partition(|row| row.action == CsvRowAction::Update || created_row_ids.contains(&row.row_id));
+    report.rows = rows;
+    report.failed_rows = failed_rows;
+
+    let violations = self.update_rows(updates).await;
+    report.violations.extend(violations);
+    Ok(report)
+  }
+
+  fn import_columns(
+    &self,
+    headers: &[String],
+    options: &CsvImportOptions,
+    report: &mut CsvImportReport,
+  ) -> Result<Vec<ImportColumn>, DatabaseError> {
+    let fields = self.get_all_fields();
+    let mut columns = vec![];
+    for (index, header) in headers.iter().enumerate() {
+      let mapping = options
+        .columns
+        .iter()
+        .find(|mapping| &mapping.column == header);
+      let (field, is_new) = match mapping.map(|mapping| &mapping.target) {
+        Some(CsvColumnTarget::Field(field_id)) => {
+          let field = fields
+            .iter()
+            .find(|field| &field.id == field_id)
+            .ok_or_else(|| {
+              DatabaseError::ImportData(format!("the field {} is not found", field_id))
+            })?;
+          (field.clone(), false)
+        },
+        Some(CsvColumnTarget::NewField(field_type)) => {
+          (Field::from_field_type(header, *field_type, false), true)
+        },
+        Some(CsvColumnTarget::Skip) => {
+          report.skipped_columns.push(header.clone());
+          continue;
+        },
+        None => match fields.iter().find(|field| &field.name == header) {
+          Some(field) => (field.clone(), false),
+          None => {
+            report.skipped_columns.push(header.clone());
+            continue;
+          },
+        },
+      };
+
+      let field_type = FieldType::from(field.field_type);
+      let parser = mapping
+        .and_then(|mapping| mapping.parser.clone())
+        .or_else(|| CsvCellParser::from_field_type(field_type))
+        .filter(|parser| parser.supports(field_type))
+        .ok_or_else(|| {
+          DatabaseError::ImportData(format!(
+            "the column {} can't be imported into the {:?} field {}",
+            header, field_type, field.name
+          ))
+        })?;
+      let select = (parser == CsvCellParser::Select).then(|| SelectColumn {
+        type_option: field
+          .get_type_option::<SelectTypeOption>(field_type)
+          .unwrap_or_default(),
+        changed: false,
+      });
+      if is_new {
+        report
+          .created_fields
+          .push((field.id.clone(), field.name.clone()));
+      }
+      columns.push(ImportColumn {
+        index,
+        header: header.clone(),
+        field,
+        field_type,
+        is_new,
+        parser,
+        select,
+        relation_row_ids: None,
+      });
+    }
+    Ok(columns)
+  }
+
+  /// Returns the ids of the rows that the cells of a relation column can link, or `None` if they
+  /// can't be checked because the related database is not registered.
+  async fn relation_row_ids(&self, column: &ImportColumn) -> Option<HashSet<RowId>> {
+    if column.parser != CsvCellParser::Relation {
+      return None;
+    }
+    let related_database_id = column
+      .field
+      .get_type_option::<RelationTypeOption>(FieldType::Relation.type_id())?
+      .database_id;
+    let row_orders = if related_database_id == self.get_database_id() {
+      self.get_all_row_orders().await
+    } else {
+      let related = self.body.related_databases.get(&related_database_id)?;
+      let related = related.read().await;
+      related.get_all_row_orders().await
+    };
+    Some(
+      row_orders
+        .into_iter()
+        .map(|row_order| row_order.id)
+        .collect(),
+    )
+  }
+
+  /// Returns the ids of the rows by the text of their cell in the key column.
+  async fn row_ids_by_key(&self, column: &ImportColumn) -> HashMap<String, RowId> {
+    let mut row_ids_by_key = HashMap::new();
+    if column.is_new {
+      return row_ids_by_key;
+    }
+    let Some(reader) = self.get_cell_reader(&column.field.id) else {
+      return row_ids_by_key;
+    };
+    let mut rows = Box::pin(
+      self
+        .get_all_rows(DEFAULT_IMPORT_CHUNK_SIZE, None, false)
+        .await,
+    );
+    while let Some(row) = rows.next().await {
+      let Ok(row) = row else {
+        continue;
+      };
+      if let Some(cell) = row.cells.get(&column.field.id) {
+        let key = reader.stringify_cell(cell).trim().to_string();
+        if !key.is_empty() {
+          row_ids_by_key.entry(key).or_insert(row.id);
+        }
+      }
+    }
+    row_ids_by_key
+  }
+}
+
+struct SelectColumn {
+  type_option: SelectTypeOption,
+  /// True if options were added to the type option.
+  changed: bool,
+}
+
+struct ImportColumn {
+  index: usize,
+  header: String,
+  field: Field,
+  field_type: FieldType,
+  is_new: bool,
+  parser: CsvCellParser,
+  select: Option<SelectColumn>,
+  /// The ids of the rows that the cells of a relation column can link, see
+  /// [Database::relation_row_ids].
+  relation_row_ids: Option<HashSet<RowId>>,
+}
+
+impl ImportColumn {
+  fn parse(&mut self, value: &str) -> Result<Cell, String> {
+    let field_type = self.field_type;
+    let text = value.trim();
+    match &self.parser {
+      CsvCellParser::Text => Ok(string_cell(field_type, value.to_string())),
+      CsvCellParser::Number => {
+        let number = text.replace(',', "");
+        number
+          .parse::<f64>()
+          .map_err(|_| format!("{} is not a number", text))?;
+        Ok(NumberCellData(number).into())
+      },
+      CsvCellParser::Checkbox => {
+        let checked = match text.to_lowercase().as_str() {
+          "1" | "true" | "yes" => true,
+          "0" | "false" | "no" => false,
+          _ => return Err(format!("{} is not a checkbox value", text)),
+        };
+        Ok(string_cell(field_type, checked.to_string()))
+      },
+      CsvCellParser::Date => {
+        parse_date(text).ok_or_else(|| format!("{} is not a supported date", text))
+      },
+      CsvCellParser::DateWithFormat(format) => {
+        let (timestamp, include_time) = parse_date_with_format(text, format)
+          .ok_or_else(|| format!("{} doesn't match the date format {}", text, format))?;
+        let cell_data = DateCellData::new(timestamp, include_time, false, String::new());
+        Ok(Cell::from(&cell_data))
+      },
+      CsvCellParser::Time => {
+        let cell_data = TimeCellData::from(text);
+        if cell_data.0.is_none() {
+          return Err(format!("{} is not a number of seconds", text));
+        }
+        Ok(Cell::from(&cell_data))
+      },
+      CsvCellParser::Select => {
+        let select = self
+          .select
+          .as_mut()
+          .ok_or_else(|| "the field has no options".to_string())?;
+        let names = split_list(text);
+        if field_type == FieldType::SingleSelect && names.len() > 1 {
+          return Err("a single select cell has only one option".to_string());
+        }
+        let option_ids = names
+          .into_iter()
+          .map(|name| select.get_or_create_option_id(name))
+          .collect::<Vec<_>>();
+        Ok(SelectOptionIds::from(option_ids).to_cell(field_type))
+      },
+      CsvCellParser::Checklist => {
+        let mut options = vec![];
+        let mut selected_options = vec![];
+        for item in split_list(text) {
+          if let Some(name) = item.strip_prefix(CHECKED_CHECKLIST_ITEM_PREFIX) {
+            options.push(name.trim().to_string());
+            selected_options.push(name.trim().to_string());
+          } else {
+            let name = item
+              .strip_prefix(UNCHECKED_CHECKLIST_ITEM_PREFIX)
+              .unwrap_or(item);
+            options.push(name.trim().to_string());
+          }
+        }
+        Ok(ChecklistCellData::from((options, selected_options)).into())
+      },
+      CsvCellParser::Media => {
+        let files = split_list(text)
+          .into_iter()
+          .map(|url| {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+              return Err(format!("{} is not an url", url));
+            }
+            let name = url
+              .trim_end_matches('/')
+              .rsplit('/')
+              .next()
+              .unwrap_or(url)
+              .to_string();
+            Ok(MediaFile::new(
+              name,
+              url.to_string(),
+              MediaUploadType::Network,
+              MediaFileType::Other,
+            ))
+          })
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(MediaCellData { files }.into())
+      },
+      CsvCellParser::Relation => {
+        let row_ids = split_list(text)
+          .into_iter()
+          .map(|row_id| {
+            if Uuid::parse_str(row_id).is_err() {
+              return Err(format!("{} is not a row id", row_id));
+            }
+            let row_id = RowId::from(row_id.to_string());
+            match &self.relation_row_ids {
+              Some(row_ids) if !row_ids.contains(&row_id) => {
+                Err(format!("the row {} is not found", row_id))
+              },
+              _ => Ok(row_id),
+            }
+          })
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(RelationCellData { row_ids }.into())
+      },
+    }
+  }
+}
+
+impl SelectColumn {
+  fn get_or_create_option_id(&mut self, name: &str) -> String {
+    if let Some(option) = self
+      .type_option
+      .options
+      .iter()
+      .find(|option| option.name == name)
+    {
+      return option.id.clone();
+    }
+    let color = SelectOptionColor::from(self.type_option.options.len() % SELECT_OPTION_COLOR_COUNT);
+    let option = SelectOption::with_color(name, color);
+    let option_id = option.id.clone();
+    self.type_option.options.push(option);
+    self.changed = true;
+    option_id
+  }
+}
+
+fn string_cell(field_type: FieldType, data: String) -> Cell {
+  let mut cell = new_cell_builder(field_type);
+  cell.insert(CELL_DATA.into(), Any::from(data));
+  cell
+}
+
+fn split_list(text: &str) -> Vec<&str> {
+  text
+    .split(SELECT_OPTION_SEPARATOR)
+    .map(|item| item.trim())
+    .filter(|item| !item.is_empty())
+    .collect()
+}
+
+fn parse_date(text: &str) -> Option<Cell> {
+  if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+    let cell_data = DateCellData::from_timestamp_include_time(date.timestamp());
+    return Some(Cell::from(&cell_data));
+  }
+  // the ranges written by the CSV export with raw dates.
+  if let Some((start, end)) = text.split_once('/') {
+    if let (Ok(start), Ok(end)) = (
+      DateTime::parse_from_rfc3339(start),
+      DateTime::parse_from_rfc3339(end),
+    ) {
+      let mut cell_data = DateCellData::new(start.timestamp(), true, true, String::new());
+      cell_data.end_timestamp = Some(end.timestamp());
+      return Some(Cell::from(&cell_data));
+    }
+  }
+  let timestamp = cast_string_to_timestamp(text)?;
+  Some(Cell::from(&DateCellData::from_timestamp(timestamp)))
+}
+
+/// Returns the timestamp of the date and whether the format includes the time.
+fn parse_date_with_format(text: &str, format: &str) -> Option<(i64, bool)> {
+  if let Ok(date_time) = NaiveDateTime::parse_from_str(text, format) {
+    return Some((Utc.from_utc_datetime(&date_time).timestamp(), true));
+  }
+  let date = NaiveDate::parse_from_str(text, format).ok()?;
+  let date_time = date.and_hms_opt(0, 0, 0)?;
+  Some((Utc.from_utc_datetime(&date_time).timestamp(), false))
+}
@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+
+use collab::lock::RwLock;
+use collab::preclude::Any;
+use futures::StreamExt;
+use tracing::{error, warn};
+
+use crate::database::{Database, gen_field_id};
+use crate::entity::FieldType;
+use crate::error::DatabaseError;
+use crate::fields::relation_type_option::{RELATION_PAIRED_FIELD_ID, RelationTypeOption};
+use crate::fields::{Field, TypeOptionData, default_field_settings_by_layout_map};
+use crate::rows::{Cell, Cells, RowId};
+use crate::template::relation_parse::RelationCellData;
+use crate::views::OrderObjectPosition;
+
+const RELATION_CHUNK_SIZE: usize = 100;
+
+/// A two-way relation field of a database and the field of the related database that holds its
+/// back-references.
+#[derive(Debug, Clone)]
+pub(crate) struct TwoWayRelation {
+  pub field_id: String,
+  pub related_database_id: String,
+  pub paired_field_id: String,
+}
+
+impl TwoWayRelation {
+  fn from_field(field: Field) -> Option<Self> {
+    if FieldType::from(field.field_type) != FieldType::Relation {
+      return None;
+    }
+    let type_option = field.get_type_option::<RelationTypeOption>(FieldType::Relation.type_id())?;
+    Some(Self {
+      field_id: field.id,
+      related_database_id: type_option.database_id,
+      paired_field_id: type_option.paired_field_id?,
+    })
+  }
+}
+
+/// The cells of a row before and after a change.
+pub(crate) struct RowCellsChange {
+  pub row_id: RowId,
+  pub previous_cells: Cells,
+  pub cells: Cells,
+}
+
+/// The databases that a database has two-way relations with, see
+/// [Database::add_related_database]. The handles are weak so that two related databases don't
+/// keep each other alive.
+#[derive(Default)]
+pub(crate) struct RelatedDatabases {
+  databases: HashMap<String, Weak<RwLock<Database>>>,
+}
+
+impl RelatedDatabases {
+  fn insert(&mut self, database_id: String, database: &Arc<RwLock<Database>>) {
+    self.databases.insert(database_id, Arc::downgrade(database));
+  }
+
+  pub(crate) fn get(&self, database_id: &str) -> Option<Arc<RwLock<Database>>> {
+    self.databases.get(database_id)?.upgrade()
+  }
+}
+
+impl Database {
+  /// Creates a two-way relation between this database and the `related` database.
+  ///
+  /// The relation `field` is added to this database and a relation field named
+  /// `paired_field_name` is added to the related database. When a row of one database links a row
+  /// of the other one, the linked row gets a back-reference in the paired field, and the
+  /// back-reference is removed when the link is removed or the row is deleted. The back-references
+  /// are only written once the databases are registered with [Database::add_related_database].
+  ///
+  /// Returns the paired field.
+  pub fn create_two_way_relation(
+    &mut self,
+    field: Field,
+    related: &mut Database,
+    paired_field_name: &str,
+  ) -> Result<Field, DatabaseError> {
+    if FieldType::from(field.field_type) != FieldType::Relation {
+      return Err(DatabaseError::InvalidRelation(format!(
+        "{} is not a relation field",
+        field.id
+      )));
+    }
+    if self.get_field(&field.id).is_some() {
+      return Err(DatabaseError::InvalidRelation(format!(
+        "{} already exists",
+        field.id
+      )));
+    }
+    let database_id = self.get_database_id();
+    let related_database_id = related.get_database_id();
+    if database_id == related_database_id {
+      return Err(DatabaseError::InvalidRelation(
+        "a database can't have a two-way relation with itself".to_string(),
+      ));
+    }
+
+    let paired_field = Field::new(
+      gen_field_id(),
+      paired_field_name.to_string(),
+      FieldType::Relation.into(),
+      false,
+    )
+    .with_type_option_data(
+      FieldType::Relation,
+      RelationTypeOption {
+        database_id,
+        paired_field_id: Some(field.id.clone()),
+      }
+      .into(),
+    );
+    let field = field.with_type_option_data(
+      FieldType::Relation,
+      RelationTypeOption {
+        database_id: related_database_id,
+        paired_field_id: Some(paired_field.id.clone()),
+      }
+      .into(),
+    );
+
+    self.create_field(
+      None,
+      field,
+      &OrderObjectPosition::End,
+      default_field_settings_by_layout_map(),
+    );
+    related.create_field(
+      None,
+      paired_field.clone(),
+      &OrderObjectPosition::End,
+      default_field_settings_by_layout_map(),
+    );
+    Ok(paired_field)
+  }
+
+  /// Deletes a two-way relation field and its paired field in the `related` database. When the
+  /// field is deleted with [Database::delete_field], the paired field becomes a one-way relation,
+  /// see [Database::unpair_deleted_relation].
+  pub fn delete_two_way_relation(
+    &mut self,
+    field_id: &str,
+    related: &mut Database,
+  ) -> Result<(), DatabaseError> {
+    let relation = self.two_way_relation(field_id).ok_or_else(|| {
+      DatabaseError::InvalidRelation(format!("{} is not a two-way relation field", field_id))
+    })?;
+    if relation.related_database_id != related.get_database_id() {
+      return Err(DatabaseError::InvalidRelation(format!(
+        "{} is not related to database {}",
+        field_id,
+        related.get_database_id()
+      )));
+    }
+
+    self.delete_field(field_id);
+    related.delete_field(&relation.paired_field_id);
+    Ok(())
+  }
+
+  /// Registers a database that this database has two-way relations with. The back-references in
+  /// the rows of the related database are written through it, so its constraints, row history and
+  /// notifications apply. The back-references in databases that are not registered are not
+  /// written.
+  ///
+  /// The related database is locked while its back-references are written, it must not be locked
+  /// by the caller of the methods that change the rows of this database.
+  pub async fn add_related_database(&mut self, related: &Arc<RwLock<Database>>) {
+    let database_id = related.read().await.get_database_id();
+    if database_id != self.get_database_id() {
+      self.body.related_databases.insert(database_id, related);
+    }
+  }
+
+  pub(crate) fn two_way_relation(&self, field_id: &str) -> Option<TwoWayRelation> {
+    self
+      .get_field(field_id)
+      .and_then(TwoWayRelation::from_field)
+  }
+
+  pub(crate) fn two_way_relations(&self) -> Vec<TwoWayRelation> {
+    self
+      .get_all_fields()
+      .into_iter()
+      .filter_map(TwoWayRelation::from_field)
+      .collect()
+  }
+
+  /// Adds or removes the back-references of the changed rows in the linked rows. The
+  /// back-references of each relation are written in a single pass over the related database,
+  /// with one write per linked row.
+  pub(crate) async fn update_back_references(
+    &mut self,
+    relations: &[TwoWayRelation],
+    changes: &[RowCellsChange],
+  ) {
+    for relation in relations {
+      let links = linked_row_changes(&relation.field_id, changes);
+      if links.is_empty() {
+        continue;
+      }
+
+      let Some(related) = self
+        .body
+        .related_databases
+        .get(&relation.related_database_id)
+      else {
+        warn!(
+          "The back-references of {} are not written, database {} is not registered",
+          relation.field_id, relation.related_database_id
+        );
+        continue;
+      };
+      let mut related = related.write().await;
+      if !related.is_paired_relation(&relation.paired_field_id, &relation.field_id) {
+        // the paired field was deleted, the field becomes a one-way relation.
+        self.clear_paired_field(&relation.field_id);
+        continue;
+      }
+      related
+        .write_back_references(&relation.paired_field_id, links)
+        .await;
+    }
+  }
+
+  /// Adds or removes the back-references in the paired field of the linked rows of this
+  /// database. The back-references of the paired field are not updated in return.
+  async fn write_back_references(
+    &mut self,
+    paired_field_id: &str,
+    links: Vec<(RowId, Vec<(RowId, bool)>)>,
+  ) {
+    let fields = self.constrained_fields();
+    let retention = self.get_row_history_retention();
+    for (linked_row_id, row_changes) in links {
+      let previous_row_ids = self
+        .body
+        .block
+        .get_cell(&linked_row_id, paired_field_id)
+        .await
+        .map(|cell| RelationCellData::from(&cell).row_ids)
+        .unwrap_or_default();
+      let mut row_ids = previous_row_ids.clone();
+      for (row_id, is_linked) in row_changes {
+        if is_linked && !row_ids.contains(&row_id) {
+          row_ids.push(row_id);
+        } else if !is_linked {
+          row_ids.retain(|id| id != &row_id);
+        }
+      }
+      if row_ids == previous_row_ids {
+        continue;
+      }
+
+      let cell = Cell::from(RelationCellData { row_ids });
+      self
+        .write_row_update(
+          linked_row_id,
+          |row| {
+            row.update_cells(|update| {
+              update.insert_cell(paired_field_id, cell);
+            });
+          },
+          &fields,
+          &[],
+          &retention,
+        )
+        .await;
+    }
+  }
+
+  /// Unpairs the paired field of a deleted two-way relation field and clears the back-references
+  /// in its cells. The related database is written in the background, so it can be locked by the
+  /// caller. A paired field in a database that is not registered with
+  /// [Database::add_related_database] becomes a one-way relation the next time one of its
+  /// back-references is written.
+  pub(crate) fn unpair_deleted_relation(&self, relation: TwoWayRelation) {
+    let Some(related) = self
+      .body
+      .related_databases
+      .get(&relation.related_database_id)
+    else {
+      return;
+    };
+    tokio::spawn(async move {
+      related
+        .write()
+        .await
+        .unpair_relation(&relation.paired_field_id, &relation.field_id)
+        .await;
+    });
+  }
+
+  /// Unpairs the relation field from the deleted field and clears the back-references in its
+  /// cells.
+  async fn unpair_relation(&mut self, field_id: &str, deleted_field_id: &str) {
+    if !self.is_paired_relation(field_id, deleted_field_id) {
+      return;
+    }
+    self.clear_paired_field(field_id);
+
+    let mut row_ids = vec![];
+    {
+      let mut rows = Box::pin(self.get_all_rows(RELATION_CHUNK_SIZE, None, false).await);
+      while let Some(row) = rows.next().await {
+        match row {
+          Ok(row) if !linked_row_ids(row.cells.get(field_id)).is_empty() => row_ids.push(row.id),
+          Ok(_) => {},
+          Err(err) => error!(
+            "Failed to read the back-references of {}: {}",
+            field_id, err
+          ),
+        }
+      }
+    }
+    let fields = self.constrained_fields();
+    let retention = self.get_row_history_retention();
+    for row_id in row_ids {
+      let cell = Cell::from(RelationCellData { row_ids: vec![] });
+      self
+        .write_row_update(
+          row_id,
+          |row| {
+            row.update_cells(|update| {
+              update.insert_cell(field_id, cell);
+            });
+          },
+          &fields,
+          &[],
+          &retention,
+        )
+        .await;
+    }
+  }
+
+  /// Returns true if the field is a relation field paired with `paired_field_id`.
+  fn is_paired_relation(&self, field_id: &str, paired_field_id: &str) -> bool {
+    self
+      .get_field(field_id)
+      .and_then(|field| field.get_type_option::<RelationTypeOption>(FieldType::Relation.type_id()))
+      .is_some_and(|type_option| type_option.paired_field_id.as_deref() == Some(paired_field_id))
+  }
+
+  fn clear_paired_field(&mut self, field_id: &str) {
+    // an empty paired field id is read as no paired field
+    let type_option = TypeOptionData::from([(RELATION_PAIRED_FIELD_ID.to_string(), Any::from(""))]);
+    self.update_field(field_id, |update| {
+      update.update_type_options(|type_options| {
+        type_options.update(&FieldType::Relation.type_id(), type_option);
+      });
+    });
+  }
+}
+
+/// Returns the rows linked or unlinked by the changes in the relation field, with the rows that
+/// link or unlink them, in the order of the changes.
+fn linked_row_changes(
+  field_id: &str,
+  changes: &[RowCellsChange],
+) -> Vec<(RowId, Vec<(RowId, bool)>)> {
+  let mut links: Vec<(RowId, Vec<(RowId, bool)>)> = vec![];
+  let mut positions: HashMap<RowId, usize> = HashMap::new();
+  for change in changes {
+    let previous = linked_row_ids(change.previous_cells.get(field_id));
+    let current = linked_row_ids(change.cells.get(field_id));
+    let added = current
+      .iter()
+      .filter(|id| !previous.contains(id))
+      .map(|id| (id, true));
+    let removed = previous
+      .iter()
+      .filter(|id| !current.contains(id))
+      .map(|id| (id, false));
+    for (linked_row_id, is_linked) in added.chain(removed) {
+      let position = *positions.entry(linked_row_id.clone()).or_insert_with(|| {
+        links.push((linked_row_id.clone(), vec![]));
+        links.len() - 1
+      });
+      links[position].1.push((change.row_id.clone(), is_linked));
+    }
+  }
+  links
+}
+
+fn linked_row_ids(cell: Option<&Cell>) -> Vec<RowId> {
+  cell
+    .map(|cell| RelationCellData::from(cell).row_ids)
+    .unwrap_or_default()
+}
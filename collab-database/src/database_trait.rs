@@ -19,6 +19,7 @@ use rayon::prelude::*;
 use std::borrow::BorrowMut;
 use std::collections::HashMap;
 use std::sync::Arc;
+use tracing::error;
 use yrs::block::ClientID;
 
 // Database holder tracks initialization status and holds the database reference
@@ -106,6 +107,28 @@ pub trait DatabaseRowCollabService: Send + Sync + 'static {
     sender: Option<RowChangeSender>,
     auto_fetch: bool,
   ) -> Result<HashMap<RowId, Arc<RwLock<DatabaseRow>>>, DatabaseError>;
+
+  /// Creates the collabs of the rows in a batch. The rows that fail to be created are not in the
+  /// returned map. The default implementation creates the rows one by one.
+  async fn batch_create_arc_database_row(
+    &self,
+    rows: Vec<(RowId, DatabaseRowDataVariant)>,
+    sender: Option<RowChangeSender>,
+  ) -> HashMap<RowId, Arc<RwLock<DatabaseRow>>> {
+    let mut created_rows = HashMap::new();
+    for (row_id, data) in rows {
+      match self
+        .create_arc_database_row(&row_id, data, sender.clone())
+        .await
+      {
+        Ok(database_row) => {
+          created_rows.insert(row_id, database_row);
+        },
+        Err(err) => error!("Failed to create row {}: {}", row_id, err),
+      }
+    }
+    created_rows
+  }
 }
 
 #[async_trait]
@@ -323,6 +346,46 @@ where
 
     Ok(result)
   }
+
+  async fn batch_create_arc_database_row(
+    &self,
+    rows: Vec<(RowId, DatabaseRowDataVariant)>,
+    sender: Option<RowChangeSender>,
+  ) -> HashMap<RowId, Arc<RwLock<DatabaseRow>>> {
+    let client_id = self.reader_client_id().await;
+    let encoded_rows = rows
+      .into_par_iter()
+      .map(|(row_id, data)| (row_id, data.into_encode_collab(client_id)))
+      .collect::<Vec<_>>();
+
+    let persistence = self.reader_persistence();
+    let futures = encoded_rows.into_iter().map(|(row_id, data)| {
+      let persistence = persistence.clone();
+      let sender = sender.clone();
+      async move {
+        if let Some(persistence) = persistence {
+          persistence.upsert_collab(&row_id, data.clone())?;
+        }
+        let collab = build_collab(client_id, &row_id, CollabType::DatabaseRow, data).await?;
+        let database_row = DatabaseRow::open(row_id.clone(), collab, sender)?;
+        Ok::<_, DatabaseError>((row_id, Arc::new(RwLock::new(database_row))))
+      }
+    });
+
+    let mut created_rows = HashMap::new();
+    for result in join_all(futures).await {
+      match result {
+        Ok((row_id, database_row)) => {
+          if let Some(cache) = self.database_row_cache() {
+            cache.insert(row_id.clone(), database_row.clone());
+          }
+          created_rows.insert(row_id, database_row);
+        },
+        Err(err) => error!("Failed to create row: {}", err),
+      }
+    }
+    created_rows
+  }
 }
 async fn build_collab(
   client_id: ClientID,
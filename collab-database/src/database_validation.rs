@@ -0,0 +1,640 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use collab::preclude::Any;
+use collab::util::AnyMapExt;
+use fancy_regex::Regex;
+use futures::StreamExt;
+
+use crate::database::Database;
+use crate::entity::{FieldType, default_type_option_data_from_type};
+use crate::error::DatabaseError;
+use crate::fields::date_type_option::DateCellData;
+use crate::fields::media_type_option::MediaCellData;
+use crate::fields::select_type_option::SelectOptionIds;
+use crate::fields::{Field, TypeOptionCellReader, TypeOptionData, type_option_cell_reader};
+use crate::rows::{Cell, Cells, RowId};
+use crate::template::check_list_parse::ChecklistCellData;
+use crate::template::number_parse::NumberCellData;
+use crate::template::relation_parse::RelationCellData;
+
+/// The key of the [FieldConstraints] in the type option of a field.
+pub const FIELD_CONSTRAINTS: &str = "constraints";
+const CONSTRAINT_MODE: &str = "mode";
+const CONSTRAINT_REQUIRED: &str = "required";
+const CONSTRAINT_UNIQUE: &str = "unique";
+const CONSTRAINT_MIN: &str = "min";
+const CONSTRAINT_MAX: &str = "max";
+const CONSTRAINT_PATTERN: &str = "pattern";
+const CONSTRAINT_MAX_SELECTIONS: &str = "max_selections";
+const VALIDATION_CHUNK_SIZE: usize = 100;
+
+/// What happens when a cell written with [Database::create_row] or [Database::update_row]
+/// violates the constraints of its field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ConstraintMode {
+  /// The cell is written and the violation is reported.
+  #[default]
+  Report = 0,
+  /// The row is not created, or the cell keeps its previous value.
+  Enforce = 1,
+}
+
+impl From<i64> for ConstraintMode {
+  fn from(value: i64) -> Self {
+    match value {
+      1 => ConstraintMode::Enforce,
+      _ => ConstraintMode::Report,
+    }
+  }
+}
+
+/// The constraints on the cells of a field, stored in the type option of the field under
+/// [FIELD_CONSTRAINTS].
+///
+/// The constraints are checked when the rows are written with the [Database], the cells changed
+/// by the other collaborators are only checked by [Database::validate_cells].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldConstraints {
+  pub mode: ConstraintMode,
+  /// The cells can't be empty.
+  pub required: bool,
+  /// Two rows of the database can't have the same text in their cells.
+  pub unique: bool,
+  /// The minimum of a number, or the earliest timestamp of a date, in seconds.
+  pub min: Option<f64>,
+  /// The maximum of a number, or the latest timestamp of a date, in seconds.
+  pub max: Option<f64>,
+  /// A regular expression that the text of a text or URL cell matches.
+  pub pattern: Option<String>,
+  /// The maximum number of options of a multi-select cell.
+  pub max_selections: Option<usize>,
+}
+
+impl FieldConstraints {
+  pub fn is_empty(&self) -> bool {
+    !self.required
+      && !self.unique
+      && self.min.is_none()
+      && self.max.is_none()
+      && self.pattern.is_none()
+      && self.max_selections.is_none()
+  }
+
+  pub fn from_type_option(type_option: &TypeOptionData) -> Self {
+    let Some(Any::Map(map)) = type_option.get(FIELD_CONSTRAINTS) else {
+      return Self::default();
+    };
+    Self {
+      mode: map
+        .get_as::<i64>(CONSTRAINT_MODE)
+        .map(ConstraintMode::from)
+        .unwrap_or_default(),
+      required: map.get_as(CONSTRAINT_REQUIRED).unwrap_or_default(),
+      unique: map.get_as(CONSTRAINT_UNIQUE).unwrap_or_default(),
+      min: map.get_as(CONSTRAINT_MIN),
+      max: map.get_as(CONSTRAINT_MAX),
+      pattern: map
+        .get_as::<String>(CONSTRAINT_PATTERN)
+        .filter(|pattern| !pattern.is_empty()),
+      max_selections: map
+        .get_as::<i64>(CONSTRAINT_MAX_SELECTIONS)
+        .map(|max_selections| max_selections.max(0) as usize),
+    }
+  }
+
+  fn to_any(&self) -> Any {
+    let optional_number = |value: Option<f64>| value.map(Any::Number).unwrap_or(Any::Null);
+    Any::from(HashMap::from([
+      (CONSTRAINT_MODE.to_string(), Any::BigInt(self.mode as i64)),
+      (CONSTRAINT_REQUIRED.to_string(), Any::Bool(self.required)),
+      (CONSTRAINT_UNIQUE.to_string(), Any::Bool(self.unique)),
+      (CONSTRAINT_MIN.to_string(), optional_number(self.min)),
+      (CONSTRAINT_MAX.to_string(), optional_number(self.max)),
+      (
+        CONSTRAINT_PATTERN.to_string(),
+        self.pattern.clone().map(Any::from).unwrap_or(Any::Null),
+      ),
+      (
+        CONSTRAINT_MAX_SELECTIONS.to_string(),
+        self
+          .max_selections
+          .map(|max_selections| Any::BigInt(max_selections as i64))
+          .unwrap_or(Any::Null),
+      ),
+    ]))
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstraintViolation {
+  Required,
+  /// Another row has the same text.
+  Duplicate,
+  /// The text of a number cell is not a number.
+  InvalidNumber,
+  BelowMin(f64),
+  AboveMax(f64),
+  PatternMismatch(String),
+  TooManySelections(usize),
+}
+
+impl fmt::Display for ConstraintViolation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ConstraintViolation::Required => write!(f, "the cell is required"),
+      ConstraintViolation::Duplicate => write!(f, "another row has the same value"),
+      ConstraintViolation::InvalidNumber => write!(f, "the cell is not a number"),
+      ConstraintViolation::BelowMin(min) => write!(f, "the value is less than {}", min),
+      ConstraintViolation::AboveMax(max) => write!(f, "the value is greater than {}", max),
+      ConstraintViolation::PatternMismatch(pattern) => {
+        write!(f, "the text doesn't match {}", pattern)
+      },
+      ConstraintViolation::TooManySelections(max) => {
+        write!(f, "more than {} options are selected", max)
+      },
+    }
+  }
+}
+
+/// A cell that violates a constraint of its field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellViolation {
+  pub row_id: RowId,
+  pub field_id: String,
+  pub violation: ConstraintViolation,
+  /// True if the change of the cell was rejected, see [ConstraintMode::Enforce].
+  pub rejected: bool,
+}
+
+impl Database {
+  /// Returns the constraints of the field, or `None` if the field doesn't exist.
+  pub fn get_field_constraints(&self, field_id: &str) -> Option<FieldConstraints> {
+    let field = self.get_field(field_id)?;
+    let field_type = FieldType::from(field.field_type);
+    Some(
+      field
+        .get_any_type_option(field_type.type_id())
+        .map(|type_option| FieldConstraints::from_type_option(&type_option))
+        .unwrap_or_default(),
+    )
+  }
+
+  /// Replaces the constraints of the field. The cells that already violate them are not changed,
+  /// use [Database::validate_cells] to find them.
+  pub fn set_field_constraints(
+    &mut self,
+    field_id: &str,
+    constraints: FieldConstraints,
+  ) -> Result<(), DatabaseError> {
+    let field = self
+      .get_field(field_id)
+      .ok_or_else(|| DatabaseError::InvalidConstraint(format!("field {} not found", field_id)))?;
+    if let Some(pattern) = &constraints.pattern {
+      Regex::new(pattern).map_err(|err| DatabaseError::InvalidConstraint(err.to_string()))?;
+    }
+    if let (Some(min), Some(max)) = (constraints.min, constraints.max) {
+      if min > max {
+        return Err(DatabaseError::InvalidConstraint(format!(
+          "the minimum {} is greater than the maximum {}",
+          min, max
+        )));
+      }
+    }
+
+    let field_type = FieldType::from(field.field_type);
+    let mut type_option = field
+      .get_any_type_option(field_type.type_id())
+      .unwrap_or_else(|| default_type_option_data_from_type(field_type));
+    type_option.insert(FIELD_CONSTRAINTS.to_string(), constraints.to_any());
+    self.update_field(field_id, |update| {
+      update.set_type_option(field_type.into(), Some(type_option));
+    });
+    Ok(())
+  }
+
+  /// Returns the cells of all the rows that violate the constraints of their fields. When rows
+  /// have the same value in a unique field, the rows after the first one are reported.
+  pub async fn validate_cells(&self) -> Result<Vec<CellViolation>, DatabaseError> {
+    let fields = self.constrained_fields();
+    if fields.is_empty() {
+      return Ok(vec![]);
+    }
+
+    let mut violations = vec![];
+    let mut unique_values = HashMap::<(&str, String), RowId>::new();
+    let mut rows = Box::pin(self.get_all_rows(VALIDATION_CHUNK_SIZE, None, false).await);
+    while let Some(row) = rows.next().await {
+      let row = row?;
+      for field in &fields {
+        let cell = row.cells.get(&field.field_id);
+        if let Some(violation) = field.check(cell) {
+          violations.push(field.violation(&row.id, violation, false));
+          continue;
+        }
+        if let Some(value) = field.unique_value(cell) {
+          let key = (field.field_id.as_str(), value);
+          match unique_values.entry(key) {
+            Entry::Occupied(_) => {
+              violations.push(field.violation(&row.id, ConstraintViolation::Duplicate, false));
+            },
+            Entry::Vacant(entry) => {
+              entry.insert(row.id.clone());
+            },
+          }
+        }
+      }
+    }
+    Ok(violations)
+  }
+
+  /// The fields that have constraints.
+  pub(crate) fn constrained_fields(&self) -> Vec<ConstrainedField> {
+    self
+      .get_all_fields()
+      .into_iter()
+      .filter_map(ConstrainedField::new)
+      .collect()
+  }
+
+  /// Checks the cells of a row that is created or updated against the constraints of the
+  /// fields. The cells of the enforced fields that violate them are marked as rejected.
+  ///
+  /// The values of the unique fields are looked up in the [UniqueValueIndex], the rows are only
+  /// scanned the first time a unique field is checked.
+  pub(crate) async fn check_row_cells(
+    &mut self,
+    row_id: &RowId,
+    cells: &Cells,
+    fields: &[&ConstrainedField],
+  ) -> Result<Vec<CellViolation>, DatabaseError> {
+    let mut violations = vec![];
+    let mut unique_fields = vec![];
+    for field in fields {
+      let cell = cells.get(&field.field_id);
+      let rejected = field.is_enforced();
+      match field.check(cell) {
+        Some(violation) => violations.push(field.violation(row_id, violation, rejected)),
+        None => {
+          if let Some(value) = field.unique_value(cell) {
+            unique_fields.push((*field, value));
+          }
+        },
+      }
+    }
+    if unique_fields.is_empty() {
+      return Ok(violations);
+    }
+
+    let indexed_fields = unique_fields
+      .iter()
+      .map(|(field, _)| *field)
+      .collect::<Vec<_>>();
+    self.load_unique_values(&indexed_fields).await?;
+    for (field, value) in unique_fields {
+      if self.has_duplicate(field, row_id, &value).await {
+        let rejected = field.is_enforced();
+        violations.push(field.violation(row_id, ConstraintViolation::Duplicate, rejected));
+      }
+    }
+    Ok(violations)
+  }
+
+  /// Checks the cells of the rows of a batch, like [Database::check_row_cells]. The values of the
+  /// unique fields are loaded with a single scan of the rows, and the values of the rows of the
+  /// batch are checked against each other.
+  pub(crate) async fn check_batch_cells(
+    &mut self,
+    rows: &[(&RowId, &Cells)],
+    fields: &[&ConstrainedField],
+  ) -> Result<Vec<CellViolation>, DatabaseError> {
+    self.load_unique_values(fields).await?;
+    let mut batch_values: HashMap<&str, HashSet<String>> = HashMap::new();
+    let mut violations = vec![];
+    for (row_id, cells) in rows {
+      let mut row_violations = self.check_row_cells(row_id, cells, fields).await?;
+      for field in fields.iter().filter(|field| field.constraints.unique) {
+        let Some(value) = field.unique_value(cells.get(&field.field_id)) else {
+          continue;
+        };
+        let is_new = batch_values
+          .entry(field.field_id.as_str())
+          .or_default()
+          .insert(value);
+        let is_reported = row_violations
+          .iter()
+          .any(|violation| violation.field_id == field.field_id);
+        if !is_new && !is_reported {
+          let rejected = field.is_enforced();
+          row_violations.push(field.violation(row_id, ConstraintViolation::Duplicate, rejected));
+        }
+      }
+      violations.extend(row_violations);
+    }
+    Ok(violations)
+  }
+
+  /// Loads the values of the unique fields that are not in the [UniqueValueIndex] yet, or whose
+  /// type option changed since they were loaded, with a single scan of the rows.
+  pub(crate) async fn load_unique_values(
+    &mut self,
+    fields: &[&ConstrainedField],
+  ) -> Result<(), DatabaseError> {
+    let fields = fields
+      .iter()
+      .filter(|field| field.constraints.unique && !self.body.unique_values.is_loaded(field))
+      .collect::<Vec<_>>();
+    if fields.is_empty() {
+      return Ok(());
+    }
+
+    let mut loaded = fields
+      .iter()
+      .map(|field| (field.field_id.clone(), UniqueFieldValues::new(field)))
+      .collect::<HashMap<_, _>>();
+    {
+      let mut rows = Box::pin(self.get_all_rows(VALIDATION_CHUNK_SIZE, None, false).await);
+      while let Some(row) = rows.next().await {
+        let row = row?;
+        for field in &fields {
+          if let Some(values) = loaded.get_mut(&field.field_id) {
+            values.set(&row.id, field.unique_value(row.cells.get(&field.field_id)));
+          }
+        }
+      }
+    }
+    self.body.unique_values.fields.extend(loaded);
+    Ok(())
+  }
+
+  /// Returns true if another row has the value in the unique field. The rows found in the index
+  /// are checked against their current cell, the index is fixed when they no longer have the
+  /// value.
+  async fn has_duplicate(&mut self, field: &ConstrainedField, row_id: &RowId, value: &str) -> bool {
+    let candidates = self
+      .body
+      .unique_values
+      .fields
+      .get(&field.field_id)
+      .map(|values| values.other_rows(row_id, value))
+      .unwrap_or_default();
+    let mut duplicate = false;
+    for candidate in candidates {
+      let cell = self.body.block.get_cell(&candidate, &field.field_id).await;
+      let candidate_value = field.unique_value(cell.as_ref());
+      duplicate |= candidate_value.as_deref() == Some(value);
+      if let Some(values) = self.body.unique_values.fields.get_mut(&field.field_id) {
+        values.set(&candidate, candidate_value);
+      }
+      if duplicate {
+        break;
+      }
+    }
+    duplicate
+  }
+
+  /// Updates the values of the row in the [UniqueValueIndex] after its cells were written.
+  pub(crate) fn index_unique_values(
+    &mut self,
+    row_id: &RowId,
+    cells: &Cells,
+    fields: &[ConstrainedField],
+  ) {
+    for field in fields {
+      if let Some(values) = self.body.unique_values.fields.get_mut(&field.field_id) {
+        values.set(row_id, field.unique_value(cells.get(&field.field_id)));
+      }
+    }
+  }
+
+  /// Removes the values of the rows from the [UniqueValueIndex] after they were removed from the
+  /// database.
+  pub(crate) fn unindex_rows(&mut self, row_ids: &[RowId]) {
+    for values in self.body.unique_values.fields.values_mut() {
+      for row_id in row_ids {
+        values.set(row_id, None);
+      }
+    }
+  }
+}
+
+/// The values of the cells of the unique fields, so that the uniqueness of a written cell is
+/// checked without loading all the rows.
+///
+/// The values of a field are loaded with a single scan of the rows the first time the field is
+/// checked, and loaded again when its type option changes. The index is updated by the rows
+/// written with the [Database], the changes of the other collaborators are not indexed. The rows
+/// found in the index are checked against their current cell before a duplicate is reported.
+#[derive(Default)]
+pub(crate) struct UniqueValueIndex {
+  fields: HashMap<String, UniqueFieldValues>,
+}
+
+impl UniqueValueIndex {
+  fn is_loaded(&self, field: &ConstrainedField) -> bool {
+    self.fields.get(&field.field_id).is_some_and(|values| {
+      values.field_type == field.field_type && values.type_option == field.type_option
+    })
+  }
+}
+
+struct UniqueFieldValues {
+  /// The type of the field and the type option the values were computed with.
+  field_type: FieldType,
+  type_option: TypeOptionData,
+  /// value -> the rows that have the value
+  rows: HashMap<String, HashSet<RowId>>,
+  /// row -> value
+  values: HashMap<RowId, String>,
+}
+
+impl UniqueFieldValues {
+  fn new(field: &ConstrainedField) -> Self {
+    Self {
+      field_type: field.field_type,
+      type_option: field.type_option.clone(),
+      rows: HashMap::new(),
+      values: HashMap::new(),
+    }
+  }
+
+  fn set(&mut self, row_id: &RowId, value: Option<String>) {
+    if let Some(previous) = self.values.remove(row_id) {
+      if let Entry::Occupied(mut entry) = self.rows.entry(previous) {
+        entry.get_mut().remove(row_id);
+        if entry.get().is_empty() {
+          entry.remove();
+        }
+      }
+    }
+    if let Some(value) = value {
+      self
+        .rows
+        .entry(value.clone())
+        .or_default()
+        .insert(row_id.clone());
+      self.values.insert(row_id.clone(), value);
+    }
+  }
+
+  fn other_rows(&self, row_id: &RowId, value: &str) -> Vec<RowId> {
+    self
+      .rows
+      .get(value)
+      .into_iter()
+      .flatten()
+      .filter(|other| *other != row_id)
+      .cloned()
+      .collect()
+  }
+}
+
+pub(crate) struct ConstrainedField {
+  pub field_id: String,
+  field_type: FieldType,
+  constraints: FieldConstraints,
+  /// The readers are created when they are used, they can't be held across the awaits.
+  type_option: TypeOptionData,
+  pattern: Option<Regex>,
+}
+
+impl ConstrainedField {
+  fn new(field: Field) -> Option<Self> {
+    let field_type = FieldType::from(field.field_type);
+    let type_option = field
+      .get_any_type_option(field_type.type_id())
+      .unwrap_or_else(|| default_type_option_data_from_type(field_type));
+    let constraints = FieldConstraints::from_type_option(&type_option);
+    if constraints.is_empty() {
+      return None;
+    }
+    let pattern = constraints
+      .pattern
+      .as_deref()
+      .and_then(|pattern| Regex::new(pattern).ok());
+    Some(Self {
+      field_id: field.id,
+      field_type,
+      constraints,
+      type_option,
+      pattern,
+    })
+  }
+
+  /// True if the cells that violate the constraints of the field are rejected.
+  pub(crate) fn is_enforced(&self) -> bool {
+    self.constraints.mode == ConstraintMode::Enforce
+  }
+
+  fn reader(&self) -> Box<dyn TypeOptionCellReader> {
+    type_option_cell_reader(self.type_option.clone(), &self.field_type)
+  }
+
+  fn violation(
+    &self,
+    row_id: &RowId,
+    violation: ConstraintViolation,
+    rejected: bool,
+  ) -> CellViolation {
+    CellViolation {
+      row_id: row_id.clone(),
+      field_id: self.field_id.clone(),
+      violation,
+      rejected,
+    }
+  }
+
+  fn is_empty(&self, cell: &Cell) -> bool {
+    match self.field_type {
+      FieldType::Number => NumberCellData::from(cell).0.trim().is_empty(),
+      FieldType::DateTime => DateCellData::from(cell).timestamp.is_none(),
+      FieldType::SingleSelect | FieldType::MultiSelect => SelectOptionIds::from(cell).is_empty(),
+      FieldType::Checklist => ChecklistCellData::from(cell).options.is_empty(),
+      FieldType::Media => MediaCellData::from(cell).files.is_empty(),
+      FieldType::Relation => RelationCellData::from(cell).row_ids.is_empty(),
+      _ => self.reader().stringify_cell(cell).trim().is_empty(),
+    }
+  }
+
+  /// Returns the first constraint, other than the uniqueness, violated by the cell.
+  fn check(&self, cell: Option<&Cell>) -> Option<ConstraintViolation> {
+    let Some(cell) = cell.filter(|cell| !self.is_empty(cell)) else {
+      return self
+        .constraints
+        .required
+        .then_some(ConstraintViolation::Required);
+    };
+    let constraints = &self.constraints;
+    match self.field_type {
+      FieldType::Number => {
+        let Some(number) = self.reader().numeric_cell(cell) else {
+          return Some(ConstraintViolation::InvalidNumber);
+        };
+        check_range(constraints, &[number])
+      },
+      FieldType::DateTime => {
+        let date = DateCellData::from(cell);
+        let timestamps = date
+          .timestamp
+          .into_iter()
+          .chain(date.end_timestamp.filter(|_| date.is_range))
+          .map(|timestamp| timestamp as f64)
+          .collect::<Vec<_>>();
+        check_range(constraints, &timestamps)
+      },
+      FieldType::RichText | FieldType::URL => {
+        let pattern = self.pattern.as_ref()?;
+        let text = self.reader().stringify_cell(cell);
+        match pattern.is_match(&text) {
+          Ok(true) => None,
+          _ => Some(ConstraintViolation::PatternMismatch(
+            pattern.as_str().to_string(),
+          )),
+        }
+      },
+      FieldType::MultiSelect => {
+        let max_selections = constraints.max_selections?;
+        (SelectOptionIds::from(cell).len() > max_selections)
+          .then_some(ConstraintViolation::TooManySelections(max_selections))
+      },
+      _ => None,
+    }
+  }
+
+  /// Returns the value compared with the other rows if the field is unique.
+  fn unique_value(&self, cell: Option<&Cell>) -> Option<String> {
+    if !self.constraints.unique {
+      return None;
+    }
+    let cell = cell.filter(|cell| !self.is_empty(cell))?;
+    let value = match self.field_type {
+      FieldType::Number => self
+        .reader()
+        .numeric_cell(cell)
+        .map(|number| number.to_string())
+        .unwrap_or_else(|| NumberCellData::from(cell).0),
+      FieldType::DateTime => {
+        let date = DateCellData::from(cell);
+        format!("{:?}/{:?}", date.timestamp, date.end_timestamp)
+      },
+      _ => self.reader().stringify_cell(cell),
+    };
+    Some(value.trim().to_string())
+  }
+}
+
+fn check_range(constraints: &FieldConstraints, values: &[f64]) -> Option<ConstraintViolation> {
+  if let Some(min) = constraints.min {
+    if values.iter().any(|value| *value < min) {
+      return Some(ConstraintViolation::BelowMin(min));
+    }
+  }
+  if let Some(max) = constraints.max {
+    if values.iter().any(|value| *value > max) {
+      return Some(ConstraintViolation::AboveMax(max));
+    }
+  }
+  None
+}
@@ -1,3 +1,4 @@
+use crate::database_validation::CellViolation;
 use crate::rows::RowId;
 use collab_entity::CollabValidateError;
 
@@ -45,6 +46,24 @@ pub enum DatabaseError {
   #[error("Import data failed: {0}")]
   ImportData(String),
 
+  #[error("Export data failed: {0}")]
+  ExportData(String),
+
+  #[error("Invalid recurrence rule: {0}")]
+  InvalidRecurrenceRule(String),
+
+  #[error("Invalid timezone: {0}")]
+  InvalidTimezone(String),
+
+  #[error("Invalid constraint: {0}")]
+  InvalidConstraint(String),
+
+  #[error("The cells violate the constraints of their fields: {0:?}")]
+  ConstraintViolation(Vec<CellViolation>),
+
+  #[error("Invalid relation: {0}")]
+  InvalidRelation(String),
+
   #[error("Internal failure: {0}")]
   Internal(#[from] anyhow::Error),
 }
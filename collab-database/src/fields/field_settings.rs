@@ -132,6 +132,20 @@ pub fn field_settings_for_field(
     .into()
 }
 
+/// Returns the visibility of the field in a view of the given layout, falling back to the
+/// default visibility when the view has no settings for the field.
+pub fn field_visibility_in_view(
+  field: &Field,
+  layout_type: DatabaseLayout,
+  field_settings: Option<&FieldSettingsMap>,
+) -> FieldVisibility {
+  match field_settings {
+    Some(settings) => FieldSettings::from_any_map(&field.id, layout_type, settings).visibility,
+    None if field.is_primary => FieldVisibility::AlwaysShown,
+    None => default_field_visibility(layout_type),
+  }
+}
+
 pub fn default_field_settings_by_layout_map() -> HashMap<DatabaseLayout, FieldSettingsMap> {
   let mut map = HashMap::new();
   for layout_ty in DatabaseLayout::iter() {
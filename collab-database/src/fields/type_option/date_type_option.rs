@@ -9,7 +9,7 @@ use crate::fields::{
 };
 use crate::rows::{Cell, new_cell_builder};
 use crate::template::entity::CELL_DATA;
-use chrono::{FixedOffset, MappedLocalTime, NaiveDateTime, NaiveTime, Offset};
+use chrono::{Duration, FixedOffset, MappedLocalTime, NaiveDate, NaiveDateTime, NaiveTime, Offset};
 use chrono_tz::Tz;
 use collab::util::AnyMapExt;
 use serde::de::Visitor;
@@ -78,8 +78,13 @@ impl Default for DateTypeOption {
 
 impl TypeOptionCellReader for DateTypeOption {
   fn json_cell(&self, cell: &Cell) -> Value {
-    let tz: Tz = self.timezone_id.parse().unwrap_or_default();
     let date_cell = DateCellData::from(cell);
+    let tz: Tz = date_cell
+      .timezone_id
+      .as_deref()
+      .unwrap_or(&self.timezone_id)
+      .parse()
+      .unwrap_or_default();
 
     let dt_start: Option<DateTime<Tz>> = date_cell
       .timestamp
@@ -113,14 +118,15 @@ impl TypeOptionCellReader for DateTypeOption {
 
   fn stringify_cell(&self, cell_data: &Cell) -> String {
     let cell_data = DateCellData::from(cell_data);
+    let type_option = self.for_cell(&cell_data);
     let include_time = cell_data.include_time;
     let timestamp = cell_data.timestamp;
     let is_range = cell_data.is_range;
 
-    let (date, time) = self.formatted_date_time_from_timestamp(&timestamp);
+    let (date, time) = type_option.formatted_date_time_from_timestamp(&timestamp);
     if is_range {
       let (end_date, end_time) = match cell_data.end_timestamp {
-        Some(timestamp) => self.formatted_date_time_from_timestamp(&Some(timestamp)),
+        Some(timestamp) => type_option.formatted_date_time_from_timestamp(&Some(timestamp)),
         None => (date.clone(), time.clone()),
       };
       if include_time && timestamp.is_some() {
@@ -217,6 +223,56 @@ impl DateTypeOption {
     }
   }
 
+  /// Sets the timezone the dates are displayed in. An empty id is the local timezone.
+  pub fn set_timezone_id(&mut self, timezone_id: &str) -> Result<(), DatabaseError> {
+    validate_timezone_id(timezone_id)?;
+    self.timezone_id = timezone_id.to_string();
+    Ok(())
+  }
+
+  /// Returns the type option used for the cell: the timezone of the cell, if any, replaces the
+  /// timezone of the field.
+  pub fn for_cell(&self, cell_data: &DateCellData) -> Self {
+    let mut type_option = self.clone();
+    if let Some(timezone_id) = &cell_data.timezone_id {
+      type_option.timezone_id = timezone_id.clone();
+    }
+    type_option
+  }
+
+  /// Returns the day of the start of the cell in its timezone. The dates are filtered and grouped
+  /// by this day.
+  pub fn local_date(&self, cell_data: &DateCellData) -> Option<NaiveDate> {
+    let timestamp = cell_data.timestamp?;
+    self
+      .for_cell(cell_data)
+      .local_date_from_timestamp(timestamp)
+  }
+
+  /// Returns the day of the timestamp in the timezone of the type option.
+  pub fn local_date_from_timestamp(&self, timestamp: i64) -> Option<NaiveDate> {
+    let date_time = DateTime::from_timestamp(timestamp, 0)?.naive_utc();
+    let offset = self.get_timezone_offset(date_time);
+    Some(offset.from_utc_datetime(&date_time).date_naive())
+  }
+
+  /// Returns the date and time of the timestamp in the timezone of the type option.
+  pub fn local_date_time_from_timestamp(&self, timestamp: i64) -> Option<NaiveDateTime> {
+    let date_time = DateTime::from_timestamp(timestamp, 0)?.naive_utc();
+    let offset = self.get_timezone_offset(date_time);
+    Some(offset.from_utc_datetime(&date_time).naive_local())
+  }
+
+  /// Returns the timestamp of a date and time in the timezone of the type option. An ambiguous
+  /// time, repeated when the clocks go back, is the first one. A time skipped when the clocks go
+  /// forward is moved forward by the length of the gap.
+  pub fn timestamp_from_local_date_time(&self, date_time: NaiveDateTime) -> Option<i64> {
+    match self.timezone() {
+      Some(timezone) => timestamp_in_timezone(&timezone, &date_time),
+      None => timestamp_in_timezone(&Local, &date_time),
+    }
+  }
+
   pub fn to_json_string(&self) -> String {
     serde_json::to_string(self).unwrap()
   }
@@ -274,43 +330,71 @@ impl DateTypeOption {
     if let Some(time) = parsed_time {
       // a valid time is provided, so we replace the time component of old timestamp
       // (or new timestamp if provided) with it.
-      let offset = changeset_timestamp
-        .or(previous_timestamp)
-        .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0))
-        .map(|date| self.get_timezone_offset(date.naive_utc()))?;
-
       let local_date = changeset_timestamp
         .or(previous_timestamp)
-        .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0))
-        .map(|date| offset.from_utc_datetime(&date.naive_utc()).date_naive())?;
+        .and_then(|timestamp| self.local_date_from_timestamp(timestamp))?;
 
-      match offset
-        .from_local_datetime(&NaiveDateTime::new(local_date, time))
-        .map(|date| date.timestamp())
-      {
-        MappedLocalTime::Single(value) => Some(value),
-        MappedLocalTime::Ambiguous(_, _) => None,
-        MappedLocalTime::None => None,
-      }
+      // the offset of the new time, which differs from the offset of the old timestamp when a
+      // daylight saving time transition happens between them.
+      self.timestamp_from_local_date_time(NaiveDateTime::new(local_date, time))
     } else {
       changeset_timestamp.or(previous_timestamp)
     }
   }
 
-  /// returns offset of Tz timezone if provided or of the local timezone otherwise
-  fn get_timezone_offset(&self, date_time: NaiveDateTime) -> FixedOffset {
-    let current_timezone_offset = Local::now().offset().fix();
+  /// returns the timezone of the type option, or `None` for the local timezone
+  fn timezone(&self) -> Option<Tz> {
     if self.timezone_id.is_empty() {
-      current_timezone_offset
+      None
     } else {
-      match Tz::from_str(&self.timezone_id) {
-        Ok(timezone) => timezone.offset_from_utc_datetime(&date_time).fix(),
-        Err(_) => current_timezone_offset,
-      }
+      Tz::from_str(&self.timezone_id).ok()
+    }
+  }
+
+  /// returns offset of Tz timezone at the given UTC date time if provided or of the local timezone
+  /// otherwise
+  fn get_timezone_offset(&self, date_time: NaiveDateTime) -> FixedOffset {
+    match self.timezone() {
+      Some(timezone) => timezone.offset_from_utc_datetime(&date_time).fix(),
+      None => Local.offset_from_utc_datetime(&date_time).fix(),
     }
   }
 }
 
+/// Returns an error if the id is not empty and is not an IANA timezone, like `Europe/Paris`.
+pub fn validate_timezone_id(timezone_id: &str) -> Result<(), DatabaseError> {
+  if timezone_id.is_empty() || Tz::from_str(timezone_id).is_ok() {
+    Ok(())
+  } else {
+    Err(DatabaseError::InvalidTimezone(timezone_id.to_string()))
+  }
+}
+
+/// See [DateTypeOption::timestamp_from_local_date_time].
+fn timestamp_in_timezone<T: TimeZone>(
+  timezone: &T,
+  local_date_time: &NaiveDateTime,
+) -> Option<i64> {
+  match timezone.from_local_datetime(local_date_time) {
+    MappedLocalTime::Single(date_time) => Some(date_time.timestamp()),
+    MappedLocalTime::Ambiguous(earliest, _) => Some(earliest.timestamp()),
+    MappedLocalTime::None => {
+      let before = timezone
+        .offset_from_utc_datetime(&(*local_date_time - Duration::days(1)))
+        .fix();
+      let after = timezone
+        .offset_from_utc_datetime(&(*local_date_time + Duration::days(1)))
+        .fix();
+      let gap = after.local_minus_utc() - before.local_minus_utc();
+      let shifted = *local_date_time + Duration::seconds(gap as i64);
+      timezone
+        .from_local_datetime(&shifted)
+        .earliest()
+        .map(|date_time| date_time.timestamp())
+    },
+  }
+}
+
 impl From<TypeOptionData> for DateTypeOption {
   fn from(data: TypeOptionData) -> Self {
     let date_format = data
@@ -418,6 +502,11 @@ impl DateFormat {
   }
 }
 
+/// The key of [DateCellData::recurrence] in the date cells.
+pub const DATE_RECURRENCE: &str = "recurrence";
+/// The key of [DateCellData::timezone_id] in the date cells.
+pub const DATE_TIMEZONE_ID: &str = "timezone_id";
+
 #[derive(Default, Clone, Debug, Serialize)]
 pub struct DateCellData {
   pub timestamp: Option<i64>,
@@ -427,6 +516,13 @@ pub struct DateCellData {
   #[serde(default)]
   pub is_range: bool,
   pub reminder_id: String,
+  /// An RRULE of RFC 5545 that repeats the date, for example `FREQ=WEEKLY;BYDAY=MO,WE;COUNT=10`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub recurrence: Option<String>,
+  /// The IANA timezone of the cell, like `Europe/Paris`. The dates are displayed in the timezone
+  /// of the field when it's `None`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub timezone_id: Option<String>,
 }
 impl TypeOptionCellData for DateCellData {
   fn is_cell_empty(&self) -> bool {
@@ -442,6 +538,8 @@ impl DateCellData {
       include_time,
       is_range,
       reminder_id,
+      recurrence: None,
+      timezone_id: None,
     }
   }
 
@@ -452,7 +550,20 @@ impl DateCellData {
       include_time: false,
       is_range: false,
       reminder_id: String::new(),
+      recurrence: None,
+      timezone_id: None,
+    }
+  }
+
+  /// Sets the timezone of the cell, `None` to use the timezone of the field.
+  pub fn with_timezone_id(mut self, timezone_id: Option<&str>) -> Result<Self, DatabaseError> {
+    if let Some(timezone_id) = timezone_id {
+      validate_timezone_id(timezone_id)?;
     }
+    self.timezone_id = timezone_id
+      .filter(|timezone_id| !timezone_id.is_empty())
+      .map(|timezone_id| timezone_id.to_string());
+    Ok(self)
   }
 
   pub fn from_timestamp_include_time(timestamp: i64) -> Self {
@@ -471,6 +582,12 @@ impl From<&Cell> for DateCellData {
     let include_time: bool = cell.get_as("include_time").unwrap_or_default();
     let is_range: bool = cell.get_as("is_range").unwrap_or_default();
     let reminder_id: String = cell.get_as("reminder_id").unwrap_or_default();
+    let recurrence = cell
+      .get_as::<String>(DATE_RECURRENCE)
+      .filter(|recurrence| !recurrence.is_empty());
+    let timezone_id = cell
+      .get_as::<String>(DATE_TIMEZONE_ID)
+      .filter(|timezone_id| validate_timezone_id(timezone_id).is_ok() && !timezone_id.is_empty());
 
     Self {
       timestamp,
@@ -478,6 +595,8 @@ impl From<&Cell> for DateCellData {
       include_time,
       is_range,
       reminder_id,
+      recurrence,
+      timezone_id,
     }
   }
 }
@@ -503,6 +622,12 @@ impl From<&DateCellData> for Cell {
       "reminder_id".into(),
       cell_data.reminder_id.to_owned().into(),
     );
+    if let Some(recurrence) = &cell_data.recurrence {
+      cell.insert(DATE_RECURRENCE.into(), recurrence.to_owned().into());
+    }
+    if let Some(timezone_id) = &cell_data.timezone_id {
+      cell.insert(DATE_TIMEZONE_ID.into(), timezone_id.to_owned().into());
+    }
     cell
   }
 }
@@ -530,6 +655,8 @@ impl<'de> serde::Deserialize<'de> for DateCellData {
           include_time: false,
           is_range: false,
           reminder_id: String::new(),
+          recurrence: None,
+          timezone_id: None,
         })
       }
 
@@ -549,6 +676,8 @@ impl<'de> serde::Deserialize<'de> for DateCellData {
         let mut include_time: Option<bool> = None;
         let mut is_range: Option<bool> = None;
         let mut reminder_id: Option<String> = None;
+        let mut recurrence: Option<String> = None;
+        let mut timezone_id: Option<String> = None;
 
         while let Some(key) = map.next_key::<String>()? {
           match key.as_str() {
@@ -567,6 +696,12 @@ impl<'de> serde::Deserialize<'de> for DateCellData {
             "reminder_id" => {
               reminder_id = map.next_value().ok();
             },
+            "recurrence" => {
+              recurrence = map.next_value().ok().flatten();
+            },
+            "timezone_id" => {
+              timezone_id = map.next_value::<Option<String>>().ok().flatten();
+            },
             _ => {
               let _: serde_json::Value = map.next_value()?; // Ignore unknown keys
             },
@@ -579,6 +714,10 @@ impl<'de> serde::Deserialize<'de> for DateCellData {
           include_time: include_time.unwrap_or_default(),
           is_range: is_range.unwrap_or_default(),
           reminder_id: reminder_id.unwrap_or_default(),
+          recurrence,
+          timezone_id: timezone_id.filter(|timezone_id| {
+            !timezone_id.is_empty() && validate_timezone_id(timezone_id).is_ok()
+          }),
         })
       }
     }
@@ -645,6 +784,8 @@ mod tests {
       include_time: true,
       is_range: true,
       reminder_id: "reminder123".to_string(),
+      recurrence: None,
+      timezone_id: None,
     };
 
     let cell = Cell::from(&date_cell_data);
@@ -820,4 +961,77 @@ mod tests {
     let str = date_type_option.stringify_cell(&Cell::from(&date_cell));
     assert_eq!(str, "Oct 12, 2019 07:20");
   }
+
+  #[test]
+  fn timestamp_from_parsed_time_across_dst_test() {
+    let mut date_type_option = DateTypeOption::default_utc();
+    date_type_option
+      .set_timezone_id("America/New_York")
+      .unwrap();
+
+    // 2024-03-10 01:00 EST, the clocks go forward at 02:00.
+    let previous_timestamp = Some(1710050400);
+    let time = NaiveTime::from_hms_opt(9, 0, 0);
+    let timestamp = date_type_option.timestamp_from_parsed_time_previous_and_new_timestamp(
+      time,
+      previous_timestamp,
+      None,
+    );
+    // 09:00 EDT
+    assert_eq!(timestamp, Some(1710075600));
+
+    // 02:30 doesn't exist on that day, it's 03:30 EDT.
+    let time = NaiveTime::from_hms_opt(2, 30, 0);
+    let timestamp = date_type_option.timestamp_from_parsed_time_previous_and_new_timestamp(
+      time,
+      previous_timestamp,
+      None,
+    );
+    assert_eq!(timestamp, Some(1710055800));
+  }
+
+  #[test]
+  fn date_cell_timezone_test() {
+    let date_type_option = DateTypeOption::default_utc();
+    // 2024-08-22 20:00 UTC
+    let date_cell = DateCellData::from_timestamp_include_time(1724356800);
+    let cell = Cell::from(&date_cell);
+    assert_eq!(date_type_option.stringify_cell(&cell), "Aug 22, 2024 20:00");
+    assert_eq!(
+      date_type_option.local_date(&date_cell),
+      NaiveDate::from_ymd_opt(2024, 8, 22)
+    );
+
+    let date_cell = date_cell.with_timezone_id(Some("Asia/Tokyo")).unwrap();
+    let cell = Cell::from(&date_cell);
+    assert_eq!(
+      DateCellData::from(&cell).timezone_id.as_deref(),
+      Some("Asia/Tokyo")
+    );
+    assert_eq!(date_type_option.stringify_cell(&cell), "Aug 23, 2024 05:00");
+    assert_eq!(
+      date_type_option.local_date(&date_cell),
+      NaiveDate::from_ymd_opt(2024, 8, 23)
+    );
+    assert_eq!(
+      date_type_option.json_cell(&cell)["timezone"],
+      json!("Asia/Tokyo")
+    );
+
+    let json = serde_json::to_value(&date_cell).unwrap();
+    assert_eq!(json["timezone_id"], json!("Asia/Tokyo"));
+    let date_cell = serde_json::from_value::<DateCellData>(json).unwrap();
+    assert_eq!(date_cell.timezone_id.as_deref(), Some("Asia/Tokyo"));
+
+    assert!(
+      DateCellData::from_timestamp(0)
+        .with_timezone_id(Some("Mars/Olympus"))
+        .is_err()
+    );
+    assert!(
+      DateTypeOption::default_utc()
+        .set_timezone_id("Mars/Olympus")
+        .is_err()
+    );
+  }
 }
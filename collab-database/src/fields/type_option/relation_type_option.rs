@@ -8,21 +8,45 @@ use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use std::str::FromStr;
 
+pub const RELATION_DATABASE_ID: &str = "database_id";
+pub const RELATION_PAIRED_FIELD_ID: &str = "paired_field_id";
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RelationTypeOption {
   pub database_id: String,
+  /// The id of the relation field in the related database that holds the back-references of
+  /// this field. Only set for two-way relations.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub paired_field_id: Option<String>,
+}
+
+impl RelationTypeOption {
+  pub fn is_two_way(&self) -> bool {
+    self.paired_field_id.is_some()
+  }
 }
 
 impl From<TypeOptionData> for RelationTypeOption {
   fn from(data: TypeOptionData) -> Self {
-    let database_id: String = data.get_as("database_id").unwrap_or_default();
-    Self { database_id }
+    let database_id: String = data.get_as(RELATION_DATABASE_ID).unwrap_or_default();
+    let paired_field_id = data
+      .get_as::<String>(RELATION_PAIRED_FIELD_ID)
+      .filter(|field_id| !field_id.is_empty());
+    Self {
+      database_id,
+      paired_field_id,
+    }
   }
 }
 
 impl From<RelationTypeOption> for TypeOptionData {
   fn from(data: RelationTypeOption) -> Self {
-    TypeOptionDataBuilder::from([("database_id".into(), data.database_id.into())])
+    let mut type_option =
+      TypeOptionDataBuilder::from([(RELATION_DATABASE_ID.into(), data.database_id.into())]);
+    if let Some(paired_field_id) = data.paired_field_id {
+      type_option.insert(RELATION_PAIRED_FIELD_ID.into(), paired_field_id.into());
+    }
+    type_option
   }
 }
 
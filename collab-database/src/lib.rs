@@ -1,5 +1,12 @@
 pub mod database;
+pub mod database_calendar;
+pub mod database_duplicate;
+pub mod database_export;
+pub mod database_history;
+pub mod database_import;
+pub mod database_relation;
 pub mod database_remapper;
+pub mod database_validation;
 pub mod fields;
 pub mod meta;
 pub mod rows;
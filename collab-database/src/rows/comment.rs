@@ -1,13 +1,61 @@
-use collab::preclude::Any;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use collab::preclude::encoding::serde::from_any;
+use collab::preclude::types::Change;
+use collab::preclude::{
+  Any, Array, ArrayRef, DeepObservable, Event, Map, MapExt, MapPrelim, MapRef, PathSegment,
+  ReadTxn, ToJson, TransactionMut, YrsValue,
+};
 use collab::util::deserialize_i64_from_numeric;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::database::timestamp;
+use crate::error::DatabaseError;
+use crate::rows::{RowChange, RowChangeSender, RowId};
+
+const COMMENT_ID: &str = "id";
+const COMMENT_PARENT_ID: &str = "parent_id";
+const COMMENT_UID: &str = "uid";
+const COMMENT_CONTENT: &str = "content";
+const COMMENT_MENTIONS: &str = "mentions";
+const COMMENT_REACTIONS: &str = "reactions";
+const COMMENT_RESOLVED_BY: &str = "resolved_by";
+const COMMENT_IS_DELETED: &str = "is_deleted";
+const COMMENT_CREATED_AT: &str = "created_at";
+const COMMENT_UPDATED_AT: &str = "updated_at";
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A comment of a row. A comment with a `parent_id` is a reply to another comment of the row.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RowComment {
-  uid: i64,
-  content: String,
+  #[serde(default)]
+  pub id: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub parent_id: Option<String>,
+  pub uid: i64,
+  pub content: String,
+  #[serde(default)]
+  pub mentions: Vec<RowCommentMention>,
+  /// The users who reacted to the comment, by emoji.
+  #[serde(default)]
+  pub reactions: BTreeMap<String, Vec<i64>>,
+  /// The user who resolved the comment, None if the comment is not resolved.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub resolved_by: Option<i64>,
+  /// A deleted comment keeps its place in the thread, its content is removed.
+  #[serde(default)]
+  pub is_deleted: bool,
   #[serde(deserialize_with = "deserialize_i64_from_numeric")]
-  created_at: i64,
+  pub created_at: i64,
+  #[serde(default, deserialize_with = "deserialize_i64_from_numeric")]
+  pub updated_at: i64,
+}
+
+impl RowComment {
+  pub fn is_resolved(&self) -> bool {
+    self.resolved_by.is_some()
+  }
 }
 
 impl TryFrom<Any> for RowComment {
@@ -27,3 +75,415 @@ impl From<RowComment> for Any {
     Any::from_json(&json).unwrap()
   }
 }
+
+/// A user or a row mentioned in a comment.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RowCommentMention {
+  User {
+    #[serde(deserialize_with = "deserialize_i64_from_numeric")]
+    uid: i64,
+  },
+  Row {
+    row_id: RowId,
+  },
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateRowCommentParams {
+  pub uid: i64,
+  pub content: String,
+  pub parent_id: Option<String>,
+  pub mentions: Vec<RowCommentMention>,
+}
+
+impl CreateRowCommentParams {
+  pub fn new(uid: i64, content: impl ToString) -> Self {
+    Self {
+      uid,
+      content: content.to_string(),
+      parent_id: None,
+      mentions: vec![],
+    }
+  }
+
+  pub fn with_parent_id(mut self, parent_id: impl ToString) -> Self {
+    self.parent_id = Some(parent_id.to_string());
+    self
+  }
+
+  pub fn with_mentions(mut self, mentions: Vec<RowCommentMention>) -> Self {
+    self.mentions = mentions;
+    self
+  }
+}
+
+/// A comment and its replies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowCommentThread {
+  pub comment: RowComment,
+  pub replies: Vec<RowCommentThread>,
+}
+
+/// The comments of a row, stored in an array of the row collab.
+pub(crate) struct RowComments<'a> {
+  array: &'a ArrayRef,
+}
+
+impl<'a> RowComments<'a> {
+  pub(crate) fn new(array: &'a ArrayRef) -> Self {
+    Self { array }
+  }
+
+  /// Returns the comments from the oldest to the newest.
+  pub fn get_all<T: ReadTxn>(&self, txn: &T) -> Vec<RowComment> {
+    self
+      .array
+      .iter(txn)
+      .filter_map(|value| match value {
+        YrsValue::YMap(map) => comment_from_map_ref(txn, &map),
+        _ => None,
+      })
+      .collect()
+  }
+
+  pub fn get<T: ReadTxn>(&self, txn: &T, comment_id: &str) -> Option<RowComment> {
+    let map = self.find(txn, comment_id)?;
+    comment_from_map_ref(txn, &map)
+  }
+
+  /// Returns the threads of the comments, from the oldest to the newest. The deleted comments
+  /// without replies are left out.
+  pub fn get_threads<T: ReadTxn>(&self, txn: &T) -> Vec<RowCommentThread> {
+    let comments = self.get_all(txn);
+    let ids = comments
+      .iter()
+      .map(|comment| comment.id.as_str())
+      .collect::<HashSet<_>>();
+    let mut replies_by_parent: HashMap<&str, Vec<&RowComment>> = HashMap::new();
+    let mut roots = vec![];
+    for comment in comments.iter() {
+      match comment.parent_id.as_deref() {
+        Some(parent_id) if ids.contains(parent_id) => replies_by_parent
+          .entry(parent_id)
+          .or_default()
+          .push(comment),
+        _ => roots.push(comment),
+      }
+    }
+    roots
+      .into_iter()
+      .filter_map(|comment| build_thread(comment, &replies_by_parent))
+      .collect()
+  }
+
+  pub fn add(
+    &self,
+    txn: &mut TransactionMut,
+    params: CreateRowCommentParams,
+  ) -> Result<RowComment, DatabaseError> {
+    if let Some(parent_id) = params.parent_id.as_deref() {
+      if self.find(txn, parent_id).is_none() {
+        return Err(DatabaseError::RecordNotFound);
+      }
+    }
+
+    let now = timestamp();
+    let comment = RowComment {
+      id: Uuid::new_v4().to_string(),
+      parent_id: params.parent_id,
+      uid: params.uid,
+      content: params.content,
+      mentions: params.mentions,
+      reactions: BTreeMap::new(),
+      resolved_by: None,
+      is_deleted: false,
+      created_at: now,
+      updated_at: now,
+    };
+    let map = self.array.push_back(txn, MapPrelim::default());
+    map.insert(txn, COMMENT_ID, comment.id.clone());
+    if let Some(parent_id) = comment.parent_id.as_ref() {
+      map.insert(txn, COMMENT_PARENT_ID, parent_id.clone());
+    }
+    map.insert(txn, COMMENT_UID, Any::BigInt(comment.uid));
+    map.insert(txn, COMMENT_CONTENT, comment.content.clone());
+    map.insert(txn, COMMENT_MENTIONS, mentions_to_any(&comment.mentions));
+    map.insert(txn, COMMENT_REACTIONS, MapPrelim::default());
+    map.insert(txn, COMMENT_IS_DELETED, false);
+    map.insert(txn, COMMENT_CREATED_AT, Any::BigInt(now));
+    map.insert(txn, COMMENT_UPDATED_AT, Any::BigInt(now));
+    Ok(comment)
+  }
+
+  pub fn update_content(
+    &self,
+    txn: &mut TransactionMut,
+    comment_id: &str,
+    content: impl ToString,
+    mentions: Vec<RowCommentMention>,
+  ) -> Result<(), DatabaseError> {
+    let map = self.find_active(txn, comment_id)?;
+    map.insert(txn, COMMENT_CONTENT, content.to_string());
+    map.insert(txn, COMMENT_MENTIONS, mentions_to_any(&mentions));
+    map.insert(txn, COMMENT_UPDATED_AT, Any::BigInt(timestamp()));
+    Ok(())
+  }
+
+  /// Marks the comment as deleted and removes its content, mentions and reactions. The replies of
+  /// the comment are kept.
+  pub fn delete(&self, txn: &mut TransactionMut, comment_id: &str) -> Result<(), DatabaseError> {
+    let map = self.find_active(txn, comment_id)?;
+    map.insert(txn, COMMENT_IS_DELETED, true);
+    map.insert(txn, COMMENT_CONTENT, "");
+    map.insert(txn, COMMENT_MENTIONS, mentions_to_any(&[]));
+    if let Some(reactions) = map.get_with_txn::<_, MapRef>(txn, COMMENT_REACTIONS) {
+      reactions.clear(txn);
+    }
+    map.insert(txn, COMMENT_UPDATED_AT, Any::BigInt(timestamp()));
+    Ok(())
+  }
+
+  pub fn add_reaction(
+    &self,
+    txn: &mut TransactionMut,
+    comment_id: &str,
+    emoji: &str,
+    uid: i64,
+  ) -> Result<(), DatabaseError> {
+    let map = self.find_active(txn, comment_id)?;
+    let reactions = map.get_or_init_map(txn, COMMENT_REACTIONS);
+    reactions.insert(txn, reaction_key(emoji, uid), true);
+    Ok(())
+  }
+
+  pub fn remove_reaction(
+    &self,
+    txn: &mut TransactionMut,
+    comment_id: &str,
+    emoji: &str,
+    uid: i64,
+  ) -> Result<(), DatabaseError> {
+    let map = self.find_active(txn, comment_id)?;
+    if let Some(reactions) = map.get_with_txn::<_, MapRef>(txn, COMMENT_REACTIONS) {
+      reactions.remove(txn, &reaction_key(emoji, uid));
+    }
+    Ok(())
+  }
+
+  /// Resolves the comment when `resolved_by` is set, reopens it otherwise.
+  pub fn set_resolved_by(
+    &self,
+    txn: &mut TransactionMut,
+    comment_id: &str,
+    resolved_by: Option<i64>,
+  ) -> Result<(), DatabaseError> {
+    let map = self.find_active(txn, comment_id)?;
+    match resolved_by {
+      Some(uid) => map.insert(txn, COMMENT_RESOLVED_BY, Any::BigInt(uid)),
+      None => map.insert(txn, COMMENT_RESOLVED_BY, Any::Null),
+    };
+    Ok(())
+  }
+
+  fn find<T: ReadTxn>(&self, txn: &T, comment_id: &str) -> Option<MapRef> {
+    self.array.iter(txn).find_map(|value| match value {
+      YrsValue::YMap(map) if map.get_id(txn).as_deref() == Some(comment_id) => Some(map),
+      _ => None,
+    })
+  }
+
+  fn find_active<T: ReadTxn>(&self, txn: &T, comment_id: &str) -> Result<MapRef, DatabaseError> {
+    self
+      .find(txn, comment_id)
+      .filter(|map| {
+        !map
+          .get_with_txn::<_, bool>(txn, COMMENT_IS_DELETED)
+          .unwrap_or_default()
+      })
+      .ok_or(DatabaseError::RecordNotFound)
+  }
+}
+
+/// The reactions are flat keys of the reactions map, so that the reactions of the users are
+/// merged when they react with the same emoji at the same time.
+fn reaction_key(emoji: &str, uid: i64) -> String {
+  format!("{}:{}", emoji, uid)
+}
+
+fn build_thread(
+  comment: &RowComment,
+  replies_by_parent: &HashMap<&str, Vec<&RowComment>>,
+) -> Option<RowCommentThread> {
+  let replies = replies_by_parent
+    .get(comment.id.as_str())
+    .map(|replies| {
+      replies
+        .iter()
+        .filter_map(|reply| build_thread(reply, replies_by_parent))
+        .collect::<Vec<_>>()
+    })
+    .unwrap_or_default();
+  if comment.is_deleted && replies.is_empty() {
+    return None;
+  }
+  Some(RowCommentThread {
+    comment: comment.clone(),
+    replies,
+  })
+}
+
+/// Returns the mentions in the format of their [Serialize] implementation.
+fn mentions_to_any(mentions: &[RowCommentMention]) -> Any {
+  let mentions = mentions
+    .iter()
+    .map(|mention| {
+      let fields = match mention {
+        RowCommentMention::User { uid } => {
+          [("type", Any::from("user")), ("uid", Any::BigInt(*uid))]
+        },
+        RowCommentMention::Row { row_id } => [
+          ("type", Any::from("row")),
+          ("row_id", Any::from(row_id.to_string())),
+        ],
+      };
+      let fields = fields
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect::<HashMap<_, _>>();
+      Any::Map(Arc::new(fields))
+    })
+    .collect::<Vec<_>>();
+  Any::Array(Arc::from(mentions))
+}
+
+fn comment_from_map_ref<T: ReadTxn>(txn: &T, map: &MapRef) -> Option<RowComment> {
+  let mut reactions: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+  if let Some(map) = map.get_with_txn::<_, MapRef>(txn, COMMENT_REACTIONS) {
+    for key in map.keys(txn) {
+      if let Some((emoji, uid)) = key.rsplit_once(':') {
+        if let Ok(uid) = uid.parse::<i64>() {
+          reactions.entry(emoji.to_string()).or_default().push(uid);
+        }
+      }
+    }
+  }
+  reactions.values_mut().for_each(|uids| uids.sort());
+  let mentions = map
+    .get(txn, COMMENT_MENTIONS)
+    .and_then(|mentions| from_any(&mentions.to_json(txn)).ok())
+    .unwrap_or_default();
+  Some(RowComment {
+    id: map.get_with_txn(txn, COMMENT_ID)?,
+    parent_id: map.get_with_txn(txn, COMMENT_PARENT_ID),
+    uid: map.get_with_txn(txn, COMMENT_UID)?,
+    content: map.get_with_txn(txn, COMMENT_CONTENT).unwrap_or_default(),
+    mentions,
+    reactions,
+    resolved_by: map.get_with_txn(txn, COMMENT_RESOLVED_BY),
+    is_deleted: map
+      .get_with_txn(txn, COMMENT_IS_DELETED)
+      .unwrap_or_default(),
+    created_at: map
+      .get_with_txn(txn, COMMENT_CREATED_AT)
+      .unwrap_or_default(),
+    updated_at: map
+      .get_with_txn(txn, COMMENT_UPDATED_AT)
+      .unwrap_or_default(),
+  })
+}
+
+/// Sends a [RowChange::DidChangeRowComment] for each added or changed comment of the row.
+pub(crate) fn subscribe_row_comment_change(
+  row_id: RowId,
+  comments: &ArrayRef,
+  change_tx: RowChangeSender,
+) {
+  let array = comments.clone();
+  comments.observe_deep_with("change", move |txn, events| {
+    let mut changed = vec![];
+    for event in events.iter() {
+      match event {
+        Event::Array(array_event) => {
+          for change in array_event.delta(txn) {
+            if let Change::Added(values) = change {
+              changed.extend(values.iter().filter_map(|value| match value {
+                YrsValue::YMap(map) => Some(map.clone()),
+                _ => None,
+              }));
+            }
+          }
+        },
+        _ => {
+          // a change in a comment, the first segment of the path is the index of the comment.
+          if let Some(PathSegment::Index(index)) = event.path().pop_front() {
+            if let Some(YrsValue::YMap(map)) = array.get(txn, index) {
+              changed.push(map);
+            }
+          }
+        },
+      }
+    }
+
+    let mut sent = HashSet::new();
+    for map in changed {
+      if let Some(comment) = comment_from_map_ref(txn, &map) {
+        if sent.insert(comment.id.clone()) {
+          let _ = change_tx.send(RowChange::DidChangeRowComment {
+            row_id: row_id.clone(),
+            comment,
+          });
+        }
+      }
+    }
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use collab::preclude::{Doc, ReadTxn, Transact, Update};
+  use yrs::updates::decoder::Decode;
+
+  use super::*;
+
+  fn sync(from: &Doc, to: &Doc) {
+    let update = from
+      .transact()
+      .encode_state_as_update_v1(&to.transact().state_vector());
+    to.transact_mut()
+      .apply_update(Update::decode_v1(&update).unwrap())
+      .unwrap();
+  }
+
+  #[test]
+  fn concurrent_reactions_test() {
+    let doc_1 = Doc::with_client_id(1);
+    let doc_2 = Doc::with_client_id(2);
+    let comments_1 = doc_1.get_or_insert_array("comments");
+    let comments_2 = doc_2.get_or_insert_array("comments");
+    let comment = RowComments::new(&comments_1)
+      .add(
+        &mut doc_1.transact_mut(),
+        CreateRowCommentParams::new(1, "Ship it"),
+      )
+      .unwrap();
+    sync(&doc_1, &doc_2);
+
+    // both users react with the same emoji before they are synced
+    RowComments::new(&comments_1)
+      .add_reaction(&mut doc_1.transact_mut(), &comment.id, "👍", 1)
+      .unwrap();
+    RowComments::new(&comments_2)
+      .add_reaction(&mut doc_2.transact_mut(), &comment.id, "👍", 2)
+      .unwrap();
+    sync(&doc_1, &doc_2);
+    sync(&doc_2, &doc_1);
+
+    for (doc, comments) in [(&doc_1, &comments_1), (&doc_2, &comments_2)] {
+      let comment = RowComments::new(comments)
+        .get(&doc.transact(), &comment.id)
+        .unwrap();
+      assert_eq!(comment.reactions.get("👍").cloned(), Some(vec![1, 2]));
+    }
+  }
+}
@@ -1,12 +1,14 @@
 pub use cell::*;
 pub use comment::*;
 pub use row::*;
+pub use row_history::*;
 pub use row_id::*;
 pub use row_meta::*;
 pub use row_observer::*;
 mod cell;
 mod comment;
 mod row;
+mod row_history;
 mod row_id;
 mod row_meta;
 mod row_observer;
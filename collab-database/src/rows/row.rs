@@ -18,7 +18,9 @@ use crate::database::timestamp;
 
 use crate::error::DatabaseError;
 use crate::rows::{
-  Cell, Cells, CellsUpdate, RowChangeSender, RowId, RowMeta, RowMetaUpdate,
+  Cell, Cells, CellsUpdate, CreateRowCommentParams, RowChangeSender, RowComment, RowCommentMention,
+  RowCommentThread, RowComments, RowHistoryChange, RowHistoryRetention, RowId, RowMeta,
+  RowMetaUpdate, push_row_changes, row_changes_from_array, subscribe_row_comment_change,
   subscribe_row_data_change,
 };
 
@@ -37,6 +39,7 @@ pub type BlockId = i64;
 
 const META: &str = "meta";
 const COMMENT: &str = "comment";
+const HISTORY: &str = "history";
 pub const LAST_MODIFIED: &str = "last_modified";
 pub const CREATED_AT: &str = "created_at";
 
@@ -83,7 +86,8 @@ impl DatabaseRow {
   ) -> Result<Self, DatabaseError> {
     let body = DatabaseRowBody::open(row_id.clone(), &mut collab)?;
     if let Some(change_tx) = change_tx {
-      subscribe_row_data_change(row_id.clone(), &body.data, change_tx);
+      subscribe_row_data_change(row_id.clone(), &body.data, change_tx.clone());
+      subscribe_row_comment_change(row_id.clone(), &body.comments, change_tx);
     }
     Ok(Self {
       row_id,
@@ -100,7 +104,8 @@ impl DatabaseRow {
   ) -> Self {
     let body = DatabaseRowBody::create(row_id.clone(), &mut collab, row);
     if let Some(change_tx) = change_tx {
-      subscribe_row_data_change(row_id.clone(), &body.data, change_tx);
+      subscribe_row_data_change(row_id.clone(), &body.data, change_tx.clone());
+      subscribe_row_comment_change(row_id.clone(), &body.comments, change_tx);
     }
     Self {
       row_id,
@@ -165,6 +170,180 @@ impl DatabaseRow {
     };
   }
 
+  /// Updates the row and records the changes of its cells in its history, in the same
+  /// transaction. Same as [DatabaseRow::update] when the history is disabled.
+  pub fn update_with_history<F>(&mut self, f: F, retention: &RowHistoryRetention)
+  where
+    F: FnOnce(RowUpdate),
+  {
+    if retention.is_disabled() {
+      return self.update(f);
+    }
+    let data = self.body.data.clone();
+    let meta = self.body.meta.clone();
+    let history = self.body.history.clone();
+    let uid = self.collab.origin().client_user_id();
+    let mut txn = self.collab.transact_mut();
+    let previous_cells = cells_from_map_ref(&txn, &data);
+    f(RowUpdate::new(&mut txn, data.clone(), meta));
+    push_cell_changes(&mut txn, &data, &history, &previous_cells, uid, retention);
+
+    // updates the row_id in case it has changed
+    if let Some(row_id) = row_id_from_map_ref(&txn, &data) {
+      self.body.row_id = row_id.clone();
+      self.row_id = row_id;
+    };
+  }
+
+  /// Updates the row like [DatabaseRow::update_with_history], except for the cells of the
+  /// `held_field_ids`: their changes are undone in the same transaction, so that they can be
+  /// checked before being written with [DatabaseRow::write_cells]. Returns the cells of the row
+  /// before the update and the cells written by the update, including the held ones.
+  pub fn update_holding_cells<F>(
+    &mut self,
+    f: F,
+    held_field_ids: &[&str],
+    retention: &RowHistoryRetention,
+  ) -> (Cells, Cells)
+  where
+    F: FnOnce(RowUpdate),
+  {
+    let data = self.body.data.clone();
+    let meta = self.body.meta.clone();
+    let history = self.body.history.clone();
+    let uid = self.collab.origin().client_user_id();
+    let mut txn = self.collab.transact_mut();
+    let previous_cells = cells_from_map_ref(&txn, &data);
+    f(RowUpdate::new(&mut txn, data.clone(), meta));
+    let cells = cells_from_map_ref(&txn, &data);
+    for field_id in held_field_ids {
+      let previous_cell = previous_cells.get(*field_id);
+      if previous_cell != cells.get(*field_id) {
+        set_cell(&mut txn, &data, field_id, previous_cell);
+      }
+    }
+    push_cell_changes(&mut txn, &data, &history, &previous_cells, uid, retention);
+
+    // updates the row_id in case it has changed
+    if let Some(row_id) = row_id_from_map_ref(&txn, &data) {
+      self.body.row_id = row_id.clone();
+      self.row_id = row_id;
+    };
+    (previous_cells, cells)
+  }
+
+  /// Writes the cells of the fields, the fields without a cell in `cells` are removed, and records
+  /// the changes in the history of the row.
+  pub fn write_cells(
+    &mut self,
+    field_ids: &[&str],
+    cells: &Cells,
+    retention: &RowHistoryRetention,
+  ) {
+    let data = self.body.data.clone();
+    let history = self.body.history.clone();
+    let uid = self.collab.origin().client_user_id();
+    let mut txn = self.collab.transact_mut();
+    let previous_cells = cells_from_map_ref(&txn, &data);
+    for field_id in field_ids {
+      set_cell(&mut txn, &data, field_id, cells.get(*field_id));
+    }
+    push_cell_changes(&mut txn, &data, &history, &previous_cells, uid, retention);
+  }
+
+  /// Records the changes of the cells from `previous_cells` to the current cells in the history
+  /// of the row. The changes are attributed to the user of the origin of the row collab.
+  pub fn record_cell_changes(&mut self, previous_cells: &Cells, retention: &RowHistoryRetention) {
+    if retention.is_disabled() {
+      return;
+    }
+    let data = self.body.data.clone();
+    let history = self.body.history.clone();
+    let uid = self.collab.origin().client_user_id();
+    let mut txn = self.collab.transact_mut();
+    push_cell_changes(&mut txn, &data, &history, previous_cells, uid, retention);
+  }
+
+  /// Returns the changes of the cells of the row, from the oldest to the newest.
+  pub fn get_history(&self) -> Vec<RowHistoryChange> {
+    let txn = self.collab.transact();
+    row_changes_from_array(&txn, &self.body.history)
+  }
+
+  /// Returns the comments of the row, from the oldest to the newest.
+  pub fn get_comments(&self) -> Vec<RowComment> {
+    let txn = self.collab.transact();
+    RowComments::new(&self.body.comments).get_all(&txn)
+  }
+
+  pub fn get_comment(&self, comment_id: &str) -> Option<RowComment> {
+    let txn = self.collab.transact();
+    RowComments::new(&self.body.comments).get(&txn, comment_id)
+  }
+
+  /// Returns the comments of the row with their replies. The deleted comments without replies
+  /// are left out.
+  pub fn get_comment_threads(&self) -> Vec<RowCommentThread> {
+    let txn = self.collab.transact();
+    RowComments::new(&self.body.comments).get_threads(&txn)
+  }
+
+  /// Adds a comment, or a reply when the params have a parent id. Returns
+  /// [DatabaseError::RecordNotFound] if the parent comment doesn't exist.
+  pub fn add_comment(
+    &mut self,
+    params: CreateRowCommentParams,
+  ) -> Result<RowComment, DatabaseError> {
+    let mut txn = self.collab.transact_mut();
+    RowComments::new(&self.body.comments).add(&mut txn, params)
+  }
+
+  pub fn update_comment(
+    &mut self,
+    comment_id: &str,
+    content: &str,
+    mentions: Vec<RowCommentMention>,
+  ) -> Result<(), DatabaseError> {
+    let mut txn = self.collab.transact_mut();
+    RowComments::new(&self.body.comments).update_content(&mut txn, comment_id, content, mentions)
+  }
+
+  /// Deletes the comment. Its replies are kept.
+  pub fn delete_comment(&mut self, comment_id: &str) -> Result<(), DatabaseError> {
+    let mut txn = self.collab.transact_mut();
+    RowComments::new(&self.body.comments).delete(&mut txn, comment_id)
+  }
+
+  pub fn add_comment_reaction(
+    &mut self,
+    comment_id: &str,
+    emoji: &str,
+    uid: i64,
+  ) -> Result<(), DatabaseError> {
+    let mut txn = self.collab.transact_mut();
+    RowComments::new(&self.body.comments).add_reaction(&mut txn, comment_id, emoji, uid)
+  }
+
+  pub fn remove_comment_reaction(
+    &mut self,
+    comment_id: &str,
+    emoji: &str,
+    uid: i64,
+  ) -> Result<(), DatabaseError> {
+    let mut txn = self.collab.transact_mut();
+    RowComments::new(&self.body.comments).remove_reaction(&mut txn, comment_id, emoji, uid)
+  }
+
+  /// Resolves the comment when `resolved_by` is set, reopens it otherwise.
+  pub fn set_comment_resolved_by(
+    &mut self,
+    comment_id: &str,
+    resolved_by: Option<i64>,
+  ) -> Result<(), DatabaseError> {
+    let mut txn = self.collab.transact_mut();
+    RowComments::new(&self.body.comments).set_resolved_by(&mut txn, comment_id, resolved_by)
+  }
+
   pub fn update_meta<F>(&mut self, f: F)
   where
     F: FnOnce(RowMetaUpdate),
@@ -213,8 +392,8 @@ pub struct DatabaseRowBody {
   data: MapRef,
   #[allow(dead_code)]
   meta: MapRef,
-  #[allow(dead_code)]
   comments: ArrayRef,
+  history: ArrayRef,
 }
 
 impl DatabaseRowBody {
@@ -232,6 +411,7 @@ impl DatabaseRowBody {
     let data: MapRef = collab.data.get_or_init(&mut txn, DATABASE_ROW_DATA);
     let meta: MapRef = collab.data.get_or_init(&mut txn, META);
     let comments: ArrayRef = collab.data.get_or_init(&mut txn, COMMENT);
+    let history: ArrayRef = collab.data.get_or_init(&mut txn, HISTORY);
     if let Some(row) = row {
       RowBuilder::new(&mut txn, data.clone(), meta.clone())
         .update(|update| {
@@ -252,6 +432,7 @@ impl DatabaseRowBody {
       data,
       meta,
       comments,
+      history,
     }
   }
 
@@ -608,6 +789,49 @@ impl<'a, 'b> RowUpdate<'a, 'b> {
   }
 }
 
+fn cells_from_map_ref<T: ReadTxn>(txn: &T, data: &MapRef) -> Cells {
+  row_from_map_ref(data, txn)
+    .map(|row| row.cells)
+    .unwrap_or_default()
+}
+
+/// Appends the changes of the cells from `previous_cells` to the cells of `data` to the history.
+fn push_cell_changes(
+  txn: &mut TransactionMut,
+  data: &MapRef,
+  history: &ArrayRef,
+  previous_cells: &Cells,
+  uid: Option<i64>,
+  retention: &RowHistoryRetention,
+) {
+  if retention.is_disabled() {
+    return;
+  }
+  let now = timestamp();
+  let cells = cells_from_map_ref(txn, data);
+  let changes = RowHistoryChange::from_cells(previous_cells, &cells, uid, now);
+  if !changes.is_empty() {
+    push_row_changes(txn, history, changes, retention, now);
+  }
+}
+
+/// Sets the cell of the field to `cell`, or removes it when `cell` is `None`.
+fn set_cell(txn: &mut TransactionMut, data: &MapRef, field_id: &str, cell: Option<&Cell>) {
+  let cells_map: MapRef = data.get_or_init(txn, ROW_CELLS);
+  match cell {
+    Some(cell) => {
+      let cell_map: MapRef = cells_map.get_or_init(txn, field_id);
+      cell_map.clear(txn);
+      if let Err(err) = Any::from(cell.clone()).fill(txn, &cell_map) {
+        error!("Failed to write the cell of field {}: {}", field_id, err);
+      }
+    },
+    None => {
+      cells_map.remove(txn, field_id);
+    },
+  }
+}
+
 pub(crate) const ROW_ID: &str = "id";
 pub const ROW_DATABASE_ID: &str = "database_id";
 pub(crate) const ROW_VISIBILITY: &str = "visibility";
@@ -0,0 +1,177 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use collab::preclude::{Any, Array, ArrayRef, ReadTxn, TransactionMut, YrsValue};
+use collab::util::AnyMapExt;
+
+use crate::rows::{Cell, Cells};
+
+const CHANGE_FIELD_ID: &str = "field_id";
+const CHANGE_OLD_VALUE: &str = "old_value";
+const CHANGE_NEW_VALUE: &str = "new_value";
+const CHANGE_UID: &str = "uid";
+const CHANGE_TIMESTAMP: &str = "timestamp";
+
+const RETENTION_MAX_ENTRIES: &str = "max_entries";
+const RETENTION_MAX_AGE: &str = "max_age";
+
+/// A change of a cell of a row, stored in the history of the row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowHistoryChange {
+  pub field_id: String,
+  /// The cell before the change, None if the cell was empty.
+  pub old_value: Option<Cell>,
+  /// The cell after the change, None if the cell was removed.
+  pub new_value: Option<Cell>,
+  /// The id of the user who changed the cell, None if the change has no client origin.
+  pub uid: Option<i64>,
+  /// The timestamp of the change, in seconds.
+  pub timestamp: i64,
+}
+
+impl RowHistoryChange {
+  /// Returns the changes of the cells from `previous_cells` to `cells`, ordered by field id.
+  pub fn from_cells(
+    previous_cells: &Cells,
+    cells: &Cells,
+    uid: Option<i64>,
+    timestamp: i64,
+  ) -> Vec<RowHistoryChange> {
+    let mut field_ids = previous_cells
+      .keys()
+      .chain(cells.keys())
+      .collect::<HashSet<_>>()
+      .into_iter()
+      .filter(|field_id| previous_cells.get(*field_id) != cells.get(*field_id))
+      .collect::<Vec<_>>();
+    field_ids.sort();
+    field_ids
+      .into_iter()
+      .map(|field_id| RowHistoryChange {
+        field_id: field_id.clone(),
+        old_value: previous_cells.get(field_id).cloned(),
+        new_value: cells.get(field_id).cloned(),
+        uid,
+        timestamp,
+      })
+      .collect()
+  }
+
+  fn from_any(value: &Any) -> Option<Self> {
+    let Any::Map(map) = value else {
+      return None;
+    };
+    let cell = |key: &str| match map.get(key) {
+      Some(Any::Map(cell)) => Some(Cell::clone(cell)),
+      _ => None,
+    };
+    Some(Self {
+      field_id: map.get_as(CHANGE_FIELD_ID)?,
+      old_value: cell(CHANGE_OLD_VALUE),
+      new_value: cell(CHANGE_NEW_VALUE),
+      uid: map.get_as(CHANGE_UID),
+      timestamp: map.get_as(CHANGE_TIMESTAMP).unwrap_or_default(),
+    })
+  }
+
+  fn into_any(self) -> Any {
+    let cell = |cell: Option<Cell>| match cell {
+      Some(cell) => Any::Map(Arc::new(cell)),
+      None => Any::Null,
+    };
+    Any::Map(Arc::new(HashMap::from([
+      (CHANGE_FIELD_ID.to_string(), Any::from(self.field_id)),
+      (CHANGE_OLD_VALUE.to_string(), cell(self.old_value)),
+      (CHANGE_NEW_VALUE.to_string(), cell(self.new_value)),
+      (
+        CHANGE_UID.to_string(),
+        self.uid.map(Any::BigInt).unwrap_or(Any::Null),
+      ),
+      (CHANGE_TIMESTAMP.to_string(), Any::BigInt(self.timestamp)),
+    ])))
+  }
+}
+
+/// How many changes are kept in the history of each row of a database. The oldest changes are
+/// removed when a change is recorded. The history is disabled by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RowHistoryRetention {
+  /// The maximum number of changes of a row. The history is disabled when it is 0.
+  pub max_entries: usize,
+  /// The maximum age of the changes, in seconds.
+  pub max_age: Option<i64>,
+}
+
+impl RowHistoryRetention {
+  pub fn is_disabled(&self) -> bool {
+    self.max_entries == 0
+  }
+}
+
+impl From<&HashMap<String, Any>> for RowHistoryRetention {
+  fn from(map: &HashMap<String, Any>) -> Self {
+    Self {
+      max_entries: map
+        .get_as::<i64>(RETENTION_MAX_ENTRIES)
+        .map(|max_entries| max_entries.max(0) as usize)
+        .unwrap_or_default(),
+      max_age: map.get_as(RETENTION_MAX_AGE),
+    }
+  }
+}
+
+impl From<RowHistoryRetention> for Any {
+  fn from(retention: RowHistoryRetention) -> Self {
+    Any::Map(Arc::new(HashMap::from([
+      (
+        RETENTION_MAX_ENTRIES.to_string(),
+        Any::BigInt(retention.max_entries as i64),
+      ),
+      (
+        RETENTION_MAX_AGE.to_string(),
+        retention.max_age.map(Any::BigInt).unwrap_or(Any::Null),
+      ),
+    ])))
+  }
+}
+
+/// Appends the changes to the history and removes the changes that are not retained.
+pub(crate) fn push_row_changes(
+  txn: &mut TransactionMut,
+  history: &ArrayRef,
+  changes: Vec<RowHistoryChange>,
+  retention: &RowHistoryRetention,
+  now: i64,
+) {
+  for change in changes {
+    history.push_back(txn, change.into_any());
+  }
+
+  let len = history.len(txn) as usize;
+  let mut expired = len.saturating_sub(retention.max_entries);
+  if let Some(max_age) = retention.max_age {
+    let changes = row_changes_from_array(txn, history);
+    let outdated = changes
+      .iter()
+      .take_while(|change| now - change.timestamp > max_age)
+      .count();
+    expired = expired.max(outdated);
+  }
+  if expired > 0 {
+    history.remove_range(txn, 0, expired as u32);
+  }
+}
+
+/// Returns the changes of the history, from the oldest to the newest.
+pub(crate) fn row_changes_from_array<T: ReadTxn>(
+  txn: &T,
+  history: &ArrayRef,
+) -> Vec<RowHistoryChange> {
+  history
+    .iter(txn)
+    .filter_map(|value| match value {
+      YrsValue::Any(any) => RowHistoryChange::from_any(&any),
+      _ => None,
+    })
+    .collect()
+}
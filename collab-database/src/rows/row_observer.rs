@@ -1,4 +1,4 @@
-use crate::rows::{Cell, ROW_CELLS, ROW_HEIGHT, ROW_VISIBILITY, Row, RowId};
+use crate::rows::{Cell, ROW_CELLS, ROW_HEIGHT, ROW_VISIBILITY, Row, RowComment, RowId};
 
 use collab::preclude::{DeepObservable, EntryChange, Event, MapRef, TransactionMut};
 use collab::preclude::{PathSegment, ToJson};
@@ -30,6 +30,16 @@ pub enum RowChange {
   DidUpdateRowComment {
     row: Row,
   },
+  /// Sent when a comment of the row is added or changed.
+  DidChangeRowComment {
+    row_id: RowId,
+    comment: RowComment,
+  },
+  /// Sent by [crate::database::Database::update_rows] with the rows that were written, after the
+  /// changes sent by each row.
+  DidUpdateRows {
+    row_ids: Vec<RowId>,
+  },
 }
 
 pub(crate) fn subscribe_row_data_change(
@@ -1,6 +1,321 @@
+use chrono::NaiveDate;
 use collab::preclude::Any;
-use std::collections::HashMap;
+use collab::util::AnyMapExt;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::entity::{FieldType, default_type_option_data_from_type};
+use crate::fields::date_type_option::{DateCellData, DateTypeOption};
+use crate::fields::select_type_option::{SELECTION_IDS_SEPARATOR, SelectOptionIds};
+use crate::fields::{Field, TypeOptionCellReader, type_option_cell_reader};
+use crate::rows::{Cell, Row};
+use crate::template::check_list_parse::ChecklistCellData;
 
 pub type FilterArray = Vec<Any>;
 pub type FilterMap = HashMap<String, Any>;
 pub type FilterMapBuilder = HashMap<String, Any>;
+
+pub const FILTER_TYPE: &str = "filter_type";
+pub const FILTER_CHILDREN: &str = "children";
+pub const FILTER_FIELD_ID: &str = "field_id";
+pub const FILTER_CONDITION: &str = "condition";
+pub const FILTER_CONTENT: &str = "content";
+
+/// The kind of a filter. The [FilterType::And] and [FilterType::Or] filters combine the filters in
+/// their [FILTER_CHILDREN], a [FilterType::Data] filter checks the cells of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FilterType {
+  And = 0,
+  Or = 1,
+  Data = 2,
+}
+
+impl From<i64> for FilterType {
+  fn from(value: i64) -> Self {
+    match value {
+      0 => FilterType::And,
+      1 => FilterType::Or,
+      _ => FilterType::Data,
+    }
+  }
+}
+
+enum FilterNode {
+  And(Vec<FilterNode>),
+  Or(Vec<FilterNode>),
+  Data {
+    field_id: String,
+    condition: i64,
+    content: String,
+  },
+}
+
+impl FilterNode {
+  fn from_any_map(map: &HashMap<String, Any>) -> Self {
+    let filter_type = map
+      .get_as::<i64>(FILTER_TYPE)
+      .map(FilterType::from)
+      .unwrap_or(FilterType::Data);
+    let children = || match map.get(FILTER_CHILDREN) {
+      Some(Any::Array(children)) => children
+        .iter()
+        .filter_map(|child| match child {
+          Any::Map(child) => Some(Self::from_any_map(child)),
+          _ => None,
+        })
+        .collect(),
+      _ => vec![],
+    };
+    match filter_type {
+      FilterType::And => Self::And(children()),
+      FilterType::Or => Self::Or(children()),
+      FilterType::Data => Self::Data {
+        field_id: map.get_as(FILTER_FIELD_ID).unwrap_or_default(),
+        condition: map.get_as(FILTER_CONDITION).unwrap_or_default(),
+        content: map.get_as(FILTER_CONTENT).unwrap_or_default(),
+      },
+    }
+  }
+}
+
+struct FilterField {
+  field_type: FieldType,
+  reader: Box<dyn TypeOptionCellReader>,
+  /// The type option of a date field, which gives the day of its cells.
+  date_type_option: Option<DateTypeOption>,
+}
+
+/// Checks the rows against the filters of a view. The filters of the view are combined with
+/// [FilterType::And].
+///
+/// The text, number, checkbox, select, checklist and date filters are supported, the other
+/// filters and the filters of deleted fields match all rows.
+pub struct RowFilter {
+  filters: Vec<FilterNode>,
+  fields: HashMap<String, FilterField>,
+}
+
+impl RowFilter {
+  pub fn new(filters: &[FilterMap], fields: Vec<Field>) -> Self {
+    let fields = fields
+      .into_iter()
+      .map(|field| {
+        let field_type = FieldType::from(field.field_type);
+        let type_option = field
+          .get_any_type_option(field_type.type_id())
+          .unwrap_or_else(|| default_type_option_data_from_type(field_type));
+        let date_type_option =
+          (field_type == FieldType::DateTime).then(|| DateTypeOption::from(type_option.clone()));
+        let reader = type_option_cell_reader(type_option, &field_type);
+        (
+          field.id,
+          FilterField {
+            field_type,
+            reader,
+            date_type_option,
+          },
+        )
+      })
+      .collect();
+    Self {
+      filters: filters.iter().map(FilterNode::from_any_map).collect(),
+      fields,
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.filters.is_empty()
+  }
+
+  pub fn matches(&self, row: &Row) -> bool {
+    self
+      .filters
+      .iter()
+      .all(|filter| self.matches_node(filter, row))
+  }
+
+  fn matches_node(&self, node: &FilterNode, row: &Row) -> bool {
+    match node {
+      FilterNode::And(children) => children.iter().all(|child| self.matches_node(child, row)),
+      FilterNode::Or(children) => {
+        children.is_empty() || children.iter().any(|child| self.matches_node(child, row))
+      },
+      FilterNode::Data {
+        field_id,
+        condition,
+        content,
+      } => match self.fields.get(field_id) {
+        Some(field) => field.matches(row.cells.get(field_id), *condition, content),
+        None => true,
+      },
+    }
+  }
+}
+
+impl FilterField {
+  fn matches(&self, cell: Option<&Cell>, condition: i64, content: &str) -> bool {
+    match self.field_type {
+      FieldType::RichText | FieldType::URL | FieldType::Summary | FieldType::Translate => {
+        let text = cell
+          .map(|cell| self.reader.stringify_cell(cell))
+          .unwrap_or_default();
+        text_matches(&text, condition, content)
+      },
+      FieldType::Number => {
+        let number = cell.and_then(|cell| self.reader.numeric_cell(cell));
+        number_matches(number, condition, content)
+      },
+      FieldType::Checkbox => {
+        let checked = cell.and_then(|cell| self.reader.numeric_cell(cell)) == Some(1.0);
+        match condition {
+          0 => checked,
+          1 => !checked,
+          _ => true,
+        }
+      },
+      FieldType::SingleSelect | FieldType::MultiSelect => {
+        let option_ids = cell.map(SelectOptionIds::from).unwrap_or_default();
+        select_matches(&option_ids, condition, content)
+      },
+      FieldType::Checklist => {
+        let cell_data = cell.map(ChecklistCellData::from).unwrap_or_default();
+        let is_complete = !cell_data.options.is_empty()
+          && cell_data.selected_options().len() == cell_data.options.len();
+        match condition {
+          0 => is_complete,
+          1 => !is_complete,
+          _ => true,
+        }
+      },
+      FieldType::DateTime => match &self.date_type_option {
+        Some(type_option) => {
+          let date = cell.and_then(|cell| type_option.local_date(&DateCellData::from(cell)));
+          date_matches(type_option, date, condition, content)
+        },
+        None => true,
+      },
+      _ => true,
+    }
+  }
+}
+
+/// Is, IsNot, Contains, DoesNotContain, StartsWith, EndsWith, IsEmpty and IsNotEmpty, case
+/// insensitive.
+fn text_matches(text: &str, condition: i64, content: &str) -> bool {
+  let text = text.to_lowercase();
+  let content = content.to_lowercase();
+  match condition {
+    0 => text == content,
+    1 => text != content,
+    2 => text.contains(&content),
+    3 => !text.contains(&content),
+    4 => text.starts_with(&content),
+    5 => text.ends_with(&content),
+    6 => text.trim().is_empty(),
+    7 => !text.trim().is_empty(),
+    _ => true,
+  }
+}
+
+/// Equal, NotEqual, GreaterThan, LessThan, GreaterThanOrEqualTo, LessThanOrEqualTo, IsEmpty and
+/// IsNotEmpty.
+fn number_matches(number: Option<f64>, condition: i64, content: &str) -> bool {
+  match condition {
+    6 => return number.is_none(),
+    7 => return number.is_some(),
+    _ => {},
+  }
+  let Ok(expected) = content.trim().parse::<f64>() else {
+    // a filter without value matches all rows.
+    return true;
+  };
+  let Some(number) = number else {
+    return false;
+  };
+  match condition {
+    0 => number == expected,
+    1 => number != expected,
+    2 => number > expected,
+    3 => number < expected,
+    4 => number >= expected,
+    5 => number <= expected,
+    _ => true,
+  }
+}
+
+/// StartsOn, StartsBefore, StartsAfter, StartsOnOrBefore, StartsOnOrAfter, StartsBetween,
+/// IsEmpty and IsNotEmpty. The content of the filter is a JSON object with the `timestamp` of the
+/// day, or the `start` and `end` timestamps of the days between. The days are compared in the
+/// timezone of the cell, or of the field.
+fn date_matches(
+  type_option: &DateTypeOption,
+  date: Option<NaiveDate>,
+  condition: i64,
+  content: &str,
+) -> bool {
+  match condition {
+    6 => return date.is_none(),
+    7 => return date.is_some(),
+    _ => {},
+  }
+  let content = serde_json::from_str::<DateFilterContent>(content).unwrap_or_default();
+  let day = |timestamp: Option<i64>| {
+    timestamp.and_then(|timestamp| type_option.local_date_from_timestamp(timestamp))
+  };
+  let (expected, start, end) = (day(content.timestamp), day(content.start), day(content.end));
+  if condition == 5 {
+    return match (date, start, end) {
+      (Some(date), Some(start), Some(end)) => start <= date && date <= end,
+      (_, None, _) | (_, _, None) => true,
+      (None, _, _) => false,
+    };
+  }
+  let Some(expected) = expected else {
+    // a filter without value matches all rows.
+    return true;
+  };
+  let Some(date) = date else {
+    return false;
+  };
+  match condition {
+    0 => date == expected,
+    1 => date < expected,
+    2 => date > expected,
+    3 => date <= expected,
+    4 => date >= expected,
+    _ => true,
+  }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DateFilterContent {
+  #[serde(default)]
+  timestamp: Option<i64>,
+  #[serde(default)]
+  start: Option<i64>,
+  #[serde(default)]
+  end: Option<i64>,
+}
+
+/// OptionIs, OptionIsNot, OptionContains, OptionDoesNotContain, OptionIsEmpty and
+/// OptionIsNotEmpty. The content of the filter is the ids of the options.
+fn select_matches(option_ids: &[String], condition: i64, content: &str) -> bool {
+  let expected = content
+    .split(SELECTION_IDS_SEPARATOR)
+    .filter(|id| !id.is_empty())
+    .collect::<HashSet<_>>();
+  let selected = option_ids
+    .iter()
+    .map(|id| id.as_str())
+    .collect::<HashSet<_>>();
+  match condition {
+    0 => expected.is_empty() || selected == expected,
+    1 => expected.is_empty() || selected != expected,
+    2 => expected.is_empty() || !selected.is_disjoint(&expected),
+    3 => expected.is_empty() || selected.is_disjoint(&expected),
+    4 => selected.is_empty(),
+    5 => !selected.is_empty(),
+    _ => true,
+  }
+}
@@ -1,7 +1,14 @@
-use collab_database::rows::CreateRowParams;
+use std::collections::HashSet;
+
+use collab::util::AnyMapExt;
+use collab_database::database::gen_row_id;
+use collab_database::rows::{CreateRowParams, RowChange, RowUpdate};
+use collab_database::template::entity::CELL_DATA;
+use collab_database::views::{DatabaseViewChange, OrderObjectPosition};
 use uuid::Uuid;
 
 use crate::database_test::helper::create_database;
+use crate::helper::TestTextCell;
 
 #[tokio::test]
 async fn create_one_row_test() {
@@ -31,3 +38,106 @@ async fn create_rows_test() {
   let rows = database_test.get_rows_for_view("v1").await;
   assert_eq!(rows.len(), 100);
 }
+
+#[tokio::test]
+async fn create_rows_in_single_transaction_test() {
+  let database_id = Uuid::new_v4().to_string();
+  let mut database_test = create_database(1, &database_id);
+  let mut view_change_rx = database_test.subscribe_view_change().unwrap();
+  let row_ids = (0..100).map(|_| gen_row_id()).collect::<Vec<_>>();
+  let params = row_ids
+    .iter()
+    .map(|row_id| CreateRowParams::new(row_id.clone(), database_id.clone()))
+    .collect();
+  let (row_orders, _) = database_test.create_rows(params).await.unwrap();
+  assert_eq!(row_orders.len(), 100);
+
+  // all the row orders are inserted with one change in each view
+  let mut inserted_row_orders = vec![];
+  while let Ok(change) = view_change_rx.try_recv() {
+    if let DatabaseViewChange::DidUpdateRowOrders {
+      database_view_id,
+      insert_row_orders,
+      ..
+    } = change
+    {
+      inserted_row_orders.push((database_view_id, insert_row_orders.len()));
+    }
+  }
+  let view_ids = inserted_row_orders
+    .iter()
+    .map(|(view_id, _)| view_id.as_str())
+    .collect::<HashSet<_>>();
+  assert_eq!(view_ids.len(), inserted_row_orders.len());
+  assert!(view_ids.contains("v1"));
+  assert!(inserted_row_orders.iter().all(|(_, len)| *len == 100));
+
+  let first_row_id = gen_row_id();
+  let after_row_id = gen_row_id();
+  database_test
+    .create_rows(vec![
+      CreateRowParams::new(first_row_id.clone(), database_id.clone())
+        .with_row_position(OrderObjectPosition::Start),
+      CreateRowParams::new(after_row_id.clone(), database_id.clone())
+        .with_row_position(OrderObjectPosition::After(row_ids[0].to_string())),
+    ])
+    .await
+    .unwrap();
+  let rows = database_test.get_rows_for_view("v1").await;
+  assert_eq!(rows.len(), 102);
+  assert_eq!(rows[0].id, first_row_id);
+  assert_eq!(rows[1].id, row_ids[0]);
+  assert_eq!(rows[2].id, after_row_id);
+  assert_eq!(rows[101].id, row_ids[99]);
+}
+
+#[tokio::test]
+async fn update_rows_test() {
+  let database_id = Uuid::new_v4().to_string();
+  let mut database_test = create_database(1, &database_id);
+  let row_ids = (0..10).map(|_| gen_row_id()).collect::<Vec<_>>();
+  let params = row_ids
+    .iter()
+    .map(|row_id| CreateRowParams::new(row_id.clone(), database_id.clone()))
+    .collect();
+  database_test.create_rows(params).await.unwrap();
+
+  let set_name = |name: String| {
+    move |row: RowUpdate| {
+      row.update_cells(|cells| {
+        cells.insert_cell("name", TestTextCell::from(name.as_str()).into());
+      });
+    }
+  };
+  let updates = row_ids
+    .iter()
+    .enumerate()
+    .map(|(index, row_id)| (row_id.clone(), set_name(format!("row {}", index))))
+    .collect();
+  let mut row_change_rx = database_test.subscribe_row_change().unwrap();
+  let violations = database_test.update_rows(updates).await;
+  assert!(violations.is_empty());
+
+  // a single event is sent for the batch
+  let mut updated_rows = vec![];
+  while let Ok(change) = row_change_rx.try_recv() {
+    if let RowChange::DidUpdateRows { row_ids } = change {
+      updated_rows.push(row_ids);
+    }
+  }
+  assert_eq!(updated_rows, vec![row_ids.clone()]);
+
+  let rows = database_test.get_rows_for_view("v1").await;
+  for (index, row) in rows.iter().enumerate() {
+    assert_eq!(
+      row
+        .cells
+        .get("name")
+        .and_then(|cell| cell.get_as::<String>(CELL_DATA)),
+      Some(format!("row {}", index))
+    );
+  }
+
+  database_test.remove_rows(&row_ids[..5]).await;
+  assert_eq!(database_test.get_rows_for_view("v1").await.len(), 5);
+}
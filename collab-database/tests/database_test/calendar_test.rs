@@ -0,0 +1,268 @@
+use std::str::FromStr;
+
+use collab::preclude::Any;
+use collab_database::database_calendar::{RecurrenceFrequency, RecurrenceRule};
+use collab_database::entity::FieldType;
+use collab_database::error::DatabaseError;
+use collab_database::fields::Field;
+use collab_database::fields::date_type_option::{DateCellData, DateTypeOption};
+use collab_database::rows::{Cell, Cells, CreateRowParams, new_cell_builder};
+use collab_database::template::entity::CELL_DATA;
+use collab_database::views::{
+  CalendarLayoutSetting, DatabaseLayout, FILTER_CONDITION, FILTER_FIELD_ID, FilterMapBuilder,
+};
+use uuid::Uuid;
+
+use crate::database_test::helper::{DatabaseTest, DatabaseTestBuilder};
+use crate::helper::TestTextCell;
+
+// 2024-08-01T00:00:00Z and 2024-09-01T00:00:00Z
+const AUGUST_START: i64 = 1722470400;
+const AUGUST_END: i64 = 1725148800;
+const DAY: i64 = 86400;
+
+fn date_cell(timestamp: i64, end: Option<i64>, include_time: bool, recurrence: &str) -> Cell {
+  let mut cell_data = DateCellData::new(timestamp, include_time, end.is_some(), String::new());
+  cell_data.end_timestamp = end;
+  if !recurrence.is_empty() {
+    cell_data.recurrence = Some(recurrence.to_string());
+  }
+  Cell::from(&cell_data)
+}
+
+fn checkbox_cell(checked: bool) -> Cell {
+  let mut cell = new_cell_builder(FieldType::Checkbox);
+  cell.insert(CELL_DATA.into(), Any::from(checked.to_string()));
+  cell
+}
+
+async fn create_calendar_database() -> DatabaseTest {
+  let database_id = Uuid::new_v4().to_string();
+  let row = |title: &str, date: Option<Cell>, done: bool| {
+    let mut cells = Cells::from([
+      ("title".to_string(), TestTextCell::from(title).into()),
+      ("done".to_string(), checkbox_cell(done)),
+    ]);
+    if let Some(date) = date {
+      cells.insert("date".to_string(), date);
+    }
+    CreateRowParams::new(Uuid::new_v4(), database_id.clone()).with_cells(cells)
+  };
+
+  DatabaseTestBuilder::new(1, &database_id)
+    .with_layout(DatabaseLayout::Calendar)
+    .with_field(Field::new(
+      "title".to_string(),
+      "title".to_string(),
+      FieldType::RichText.into(),
+      true,
+    ))
+    .with_field(Field::new(
+      "date".to_string(),
+      "date".to_string(),
+      FieldType::DateTime.into(),
+      false,
+    ))
+    .with_field(Field::new(
+      "done".to_string(),
+      "done".to_string(),
+      FieldType::Checkbox.into(),
+      false,
+    ))
+    .with_layout_setting(CalendarLayoutSetting::new("date".to_string()).into())
+    // 2024-08-05T09:00:00Z, every monday and wednesday
+    .with_row(row(
+      "standup",
+      Some(date_cell(
+        1722848400,
+        None,
+        true,
+        "FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4",
+      )),
+      false,
+    ))
+    // from 2024-07-29 to 2024-08-01
+    .with_row(row(
+      "trip",
+      Some(date_cell(1722211200, Some(AUGUST_START), false, "")),
+      false,
+    ))
+    // 2024-09-01
+    .with_row(row(
+      "release",
+      Some(date_cell(AUGUST_END, None, false, "")),
+      false,
+    ))
+    // 2024-08-09
+    .with_row(row(
+      "review",
+      Some(date_cell(1723161600, None, false, "")),
+      true,
+    ))
+    .with_row(row("someday", None, false))
+    .build()
+    .await
+}
+
+#[tokio::test]
+async fn calendar_events_test() {
+  let database_test = create_calendar_database().await;
+  let events = database_test
+    .get_calendar_events("v1", AUGUST_START, AUGUST_END)
+    .await
+    .unwrap();
+  let titles = events
+    .iter()
+    .map(|event| event.title.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(
+    titles,
+    vec!["trip", "standup", "standup", "review", "standup", "standup"]
+  );
+
+  let trip = &events[0];
+  assert!(trip.is_all_day);
+  assert_eq!(trip.end, Some(AUGUST_START));
+  assert_eq!(trip.occurrence, None);
+
+  let standups = events
+    .iter()
+    .filter(|event| event.title == "standup")
+    .collect::<Vec<_>>();
+  assert!(standups.iter().all(|event| !event.is_all_day));
+  let starts = standups.iter().map(|event| event.start).collect::<Vec<_>>();
+  let monday = 1722848400;
+  assert_eq!(
+    starts,
+    vec![monday, monday + 2 * DAY, monday + 7 * DAY, monday + 9 * DAY]
+  );
+  assert_eq!(standups[3].occurrence, Some(3));
+}
+
+#[tokio::test]
+async fn calendar_events_with_filter_test() {
+  let mut database_test = create_calendar_database().await;
+  // hide the checked rows
+  database_test.insert_filter(
+    "v1",
+    FilterMapBuilder::from([
+      ("id".into(), "filter_1".into()),
+      (FILTER_FIELD_ID.into(), "done".into()),
+      (FILTER_CONDITION.into(), Any::BigInt(1)),
+    ]),
+  );
+  let events = database_test
+    .get_calendar_events("v1", AUGUST_START, AUGUST_END)
+    .await
+    .unwrap();
+  assert!(events.iter().all(|event| event.title != "review"));
+  assert_eq!(events.len(), 5);
+
+  // the second week only
+  let from = AUGUST_START + 10 * DAY;
+  let events = database_test
+    .get_calendar_events("v1", from, from + 7 * DAY)
+    .await
+    .unwrap();
+  let titles = events
+    .iter()
+    .map(|event| (event.title.as_str(), event.occurrence))
+    .collect::<Vec<_>>();
+  assert_eq!(titles, vec![("standup", Some(2)), ("standup", Some(3))]);
+}
+
+#[tokio::test]
+async fn calendar_events_timezone_test() {
+  let mut database_test = create_calendar_database().await;
+  database_test.update_field("date", |update| {
+    update.update_type_options(|type_options| {
+      type_options.insert(
+        &FieldType::DateTime.type_id(),
+        DateTypeOption {
+          timezone_id: "America/New_York".to_string(),
+          ..DateTypeOption::default_utc()
+        },
+      );
+    });
+  });
+  let database_id = database_test.get_database_id();
+  let row = |title: &str, date: DateCellData| {
+    let cells = Cells::from([
+      ("title".to_string(), TestTextCell::from(title).into()),
+      ("date".to_string(), Cell::from(&date)),
+    ]);
+    CreateRowParams::new(Uuid::new_v4(), database_id.clone()).with_cells(cells)
+  };
+  // 2024-10-28T09:00 in New York, the second occurrence is after the end of the daylight saving
+  // time.
+  let mut call = DateCellData::new(1730120400, true, false, String::new());
+  call.recurrence = Some("FREQ=WEEKLY;COUNT=2".to_string());
+  // 2024-08-10 in Tokyo, the cell's timezone replaces the one of the field.
+  let mut holiday = DateCellData::new(1723215600, false, false, String::new());
+  holiday.timezone_id = Some("Asia/Tokyo".to_string());
+  let rows = vec![row("call", call), row("holiday", holiday)];
+  database_test.create_rows(rows).await.unwrap();
+
+  // from 2024-10-01 to 2024-12-01
+  let events = database_test
+    .get_calendar_events("v1", 1727740800, 1733011200)
+    .await
+    .unwrap();
+  let starts = events
+    .iter()
+    .map(|event| (event.title.as_str(), event.start))
+    .collect::<Vec<_>>();
+  assert_eq!(starts, vec![("call", 1730120400), ("call", 1730728800)]);
+
+  // the holiday lasts until the end of the day in Tokyo, 2024-08-10T15:00:00Z.
+  let from = 1723298400;
+  let events = database_test
+    .get_calendar_events("v1", from, from + 1800)
+    .await
+    .unwrap();
+  let titles = events
+    .iter()
+    .map(|event| event.title.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(titles, vec!["holiday"]);
+}
+
+#[test]
+fn recurrence_rule_test() {
+  let rule = RecurrenceRule::from_str("RRULE:FREQ=MONTHLY;INTERVAL=1;UNTIL=20241231").unwrap();
+  assert_eq!(rule.frequency, RecurrenceFrequency::Monthly);
+  // 2024-08-31, the months without a 31st day are skipped
+  let occurrences = rule.occurrences(1725062400).collect::<Vec<_>>();
+  assert_eq!(occurrences, vec![1725062400, 1730332800, 1735603200]);
+
+  let rule = RecurrenceRule::from_str("FREQ=YEARLY;COUNT=3").unwrap();
+  let occurrences = rule.occurrences(AUGUST_START).collect::<Vec<_>>();
+  assert_eq!(
+    occurrences,
+    vec![
+      AUGUST_START,
+      AUGUST_START + 365 * DAY,
+      AUGUST_START + 730 * DAY
+    ]
+  );
+
+  assert!(RecurrenceRule::from_str("FREQ=HOURLY").is_err());
+  assert!(RecurrenceRule::from_str("INTERVAL=2").is_err());
+  assert!(matches!(
+    RecurrenceRule::from_str("FREQ=DAILY;BYDAY=MO"),
+    Err(DatabaseError::InvalidRecurrenceRule(_))
+  ));
+}
+
+#[test]
+fn recurrence_in_skipped_hour_test() {
+  let mut type_option = DateTypeOption::default_utc();
+  type_option.set_timezone_id("America/New_York").unwrap();
+  let rule = RecurrenceRule::from_str("FREQ=DAILY;COUNT=3").unwrap();
+  // 2024-03-09T02:30 in New York, the hour from 02:00 is skipped on 2024-03-10 and the occurrence
+  // is moved to 03:30.
+  let occurrences = rule
+    .occurrences_in_timezone(1709969400, type_option)
+    .collect::<Vec<_>>();
+  assert_eq!(occurrences, vec![1709969400, 1710055800, 1710138600]);
+}
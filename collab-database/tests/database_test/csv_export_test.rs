@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use collab::core::collab::default_client_id;
+use collab_database::database::{Database, gen_database_id, gen_database_view_id};
+use collab_database::database_export::{CellValueFormat, CsvExportOptions};
+use collab_database::entity::FieldType;
+use collab_database::fields::media_type_option::{
+  MediaCellData, MediaFile, MediaFileType, MediaUploadType,
+};
+use collab_database::fields::{FieldSettingsBuilder, FieldVisibility};
+use collab_database::rows::Cell;
+use collab_database::template::builder::DatabaseTemplateBuilder;
+use uuid::Uuid;
+
+use crate::helper::make_rocks_db;
+use crate::user_test::helper::TestUserDatabaseServiceImpl;
+
+async fn create_export_database() -> (Database, String) {
+  let database_id = gen_database_id();
+  let view_id = gen_database_view_id();
+  let template = DatabaseTemplateBuilder::new(database_id.clone(), view_id.clone(), None)
+    .create_field(
+      &None,
+      &database_id,
+      "name",
+      FieldType::RichText,
+      true,
+      |field_builder| {
+        field_builder
+          .create_cell("Tom, \"the cat\"")
+          .create_cell("Jerry")
+          .create_cell("Spike")
+      },
+    )
+    .await
+    .create_field(
+      &None,
+      &database_id,
+      "amount",
+      FieldType::Number,
+      false,
+      |field_builder| field_builder.create_cell("1000.5").create_cell("-3"),
+    )
+    .await
+    .create_field(
+      &None,
+      &database_id,
+      "date",
+      FieldType::DateTime,
+      false,
+      |field_builder| field_builder.create_cell("2024-08-22"),
+    )
+    .await
+    .create_field(
+      &None,
+      &database_id,
+      "tasks",
+      FieldType::Checklist,
+      false,
+      |field_builder| {
+        field_builder
+          .create_checklist_cell(vec!["A", "B", "C"], vec!["A"])
+          .create_checklist_cell(Vec::<String>::new(), Vec::<String>::new())
+      },
+    )
+    .await
+    .create_field(
+      &None,
+      &database_id,
+      "files",
+      FieldType::Media,
+      false,
+      |field_builder| field_builder,
+    )
+    .await
+    .build();
+
+  let service = Arc::new(TestUserDatabaseServiceImpl::new(
+    1,
+    Uuid::new_v4().to_string(),
+    make_rocks_db(),
+    default_client_id(),
+  ));
+  let mut database = Database::create_with_template(template, service.clone(), service)
+    .await
+    .unwrap();
+
+  let files_field = database
+    .get_all_fields()
+    .into_iter()
+    .find(|field| field.name == "files")
+    .unwrap();
+  let row_id = database.get_row_orders_for_view(&view_id)[0].id.clone();
+  let cell = Cell::from(MediaCellData {
+    files: vec![
+      MediaFile::new(
+        "cat.png".to_string(),
+        "https://example.com/cat.png".to_string(),
+        MediaUploadType::Network,
+        MediaFileType::Image,
+      ),
+      MediaFile::new(
+        "dog.png".to_string(),
+        "https://example.com/dog.png".to_string(),
+        MediaUploadType::Network,
+        MediaFileType::Image,
+      ),
+    ],
+  });
+  database
+    .update_row(row_id, |row| {
+      row.update_cells(|cells| {
+        cells.insert_cell(&files_field.id, cell);
+      });
+    })
+    .await;
+  (database, view_id)
+}
+
+async fn export_csv(database: &Database, view_id: &str, options: &CsvExportOptions) -> String {
+  let mut output = vec![];
+  database
+    .export_view_csv(view_id, &mut output, options)
+    .await
+    .unwrap();
+  String::from_utf8(output).unwrap()
+}
+
+#[tokio::test]
+async fn export_view_csv_formatted_test() {
+  let (database, view_id) = create_export_database().await;
+  let csv = export_csv(&database, &view_id, &CsvExportOptions::default()).await;
+  let lines = csv.lines().collect::<Vec<_>>();
+  assert_eq!(lines.len(), 4);
+  assert_eq!(lines[0], "name,amount,date,tasks,files");
+  assert_eq!(
+    lines[1],
+    "\"Tom, \"\"the cat\"\"\",1000.5,\"August 22, 2024\",\"A,B,C\",\"cat.png, dog.png\""
+  );
+  assert_eq!(lines[2], "Jerry,-3,,,");
+  assert_eq!(lines[3], "Spike,,,,");
+}
+
+#[tokio::test]
+async fn export_view_csv_raw_test() {
+  let (database, view_id) = create_export_database().await;
+  let options = CsvExportOptions {
+    number_format: CellValueFormat::Raw,
+    date_format: CellValueFormat::Raw,
+    checklist_as_progress: true,
+    media_as_urls: true,
+    ..Default::default()
+  };
+  let csv = export_csv(&database, &view_id, &options).await;
+  let lines = csv.lines().collect::<Vec<_>>();
+  assert_eq!(
+    lines[1],
+    "\"Tom, \"\"the cat\"\"\",1000.5,2024-08-22T00:00:00Z,1/3,\"https://example.com/cat.png, https://example.com/dog.png\""
+  );
+  assert_eq!(lines[2], "Jerry,-3,,,");
+}
+
+#[tokio::test]
+async fn export_view_csv_hidden_field_and_excel_test() {
+  let (mut database, view_id) = create_export_database().await;
+  let amount_field = database
+    .get_all_fields()
+    .into_iter()
+    .find(|field| field.name == "amount")
+    .unwrap();
+  database.update_field_settings(
+    &view_id,
+    Some(vec![amount_field.id.clone()]),
+    FieldSettingsBuilder::new(&amount_field.id)
+      .visibility(FieldVisibility::AlwaysHidden)
+      .build(),
+  );
+
+  let options = CsvExportOptions {
+    excel_compatible: true,
+    chunk_size: 1,
+    ..Default::default()
+  };
+  let mut output = vec![];
+  let num_rows = database
+    .export_view_csv(&view_id, &mut output, &options)
+    .await
+    .unwrap();
+  assert_eq!(num_rows, 3);
+  assert!(output.starts_with(b"\xEF\xBB\xBF"));
+  let csv = String::from_utf8(output[3..].to_vec()).unwrap();
+  assert!(csv.starts_with("name,date,tasks,files\r\n\"Tom"));
+  assert_eq!(csv.matches("\r\n").count(), 4);
+
+  let options = CsvExportOptions {
+    include_hidden_fields: true,
+    ..Default::default()
+  };
+  let csv = export_csv(&database, &view_id, &options).await;
+  assert!(csv.starts_with("name,amount,date,tasks,files\n"));
+
+  let result = database
+    .export_view_csv("unknown", &mut vec![], &CsvExportOptions::default())
+    .await;
+  assert!(result.is_err());
+}
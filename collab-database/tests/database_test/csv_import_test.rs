@@ -0,0 +1,368 @@
+use std::sync::Arc;
+
+use collab::core::collab::default_client_id;
+use collab_database::database::{Database, gen_database_id, gen_database_view_id};
+use collab_database::database_export::CsvExportOptions;
+use collab_database::database_import::{
+  CsvCellParser, CsvColumnMapping, CsvColumnTarget, CsvImportOptions, CsvRowAction,
+};
+use collab_database::database_validation::{ConstraintMode, FieldConstraints};
+use collab_database::entity::FieldType;
+use collab_database::error::DatabaseError;
+use collab_database::fields::relation_type_option::RelationTypeOption;
+use collab_database::fields::select_type_option::{MultiSelectTypeOption, SingleSelectTypeOption};
+use collab_database::fields::{Field, default_field_settings_by_layout_map};
+use collab_database::template::builder::DatabaseTemplateBuilder;
+use collab_database::template::relation_parse::RelationCellData;
+use collab_database::views::OrderObjectPosition;
+use uuid::Uuid;
+
+use crate::helper::make_rocks_db;
+use crate::user_test::helper::TestUserDatabaseServiceImpl;
+
+async fn create_import_database() -> (Database, String) {
+  let database_id = gen_database_id();
+  let view_id = gen_database_view_id();
+  let template = DatabaseTemplateBuilder::new(database_id.clone(), view_id.clone(), None)
+    .create_field(
+      &None,
+      &database_id,
+      "name",
+      FieldType::RichText,
+      true,
+      |field_builder| field_builder.create_cell("Tom").create_cell("Jerry"),
+    )
+    .await
+    .create_field(
+      &None,
+      &database_id,
+      "amount",
+      FieldType::Number,
+      false,
+      |field_builder| field_builder.create_cell("1").create_cell("2"),
+    )
+    .await
+    .create_field(
+      &None,
+      &database_id,
+      "status",
+      FieldType::SingleSelect,
+      false,
+      |field_builder| field_builder.create_cell("Todo").create_cell("Done"),
+    )
+    .await
+    .build();
+
+  let service = Arc::new(TestUserDatabaseServiceImpl::new(
+    1,
+    Uuid::new_v4().to_string(),
+    make_rocks_db(),
+    default_client_id(),
+  ));
+  let database = Database::create_with_template(template, service.clone(), service)
+    .await
+    .unwrap();
+  (database, view_id)
+}
+
+async fn export_csv(database: &Database, view_id: &str) -> Vec<String> {
+  let mut output = vec![];
+  database
+    .export_view_csv(view_id, &mut output, &CsvExportOptions::default())
+    .await
+    .unwrap();
+  String::from_utf8(output)
+    .unwrap()
+    .lines()
+    .map(|line| line.to_string())
+    .collect()
+}
+
+fn field_id(database: &Database, name: &str) -> String {
+  database
+    .get_all_fields()
+    .into_iter()
+    .find(|field| field.name == name)
+    .unwrap()
+    .id
+}
+
+#[tokio::test]
+async fn import_csv_upsert_by_key_test() {
+  let (mut database, view_id) = create_import_database().await;
+  let csv =
+    "Full name,amount,status,comment\nJerry,\"1,500\",Done,x\nSpike,7,Doing,y\nSpike,8,,z\n";
+  let options = CsvImportOptions {
+    columns: vec![
+      CsvColumnMapping::new(
+        "Full name",
+        CsvColumnTarget::Field(field_id(&database, "name")),
+      ),
+      CsvColumnMapping::new("comment", CsvColumnTarget::Skip),
+    ],
+    key_column: Some("Full name".to_string()),
+    ..Default::default()
+  };
+  let report = database.import_csv(csv.as_bytes(), &options).await.unwrap();
+  assert_eq!(report.num_created_rows(), 1);
+  assert_eq!(report.num_updated_rows(), 2);
+  assert_eq!(report.skipped_columns, vec!["comment".to_string()]);
+  assert!(report.cell_errors.is_empty());
+  // the second Spike row updates the row created by the first one.
+  assert_eq!(report.rows[1].action, CsvRowAction::Create);
+  assert_eq!(report.rows[2].row_id, report.rows[1].row_id);
+
+  let lines = export_csv(&database, &view_id).await;
+  assert_eq!(
+    lines,
+    vec![
+      "name,amount,status",
+      "Tom,1,Todo",
+      "Jerry,1500,Done",
+      "Spike,8,Doing"
+    ]
+  );
+}
+
+#[tokio::test]
+async fn import_csv_dry_run_test() {
+  let (mut database, view_id) = create_import_database().await;
+  let before = export_csv(&database, &view_id).await;
+  let csv = "name,amount,rating\nTom,abc,5\nLily,3,4\n";
+  let options = CsvImportOptions {
+    columns: vec![CsvColumnMapping::new(
+      "rating",
+      CsvColumnTarget::NewField(FieldType::Number),
+    )],
+    key_column: Some("name".to_string()),
+    dry_run: true,
+    ..Default::default()
+  };
+  let report = database.import_csv(csv.as_bytes(), &options).await.unwrap();
+  assert_eq!(report.num_created_rows(), 1);
+  assert_eq!(report.num_updated_rows(), 1);
+  assert_eq!(report.created_fields.len(), 1);
+  assert_eq!(report.cell_errors.len(), 1);
+  assert_eq!(report.cell_errors[0].line, 2);
+  assert_eq!(report.cell_errors[0].column, "amount");
+  assert_eq!(report.cell_errors[0].value, "abc");
+
+  assert_eq!(export_csv(&database, &view_id).await, before);
+  assert_eq!(database.get_all_fields().len(), 3);
+}
+
+#[tokio::test]
+async fn import_csv_new_field_with_parser_test() {
+  let (mut database, _) = create_import_database().await;
+  let csv = "name,due,done\nLily,22/08/2024,yes\nMax,2024-08-22,no\n";
+  let options = CsvImportOptions {
+    columns: vec![
+      CsvColumnMapping::new("due", CsvColumnTarget::NewField(FieldType::DateTime))
+        .with_parser(CsvCellParser::DateWithFormat("%d/%m/%Y".to_string())),
+      CsvColumnMapping::new("done", CsvColumnTarget::NewField(FieldType::Checkbox)),
+    ],
+    ..Default::default()
+  };
+  let report = database.import_csv(csv.as_bytes(), &options).await.unwrap();
+  assert_eq!(report.num_created_rows(), 2);
+  assert_eq!(report.created_fields.len(), 2);
+  assert_eq!(report.cell_errors.len(), 1);
+  assert_eq!(report.cell_errors[0].column, "due");
+
+  let due_field_id = field_id(&database, "due");
+  let done_field_id = field_id(&database, "done");
+  let row = database.get_row(&report.rows[0].row_id).await;
+  let due_reader = database.get_cell_reader(&due_field_id).unwrap();
+  assert_eq!(
+    due_reader.stringify_cell(row.cells.get(&due_field_id).unwrap()),
+    "Aug 22, 2024"
+  );
+  let done_reader = database.get_cell_reader(&done_field_id).unwrap();
+  assert_eq!(
+    done_reader.stringify_cell(row.cells.get(&done_field_id).unwrap()),
+    "true"
+  );
+}
+
+#[tokio::test]
+async fn import_csv_new_select_field_test() {
+  let (mut database, view_id) = create_import_database().await;
+  let csv = "name,priority,tags\nLily,High,\"a,b\"\nMax,Low,b\n";
+  let options = CsvImportOptions {
+    columns: vec![
+      CsvColumnMapping::new(
+        "priority",
+        CsvColumnTarget::NewField(FieldType::SingleSelect),
+      ),
+      CsvColumnMapping::new("tags", CsvColumnTarget::NewField(FieldType::MultiSelect)),
+    ],
+    ..Default::default()
+  };
+  let report = database.import_csv(csv.as_bytes(), &options).await.unwrap();
+  assert_eq!(report.num_created_rows(), 2);
+  assert!(report.cell_errors.is_empty());
+
+  // the options are written with the new fields.
+  let priority_field = database
+    .get_field(&field_id(&database, "priority"))
+    .unwrap();
+  let priority = priority_field
+    .get_type_option::<SingleSelectTypeOption>(FieldType::SingleSelect)
+    .unwrap();
+  let names = priority
+    .options
+    .iter()
+    .map(|option| option.name.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(names, vec!["High", "Low"]);
+  let tags_field = database.get_field(&field_id(&database, "tags")).unwrap();
+  let tags = tags_field
+    .get_type_option::<MultiSelectTypeOption>(FieldType::MultiSelect)
+    .unwrap();
+  assert_eq!(tags.options.len(), 2);
+
+  let lines = export_csv(&database, &view_id).await;
+  assert_eq!(lines[3], "Lily,,,High,\"a, b\"");
+  assert_eq!(lines[4], "Max,,,Low,b");
+}
+
+#[tokio::test]
+async fn import_csv_invalid_mapping_test() {
+  let (mut database, _) = create_import_database().await;
+  let csv = "name,amount\nTom,1\n";
+  let options = CsvImportOptions {
+    columns: vec![
+      CsvColumnMapping::new(
+        "amount",
+        CsvColumnTarget::Field(field_id(&database, "amount")),
+      )
+      .with_parser(CsvCellParser::Select),
+    ],
+    ..Default::default()
+  };
+  let result = database.import_csv(csv.as_bytes(), &options).await;
+  assert!(matches!(result, Err(DatabaseError::ImportData(_))));
+
+  let options = CsvImportOptions {
+    key_column: Some("id".to_string()),
+    ..Default::default()
+  };
+  let result = database.import_csv(csv.as_bytes(), &options).await;
+  assert!(matches!(result, Err(DatabaseError::ImportData(_))));
+}
+
+#[tokio::test]
+async fn import_csv_relation_row_ids_test() {
+  let (mut database, view_id) = create_import_database().await;
+  let field = Field::new(
+    "link".to_string(),
+    "link".to_string(),
+    FieldType::Relation.into(),
+    false,
+  )
+  .with_type_option_data(
+    FieldType::Relation,
+    RelationTypeOption {
+      database_id: database.get_database_id(),
+      paired_field_id: None,
+    }
+    .into(),
+  );
+  database.create_field(
+    None,
+    field,
+    &OrderObjectPosition::End,
+    default_field_settings_by_layout_map(),
+  );
+  let tom_row_id = database.get_all_row_orders().await[0].id.clone();
+  let csv = format!(
+    "name,link\nLily,abc\nMax,{}\nSpike,{}\n",
+    Uuid::new_v4(),
+    tom_row_id
+  );
+  let report = database
+    .import_csv(csv.as_bytes(), &Default::default())
+    .await
+    .unwrap();
+  assert_eq!(report.num_created_rows(), 3);
+  assert_eq!(report.cell_errors.len(), 2);
+  assert_eq!(report.cell_errors[0].value, "abc");
+  assert_eq!(report.cell_errors[1].line, 3);
+
+  let row = database.get_row(&report.rows[2].row_id).await;
+  let cell = row.cells.get("link").unwrap();
+  assert_eq!(RelationCellData::from(cell).row_ids, vec![tom_row_id]);
+  assert_eq!(export_csv(&database, &view_id).await.len(), 6);
+}
+
+#[tokio::test]
+async fn import_csv_constraint_violations_test() {
+  let (mut database, view_id) = create_import_database().await;
+  let amount_field_id = field_id(&database, "amount");
+  database
+    .set_field_constraints(
+      &amount_field_id,
+      FieldConstraints {
+        max: Some(10.0),
+        ..Default::default()
+      },
+    )
+    .unwrap();
+  let csv = "name,amount\nJerry,20\nLily,30\nMax,3\n";
+  let options = CsvImportOptions {
+    key_column: Some("name".to_string()),
+    ..Default::default()
+  };
+  let report = database.import_csv(csv.as_bytes(), &options).await.unwrap();
+  assert_eq!(report.num_created_rows(), 2);
+  assert_eq!(report.num_updated_rows(), 1);
+  assert!(report.failed_rows.is_empty());
+  // the created row is checked before the updated one.
+  let violated_rows = report
+    .violations
+    .iter()
+    .map(|violation| &violation.row_id)
+    .collect::<Vec<_>>();
+  assert_eq!(
+    violated_rows,
+    vec![&report.rows[1].row_id, &report.rows[0].row_id]
+  );
+  assert!(
+    report
+      .violations
+      .iter()
+      .all(|violation| !violation.rejected)
+  );
+  assert_eq!(export_csv(&database, &view_id).await.len(), 5);
+}
+
+#[tokio::test]
+async fn import_csv_enforced_constraint_test() {
+  let (mut database, view_id) = create_import_database().await;
+  let before = export_csv(&database, &view_id).await;
+  let name_field_id = field_id(&database, "name");
+  database
+    .set_field_constraints(
+      &name_field_id,
+      FieldConstraints {
+        mode: ConstraintMode::Enforce,
+        unique: true,
+        ..Default::default()
+      },
+    )
+    .unwrap();
+  let csv = "name,rating\nLily,5\nTom,4\n";
+  let options = CsvImportOptions {
+    columns: vec![CsvColumnMapping::new(
+      "rating",
+      CsvColumnTarget::NewField(FieldType::Number),
+    )],
+    ..Default::default()
+  };
+  let result = database.import_csv(csv.as_bytes(), &options).await;
+  assert!(matches!(result, Err(DatabaseError::ConstraintViolation(_))));
+
+  // nothing is written when a new row is rejected.
+  assert_eq!(export_csv(&database, &view_id).await, before);
+  assert_eq!(database.get_all_fields().len(), 3);
+}
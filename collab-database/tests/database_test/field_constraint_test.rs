@@ -0,0 +1,362 @@
+use collab_database::database_validation::{ConstraintMode, ConstraintViolation, FieldConstraints};
+use collab_database::entity::{FieldType, default_type_option_data_from_type};
+use collab_database::error::DatabaseError;
+use collab_database::fields::Field;
+use collab_database::fields::select_type_option::SelectOptionIds;
+use collab_database::rows::{Cell, Cells, CreateRowParams, RowChange, RowId};
+use collab_database::template::number_parse::NumberCellData;
+use uuid::Uuid;
+
+use crate::database_test::helper::{DatabaseTest, DatabaseTestBuilder};
+use crate::helper::TestTextCell;
+
+fn field(id: &str, field_type: FieldType, is_primary: bool) -> Field {
+  Field::new(
+    id.to_string(),
+    id.to_string(),
+    field_type.into(),
+    is_primary,
+  )
+  .with_type_option_data(field_type, default_type_option_data_from_type(field_type))
+}
+
+fn number_cell(number: &str) -> Cell {
+  NumberCellData(number.to_string()).into()
+}
+
+fn row(database_test: &DatabaseTest, cells: Vec<(&str, Cell)>) -> CreateRowParams {
+  let cells = cells
+    .into_iter()
+    .map(|(field_id, cell)| (field_id.to_string(), cell))
+    .collect::<Cells>();
+  CreateRowParams::new(Uuid::new_v4(), database_test.get_database_id()).with_cells(cells)
+}
+
+async fn create_constraint_database() -> (DatabaseTest, RowId) {
+  let database_id = Uuid::new_v4().to_string();
+  let row_id = RowId::from(Uuid::new_v4().to_string());
+  let database_test = DatabaseTestBuilder::new(1, &database_id)
+    .with_field(field("name", FieldType::RichText, true))
+    .with_field(field("amount", FieldType::Number, false))
+    .with_field(field("tags", FieldType::MultiSelect, false))
+    .with_row(
+      CreateRowParams::new(row_id.clone(), database_id.clone()).with_cells(Cells::from([
+        ("name".to_string(), TestTextCell::from("Tom").into()),
+        ("amount".to_string(), number_cell("5")),
+      ])),
+    )
+    .build()
+    .await;
+  (database_test, row_id)
+}
+
+#[tokio::test]
+async fn create_row_with_enforced_constraints_test() {
+  let (mut database_test, _) = create_constraint_database().await;
+  database_test
+    .set_field_constraints(
+      "name",
+      FieldConstraints {
+        mode: ConstraintMode::Enforce,
+        required: true,
+        unique: true,
+        ..Default::default()
+      },
+    )
+    .unwrap();
+  assert!(database_test.get_field_constraints("name").unwrap().unique);
+
+  let params = row(&database_test, vec![("amount", number_cell("1"))]);
+  let err = database_test.create_row(params).await.unwrap_err();
+  let DatabaseError::ConstraintViolation(violations) = err else {
+    panic!("unexpected error: {:?}", err);
+  };
+  assert_eq!(violations.len(), 1);
+  assert_eq!(violations[0].field_id, "name");
+  assert_eq!(violations[0].violation, ConstraintViolation::Required);
+  assert!(violations[0].rejected);
+
+  let params = row(
+    &database_test,
+    vec![("name", TestTextCell::from("Tom").into())],
+  );
+  let err = database_test.create_row(params).await.unwrap_err();
+  assert!(matches!(
+    err,
+    DatabaseError::ConstraintViolation(violations)
+      if violations[0].violation == ConstraintViolation::Duplicate
+  ));
+
+  let params = row(
+    &database_test,
+    vec![("name", TestTextCell::from("Jerry").into())],
+  );
+  database_test.create_row(params).await.unwrap();
+  assert_eq!(database_test.get_all_row_orders().await.len(), 2);
+}
+
+#[tokio::test]
+async fn create_row_with_reported_constraints_test() {
+  let (mut database_test, _) = create_constraint_database().await;
+  database_test
+    .set_field_constraints(
+      "name",
+      FieldConstraints {
+        unique: true,
+        ..Default::default()
+      },
+    )
+    .unwrap();
+
+  let params = row(
+    &database_test,
+    vec![("name", TestTextCell::from("Tom").into())],
+  );
+  let (row_order, violations) = database_test
+    .create_row_with_violations(params)
+    .await
+    .unwrap();
+  assert_eq!(violations.len(), 1);
+  assert_eq!(violations[0].row_id, row_order.id);
+  assert_eq!(violations[0].violation, ConstraintViolation::Duplicate);
+  assert!(!violations[0].rejected);
+  assert_eq!(database_test.get_all_row_orders().await.len(), 2);
+
+  // the values of the updated rows are checked against the new value of the row
+  database_test
+    .update_row(row_order.id.clone(), |row| {
+      row.update_cells(|cells| {
+        cells.insert_cell("name", TestTextCell::from("Jerry").into());
+      });
+    })
+    .await;
+  let params = row(
+    &database_test,
+    vec![("name", TestTextCell::from("Tom").into())],
+  );
+  let (_, violations) = database_test
+    .create_row_with_violations(params)
+    .await
+    .unwrap();
+  assert_eq!(violations.len(), 1);
+  let params = row(
+    &database_test,
+    vec![("name", TestTextCell::from("Lucy").into())],
+  );
+  let (_, violations) = database_test
+    .create_row_with_violations(params)
+    .await
+    .unwrap();
+  assert!(violations.is_empty());
+}
+
+#[tokio::test]
+async fn create_rows_with_duplicates_in_batch_test() {
+  let (mut database_test, _) = create_constraint_database().await;
+  database_test
+    .set_field_constraints(
+      "name",
+      FieldConstraints {
+        mode: ConstraintMode::Enforce,
+        unique: true,
+        ..Default::default()
+      },
+    )
+    .unwrap();
+
+  let rows = vec![
+    row(
+      &database_test,
+      vec![("name", TestTextCell::from("Jerry").into())],
+    ),
+    row(
+      &database_test,
+      vec![("name", TestTextCell::from("Jerry").into())],
+    ),
+  ];
+  let second_row_id = rows[1].id.clone();
+  let err = database_test.create_rows(rows).await.unwrap_err();
+  let DatabaseError::ConstraintViolation(violations) = err else {
+    panic!("unexpected error: {:?}", err);
+  };
+  assert_eq!(violations.len(), 1);
+  assert_eq!(violations[0].row_id, second_row_id);
+  assert_eq!(violations[0].violation, ConstraintViolation::Duplicate);
+  assert_eq!(database_test.get_all_row_orders().await.len(), 1);
+
+  let rows = vec![
+    row(
+      &database_test,
+      vec![("name", TestTextCell::from("Jerry").into())],
+    ),
+    row(
+      &database_test,
+      vec![("name", TestTextCell::from("Lucy").into())],
+    ),
+  ];
+  let (row_orders, violations) = database_test.create_rows(rows).await.unwrap();
+  assert_eq!(row_orders.len(), 2);
+  assert!(violations.is_empty());
+}
+
+#[tokio::test]
+async fn update_row_with_constraints_test() {
+  let (mut database_test, row_id) = create_constraint_database().await;
+  database_test
+    .set_field_constraints(
+      "amount",
+      FieldConstraints {
+        mode: ConstraintMode::Enforce,
+        min: Some(0.0),
+        max: Some(10.0),
+        ..Default::default()
+      },
+    )
+    .unwrap();
+
+  let mut rx = database_test.subscribe_row_change().unwrap();
+  let violations = database_test
+    .update_row(row_id.clone(), |row| {
+      row.update_cells(|cells| {
+        cells
+          .insert_cell("amount", number_cell("20"))
+          .insert_cell("name", TestTextCell::from("Tommy").into());
+      });
+    })
+    .await;
+  assert_eq!(violations.len(), 1);
+  assert_eq!(violations[0].violation, ConstraintViolation::AboveMax(10.0));
+  assert!(violations[0].rejected);
+  // the rejected amount is never written, the name is changed.
+  let mut amounts = vec![];
+  while let Ok(change) = rx.try_recv() {
+    if let RowChange::DidUpdateCell {
+      field_id, value, ..
+    } = change
+    {
+      if field_id == "amount" {
+        amounts.push(NumberCellData::from(&value).0);
+      }
+    }
+  }
+  assert!(amounts.iter().all(|amount| amount == "5"));
+  let row = database_test.get_row(&row_id).await;
+  assert_eq!(
+    NumberCellData::from(row.cells.get("amount").unwrap()).0,
+    "5"
+  );
+  assert_eq!(
+    TestTextCell::from(row.cells.get("name").unwrap().clone()).0,
+    "Tommy"
+  );
+
+  // the violations are only reported in the report mode.
+  database_test
+    .set_field_constraints(
+      "amount",
+      FieldConstraints {
+        min: Some(0.0),
+        max: Some(10.0),
+        ..Default::default()
+      },
+    )
+    .unwrap();
+  let violations = database_test
+    .update_row(row_id.clone(), |row| {
+      row.update_cells(|cells| {
+        cells.insert_cell("amount", number_cell("-1"));
+      });
+    })
+    .await;
+  assert_eq!(violations[0].violation, ConstraintViolation::BelowMin(0.0));
+  assert!(!violations[0].rejected);
+  let row = database_test.get_row(&row_id).await;
+  assert_eq!(
+    NumberCellData::from(row.cells.get("amount").unwrap()).0,
+    "-1"
+  );
+}
+
+#[tokio::test]
+async fn validate_cells_test() {
+  let (mut database_test, row_id) = create_constraint_database().await;
+  let tags = SelectOptionIds::from(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+  let params = row(
+    &database_test,
+    vec![
+      ("name", TestTextCell::from("tom").into()),
+      ("amount", number_cell("abc")),
+      ("tags", tags.to_cell(FieldType::MultiSelect)),
+    ],
+  );
+  let second_row_id = params.id.clone();
+  database_test.create_row(params).await.unwrap();
+  assert!(database_test.validate_cells().await.unwrap().is_empty());
+
+  database_test
+    .set_field_constraints(
+      "name",
+      FieldConstraints {
+        pattern: Some("^[A-Z]".to_string()),
+        ..Default::default()
+      },
+    )
+    .unwrap();
+  database_test
+    .set_field_constraints(
+      "amount",
+      FieldConstraints {
+        required: true,
+        ..Default::default()
+      },
+    )
+    .unwrap();
+  database_test
+    .set_field_constraints(
+      "tags",
+      FieldConstraints {
+        required: true,
+        max_selections: Some(2),
+        ..Default::default()
+      },
+    )
+    .unwrap();
+
+  let violations = database_test
+    .validate_cells()
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|violation| (violation.row_id, violation.field_id, violation.violation))
+    .collect::<Vec<_>>();
+  assert_eq!(violations.len(), 4);
+  assert!(violations.contains(&(
+    row_id.clone(),
+    "tags".to_string(),
+    ConstraintViolation::Required
+  )));
+  assert!(violations.contains(&(
+    second_row_id.clone(),
+    "name".to_string(),
+    ConstraintViolation::PatternMismatch("^[A-Z]".to_string())
+  )));
+  assert!(violations.contains(&(
+    second_row_id.clone(),
+    "amount".to_string(),
+    ConstraintViolation::InvalidNumber
+  )));
+  assert!(violations.contains(&(
+    second_row_id,
+    "tags".to_string(),
+    ConstraintViolation::TooManySelections(2)
+  )));
+
+  let result = database_test.set_field_constraints(
+    "name",
+    FieldConstraints {
+      pattern: Some("(".to_string()),
+      ..Default::default()
+    },
+  );
+  assert!(matches!(result, Err(DatabaseError::InvalidConstraint(_))));
+}
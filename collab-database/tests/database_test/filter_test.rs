@@ -1,4 +1,11 @@
-use crate::database_test::helper::{DatabaseTest, create_database_with_default_data};
+use collab_database::entity::FieldType;
+use collab_database::fields::Field;
+use collab_database::fields::date_type_option::{DateCellData, DateTypeOption};
+use collab_database::rows::{Cell, Cells, CreateRowParams};
+
+use crate::database_test::helper::{
+  DatabaseTest, DatabaseTestBuilder, create_database_with_default_data,
+};
 use crate::helper::{FILTER_CONTENT, TestFieldType, TestFilter};
 
 #[tokio::test]
@@ -99,3 +106,48 @@ async fn create_database_with_two_filters() -> DatabaseTest {
 
   database_test
 }
+
+#[tokio::test]
+async fn filter_date_by_local_day_test() {
+  let database_id = uuid::Uuid::new_v4().to_string();
+  // 2024-08-22 20:00 UTC, which is 2024-08-23 in Tokyo
+  let date_cell = DateCellData::from_timestamp_include_time(1724356800);
+  let tokyo_date_cell = date_cell
+    .clone()
+    .with_timezone_id(Some("Asia/Tokyo"))
+    .unwrap();
+  let row_1 = CreateRowParams::new(uuid::Uuid::new_v4(), database_id.clone())
+    .with_cells(Cells::from([("f1".into(), Cell::from(&date_cell))]));
+  let row_2 = CreateRowParams::new(uuid::Uuid::new_v4(), database_id.clone())
+    .with_cells(Cells::from([("f1".into(), Cell::from(&tokyo_date_cell))]));
+  let (row_1_id, row_2_id) = (row_1.id.clone(), row_2.id.clone());
+  let mut database_test = DatabaseTestBuilder::new(1, &database_id)
+    .with_field(
+      Field::new(
+        "f1".to_string(),
+        "date".to_string(),
+        FieldType::DateTime.into(),
+        true,
+      )
+      .with_type_option_data(FieldType::DateTime, DateTypeOption::default_utc().into()),
+    )
+    .with_row(row_1)
+    .with_row(row_2)
+    .build()
+    .await;
+
+  // starts on 2024-08-23
+  database_test.insert_filter(
+    "v1",
+    TestFilter {
+      id: "filter_1".to_string(),
+      field_id: "f1".to_string(),
+      field_type: TestFieldType::DateTime,
+      condition: 0,
+      content: r#"{"timestamp":1724371200}"#.to_string(),
+    },
+  );
+  let row_filter = database_test.get_row_filter("v1");
+  assert!(!row_filter.matches(&database_test.get_row(&row_1_id).await));
+  assert!(row_filter.matches(&database_test.get_row(&row_2_id).await));
+}
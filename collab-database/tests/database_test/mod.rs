@@ -1,7 +1,11 @@
 mod block_test;
+mod calendar_test;
 mod cell_test;
 mod cell_type_option_test;
+mod csv_export_test;
+mod csv_import_test;
 mod encode_collab_test;
+mod field_constraint_test;
 mod field_observe_test;
 mod field_setting_test;
 mod field_test;
@@ -10,9 +14,13 @@ mod group_test;
 pub mod helper;
 mod layout_test;
 // mod restore_test;
+mod relation_test;
+mod row_comment_test;
+mod row_history_test;
 mod row_observe_test;
 mod row_test;
 mod sort_test;
 mod type_option_test;
+mod view_duplicate_test;
 mod view_observe_test;
 mod view_test;
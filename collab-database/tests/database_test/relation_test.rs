@@ -0,0 +1,318 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use collab::core::collab::default_client_id;
+use collab::lock::RwLock;
+use collab_database::database::{Database, DatabaseContext};
+use collab_database::entity::{CreateDatabaseParams, CreateViewParams, FieldType};
+use collab_database::error::DatabaseError;
+use collab_database::fields::Field;
+use collab_database::fields::relation_type_option::RelationTypeOption;
+use collab_database::rows::{Cell, Cells, CreateRowParams, RowHistoryRetention, RowId, RowUpdate};
+use collab_database::template::relation_parse::RelationCellData;
+use tokio::time::{sleep, timeout};
+use uuid::Uuid;
+
+use crate::helper::{TestTextCell, make_rocks_db};
+use crate::user_test::helper::TestUserDatabaseServiceImpl;
+
+fn relation_cell(row_ids: &[&RowId]) -> Cell {
+  Cell::from(RelationCellData {
+    row_ids: row_ids.iter().map(|id| (*id).clone()).collect(),
+  })
+}
+
+async fn linked_rows(database: &RwLock<Database>, row_id: &RowId, field_id: &str) -> Vec<RowId> {
+  database
+    .read()
+    .await
+    .get_row(row_id)
+    .await
+    .cells
+    .get(field_id)
+    .map(|cell| RelationCellData::from(cell).row_ids)
+    .unwrap_or_default()
+}
+
+/// Creates two databases that share their collab services, each with a primary field and the
+/// given rows, and registers them as related databases of each other.
+async fn create_related_databases(
+  left_rows: &[&str],
+  right_rows: &[&str],
+) -> (
+  Arc<RwLock<Database>>,
+  Vec<RowId>,
+  Arc<RwLock<Database>>,
+  Vec<RowId>,
+) {
+  let collab_service = Arc::new(TestUserDatabaseServiceImpl::new(
+    1,
+    Uuid::new_v4().to_string(),
+    make_rocks_db(),
+    default_client_id(),
+  ));
+  let context = DatabaseContext::new(collab_service.clone(), collab_service);
+  let mut databases = vec![];
+  for names in [left_rows, right_rows] {
+    let database_id = Uuid::new_v4().to_string();
+    let rows = names
+      .iter()
+      .map(|name| {
+        CreateRowParams::new(Uuid::new_v4(), database_id.clone()).with_cells(Cells::from([(
+          "name".to_string(),
+          TestTextCell::from(*name).into(),
+        )]))
+      })
+      .collect::<Vec<_>>();
+    let row_ids = rows.iter().map(|row| row.id.clone()).collect::<Vec<_>>();
+    let params = CreateDatabaseParams {
+      database_id: database_id.clone(),
+      views: vec![CreateViewParams {
+        database_id,
+        view_id: Uuid::new_v4().to_string(),
+        name: "grid".to_string(),
+        ..Default::default()
+      }],
+      rows,
+      fields: vec![Field::new(
+        "name".to_string(),
+        "Name".to_string(),
+        FieldType::RichText.into(),
+        true,
+      )],
+    };
+    let database = Database::create_with_view(params, context.clone())
+      .await
+      .unwrap();
+    databases.push((Arc::new(RwLock::new(database)), row_ids));
+  }
+  let (right, right_row_ids) = databases.pop().unwrap();
+  let (left, left_row_ids) = databases.pop().unwrap();
+  left.write().await.add_related_database(&right).await;
+  right.write().await.add_related_database(&left).await;
+  (left, left_row_ids, right, right_row_ids)
+}
+
+fn relation_field() -> Field {
+  Field::new(
+    "tasks".to_string(),
+    "Tasks".to_string(),
+    FieldType::Relation.into(),
+    false,
+  )
+}
+
+async fn create_relation(projects: &RwLock<Database>, tasks: &RwLock<Database>) -> Field {
+  let mut projects = projects.write().await;
+  let mut tasks = tasks.write().await;
+  projects
+    .create_two_way_relation(relation_field(), &mut tasks, "Project")
+    .unwrap()
+}
+
+async fn link_rows(
+  database: &RwLock<Database>,
+  row_id: &RowId,
+  field_id: &str,
+  row_ids: &[&RowId],
+) {
+  database
+    .write()
+    .await
+    .update_row(row_id.clone(), |row| {
+      row.update_cells(|cells| {
+        cells.insert_cell(field_id, relation_cell(row_ids));
+      });
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn two_way_relation_back_reference_test() {
+  let (projects, project_ids, tasks, task_ids) =
+    create_related_databases(&["website", "app"], &["design", "build", "ship"]).await;
+  let paired_field = create_relation(&projects, &tasks).await;
+  let type_option = tasks
+    .read()
+    .await
+    .get_field(&paired_field.id)
+    .unwrap()
+    .get_type_option::<RelationTypeOption>(FieldType::Relation.type_id())
+    .unwrap();
+  assert_eq!(
+    type_option.database_id,
+    projects.read().await.get_database_id()
+  );
+  assert_eq!(type_option.paired_field_id.as_deref(), Some("tasks"));
+
+  // link the website to the design and build tasks
+  let (website, app) = (&project_ids[0], &project_ids[1]);
+  link_rows(&projects, website, "tasks", &[&task_ids[0], &task_ids[1]]).await;
+  assert_eq!(
+    linked_rows(&tasks, &task_ids[0], &paired_field.id).await,
+    vec![website.clone()]
+  );
+  assert_eq!(
+    linked_rows(&tasks, &task_ids[1], &paired_field.id).await,
+    vec![website.clone()]
+  );
+
+  // unlink the build task
+  link_rows(&projects, website, "tasks", &[&task_ids[0]]).await;
+  assert!(
+    linked_rows(&tasks, &task_ids[1], &paired_field.id)
+      .await
+      .is_empty()
+  );
+
+  // link from the other side
+  link_rows(&tasks, &task_ids[2], &paired_field.id, &[app]).await;
+  assert_eq!(
+    linked_rows(&projects, app, "tasks").await,
+    vec![task_ids[2].clone()]
+  );
+
+  // a new row links the build task
+  let params =
+    CreateRowParams::new(Uuid::new_v4(), projects.read().await.get_database_id()).with_cells(
+      Cells::from([("tasks".to_string(), relation_cell(&[&task_ids[1]]))]),
+    );
+  let new_project = params.id.clone();
+  projects.write().await.create_row(params).await.unwrap();
+  assert_eq!(
+    linked_rows(&tasks, &task_ids[1], &paired_field.id).await,
+    vec![new_project]
+  );
+
+  // deleting a row removes its back-references
+  projects.write().await.remove_row(website).await;
+  assert!(
+    linked_rows(&tasks, &task_ids[0], &paired_field.id)
+      .await
+      .is_empty()
+  );
+  tasks
+    .write()
+    .await
+    .remove_rows(&[task_ids[2].clone()])
+    .await;
+  assert!(linked_rows(&projects, app, "tasks").await.is_empty());
+}
+
+#[tokio::test]
+async fn update_rows_back_reference_test() {
+  let (projects, project_ids, tasks, task_ids) =
+    create_related_databases(&["website", "app"], &["design"]).await;
+  let paired_field = create_relation(&projects, &tasks).await;
+  let link = |row_ids: Vec<RowId>| {
+    move |row: RowUpdate| {
+      row.update_cells(|cells| {
+        cells.insert_cell("tasks", Cell::from(RelationCellData { row_ids }));
+      });
+    }
+  };
+  let updates = project_ids
+    .iter()
+    .map(|project_id| (project_id.clone(), link(vec![task_ids[0].clone()])))
+    .collect();
+  projects.write().await.update_rows(updates).await;
+  assert_eq!(
+    linked_rows(&tasks, &task_ids[0], &paired_field.id).await,
+    project_ids
+  );
+}
+
+#[tokio::test]
+async fn back_reference_history_test() {
+  let (projects, project_ids, tasks, task_ids) =
+    create_related_databases(&["website"], &["design"]).await;
+  let paired_field = create_relation(&projects, &tasks).await;
+  // only the history of the related database is enabled
+  tasks
+    .write()
+    .await
+    .set_row_history_retention(RowHistoryRetention {
+      max_entries: 10,
+      max_age: None,
+    });
+
+  link_rows(&projects, &project_ids[0], "tasks", &[&task_ids[0]]).await;
+  let history = tasks.read().await.get_row_history(&task_ids[0], None).await;
+  assert_eq!(history.len(), 1);
+  assert_eq!(history[0].field_id, paired_field.id);
+  assert_eq!(history[0].old_value, "");
+  assert!(
+    projects
+      .read()
+      .await
+      .get_row_history(&project_ids[0], None)
+      .await
+      .is_empty()
+  );
+}
+
+#[tokio::test]
+async fn delete_paired_field_test() {
+  let (projects, project_ids, tasks, task_ids) =
+    create_related_databases(&["website", "app"], &["design"]).await;
+  let paired_field = create_relation(&projects, &tasks).await;
+  link_rows(&projects, &project_ids[0], "tasks", &[&task_ids[0]]).await;
+  projects.write().await.delete_field("tasks");
+
+  // the paired field becomes a one-way relation and its back-references are cleared
+  let paired_field_id = || async {
+    tasks
+      .read()
+      .await
+      .get_field(&paired_field.id)
+      .unwrap()
+      .get_type_option::<RelationTypeOption>(FieldType::Relation.type_id())
+      .unwrap()
+      .paired_field_id
+  };
+  timeout(Duration::from_secs(5), async {
+    while paired_field_id().await.is_some() {
+      sleep(Duration::from_millis(10)).await;
+    }
+  })
+  .await
+  .unwrap();
+  assert!(
+    linked_rows(&tasks, &task_ids[0], &paired_field.id)
+      .await
+      .is_empty()
+  );
+
+  // the deleted field isn't written
+  link_rows(&tasks, &task_ids[0], &paired_field.id, &[&project_ids[1]]).await;
+  assert!(
+    linked_rows(&projects, &project_ids[1], "tasks")
+      .await
+      .is_empty()
+  );
+}
+
+#[tokio::test]
+async fn delete_two_way_relation_test() {
+  let (projects, _, tasks, _) = create_related_databases(&["website"], &["design"]).await;
+  let paired_field = create_relation(&projects, &tasks).await;
+  let mut projects = projects.write().await;
+  let mut tasks = tasks.write().await;
+
+  let result = projects.delete_two_way_relation("name", &mut tasks);
+  assert!(matches!(result, Err(DatabaseError::InvalidRelation(_))));
+  projects
+    .delete_two_way_relation("tasks", &mut tasks)
+    .unwrap();
+  assert!(projects.get_field("tasks").is_none());
+  assert!(tasks.get_field(&paired_field.id).is_none());
+
+  let text_field = Field::new(
+    "notes".to_string(),
+    "Notes".to_string(),
+    FieldType::RichText.into(),
+    false,
+  );
+  let result = projects.create_two_way_relation(text_field, &mut tasks, "Project");
+  assert!(matches!(result, Err(DatabaseError::InvalidRelation(_))));
+}
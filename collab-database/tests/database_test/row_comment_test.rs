@@ -0,0 +1,137 @@
+use collab_database::error::DatabaseError;
+use collab_database::rows::{
+  CreateRowCommentParams, CreateRowParams, RowChange, RowChangeReceiver, RowComment,
+  RowCommentMention, RowId,
+};
+use uuid::Uuid;
+
+use crate::database_test::helper::{DatabaseTest, DatabaseTestBuilder};
+
+async fn create_comment_database() -> (DatabaseTest, RowId) {
+  let database_id = Uuid::new_v4().to_string();
+  let row_id = RowId::from(Uuid::new_v4().to_string());
+  let database_test = DatabaseTestBuilder::new(1, &database_id)
+    .with_row(CreateRowParams::new(row_id.clone(), database_id.clone()))
+    .build()
+    .await;
+  (database_test, row_id)
+}
+
+fn received_comments(rx: &mut RowChangeReceiver) -> Vec<RowComment> {
+  let mut comments = vec![];
+  while let Ok(change) = rx.try_recv() {
+    if let RowChange::DidChangeRowComment { comment, .. } = change {
+      comments.push(comment);
+    }
+  }
+  comments
+}
+
+#[tokio::test]
+async fn row_comment_thread_test() {
+  let (database_test, row_id) = create_comment_database().await;
+  let mentioned_row = RowId::from(Uuid::new_v4().to_string());
+  let database_row = database_test.get_database_row(&row_id).await.unwrap();
+  let mut row = database_row.write().await;
+
+  let comment = row
+    .add_comment(
+      CreateRowCommentParams::new(1, "Can you check this?").with_mentions(vec![
+        RowCommentMention::User { uid: 2 },
+        RowCommentMention::Row {
+          row_id: mentioned_row.clone(),
+        },
+      ]),
+    )
+    .unwrap();
+  let reply = row
+    .add_comment(CreateRowCommentParams::new(2, "Done").with_parent_id(&comment.id))
+    .unwrap();
+  row
+    .add_comment(CreateRowCommentParams::new(1, "Thanks").with_parent_id(&reply.id))
+    .unwrap();
+  let other = row
+    .add_comment(CreateRowCommentParams::new(3, "Another topic"))
+    .unwrap();
+  let result = row.add_comment(CreateRowCommentParams::new(3, "?").with_parent_id("unknown"));
+  assert!(matches!(result, Err(DatabaseError::RecordNotFound)));
+
+  let stored = row.get_comment(&comment.id).unwrap();
+  assert_eq!(stored, comment);
+  assert_eq!(
+    stored.mentions,
+    vec![
+      RowCommentMention::User { uid: 2 },
+      RowCommentMention::Row {
+        row_id: mentioned_row
+      }
+    ]
+  );
+
+  row.update_comment(&other.id, "Edited", vec![]).unwrap();
+  row.add_comment_reaction(&comment.id, "👍", 2).unwrap();
+  row.add_comment_reaction(&comment.id, "👍", 3).unwrap();
+  row.add_comment_reaction(&comment.id, "🎉", 3).unwrap();
+  row.remove_comment_reaction(&comment.id, "🎉", 3).unwrap();
+  row.set_comment_resolved_by(&comment.id, Some(2)).unwrap();
+  drop(row);
+
+  let threads = database_test.get_row_comment_threads(&row_id).await;
+  assert_eq!(threads.len(), 2);
+  let thread = &threads[0];
+  assert_eq!(thread.comment.id, comment.id);
+  assert!(thread.comment.is_resolved());
+  assert_eq!(
+    thread.comment.reactions.get("👍").cloned(),
+    Some(vec![2, 3])
+  );
+  assert!(!thread.comment.reactions.contains_key("🎉"));
+  assert_eq!(thread.replies.len(), 1);
+  assert_eq!(thread.replies[0].comment.content, "Done");
+  assert_eq!(thread.replies[0].replies[0].comment.content, "Thanks");
+  assert_eq!(threads[1].comment.content, "Edited");
+
+  // the deleted comment stays in the thread of its replies
+  let mut row = database_row.write().await;
+  row.delete_comment(&reply.id).unwrap();
+  row.delete_comment(&other.id).unwrap();
+  assert!(matches!(
+    row.update_comment(&other.id, "Again", vec![]),
+    Err(DatabaseError::RecordNotFound)
+  ));
+  let threads = row.get_comment_threads();
+  assert_eq!(threads.len(), 1);
+  let deleted_reply = &threads[0].replies[0].comment;
+  assert!(deleted_reply.is_deleted);
+  assert!(deleted_reply.content.is_empty());
+  assert_eq!(threads[0].replies[0].replies.len(), 1);
+  assert_eq!(row.get_comments().len(), 4);
+}
+
+#[tokio::test]
+async fn row_comment_notification_test() {
+  let (database_test, row_id) = create_comment_database().await;
+  let mut rx = database_test.subscribe_row_change().unwrap();
+  let database_row = database_test.get_database_row(&row_id).await.unwrap();
+  let mut row = database_row.write().await;
+
+  let comment = row
+    .add_comment(CreateRowCommentParams::new(1, "Hello"))
+    .unwrap();
+  let comments = received_comments(&mut rx);
+  assert_eq!(comments, vec![comment.clone()]);
+
+  row
+    .update_comment(&comment.id, "Hello world", vec![])
+    .unwrap();
+  row.add_comment_reaction(&comment.id, "👀", 2).unwrap();
+  let comments = received_comments(&mut rx);
+  assert_eq!(comments.len(), 2);
+  assert_eq!(comments[0].content, "Hello world");
+  assert_eq!(comments[1].reactions.get("👀").cloned(), Some(vec![2]));
+
+  row.delete_comment(&comment.id).unwrap();
+  let comments = received_comments(&mut rx);
+  assert_eq!(comments.len(), 1);
+  assert!(comments[0].is_deleted);
+}
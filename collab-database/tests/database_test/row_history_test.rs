@@ -0,0 +1,203 @@
+use collab::core::collab::{CollabOptions, default_client_id};
+use collab::core::origin::{CollabClient, CollabOrigin};
+use collab::preclude::Collab;
+use collab_database::entity::FieldType;
+use collab_database::fields::Field;
+use collab_database::fields::select_type_option::{
+  SelectOption, SelectOptionIds, SelectTypeOption, SingleSelectTypeOption,
+};
+use collab_database::rows::{Cells, CreateRowParams, DatabaseRow, Row, RowHistoryRetention, RowId};
+use collab_database::template::number_parse::NumberCellData;
+use uuid::Uuid;
+
+use crate::database_test::helper::{DatabaseTest, DatabaseTestBuilder};
+use crate::helper::TestTextCell;
+
+async fn create_history_database() -> (DatabaseTest, RowId, SelectOption) {
+  let database_id = Uuid::new_v4().to_string();
+  let row_id = RowId::from(Uuid::new_v4().to_string());
+  let done = SelectOption::new("Done");
+  let status_type_option = SingleSelectTypeOption(SelectTypeOption {
+    options: vec![SelectOption::new("Todo"), done.clone()],
+    disable_color: false,
+  });
+  let database_test = DatabaseTestBuilder::new(1, &database_id)
+    .with_field(Field::new(
+      "name".to_string(),
+      "Name".to_string(),
+      FieldType::RichText.into(),
+      true,
+    ))
+    .with_field(Field::new(
+      "amount".to_string(),
+      "Amount".to_string(),
+      FieldType::Number.into(),
+      false,
+    ))
+    .with_field(
+      Field::new(
+        "status".to_string(),
+        "Status".to_string(),
+        FieldType::SingleSelect.into(),
+        false,
+      )
+      .with_type_option_data(FieldType::SingleSelect, status_type_option.into()),
+    )
+    .with_row(
+      CreateRowParams::new(row_id.clone(), database_id.clone()).with_cells(Cells::from([(
+        "name".to_string(),
+        TestTextCell::from("Tom").into(),
+      )])),
+    )
+    .build()
+    .await;
+  (database_test, row_id, done)
+}
+
+#[tokio::test]
+async fn row_history_test() {
+  let (mut database_test, row_id, done) = create_history_database().await;
+  database_test.set_row_history_retention(RowHistoryRetention {
+    max_entries: 100,
+    max_age: None,
+  });
+  database_test
+    .update_row(row_id.clone(), |row| {
+      row.update_cells(|cells| {
+        cells
+          .insert_cell("name", TestTextCell::from("Tommy").into())
+          .insert_cell("amount", NumberCellData("5".to_string()).into());
+      });
+    })
+    .await;
+  database_test
+    .update_row(row_id.clone(), |row| {
+      row.update_cells(|cells| {
+        cells.insert_cell(
+          "status",
+          SelectOptionIds::from(vec![done.id.clone()]).to_cell(FieldType::SingleSelect),
+        );
+      });
+    })
+    .await;
+  // an update that doesn't change the cells isn't recorded
+  database_test
+    .update_row(row_id.clone(), |row| {
+      row.set_height(100);
+    })
+    .await;
+
+  let history = database_test.get_row_history(&row_id, None).await;
+  let changes = history
+    .iter()
+    .map(|entry| {
+      (
+        entry.field_id.as_str(),
+        entry.old_value.as_str(),
+        entry.new_value.as_str(),
+      )
+    })
+    .collect::<Vec<_>>();
+  assert_eq!(
+    changes,
+    vec![
+      ("status", "", "Done"),
+      ("name", "Tom", "Tommy"),
+      ("amount", "", "5"),
+    ]
+  );
+  assert!(history.iter().all(|entry| entry.uid.is_none()));
+  assert!(history.iter().all(|entry| entry.timestamp > 0));
+
+  let history = database_test.get_row_history(&row_id, Some("name")).await;
+  assert_eq!(history.len(), 1);
+  assert_eq!(history[0].new_value, "Tommy");
+}
+
+#[tokio::test]
+async fn row_history_retention_test() {
+  let (mut database_test, row_id, _) = create_history_database().await;
+  // the history is disabled by default
+  assert!(database_test.get_row_history_retention().is_disabled());
+  database_test
+    .update_row(row_id.clone(), |row| {
+      row.update_cells(|cells| {
+        cells.insert_cell("name", TestTextCell::from("Tommy").into());
+      });
+    })
+    .await;
+  assert!(
+    database_test
+      .get_row_history(&row_id, None)
+      .await
+      .is_empty()
+  );
+
+  let retention = RowHistoryRetention {
+    max_entries: 2,
+    max_age: Some(3600),
+  };
+  database_test.set_row_history_retention(retention);
+  assert_eq!(database_test.get_row_history_retention(), retention);
+
+  for name in ["a", "b", "c"] {
+    database_test
+      .update_row(row_id.clone(), |row| {
+        row.update_cells(|cells| {
+          cells.insert_cell("name", TestTextCell::from(name).into());
+        });
+      })
+      .await;
+  }
+  let values = database_test
+    .get_row_history(&row_id, None)
+    .await
+    .into_iter()
+    .map(|entry| entry.new_value)
+    .collect::<Vec<_>>();
+  assert_eq!(values, vec!["c", "b"]);
+
+  // the history is disabled
+  database_test.set_row_history_retention(RowHistoryRetention {
+    max_entries: 0,
+    max_age: None,
+  });
+  database_test
+    .update_row(row_id.clone(), |row| {
+      row.update_cells(|cells| {
+        cells.insert_cell("name", TestTextCell::from("d").into());
+      });
+    })
+    .await;
+  assert_eq!(database_test.get_row_history(&row_id, None).await.len(), 2);
+}
+
+#[test]
+fn row_history_uid_from_origin_test() {
+  let row_id = RowId::from(Uuid::new_v4().to_string());
+  let origin = CollabOrigin::Client(CollabClient::new(42, "device"));
+  let options = CollabOptions::new(row_id.to_string(), default_client_id());
+  let mut collab = Collab::new_with_options(origin, options).unwrap();
+  collab.initialize();
+  let mut row = DatabaseRow::create(row_id.clone(), collab, None, Row::new(row_id, "database"));
+
+  let previous_cells = row.get_row().unwrap().cells;
+  row.update(|update| {
+    update.update_cells(|cells| {
+      cells.insert_cell("name", TestTextCell::from("Tom").into());
+    });
+  });
+  row.record_cell_changes(
+    &previous_cells,
+    &RowHistoryRetention {
+      max_entries: 10,
+      max_age: None,
+    },
+  );
+
+  let history = row.get_history();
+  assert_eq!(history.len(), 1);
+  assert_eq!(history[0].field_id, "name");
+  assert_eq!(history[0].old_value, None);
+  assert_eq!(history[0].uid, Some(42));
+}
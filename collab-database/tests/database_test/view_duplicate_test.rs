@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use collab::core::collab::CollabOptions;
+use collab::core::origin::CollabOrigin;
+use collab::preclude::{Collab, Map, MapRef};
+use collab::util::AnyMapExt;
+use collab_database::database::{DatabaseContext, get_row_document_id};
+use collab_database::database_trait::DatabaseCollabReader;
+use collab_database::entity::FieldType;
+use collab_database::fields::Field;
+use collab_database::fields::select_type_option::{
+  SelectOption, SelectOptionIds, SelectTypeOption, SingleSelectTypeOption,
+};
+use collab_database::rows::{Cells, CreateRowParams, RowId};
+use collab_database::template::entity::CELL_DATA;
+use collab_database::views::{DatabaseLayout, SortMap};
+use collab_entity::CollabType;
+use futures::StreamExt;
+use uuid::Uuid;
+
+use crate::database_test::helper::DatabaseTestBuilder;
+use crate::helper::{TestFieldSetting, TestFieldType, TestFilter, TestTextCell};
+use crate::user_test::helper::TestUserDatabaseServiceImpl;
+
+#[tokio::test]
+async fn duplicate_view_as_database_test() {
+  let database_id = Uuid::new_v4().to_string();
+  let todo = SelectOption::new("Todo");
+  let done = SelectOption::new("Done");
+  let status_type_option = SingleSelectTypeOption(SelectTypeOption {
+    options: vec![todo.clone(), done.clone()],
+    disable_color: false,
+  });
+  let row_ids = (0..3)
+    .map(|_| RowId::from(Uuid::new_v4().to_string()))
+    .collect::<Vec<_>>();
+  let row = |row_id: &RowId, name: &str, status: &SelectOption| {
+    CreateRowParams::new(row_id.clone(), database_id.clone()).with_cells(Cells::from([
+      ("name".to_string(), TestTextCell::from(name).into()),
+      ("secret".to_string(), TestTextCell::from("hidden").into()),
+      (
+        "status".to_string(),
+        SelectOptionIds::from(vec![status.id.clone()]).to_cell(FieldType::SingleSelect),
+      ),
+    ]))
+  };
+  let mut database_test = DatabaseTestBuilder::new(1, &database_id)
+    .with_field(Field::new(
+      "name".to_string(),
+      "Name".to_string(),
+      FieldType::RichText.into(),
+      true,
+    ))
+    .with_field(Field::new(
+      "secret".to_string(),
+      "Secret".to_string(),
+      FieldType::RichText.into(),
+      false,
+    ))
+    .with_field(
+      Field::new(
+        "status".to_string(),
+        "Status".to_string(),
+        FieldType::SingleSelect.into(),
+        false,
+      )
+      .with_type_option_data(FieldType::SingleSelect, status_type_option.into()),
+    )
+    .with_row(row(&row_ids[0], "a", &done))
+    .with_row(row(&row_ids[1], "b", &todo))
+    .with_row(row(&row_ids[2], "c", &done))
+    .build()
+    .await;
+
+  database_test.update_field_settings(
+    "v1",
+    Some(vec!["secret".to_string()]),
+    TestFieldSetting {
+      width: 100,
+      visibility: 2,
+    },
+  );
+  database_test.update_field_settings(
+    "v1",
+    Some(vec!["status".to_string()]),
+    TestFieldSetting {
+      width: 120,
+      visibility: 0,
+    },
+  );
+  database_test.insert_filter(
+    "v1",
+    TestFilter {
+      id: "filter_1".to_string(),
+      field_id: "status".to_string(),
+      field_type: TestFieldType::SingleSelect,
+      condition: 0,
+      content: done.id.clone(),
+    },
+  );
+  database_test.insert_sort(
+    "v1",
+    SortMap::from([
+      ("id".to_string(), "sort_1".into()),
+      ("field_id".to_string(), "name".into()),
+    ]),
+  );
+  database_test
+    .update_row_meta(&row_ids[2], |meta_update| {
+      meta_update
+        .insert_icon("icon 123")
+        .update_is_document_empty(false);
+    })
+    .await;
+
+  let service = Arc::new(TestUserDatabaseServiceImpl::new(
+    1,
+    database_test.workspace_id.clone(),
+    database_test.collab_db.clone(),
+    database_test.client_id,
+  ));
+  let persistence = service.reader_persistence().unwrap();
+  let document_id = get_row_document_id(&row_ids[2]).unwrap();
+  let options = CollabOptions::new(document_id.clone(), database_test.client_id);
+  let mut document = Collab::new_with_options(CollabOrigin::Empty, options).unwrap();
+  {
+    let mut txn = document.context.transact_mut();
+    let root: MapRef = document.data.get_or_init(&mut txn, "document");
+    root.insert(&mut txn, "page_id", "page");
+  }
+  persistence
+    .upsert_collab(
+      &document_id,
+      document
+        .encode_collab_v1(|collab| CollabType::Document.validate_require_data(collab))
+        .unwrap(),
+    )
+    .unwrap();
+
+  let context = DatabaseContext::new(service.clone(), service);
+  let (database, ids) = database_test
+    .duplicate_view_as_database("v1", context)
+    .await
+    .unwrap();
+  assert_eq!(database.get_database_id(), ids.database_id);
+  assert_ne!(ids.database_id, database_id);
+
+  // the hidden field and the filtered out row are not duplicated
+  let fields = database.get_fields_in_view(&ids.view_id, None);
+  assert_eq!(
+    fields
+      .iter()
+      .map(|field| field.name.as_str())
+      .collect::<Vec<_>>(),
+    vec!["Name", "Status"]
+  );
+  assert!(!ids.field_ids.contains_key("secret"));
+  let name_field_id = &ids.field_ids["name"];
+  let status_field_id = &ids.field_ids["status"];
+  assert_ne!(name_field_id, "name");
+  assert!(fields[0].is_primary);
+
+  let new_done_id = &ids.option_ids[&done.id];
+  assert_ne!(new_done_id, &done.id);
+  let type_option = fields[1]
+    .get_type_option::<SingleSelectTypeOption>(FieldType::SingleSelect.type_id())
+    .unwrap();
+  assert_eq!(type_option.options[1].id, *new_done_id);
+  assert_eq!(type_option.options[1].name, "Done");
+
+  let rows = database
+    .get_rows_for_view(&ids.view_id, 10, None, true)
+    .await
+    .collect::<Vec<_>>()
+    .await
+    .into_iter()
+    .map(|row| row.unwrap())
+    .collect::<Vec<_>>();
+  assert_eq!(rows.len(), 2);
+  assert_eq!(rows[0].id, ids.row_ids[&row_ids[0]]);
+  assert_eq!(rows[1].id, ids.row_ids[&row_ids[2]]);
+  assert!(!ids.row_ids.contains_key(&row_ids[1]));
+  assert_eq!(rows[1].cells.len(), 2);
+  assert_eq!(
+    rows[1].cells[name_field_id].get_as::<String>(CELL_DATA),
+    Some("c".to_string())
+  );
+  assert_eq!(
+    SelectOptionIds::from(&rows[1].cells[status_field_id]).into_inner(),
+    vec![new_done_id.clone()]
+  );
+
+  // the row meta and the row document are copied
+  let row_meta = database.get_row_meta(&rows[1].id).await.unwrap();
+  assert_eq!(row_meta.icon_url, Some("icon 123".to_string()));
+  assert!(!row_meta.is_document_empty);
+  assert!(ids.skipped_document_row_ids.is_empty());
+  assert!(ids.skipped_row_ids.is_empty());
+  let new_document_id = get_row_document_id(&rows[1].id).unwrap();
+  assert!(
+    persistence
+      .get_encoded_collab(&new_document_id, CollabType::Document)
+      .is_some()
+  );
+
+  // the view keeps the sorts and field settings of the duplicated fields, but not the filters
+  let view = database.get_view(&ids.view_id).unwrap();
+  assert_eq!(view.layout, DatabaseLayout::Grid);
+  assert!(view.filters.is_empty());
+  assert_eq!(
+    view.sorts[0].get_as::<String>("field_id"),
+    Some(name_field_id.clone())
+  );
+  assert_eq!(
+    view.field_settings[status_field_id].get_as::<i64>("width"),
+    Some(120)
+  );
+  assert!(!view.field_settings.contains_key("secret"));
+}
@@ -1,8 +1,8 @@
 use crate::block_parser::{
   BlockParserRegistry, BulletedListParser, CalloutParser, CodeBlockParser, DividerParser,
   DocumentParserDelegate, FileBlockParser, HeadingParser, ImageParser, LinkPreviewParser,
-  MathEquationParser, NumberedListParser, OutputFormat, PageParser, ParagraphParser, ParseContext,
-  QuoteListParser, SimpleColumnParser, SimpleColumnsParser, SimpleTableCellParser,
+  MathEquationParser, NumberedListParser, OutlineParser, OutputFormat, PageParser, ParagraphParser,
+  ParseContext, QuoteListParser, SimpleColumnParser, SimpleColumnsParser, SimpleTableCellParser,
   SimpleTableParser, SimpleTableRowParser, SubpageParser, TodoListParser, ToggleListParser,
 };
 use crate::blocks::{Block, DocumentData};
@@ -58,6 +58,7 @@ impl DocumentParser {
       .register(Arc::new(FileBlockParser))
       .register(Arc::new(LinkPreviewParser))
       .register(Arc::new(MathEquationParser))
+      .register(Arc::new(OutlineParser))
       .register(Arc::new(SimpleColumnsParser))
       .register(Arc::new(SimpleColumnParser))
       .register(Arc::new(SimpleTableParser))
@@ -88,6 +89,12 @@ impl DocumentParser {
     block: &Block,
     context: &ParseContext,
   ) -> Result<String, DocumentError> {
+    if let Some(delegate) = &self.delegate {
+      if let Some(content) = delegate.handle_block(block, context) {
+        return Ok(content);
+      }
+    }
+
     let result = self.registry.parse_block(block, context)?;
 
     if result.is_container {
@@ -14,7 +14,7 @@ use crate::error::DocumentError;
 ///   delta: delta,
 pub struct HeadingParser;
 
-const MAX_LEVEL: usize = 6;
+pub const MAX_HEADING_LEVEL: usize = 6;
 const MIN_LEVEL: usize = 1;
 
 // do not change the key value, it comes from the flutter code.
@@ -25,16 +25,7 @@ impl BlockParser for HeadingParser {
     let text_extractor = DefaultDocumentTextExtractor;
     let content = text_extractor.extract_text_from_block(block, context)?;
 
-    let level = block
-      .data
-      .get(LEVEL_KEY)
-      .and_then(|v| match v {
-        Value::Number(n) => n.as_u64().map(|n| n as usize),
-        Value::String(s) => s.parse::<usize>().ok(),
-        _ => None,
-      })
-      .unwrap_or(1)
-      .clamp(MIN_LEVEL, MAX_LEVEL);
+    let level = heading_level(block);
 
     let formatted_content = match context.format {
       OutputFormat::Markdown => {
@@ -58,3 +49,17 @@ impl BlockParser for HeadingParser {
     BlockType::Heading.as_str()
   }
 }
+
+/// Returns the level of the heading block, 1 if the level is missing or invalid.
+pub fn heading_level(block: &Block) -> usize {
+  block
+    .data
+    .get(LEVEL_KEY)
+    .and_then(|v| match v {
+      Value::Number(n) => n.as_u64().map(|n| n as usize),
+      Value::String(s) => s.parse::<usize>().ok(),
+      _ => None,
+    })
+    .unwrap_or(1)
+    .clamp(MIN_LEVEL, MAX_HEADING_LEVEL)
+}
@@ -8,6 +8,7 @@ pub mod image;
 pub mod link_preview;
 pub mod math_equation;
 pub mod numbered_list;
+pub mod outline;
 pub mod page;
 pub mod paragraph;
 pub mod quote_list;
@@ -30,6 +31,7 @@ pub use image::*;
 pub use link_preview::*;
 pub use math_equation::*;
 pub use numbered_list::*;
+pub use outline::*;
 pub use page::*;
 pub use paragraph::*;
 pub use quote_list::*;
@@ -0,0 +1,156 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::block_parser::{
+  BlockParser, DefaultDocumentTextExtractor, DocumentTextExtractor, MAX_HEADING_LEVEL,
+  OutputFormat, ParseContext, ParseResult, heading_level,
+};
+use crate::blocks::{Block, BlockType};
+use crate::error::DocumentError;
+
+/// Parse the outline block, the table of contents of the document.
+///
+/// Outline block data:
+///   depth: int, the deepest heading level to include, 6 by default
+pub struct OutlineParser;
+
+// do not change the key value, it comes from the flutter code.
+const DEPTH_KEY: &str = "depth";
+
+impl BlockParser for OutlineParser {
+  fn parse(&self, block: &Block, context: &ParseContext) -> Result<ParseResult, DocumentError> {
+    let max_level = block
+      .data
+      .get(DEPTH_KEY)
+      .and_then(|v| match v {
+        Value::Number(n) => n.as_u64().map(|n| n as usize),
+        Value::String(s) => s.parse::<usize>().ok(),
+        _ => None,
+      })
+      .unwrap_or(MAX_HEADING_LEVEL)
+      .clamp(1, MAX_HEADING_LEVEL);
+
+    let outline = build_outline(context, max_level);
+    if outline.is_empty() {
+      return Ok(ParseResult::empty());
+    }
+
+    let mut lines = vec![];
+    render_outline_items(&outline, context, 0, &mut lines);
+    Ok(ParseResult::new(lines.join("\n")))
+  }
+
+  fn block_type(&self) -> &'static str {
+    BlockType::Outline.as_str()
+  }
+}
+
+/// A heading of the document, with the headings of a deeper level that follow it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutlineItem {
+  pub block_id: String,
+  pub level: usize,
+  pub text: String,
+  pub children: Vec<OutlineItem>,
+}
+
+/// Builds the heading hierarchy of the document in document order.
+///
+/// The headings nested in other blocks, like toggles and columns, are included. The headings
+/// without text or with a level above `max_level` are skipped. A heading becomes a child of the
+/// closest previous heading with a lower level.
+pub fn build_outline(context: &ParseContext, max_level: usize) -> Vec<OutlineItem> {
+  let mut outline = vec![];
+  if let Some(page) = context
+    .document_data
+    .blocks
+    .get(&context.document_data.page_id)
+  {
+    let mut visited = HashSet::from([page.id.as_str()]);
+    collect_headings(page, context, max_level, &mut visited, &mut outline);
+  }
+  outline
+}
+
+/// `visited` holds the blocks that were already collected, a block listed more than once or
+/// that is its own ancestor is only collected the first time.
+fn collect_headings<'a>(
+  block: &Block,
+  context: &'a ParseContext,
+  max_level: usize,
+  visited: &mut HashSet<&'a str>,
+  outline: &mut Vec<OutlineItem>,
+) {
+  let Some(child_ids) = context.document_data.meta.children_map.get(&block.children) else {
+    return;
+  };
+  for child in child_ids
+    .iter()
+    .filter_map(|child_id| context.document_data.blocks.get(child_id))
+  {
+    if !visited.insert(child.id.as_str()) {
+      continue;
+    }
+    if child.ty == BlockType::Heading.as_str() {
+      let level = heading_level(child);
+      let text = heading_text(child, context);
+      if level <= max_level && !text.is_empty() {
+        insert_outline_item(
+          outline,
+          OutlineItem {
+            block_id: child.id.clone(),
+            level,
+            text,
+            children: vec![],
+          },
+        );
+      }
+    }
+    collect_headings(child, context, max_level, visited, outline);
+  }
+}
+
+fn heading_text(block: &Block, context: &ParseContext) -> String {
+  let delta_json = block.external_id.as_ref().and_then(|external_id| {
+    context
+      .document_data
+      .meta
+      .text_map
+      .as_ref()
+      .and_then(|text_map| text_map.get(external_id))
+  });
+  delta_json
+    .and_then(|json| {
+      DefaultDocumentTextExtractor
+        .extract_plain_text_from_delta_with_context(json, Some(context))
+        .ok()
+    })
+    .map(|text| text.trim().to_string())
+    .unwrap_or_default()
+}
+
+fn insert_outline_item(items: &mut Vec<OutlineItem>, item: OutlineItem) {
+  match items.last_mut() {
+    Some(last) if last.level < item.level => insert_outline_item(&mut last.children, item),
+    _ => items.push(item),
+  }
+}
+
+fn render_outline_items(
+  items: &[OutlineItem],
+  context: &ParseContext,
+  nesting: usize,
+  lines: &mut Vec<String>,
+) {
+  let indent = format!("{}{}", context.get_indent(), "  ".repeat(nesting));
+  for item in items {
+    let line = match context.format {
+      OutputFormat::Markdown => format!("{}- {}", indent, item.text),
+      OutputFormat::PlainText => format!("{}{}", indent, item.text),
+    };
+    lines.push(line);
+    render_outline_items(&item.children, context, nesting + 1, lines);
+  }
+}
@@ -185,4 +185,13 @@ pub trait DocumentParserDelegate: Debug {
   ) -> Option<String> {
     None
   }
+
+  /// Delegate the whole block to the caller. The returned content replaces the output of the
+  /// registered parser, and the children of the block are not parsed.
+  ///
+  /// For example, for an inline grid, the caller should return the rows of the referenced
+  /// database view.
+  fn handle_block(&self, _block: &Block, _context: &ParseContext) -> Option<String> {
+    None
+  }
 }
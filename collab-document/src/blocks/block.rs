@@ -18,6 +18,7 @@ const EXTERNAL_ID: &str = "external_id";
 const EXTERNAL_TYPE: &str = "external_type";
 
 /// for block operate, there has a root map, and a children map.
+#[derive(Clone)]
 pub struct BlockOperation {
   root: MapRef,
   children_operation: ChildrenOperation,
@@ -107,6 +108,11 @@ impl BlockOperation {
       .map(|map| block_from_map(txn, map))
   }
 
+  /// Returns the map that stores the block with the given id.
+  pub fn get_block_map_with_txn<T: ReadTxn>(&self, txn: &T, id: &str) -> Option<MapRef> {
+    self.root.get_with_txn::<T, MapRef>(txn, id)
+  }
+
   /// Update the block with the given id.
   /// Except \`data\` and \`parent\` and \'external_id\' and \'external_type\' field, other fields can be updated.
   /// If you want to turn into other block, you should delete the block and create a new block.
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use serde_json::Value;
+
+use crate::blocks::{Block, BlockType};
+
+/// The type of a value in the data of a block.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockDataFieldType {
+  String,
+  /// A string that is not empty.
+  NonEmptyString,
+  Bool,
+  /// An integer in the inclusive range. A string that contains an integer is accepted as well,
+  /// older clients write numbers as strings.
+  Integer {
+    min: Option<i64>,
+    max: Option<i64>,
+  },
+  Number,
+  Array,
+  Object,
+  /// One of the given strings.
+  Enum(Vec<String>),
+}
+
+impl Display for BlockDataFieldType {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      BlockDataFieldType::String => write!(f, "string"),
+      BlockDataFieldType::NonEmptyString => write!(f, "non-empty string"),
+      BlockDataFieldType::Bool => write!(f, "bool"),
+      BlockDataFieldType::Integer { min, max } => match (min, max) {
+        (Some(min), Some(max)) => write!(f, "integer between {} and {}", min, max),
+        (Some(min), None) => write!(f, "integer >= {}", min),
+        (None, Some(max)) => write!(f, "integer <= {}", max),
+        (None, None) => write!(f, "integer"),
+      },
+      BlockDataFieldType::Number => write!(f, "number"),
+      BlockDataFieldType::Array => write!(f, "array"),
+      BlockDataFieldType::Object => write!(f, "object"),
+      BlockDataFieldType::Enum(values) => write!(f, "one of {}", values.join(", ")),
+    }
+  }
+}
+
+impl BlockDataFieldType {
+  pub fn integer_between(min: i64, max: i64) -> Self {
+    Self::Integer {
+      min: Some(min),
+      max: Some(max),
+    }
+  }
+
+  fn accepts(&self, value: &Value) -> bool {
+    match self {
+      BlockDataFieldType::String => value.is_string(),
+      BlockDataFieldType::NonEmptyString => value.as_str().is_some_and(|s| !s.trim().is_empty()),
+      BlockDataFieldType::Bool => value.is_boolean(),
+      BlockDataFieldType::Integer { min, max } => {
+        let integer = match value {
+          Value::Number(n) => n.as_i64(),
+          Value::String(s) => s.parse::<i64>().ok(),
+          _ => None,
+        };
+        integer.is_some_and(|n| min.is_none_or(|min| n >= min) && max.is_none_or(|max| n <= max))
+      },
+      BlockDataFieldType::Number => value.is_number(),
+      BlockDataFieldType::Array => value.is_array(),
+      BlockDataFieldType::Object => value.is_object(),
+      BlockDataFieldType::Enum(values) => value
+        .as_str()
+        .is_some_and(|s| values.iter().any(|v| v == s)),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockDataField {
+  pub key: String,
+  pub ty: BlockDataFieldType,
+  pub required: bool,
+}
+
+/// The expected data of a block type.
+///
+/// Only the declared fields are checked, other keys are accepted unless the schema is
+/// [BlockDataSchema::strict]. A `null` optional field is treated as missing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockDataSchema {
+  pub fields: Vec<BlockDataField>,
+  pub strict: bool,
+}
+
+impl BlockDataSchema {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn required(mut self, key: &str, ty: BlockDataFieldType) -> Self {
+    self.fields.push(BlockDataField {
+      key: key.to_string(),
+      ty,
+      required: true,
+    });
+    self
+  }
+
+  pub fn optional(mut self, key: &str, ty: BlockDataFieldType) -> Self {
+    self.fields.push(BlockDataField {
+      key: key.to_string(),
+      ty,
+      required: false,
+    });
+    self
+  }
+
+  /// Rejects the keys that are not declared in the schema.
+  pub fn strict(mut self) -> Self {
+    self.strict = true;
+    self
+  }
+
+  pub fn validate(&self, data: &HashMap<String, Value>) -> Result<(), BlockDataError> {
+    for field in &self.fields {
+      match data.get(&field.key) {
+        None | Some(Value::Null) if field.required => {
+          return Err(BlockDataError::MissingField(field.key.clone()));
+        },
+        None | Some(Value::Null) => {},
+        Some(value) if !field.ty.accepts(value) => {
+          return Err(BlockDataError::InvalidField {
+            key: field.key.clone(),
+            expected: field.ty.to_string(),
+            value: value.clone(),
+          });
+        },
+        Some(_) => {},
+      }
+    }
+    if self.strict {
+      if let Some(key) = data
+        .keys()
+        .find(|key| self.fields.iter().all(|field| &field.key != *key))
+      {
+        return Err(BlockDataError::UnknownField(key.clone()));
+      }
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum BlockDataError {
+  #[error("missing required field `{0}`")]
+  MissingField(String),
+
+  #[error("field `{key}` should be {expected}, got {value}")]
+  InvalidField {
+    key: String,
+    expected: String,
+    value: Value,
+  },
+
+  #[error("unknown field `{0}`")]
+  UnknownField(String),
+}
+
+/// Holds the [BlockDataSchema] of each block type. The block types without a schema accept any
+/// data.
+#[derive(Debug, Clone)]
+pub struct BlockSchemaRegistry {
+  schemas: HashMap<String, BlockDataSchema>,
+}
+
+impl Default for BlockSchemaRegistry {
+  fn default() -> Self {
+    Self::with_builtin_schemas()
+  }
+}
+
+impl BlockSchemaRegistry {
+  /// A registry without any schema.
+  pub fn empty() -> Self {
+    Self {
+      schemas: HashMap::new(),
+    }
+  }
+
+  /// A registry with the schemas of the built-in block types.
+  ///
+  /// The built-in fields are optional, the parsers fall back to a default value when a field is
+  /// missing. Only the type of a present field is checked.
+  pub fn with_builtin_schemas() -> Self {
+    use BlockDataFieldType::*;
+
+    let mut registry = Self::empty();
+    registry
+      .register(
+        BlockType::Heading,
+        BlockDataSchema::new().optional("level", BlockDataFieldType::integer_between(1, 6)),
+      )
+      .register(
+        BlockType::TodoList,
+        BlockDataSchema::new().optional("checked", Bool),
+      )
+      .register(
+        BlockType::ToggleList,
+        BlockDataSchema::new().optional("collapsed", Bool),
+      )
+      .register(
+        BlockType::Image,
+        BlockDataSchema::new().optional("url", String),
+      )
+      .register(
+        BlockType::LinkPreview,
+        BlockDataSchema::new().optional("url", String),
+      )
+      .register(
+        BlockType::File,
+        BlockDataSchema::new()
+          .optional("url", String)
+          .optional("name", String),
+      )
+      .register(
+        BlockType::Callout,
+        BlockDataSchema::new().optional("icon", String),
+      )
+      .register(
+        BlockType::Code,
+        BlockDataSchema::new().optional("language", String),
+      )
+      .register(
+        BlockType::MathEquation,
+        BlockDataSchema::new().optional("formula", String),
+      )
+      .register(
+        BlockType::SubPage,
+        BlockDataSchema::new().optional("viewId", String),
+      )
+      .register(
+        BlockType::MultiImage,
+        BlockDataSchema::new().optional("images", Array),
+      );
+    for block_type in [BlockType::Grid, BlockType::Board, BlockType::Calendar] {
+      registry.register(
+        block_type,
+        BlockDataSchema::new()
+          .optional("view_id", String)
+          .optional("parent_id", String),
+      );
+    }
+    registry
+  }
+
+  /// Registers the schema of the block type, replacing the existing one. Use
+  /// [BlockType::Custom] to register the schema of a custom block.
+  pub fn register(&mut self, block_type: BlockType, schema: BlockDataSchema) -> &mut Self {
+    self.schemas.insert(block_type.as_str().to_string(), schema);
+    self
+  }
+
+  pub fn unregister(&mut self, block_type: &BlockType) -> Option<BlockDataSchema> {
+    self.schemas.remove(block_type.as_str())
+  }
+
+  pub fn get(&self, block_type: &str) -> Option<&BlockDataSchema> {
+    self.schemas.get(block_type)
+  }
+
+  pub fn validate_block(&self, block: &Block) -> Result<(), BlockDataError> {
+    match self.get(&block.ty) {
+      Some(schema) => schema.validate(&block.data),
+      None => Ok(()),
+    }
+  }
+}
+
+/// A problem found by [crate::document::Document::validate_blocks].
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockValidationIssue {
+  /// The data of the block doesn't match the schema of its type.
+  InvalidData {
+    block_id: String,
+    block_type: String,
+    error: BlockDataError,
+  },
+  /// The `children` id of the block has no entry in the children map.
+  MissingChildren {
+    block_id: String,
+    children_id: String,
+  },
+  /// The children of a block contain an id that is not a block.
+  MissingBlock { parent_id: String, block_id: String },
+  /// The `parent` of the block is not the block that lists it as a child.
+  ParentMismatch {
+    block_id: String,
+    parent_id: String,
+    actual_parent_id: String,
+  },
+  /// The block is its own ancestor.
+  Cycle { block_id: String },
+  /// The block is listed as a child of more than one block, or more than once.
+  DuplicateChild { block_id: String },
+  /// The block can't be reached from the page block.
+  Orphan { block_id: String },
+  /// The page block doesn't exist.
+  MissingPage { page_id: String },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockValidationReport {
+  pub issues: Vec<BlockValidationIssue>,
+}
+
+impl BlockValidationReport {
+  pub fn is_valid(&self) -> bool {
+    self.issues.is_empty()
+  }
+}
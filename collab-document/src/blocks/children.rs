@@ -18,6 +18,15 @@ impl ChildrenOperation {
     }
   }
 
+  /// get the children array with the given id, return None if it doesn't exist
+  pub fn get_children_ref_with_txn<T: ReadTxn>(
+    &self,
+    txn: &T,
+    children_id: &str,
+  ) -> Option<ArrayRef> {
+    self.root.get(txn, children_id)?.cast().ok()
+  }
+
   /// get the children of a block with the given id or create it if it does not exist
   pub fn get_or_init_children(&self, txn: &mut TransactionMut, children_id: &str) -> ArrayRef {
     self.root.get_or_init_array(txn, children_id)
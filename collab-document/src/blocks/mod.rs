@@ -1,5 +1,6 @@
 mod attr_keys;
 mod block;
+mod block_schema;
 mod block_types;
 mod children;
 mod entities;
@@ -9,6 +10,7 @@ mod utils;
 
 pub use attr_keys::*;
 pub use block::*;
+pub use block_schema::*;
 pub use block_types::*;
 pub use children::*;
 pub use entities::*;
@@ -5,6 +5,7 @@ use serde::Deserialize;
 use serde_json::json;
 use std::collections::HashMap;
 
+#[derive(Clone)]
 pub struct TextOperation {
   root: MapRef,
 }
@@ -19,6 +20,11 @@ impl TextOperation {
     self.root.get_or_init_text(txn, text_id)
   }
 
+  /// get text ref with text_id, return None if the text doesn't exist
+  pub fn get_text_ref_with_txn<T: ReadTxn>(&self, txn: &T, text_id: &str) -> Option<TextRef> {
+    self.root.get(txn, text_id)?.cast().ok()
+  }
+
   /// delete text ref wrapper with text_id
   pub fn delete_text_with_txn(&self, txn: &mut TransactionMut, text_id: &str) {
     self.root.remove(txn, text_id);
@@ -7,7 +7,7 @@ use collab::preclude::{
   YrsDelta, YrsValue,
 };
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// block data json string to hashmap
 pub fn json_str_to_hashmap(json_str: &str) -> Result<HashMap<String, Value>, DocumentError> {
@@ -19,6 +19,42 @@ pub fn hashmap_to_json_str(data: HashMap<String, Value>) -> Result<String, Docum
   serde_json::to_string(&data).map_err(|_| DocumentError::ConvertDataError)
 }
 
+const BLOCKS_PATH: &str = "blocks";
+const TEXT_MAP_PATH: &str = "text_map";
+
+/// Returns the ids of the blocks changed by the events. A text event only carries the id of the
+/// text, it's mapped to its block with `text_owners`, text id -> block id.
+pub(crate) fn touched_block_ids(
+  events: &[BlockEvent],
+  text_owners: &HashMap<String, String>,
+) -> HashSet<String> {
+  let mut block_ids = HashSet::new();
+  for payload in events.iter().flat_map(|event| event.iter()) {
+    match payload
+      .path
+      .iter()
+      .map(|s| s.as_str())
+      .collect::<Vec<_>>()
+      .as_slice()
+    {
+      [BLOCKS_PATH] => {
+        block_ids.insert(payload.id.clone());
+      },
+      [BLOCKS_PATH, block_id, ..] => {
+        block_ids.insert(block_id.to_string());
+      },
+      [_, TEXT_MAP_PATH] => {
+        block_ids.extend(text_owners.get(&payload.id).cloned());
+      },
+      [_, TEXT_MAP_PATH, text_id, ..] => {
+        block_ids.extend(text_owners.get(*text_id).cloned());
+      },
+      _ => {},
+    }
+  }
+  block_ids
+}
+
 /// parse block change event to BlockEvent
 pub fn parse_event(_object_id: &str, txn: &TransactionMut, event: &Event) -> BlockEvent {
   let path = event
@@ -0,0 +1,570 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+use collab::preclude::updates::decoder::Decode;
+use collab::preclude::updates::encoder::Encode;
+use collab::preclude::{
+  Any, Array, ArrayPrelim, ArrayRef, EntryChange, Event, Map, MapExt, MapPrelim, MapRef,
+  PathSegment, ReadTxn, Root, StickyIndex, TransactionMut, YrsValue,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::document_data::{generate_id, timestamp};
+use crate::error::DocumentError;
+
+/// The name of the root-level map that stores the comment threads of a document.
+///
+/// The threads live next to the `data` section of the collab instead of inside the document
+/// root. Root-level types can't be lost when two clients create them concurrently, and changes to
+/// them are neither tracked by the undo manager nor reported as block events.
+pub const DOCUMENT_COMMENTS: &str = "comments";
+
+const ID: &str = "id";
+const BLOCK_ID: &str = "block_id";
+const START: &str = "start";
+const END: &str = "end";
+const QUOTE: &str = "quote";
+const CREATED_BY: &str = "created_by";
+const CREATED_AT: &str = "created_at";
+const UPDATED_AT: &str = "updated_at";
+const RESOLVED: &str = "resolved";
+const RESOLVED_BY: &str = "resolved_by";
+const RESOLVED_AT: &str = "resolved_at";
+const COMMENTS: &str = "comments";
+const UID: &str = "uid";
+const CONTENT: &str = "content";
+const MENTIONS: &str = "mentions";
+const REACTIONS: &str = "reactions";
+
+/// Stores the comment threads of a document.
+///
+/// comments {
+///   thread_id: {
+///     id, block_id, start, end, quote, created_by, created_at, resolved, resolved_by, resolved_at,
+///     comments: [
+///       {
+///         id, uid, content, mentions: [uid], created_at, updated_at,
+///         reactions: { "uid:emoji": timestamp }
+///       }
+///     ]
+///   }
+/// }
+///
+/// `start` and `end` are the encoded [StickyIndex] of the commented range in the text of the
+/// block, so the range follows the text when it's edited. A reaction is stored as a flat
+/// `uid:emoji` key, so two users reacting with the same emoji at the same time don't overwrite
+/// each other.
+pub struct CommentOperation {
+  root: Root<MapRef>,
+}
+
+impl Default for CommentOperation {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl CommentOperation {
+  pub fn new() -> Self {
+    Self {
+      root: Root::new(DOCUMENT_COMMENTS),
+    }
+  }
+
+  /// Returns the map that stores the threads, creating it if it doesn't exist yet.
+  pub fn get_or_create_container(&self, txn: &mut TransactionMut) -> MapRef {
+    self.root.get_or_create(txn)
+  }
+
+  fn get_thread_map<T: ReadTxn>(&self, txn: &T, thread_id: &str) -> Option<MapRef> {
+    self.root.get(txn)?.get_with_txn(txn, thread_id)
+  }
+
+  fn get_comment_map<T: ReadTxn>(
+    &self,
+    txn: &T,
+    thread_id: &str,
+    comment_id: &str,
+  ) -> Result<MapRef, DocumentError> {
+    let thread_map = self
+      .get_thread_map(txn, thread_id)
+      .ok_or(DocumentError::CommentThreadNotFound)?;
+    let comments: ArrayRef = thread_map
+      .get_with_txn(txn, COMMENTS)
+      .ok_or(DocumentError::CommentNotFound)?;
+    comments
+      .iter(txn)
+      .filter_map(|value| value.cast::<MapRef>().ok())
+      .find(|map| map.get_id(txn).as_deref() == Some(comment_id))
+      .ok_or(DocumentError::CommentNotFound)
+  }
+
+  /// Creates a thread anchored to the range between `start` and `end`, with `comment` as its first
+  /// comment.
+  pub fn create_thread_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    anchor: CommentAnchor,
+    quote: String,
+    comment: NewComment,
+  ) -> CommentThread {
+    let thread_id = generate_id();
+    let created_at = timestamp();
+    let container = self.get_or_create_container(txn);
+    let thread_map: MapRef = container.insert(txn, thread_id.as_str(), MapPrelim::default());
+    thread_map.insert(txn, ID, thread_id.as_str());
+    thread_map.insert(txn, BLOCK_ID, anchor.block_id.as_str());
+    thread_map.insert(txn, START, Any::from(anchor.start.encode_v1()));
+    thread_map.insert(txn, END, Any::from(anchor.end.encode_v1()));
+    thread_map.insert(txn, QUOTE, quote.as_str());
+    thread_map.insert(txn, CREATED_BY, comment.uid);
+    thread_map.insert(txn, CREATED_AT, created_at);
+    thread_map.insert(txn, RESOLVED, false);
+    let comments = thread_map.insert(txn, COMMENTS, ArrayPrelim::default());
+    let comment = insert_comment(txn, &comments, comment, created_at);
+
+    CommentThread {
+      id: thread_id,
+      anchor,
+      quote,
+      created_by: comment.uid,
+      created_at,
+      resolved: false,
+      resolved_by: None,
+      resolved_at: None,
+      comments: vec![comment],
+    }
+  }
+
+  pub fn reply_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    thread_id: &str,
+    comment: NewComment,
+  ) -> Result<Comment, DocumentError> {
+    let thread_map = self
+      .get_thread_map(txn, thread_id)
+      .ok_or(DocumentError::CommentThreadNotFound)?;
+    let comments = thread_map.get_or_init_array(txn, COMMENTS);
+    Ok(insert_comment(txn, &comments, comment, timestamp()))
+  }
+
+  /// Replaces the content and the mentions of the comment.
+  pub fn update_comment_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    thread_id: &str,
+    comment_id: &str,
+    content: String,
+    mentions: Vec<i64>,
+  ) -> Result<(), DocumentError> {
+    let comment_map = self.get_comment_map(txn, thread_id, comment_id)?;
+    comment_map.insert(txn, CONTENT, content);
+    comment_map.insert(txn, MENTIONS, mentions_to_any(mentions));
+    comment_map.insert(txn, UPDATED_AT, timestamp());
+    Ok(())
+  }
+
+  /// Deletes the comment. The thread is deleted along with its last comment.
+  pub fn delete_comment_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    thread_id: &str,
+    comment_id: &str,
+  ) -> Result<(), DocumentError> {
+    let thread_map = self
+      .get_thread_map(txn, thread_id)
+      .ok_or(DocumentError::CommentThreadNotFound)?;
+    let comments: ArrayRef = thread_map
+      .get_with_txn(txn, COMMENTS)
+      .ok_or(DocumentError::CommentNotFound)?;
+    let index = comments
+      .iter(txn)
+      .position(|value| {
+        value
+          .cast::<MapRef>()
+          .ok()
+          .and_then(|map| map.get_id(txn))
+          .as_deref()
+          == Some(comment_id)
+      })
+      .ok_or(DocumentError::CommentNotFound)?;
+    comments.remove(txn, index as u32);
+    if comments.len(txn) == 0 {
+      self.delete_thread_with_txn(txn, thread_id)?;
+    }
+    Ok(())
+  }
+
+  pub fn delete_thread_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    thread_id: &str,
+  ) -> Result<(), DocumentError> {
+    let container = self
+      .root
+      .get(txn)
+      .ok_or(DocumentError::CommentThreadNotFound)?;
+    container
+      .remove(txn, thread_id)
+      .map(|_| ())
+      .ok_or(DocumentError::CommentThreadNotFound)
+  }
+
+  pub fn resolve_thread_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    thread_id: &str,
+    uid: i64,
+  ) -> Result<(), DocumentError> {
+    let thread_map = self
+      .get_thread_map(txn, thread_id)
+      .ok_or(DocumentError::CommentThreadNotFound)?;
+    thread_map.insert(txn, RESOLVED, true);
+    thread_map.insert(txn, RESOLVED_BY, uid);
+    thread_map.insert(txn, RESOLVED_AT, timestamp());
+    Ok(())
+  }
+
+  pub fn reopen_thread_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    thread_id: &str,
+  ) -> Result<(), DocumentError> {
+    let thread_map = self
+      .get_thread_map(txn, thread_id)
+      .ok_or(DocumentError::CommentThreadNotFound)?;
+    thread_map.insert(txn, RESOLVED, false);
+    thread_map.remove(txn, RESOLVED_BY);
+    thread_map.remove(txn, RESOLVED_AT);
+    Ok(())
+  }
+
+  pub fn add_reaction_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    thread_id: &str,
+    comment_id: &str,
+    uid: i64,
+    emoji: &str,
+  ) -> Result<(), DocumentError> {
+    let comment_map = self.get_comment_map(txn, thread_id, comment_id)?;
+    let reactions = comment_map.get_or_init_map(txn, REACTIONS);
+    reactions.insert(txn, reaction_key(uid, emoji), timestamp());
+    Ok(())
+  }
+
+  pub fn remove_reaction_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    thread_id: &str,
+    comment_id: &str,
+    uid: i64,
+    emoji: &str,
+  ) -> Result<(), DocumentError> {
+    let comment_map = self.get_comment_map(txn, thread_id, comment_id)?;
+    if let Some(reactions) = comment_map.get_with_txn::<_, MapRef>(txn, REACTIONS) {
+      reactions.remove(txn, &reaction_key(uid, emoji));
+    }
+    Ok(())
+  }
+
+  pub fn get_thread_with_txn<T: ReadTxn>(&self, txn: &T, thread_id: &str) -> Option<CommentThread> {
+    let thread_map = self.get_thread_map(txn, thread_id)?;
+    thread_from_map(txn, &thread_map)
+  }
+
+  /// Returns all the threads, ordered by creation time.
+  pub fn get_all_threads_with_txn<T: ReadTxn>(&self, txn: &T) -> Vec<CommentThread> {
+    let Some(container) = self.root.get(txn) else {
+      return vec![];
+    };
+    let mut threads = container
+      .iter(txn)
+      .filter_map(|(_, value)| thread_from_map(txn, &value.cast::<MapRef>().ok()?))
+      .collect::<Vec<_>>();
+    threads.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+    threads
+  }
+}
+
+fn insert_comment(
+  txn: &mut TransactionMut,
+  comments: &ArrayRef,
+  comment: NewComment,
+  created_at: i64,
+) -> Comment {
+  let comment_id = generate_id();
+  let map: MapRef = comments.push_back(txn, MapPrelim::default());
+  map.insert(txn, ID, comment_id.as_str());
+  map.insert(txn, UID, comment.uid);
+  map.insert(txn, CONTENT, comment.content.as_str());
+  map.insert(txn, MENTIONS, mentions_to_any(comment.mentions.clone()));
+  map.insert(txn, CREATED_AT, created_at);
+  map.insert(txn, REACTIONS, MapPrelim::default());
+  Comment {
+    id: comment_id,
+    uid: comment.uid,
+    content: comment.content,
+    mentions: comment.mentions,
+    reactions: vec![],
+    created_at,
+    updated_at: None,
+  }
+}
+
+fn thread_from_map<T: ReadTxn>(txn: &T, map: &MapRef) -> Option<CommentThread> {
+  let id: String = map.get_with_txn(txn, ID)?;
+  let anchor = CommentAnchor {
+    block_id: map.get_with_txn(txn, BLOCK_ID)?,
+    start: sticky_index_from_map(txn, map, START)?,
+    end: sticky_index_from_map(txn, map, END)?,
+  };
+  let comments = map
+    .get_with_txn::<_, ArrayRef>(txn, COMMENTS)
+    .map(|comments| {
+      comments
+        .iter(txn)
+        .filter_map(|value| comment_from_map(txn, &value.cast::<MapRef>().ok()?))
+        .collect::<Vec<_>>()
+    })
+    .unwrap_or_default();
+  let resolved = map.get_with_txn(txn, RESOLVED).unwrap_or(false);
+  Some(CommentThread {
+    id,
+    anchor,
+    quote: map.get_with_txn(txn, QUOTE).unwrap_or_default(),
+    created_by: map.get_with_txn(txn, CREATED_BY).unwrap_or_default(),
+    created_at: map.get_with_txn(txn, CREATED_AT).unwrap_or_default(),
+    resolved,
+    resolved_by: map.get_with_txn(txn, RESOLVED_BY),
+    resolved_at: map.get_with_txn(txn, RESOLVED_AT),
+    comments,
+  })
+}
+
+fn comment_from_map<T: ReadTxn>(txn: &T, map: &MapRef) -> Option<Comment> {
+  let id: String = map.get_with_txn(txn, ID)?;
+  let mentions = match map.get(txn, MENTIONS) {
+    Some(YrsValue::Any(Any::Array(values))) => values
+      .iter()
+      .filter_map(|value| match value {
+        Any::BigInt(uid) => Some(*uid),
+        Any::Number(uid) => Some(*uid as i64),
+        _ => None,
+      })
+      .collect(),
+    _ => vec![],
+  };
+
+  // Group the `uid:emoji` keys by emoji, ordered by the time of the first reaction.
+  let mut reactions = BTreeMap::<String, (i64, Vec<(i64, i64)>)>::new();
+  if let Some(reaction_map) = map.get_with_txn::<_, MapRef>(txn, REACTIONS) {
+    for (key, value) in reaction_map.iter(txn) {
+      if let Some((uid, emoji)) = parse_reaction_key(key) {
+        let reacted_at = value.cast::<i64>().unwrap_or_default();
+        let entry = reactions
+          .entry(emoji.to_string())
+          .or_insert((reacted_at, vec![]));
+        entry.0 = entry.0.min(reacted_at);
+        entry.1.push((reacted_at, uid));
+      }
+    }
+  }
+  let mut reactions = reactions
+    .into_iter()
+    .map(|(emoji, (reacted_at, mut uids))| {
+      uids.sort();
+      (
+        reacted_at,
+        CommentReaction {
+          emoji,
+          uids: uids.into_iter().map(|(_, uid)| uid).collect(),
+        },
+      )
+    })
+    .collect::<Vec<_>>();
+  reactions.sort_by_key(|(reacted_at, _)| *reacted_at);
+
+  Some(Comment {
+    id,
+    uid: map.get_with_txn(txn, UID).unwrap_or_default(),
+    content: map.get_with_txn(txn, CONTENT).unwrap_or_default(),
+    mentions,
+    reactions: reactions
+      .into_iter()
+      .map(|(_, reaction)| reaction)
+      .collect(),
+    created_at: map.get_with_txn(txn, CREATED_AT).unwrap_or_default(),
+    updated_at: map.get_with_txn(txn, UPDATED_AT),
+  })
+}
+
+fn sticky_index_from_map<T: ReadTxn>(txn: &T, map: &MapRef, key: &str) -> Option<StickyIndex> {
+  match map.get(txn, key)? {
+    YrsValue::Any(Any::Buffer(bytes)) => StickyIndex::decode_v1(&bytes).ok(),
+    _ => None,
+  }
+}
+
+fn mentions_to_any(mentions: Vec<i64>) -> Any {
+  Any::Array(Arc::from(
+    mentions.into_iter().map(Any::BigInt).collect::<Vec<_>>(),
+  ))
+}
+
+fn reaction_key(uid: i64, emoji: &str) -> String {
+  format!("{}:{}", uid, emoji)
+}
+
+/// The uid is a number, so the first colon always separates it from the emoji.
+fn parse_reaction_key(key: &str) -> Option<(i64, &str)> {
+  let (uid, emoji) = key.split_once(':')?;
+  Some((uid.parse().ok()?, emoji))
+}
+
+/// Converts the events of the comments map into the list of threads that changed, in the order
+/// they were first changed.
+pub fn parse_comment_events(txn: &TransactionMut, events: &[&Event]) -> Vec<CommentThreadChange> {
+  let mut seen = HashSet::new();
+  let mut changes = vec![];
+  let mut push = |thread_id: String, kind: CommentThreadChangeKind| {
+    if seen.insert(thread_id.clone()) {
+      changes.push(CommentThreadChange { thread_id, kind });
+    }
+  };
+
+  for event in events {
+    match event.path().front() {
+      Some(PathSegment::Key(thread_id)) => {
+        push(thread_id.to_string(), CommentThreadChangeKind::Updated);
+      },
+      Some(PathSegment::Index(_)) => {},
+      None => {
+        if let Event::Map(event) = event {
+          for (thread_id, change) in event.keys(txn) {
+            let kind = match change {
+              EntryChange::Inserted(_) => CommentThreadChangeKind::Created,
+              EntryChange::Updated(_, _) => CommentThreadChangeKind::Updated,
+              EntryChange::Removed(_) => CommentThreadChangeKind::Deleted,
+            };
+            push(thread_id.to_string(), kind);
+          }
+        }
+      },
+    }
+  }
+  changes
+}
+
+/// The content of a new comment or reply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewComment {
+  pub uid: i64,
+  pub content: String,
+  /// The users mentioned in the comment.
+  pub mentions: Vec<i64>,
+}
+
+impl NewComment {
+  pub fn new<T: ToString>(uid: i64, content: T) -> Self {
+    Self {
+      uid,
+      content: content.to_string(),
+      mentions: vec![],
+    }
+  }
+
+  pub fn with_mentions(mut self, mentions: Vec<i64>) -> Self {
+    self.mentions = mentions;
+    self
+  }
+}
+
+/// The commented range in the text of a block.
+///
+/// `start` sticks to the first commented character and `end` to the last one, so the text typed
+/// right before or right after the range is not part of the comment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommentAnchor {
+  pub block_id: String,
+  pub start: StickyIndex,
+  pub end: StickyIndex,
+}
+
+/// The commented range resolved against the current state of the document. The offsets are
+/// counted in UTF-16 code units.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommentRange {
+  pub block_id: String,
+  pub start: u32,
+  pub end: u32,
+}
+
+impl CommentRange {
+  /// The commented text was deleted.
+  pub fn is_collapsed(&self) -> bool {
+    self.start >= self.end
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommentThread {
+  pub id: String,
+  pub anchor: CommentAnchor,
+  /// The commented text at the time the thread was created.
+  pub quote: String,
+  pub created_by: i64,
+  pub created_at: i64,
+  pub resolved: bool,
+  pub resolved_by: Option<i64>,
+  pub resolved_at: Option<i64>,
+  /// The first comment starts the thread, the others are the replies, in the order they were
+  /// added.
+  pub comments: Vec<Comment>,
+}
+
+impl CommentThread {
+  pub fn block_id(&self) -> &str {
+    &self.anchor.block_id
+  }
+
+  /// Whether the user is mentioned in any comment of the thread.
+  pub fn mentions(&self, uid: i64) -> bool {
+    self
+      .comments
+      .iter()
+      .any(|comment| comment.mentions.contains(&uid))
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Comment {
+  pub id: String,
+  pub uid: i64,
+  pub content: String,
+  pub mentions: Vec<i64>,
+  pub reactions: Vec<CommentReaction>,
+  pub created_at: i64,
+  pub updated_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommentReaction {
+  pub emoji: String,
+  /// The users who reacted, in the order they reacted.
+  pub uids: Vec<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentThreadChange {
+  pub thread_id: String,
+  pub kind: CommentThreadChangeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentThreadChangeKind {
+  Created,
+  Updated,
+  Deleted,
+}
@@ -1,28 +1,53 @@
 use collab::core::collab::CollabOptions;
 use collab::core::collab::DataSource;
+use collab::core::collab::UndoOptions;
 use collab::core::origin::CollabOrigin;
 use collab::entity::EncodedCollab;
 use collab::preclude::block::ClientID;
+use collab::preclude::branch::{Branch, BranchPtr};
 use collab::preclude::*;
 use collab_entity::CollabType;
 use collab_entity::define::DOCUMENT_ROOT;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::borrow::{Borrow, BorrowMut};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
 use std::vec;
 
 use crate::block_parser::DocumentParser;
 use crate::block_parser::OutputFormat;
+use crate::block_parser::{MAX_HEADING_LEVEL, OutlineItem, ParseContext, build_outline};
 use crate::blocks::BlockType;
 use crate::blocks::{
-  Block, BlockAction, BlockActionPayload, BlockActionType, BlockEvent, BlockOperation,
+  Block, BlockAction, BlockActionPayload, BlockActionType, BlockDataSchema, BlockEvent,
+  BlockOperation, BlockSchemaRegistry, BlockValidationIssue, BlockValidationReport,
   ChildrenOperation, DocumentData, DocumentMeta, EXTERNAL_TYPE_TEXT, TextDelta, TextOperation,
   deserialize_text_delta, parse_event,
 };
-use crate::document_awareness::DocumentAwarenessState;
+use crate::comment::{
+  Comment, CommentAnchor, CommentOperation, CommentRange, CommentThread, CommentThreadChange,
+  NewComment, parse_comment_events,
+};
+use crate::document_awareness::{
+  BlockTextPosition, DocumentAwarenessState, DocumentAwarenessStickyPosition,
+  DocumentAwarenessStickySelection, ResolvedAwarenessSelection,
+};
+use crate::document_data::generate_id;
+use crate::document_merge::{MergeConflict, merge_document_data};
+use crate::document_stats::{DocumentStats, StatsTracker};
+use crate::document_undo::{
+  BlockSubtree, UndoScope, UndoScopeOptions, UndoSelection, UndoStackMeta,
+};
 use crate::error::DocumentError;
+use crate::link_graph::{DocumentLink, DocumentLinksChange, LinkTracker, extract_document_links};
+use crate::suggestion::{
+  BLOCK_SUGGESTION_KEY, BlockSuggestion, BlockSuggestionType, DocumentRenderOptions, Suggestion,
+  SuggestionKind, SuggestionMark, apply_suggested_delta, render_suggestions,
+  resolve_text_suggestions, text_suggestions,
+};
+use crate::template::{DocumentTemplate, TemplateVariables};
 
 /// The page_id is a reference that points to the block's id.
 /// The block that is referenced by this page_id is the first block of the document.
@@ -42,6 +67,12 @@ const TEXT_MAP: &str = "text_map";
 pub struct Document {
   collab: Collab,
   body: DocumentBody,
+  /// The author of the suggestions when the document is in suggestion mode.
+  suggestion_author: Option<i64>,
+  /// The undo scopes added with [Document::add_undo_scope], by name.
+  undo_scopes: HashMap<String, UndoScope>,
+  /// The origin of the changes made within [Document::with_origin].
+  actor_origin: Option<CollabOrigin>,
 }
 
 impl Document {
@@ -50,7 +81,13 @@ impl Document {
   pub fn open(mut collab: Collab) -> Result<Self, DocumentError> {
     CollabType::Document.validate_require_data(&collab)?;
     let body = DocumentBody::new(&mut collab, None)?;
-    Ok(Self { collab, body })
+    Ok(Self {
+      collab,
+      body,
+      suggestion_author: None,
+      undo_scopes: HashMap::new(),
+      actor_origin: None,
+    })
   }
 
   /// Opening a document with given [DataSource]
@@ -69,7 +106,13 @@ impl Document {
 
   pub fn create_with_data(mut collab: Collab, data: DocumentData) -> Result<Self, DocumentError> {
     let body = DocumentBody::new(&mut collab, Some(data))?;
-    Ok(Self { collab, body })
+    Ok(Self {
+      collab,
+      body,
+      suggestion_author: None,
+      undo_scopes: HashMap::new(),
+      actor_origin: None,
+    })
   }
 
   pub fn create(
@@ -82,6 +125,18 @@ impl Document {
     Self::create_with_data(collab, data)
   }
 
+  /// Creates a document from the template, its placeholders are substituted with the variables.
+  /// Returns [DocumentError::InvalidTemplate] if a required variable is missing.
+  pub fn create_from_template(
+    document_id: &str,
+    template: &DocumentTemplate,
+    variables: &TemplateVariables,
+    client_id: ClientID,
+  ) -> Result<Self, DocumentError> {
+    let data = template.instantiate(document_id, variables)?;
+    Self::create(document_id, data, client_id)
+  }
+
   #[inline]
   pub fn split(self) -> (Collab, DocumentBody) {
     (self.collab, self.body)
@@ -121,12 +176,101 @@ impl Document {
     });
   }
 
+  /// Subscribes to the changes of the outgoing links of the document, see
+  /// [crate::link_graph::LinkIndex]. The callback is only called when the links of a block
+  /// changed, not on every edit.
+  pub fn subscribe_link_changed<K, F>(&mut self, key: K, callback: F)
+  where
+    K: Into<Origin>,
+    F: Fn(&DocumentLinksChange) + Send + Sync + 'static,
+  {
+    let object_id = self.object_id().to_string();
+    let tracker = {
+      let txn = self.collab.transact();
+      LinkTracker::new(
+        &txn,
+        self.body.block_operation.clone(),
+        self.body.text_operation.clone(),
+      )
+    };
+    let tracker = Mutex::new(tracker);
+    self.body.root.observe_deep_with(key, move |txn, events| {
+      let block_events = events
+        .iter()
+        .map(|deep_event| parse_event(&object_id, txn, deep_event))
+        .collect::<Vec<BlockEvent>>();
+      let blocks = match tracker.lock() {
+        Ok(mut tracker) => tracker.update(txn, &block_events),
+        Err(_) => return,
+      };
+      if !blocks.is_empty() {
+        callback(&DocumentLinksChange {
+          document_id: object_id.clone(),
+          blocks,
+        });
+      }
+    });
+  }
+
+  /// Returns the word, character, block, image, file and todo counts of the document.
+  pub fn stats(&self) -> Result<DocumentStats, DocumentError> {
+    let data = self.get_document_data()?;
+    Ok(DocumentStats::from_document_data(&data))
+  }
+
+  /// Subscribes to the changes of the [DocumentStats]. The stats are updated from the block events,
+  /// only the changed blocks are read again. The callback is called when the stats changed.
+  pub fn subscribe_stats_changed<K, F>(&mut self, key: K, callback: F)
+  where
+    K: Into<Origin>,
+    F: Fn(&DocumentStats) + Send + Sync + 'static,
+  {
+    let object_id = self.object_id().to_string();
+    let tracker = {
+      let txn = self.collab.transact();
+      StatsTracker::new(
+        &txn,
+        self.body.block_operation.clone(),
+        self.body.text_operation.clone(),
+      )
+    };
+    let tracker = Mutex::new(tracker);
+    self.body.root.observe_deep_with(key, move |txn, events| {
+      let block_events = events
+        .iter()
+        .map(|deep_event| parse_event(&object_id, txn, deep_event))
+        .collect::<Vec<BlockEvent>>();
+      let stats = match tracker.lock() {
+        Ok(mut tracker) => tracker.update(txn, &block_events),
+        Err(_) => return,
+      };
+      if let Some(stats) = stats {
+        callback(&stats);
+      }
+    });
+  }
+
+  /// Returns the outgoing links of the document: page mentions, sub pages, inline databases and
+  /// link previews.
+  pub fn get_links(&self) -> Vec<DocumentLink> {
+    let txn = self.collab.transact();
+    match self.body.get_document_data(&txn) {
+      Ok(data) => extract_document_links(&data),
+      Err(_) => vec![],
+    }
+  }
+
   /// Get document data.
   pub fn get_document_data(&self) -> Result<DocumentData, DocumentError> {
     let txn = self.collab.transact();
     self.body.get_document_data(&txn)
   }
 
+  /// Returns the document in the nested json format, see [crate::nested_json].
+  pub fn to_nested_json(&self) -> Result<Value, DocumentError> {
+    self.get_document_data()?.to_nested_json()
+  }
+
   /// Get page id
   pub fn get_page_id(&self) -> Option<String> {
     let txn = self.collab.transact();
@@ -135,38 +279,68 @@ impl Document {
 
   #[deprecated(note = "use apply_text_delta instead")]
   pub fn create_text(&mut self, text_id: &str, delta: String) {
-    self.apply_text_delta(text_id, delta);
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+    let delta = deserialize_text_delta(&delta).ok().unwrap_or_default();
+    self
+      .body
+      .text_operation
+      .apply_delta(&mut txn, text_id, delta);
   }
 
   /// Create a yText for incremental synchronization.
   /// Apply a delta to the yText.
   /// - @param text_id: The text block's external_id.
   /// - @param delta: The text block's delta. "\[{"insert": "Hello", "attributes": { "bold": true, "italic": true } }, {"insert": " World!"}]".
+  ///
+  /// In suggestion mode, the inserts and deletes are recorded as suggestions, see
+  /// [Document::enable_suggestion_mode].
   pub fn apply_text_delta(&mut self, text_id: &str, delta: String) {
-    let mut txn = self.collab.transact_mut();
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
     let delta = deserialize_text_delta(&delta).ok().unwrap_or_default();
     #[cfg(feature = "verbose_log")]
     tracing::trace!("apply_text_delta: text_id: {}, delta: {:?}", text_id, delta);
 
-    self
-      .body
-      .text_operation
-      .apply_delta(&mut txn, text_id, delta);
+    match self.suggestion_author {
+      Some(author) => self
+        .body
+        .apply_suggested_text_delta(&mut txn, text_id, delta, author),
+      None => self
+        .body
+        .text_operation
+        .apply_delta(&mut txn, text_id, delta),
+    }
   }
 
   /// Apply actions to the document.
+  ///
+  /// In suggestion mode, inserting and deleting blocks and applying text deltas are recorded as
+  /// suggestions. Updating and moving blocks are applied as is.
   pub fn apply_action(&mut self, actions: Vec<BlockAction>) -> Result<(), DocumentError> {
-    let mut txn = self.collab.transact_mut();
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
     for action in actions {
       #[cfg(feature = "verbose_log")]
       tracing::trace!("apply_action: {:?}", action);
 
-      let result = match action.action {
-        BlockActionType::Insert => self.body.handle_insert_action(&mut txn, action.payload),
-        BlockActionType::Update => self.body.handle_update_action(&mut txn, action.payload),
-        BlockActionType::Delete => self.body.handle_delete_action(&mut txn, action.payload),
-        BlockActionType::Move => self.body.handle_move_action(&mut txn, action.payload),
-        BlockActionType::InsertText | BlockActionType::ApplyTextDelta => self
+      let result = match (action.action, self.suggestion_author) {
+        (BlockActionType::Insert, Some(author)) => {
+          self
+            .body
+            .handle_suggest_insert_action(&mut txn, action.payload, author)
+        },
+        (BlockActionType::Delete, Some(author)) => {
+          self
+            .body
+            .handle_suggest_delete_action(&mut txn, action.payload, author)
+        },
+        (BlockActionType::ApplyTextDelta, Some(author)) => self
+          .body
+          .handle_suggest_text_delta_action(&mut txn, action.payload, author),
+        (BlockActionType::Insert, None) => self.body.handle_insert_action(&mut txn, action.payload),
+        (BlockActionType::Update, _) => self.body.handle_update_action(&mut txn, action.payload),
+        (BlockActionType::Delete, None) => self.body.handle_delete_action(&mut txn, action.payload),
+        (BlockActionType::Move, _) => self.body.handle_move_action(&mut txn, action.payload),
+        // Creating the text of a new block is never recorded as a suggestion
+        (BlockActionType::InsertText, _) | (BlockActionType::ApplyTextDelta, None) => self
           .body
           .handle_apply_text_delta_action(&mut txn, action.payload),
       };
@@ -209,12 +383,12 @@ impl Document {
     block: Block,
     prev_id: Option<String>,
   ) -> Result<Block, DocumentError> {
-    let mut txn = self.collab.transact_mut();
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
     self.body.insert_block(&mut txn, block, prev_id)
   }
 
   pub fn delete_block(&mut self, block_id: &str) -> Result<(), DocumentError> {
-    let mut txn = self.collab.transact_mut();
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
     self.body.delete_block(&mut txn, block_id)
   }
 
@@ -293,7 +467,7 @@ impl Document {
 
   pub fn remove_block_delta<T: AsRef<str>>(&mut self, block_id: T) {
     let block_id = block_id.as_ref();
-    let mut txn = self.collab.transact_mut();
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
     let block = self.body.block_operation.get_block_with_txn(&txn, block_id);
     if let Some(block) = block {
       if let Some(external_id) = &block.external_id {
@@ -315,7 +489,7 @@ impl Document {
     }
 
     let block_id = block_id.as_ref();
-    let mut txn = self.collab.transact_mut();
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
     let block = self.body.block_operation.get_block_with_txn(&txn, block_id);
     if let Some(block) = block {
       let external_id = block
@@ -333,7 +507,7 @@ impl Document {
   }
 
   pub fn delete_block_from_parent(&mut self, block_id: &str, parent_id: &str) {
-    let mut txn = self.collab.transact_mut();
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
     self
       .body
       .delete_block_from_parent(&mut txn, block_id, parent_id);
@@ -344,7 +518,7 @@ impl Document {
     block_id: &str,
     data: HashMap<String, Value>,
   ) -> Result<(), DocumentError> {
-    let mut txn = self.collab.transact_mut();
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
     self
       .body
       .update_block_data(&mut txn, block_id, data, None, None)
@@ -356,10 +530,135 @@ impl Document {
     parent_id: Option<String>,
     prev_id: Option<String>,
   ) -> Result<(), DocumentError> {
-    let mut txn = self.collab.transact_mut();
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
     self.body.move_block(&mut txn, block_id, parent_id, prev_id)
   }
 
+  /// Registers the data schema of the block type, replacing the existing one. The schema is
+  /// checked whenever a block of this type is inserted or its data is updated.
+  pub fn register_block_schema(&mut self, block_type: BlockType, schema: BlockDataSchema) {
+    self.body.schema_registry.register(block_type, schema);
+  }
+
+  pub fn unregister_block_schema(&mut self, block_type: &BlockType) -> Option<BlockDataSchema> {
+    self.body.schema_registry.unregister(block_type)
+  }
+
+  pub fn get_block_schema(&self, block_type: &BlockType) -> Option<&BlockDataSchema> {
+    self.body.schema_registry.get(block_type.as_str())
+  }
+
+  /// Checks the data of every block against its schema and the invariants of the block tree.
+  ///
+  /// The blocks that were written before their schema was registered, or that were synced from
+  /// another client, are not checked on write, so the report may contain issues even if every
+  /// local mutation succeeded.
+  pub fn validate_blocks(&self) -> BlockValidationReport {
+    let txn = self.collab.transact();
+    self.body.validate_blocks(&txn)
+  }
+
+  /// Copies the given blocks with their descendants and texts into a standalone [DocumentData].
+  ///
+  /// The page of the fragment is a placeholder block whose children are the given blocks, in the
+  /// given order. A block that is a descendant of another given block is only copied once, as part
+  /// of its ancestor. Every block of the fragment has an entry in the children map, so the ids can
+  /// be regenerated without looking at the source document, see [Document::insert_fragment].
+  pub fn extract_fragment<T: AsRef<str>>(
+    &self,
+    block_ids: &[T],
+  ) -> Result<DocumentData, DocumentError> {
+    let txn = self.collab.transact();
+    self.body.extract_fragment(&txn, block_ids)
+  }
+
+  /// Inserts the blocks of a fragment created by [Document::extract_fragment] as the children of
+  /// `parent_id`, after `prev_id`. The block, children and text ids are regenerated, so the same
+  /// fragment can be inserted more than once.
+  ///
+  /// The fragment is inserted in a single transaction, it's undone in one step. In suggestion mode
+  /// the top level blocks are suggested insertions. Returns the ids of the top level blocks.
+  pub fn insert_fragment(
+    &mut self,
+    fragment: DocumentData,
+    parent_id: &str,
+    prev_id: Option<String>,
+  ) -> Result<Vec<String>, DocumentError> {
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+    self.body.insert_fragment_with_txn(
+      &mut txn,
+      fragment,
+      parent_id,
+      prev_id,
+      self.suggestion_author,
+    )
+  }
+
+  /// Merges `theirs`, a version of the document edited apart from `base`, into the document. The
+  /// actions of [merge_document_data] are checked, then applied in a single transaction. Returns
+  /// the conflicts, they are resolved in favor of the document.
+  pub fn merge_document(
+    &mut self,
+    base: &DocumentData,
+    theirs: &DocumentData,
+  ) -> Result<Vec<MergeConflict>, DocumentError> {
+    let ours = self.get_document_data()?;
+    let merge = merge_document_data(base, &ours, theirs);
+    // Nothing is written if an action would fail, a transaction can't be rolled back
+    self.check_merge_actions(&ours, &merge.actions)?;
+    self.apply_action(merge.actions)?;
+    Ok(merge.conflicts)
+  }
+
+  fn check_merge_actions(
+    &self,
+    ours: &DocumentData,
+    actions: &[BlockAction],
+  ) -> Result<(), DocumentError> {
+    let mut block_ids = ours
+      .blocks
+      .keys()
+      .map(String::as_str)
+      .collect::<HashSet<_>>();
+    for action in actions {
+      let Some(block) = action.payload.block.as_ref() else {
+        continue;
+      };
+      if matches!(
+        action.action,
+        BlockActionType::Insert | BlockActionType::Move
+      ) {
+        let parent_id = action.payload.parent_id.as_deref().unwrap_or(&block.parent);
+        if !block_ids.contains(parent_id) {
+          return Err(DocumentError::ParentIsNotFound);
+        }
+      }
+      match action.action {
+        BlockActionType::Insert => {
+          self
+            .body
+            .validate_block_data(&block.id, &block.ty, &block.data)?;
+          block_ids.insert(&block.id);
+        },
+        BlockActionType::Update => {
+          if !block_ids.contains(block.id.as_str()) {
+            return Err(DocumentError::BlockIsNotFound);
+          }
+          self
+            .body
+            .validate_block_data(&block.id, &block.ty, &block.data)?;
+        },
+        BlockActionType::Move | BlockActionType::Delete => {
+          if !block_ids.contains(block.id.as_str()) {
+            return Err(DocumentError::BlockIsNotFound);
+          }
+        },
+        _ => {},
+      }
+    }
+    Ok(())
+  }
+
   pub fn redo(&mut self) -> bool {
     self.collab.redo().unwrap_or(false)
   }
@@ -368,6 +667,132 @@ impl Document {
     self.collab.undo().unwrap_or(false)
   }
 
+  /// Replaces the options of the default undo manager used by [Document::undo] and
+  /// [Document::redo]. Its undo and redo stacks are cleared.
+  pub fn set_undo_options(&mut self, options: UndoOptions) {
+    self.collab.enable_undo_redo_with_options(options);
+  }
+
+  /// Makes the changes of `f` with the given origin instead of the origin of the document, for
+  /// example on behalf of another local actor. The changes are only undone by the undo scopes
+  /// that track this origin, see [UndoScopeOptions::tracked_origins]. The plugins receive them
+  /// as local updates.
+  pub fn with_origin<F, T>(&mut self, origin: CollabOrigin, f: F) -> T
+  where
+    F: FnOnce(&mut Document) -> T,
+  {
+    self.collab.context.add_local_origin(origin.clone());
+    let previous = self.actor_origin.replace(origin);
+    let mut guard = OriginGuard {
+      document: self,
+      previous,
+    };
+    f(&mut guard)
+  }
+
+  /// Adds an undo scope with its own undo and redo stacks, replacing the scope with the same name.
+  ///
+  /// A scope only captures the changes made with its tracked origins, and when
+  /// [UndoScopeOptions::block_id] is set, the changes of that block and its descendants. Returns
+  /// [DocumentError::BlockIsNotFound] if the block doesn't exist.
+  pub fn add_undo_scope(
+    &mut self,
+    name: &str,
+    options: UndoScopeOptions,
+  ) -> Result<(), DocumentError> {
+    let subtree = match &options.block_id {
+      Some(block_id) => {
+        let txn = self.collab.transact();
+        if self
+          .body
+          .block_operation
+          .get_block_with_txn(&txn, block_id)
+          .is_none()
+        {
+          return Err(DocumentError::BlockIsNotFound);
+        }
+        // the changed types of a transaction include the ancestors of the changed ones.
+        let mut containers = self.body.container_branches(&txn);
+        containers.insert(BranchPtr::from(self.collab.data.as_ref()));
+        Some(BlockSubtree::new(
+          block_id.clone(),
+          self.body.block_operation.clone(),
+          self.body.children_operation.clone(),
+          self.body.text_operation.clone(),
+          containers,
+        ))
+      },
+      None => None,
+    };
+    // remove the previous scope first, so that it stops observing the document.
+    self.undo_scopes.remove(name);
+    let scope = UndoScope::new(
+      self.collab.context.doc(),
+      &self.collab.data,
+      options,
+      self.collab.origin(),
+      subtree,
+    );
+    self.undo_scopes.insert(name.to_string(), scope);
+    Ok(())
+  }
+
+  pub fn remove_undo_scope(&mut self, name: &str) -> bool {
+    self.undo_scopes.remove(name).is_some()
+  }
+
+  /// Undoes the last step of the undo scope. Returns the metadata of the step, or `None` if
+  /// there was nothing to undo.
+  pub fn undo_scope(&mut self, name: &str) -> Result<Option<UndoStackMeta>, DocumentError> {
+    Ok(self.get_undo_scope_mut(name)?.undo())
+  }
+
+  /// Redoes the last undone step of the undo scope. Returns the metadata of the step, or `None`
+  /// if there was nothing to redo.
+  pub fn redo_scope(&mut self, name: &str) -> Result<Option<UndoStackMeta>, DocumentError> {
+    Ok(self.get_undo_scope_mut(name)?.redo())
+  }
+
+  pub fn can_undo_scope(&self, name: &str) -> bool {
+    self
+      .undo_scopes
+      .get(name)
+      .map(|scope| scope.can_undo())
+      .unwrap_or(false)
+  }
+
+  pub fn can_redo_scope(&self, name: &str) -> bool {
+    self
+      .undo_scopes
+      .get(name)
+      .map(|scope| scope.can_redo())
+      .unwrap_or(false)
+  }
+
+  /// Sets the selection recorded in the next undo steps of the scope, returned by
+  /// [Document::undo_scope] and [Document::redo_scope] to restore the cursor.
+  pub fn set_undo_selection(
+    &mut self,
+    name: &str,
+    selection: Option<UndoSelection>,
+  ) -> Result<(), DocumentError> {
+    self.get_undo_scope_mut(name)?.set_selection(selection);
+    Ok(())
+  }
+
+  /// The next change of the undo scope starts a new step, even within the capture timeout.
+  pub fn stop_undo_scope_capturing(&mut self, name: &str) -> Result<(), DocumentError> {
+    self.get_undo_scope_mut(name)?.reset();
+    Ok(())
+  }
+
+  fn get_undo_scope_mut(&mut self, name: &str) -> Result<&mut UndoScope, DocumentError> {
+    self
+      .undo_scopes
+      .get_mut(name)
+      .ok_or_else(|| DocumentError::UndoScopeNotFound(name.to_string()))
+  }
+
   /// Set the local state of the awareness.
   /// It will override the previous state.
   pub fn set_awareness_local_state(&self, state: DocumentAwarenessState) {
@@ -415,6 +840,237 @@ impl Document {
     });
   }
 
+  /// Create a sticky position for the given offset in the text of the block.
+  ///
+  /// Returns None if the block doesn't have a text or the offset is out of range.
+  pub fn sticky_position(
+    &self,
+    position: &BlockTextPosition,
+  ) -> Option<DocumentAwarenessStickyPosition> {
+    let txn = self.collab.transact();
+    self.body.sticky_position(&txn, position)
+  }
+
+  /// Resolve the sticky position against the current state of the document.
+  ///
+  /// Returns None if the block or the text that the position is anchored to was deleted.
+  pub fn resolve_sticky_position(
+    &self,
+    position: &DocumentAwarenessStickyPosition,
+  ) -> Option<BlockTextPosition> {
+    let txn = self.collab.transact();
+    self.body.resolve_sticky_position(&txn, position)
+  }
+
+  pub fn sticky_selection(
+    &self,
+    start: &BlockTextPosition,
+    end: &BlockTextPosition,
+  ) -> Option<DocumentAwarenessStickySelection> {
+    let txn = self.collab.transact();
+    Some(DocumentAwarenessStickySelection {
+      start: self.body.sticky_position(&txn, start)?,
+      end: self.body.sticky_position(&txn, end)?,
+    })
+  }
+
+  /// Resolve the sticky selection of every remote user against the current state of the
+  /// document. The users without a sticky selection, or whose selection points to deleted
+  /// blocks, are skipped.
+  pub fn resolve_remote_selections(&self) -> HashMap<ClientID, ResolvedAwarenessSelection> {
+    let awareness = self.collab.get_awareness();
+    let local_client_id = awareness.client_id();
+    let txn = self.collab.transact();
+    awareness
+      .iter()
+      .filter(|(client_id, _)| *client_id != local_client_id)
+      .filter_map(|(client_id, client_state)| {
+        let state =
+          serde_json::from_str::<DocumentAwarenessState>(client_state.data.as_deref()?).ok()?;
+        let selection = state.sticky_selection?;
+        let resolved = ResolvedAwarenessSelection {
+          user: state.user,
+          metadata: state.metadata,
+          start: self.body.resolve_sticky_position(&txn, &selection.start)?,
+          end: self.body.resolve_sticky_position(&txn, &selection.end)?,
+        };
+        Some((client_id, resolved))
+      })
+      .collect()
+  }
+
+  /// Create a comment thread on the text between `start` and `end` of the block. The offsets are
+  /// counted in UTF-16 code units.
+  pub fn create_comment_thread(
+    &mut self,
+    block_id: &str,
+    start: u32,
+    end: u32,
+    comment: NewComment,
+  ) -> Result<CommentThread, DocumentError> {
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+    self
+      .body
+      .create_comment_thread_with_txn(&mut txn, block_id, start, end, comment)
+  }
+
+  pub fn reply_comment_thread(
+    &mut self,
+    thread_id: &str,
+    comment: NewComment,
+  ) -> Result<Comment, DocumentError> {
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+    self
+      .body
+      .comment_operation
+      .reply_with_txn(&mut txn, thread_id, comment)
+  }
+
+  pub fn update_comment(
+    &mut self,
+    thread_id: &str,
+    comment_id: &str,
+    content: String,
+    mentions: Vec<i64>,
+  ) -> Result<(), DocumentError> {
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+    self
+      .body
+      .comment_operation
+      .update_comment_with_txn(&mut txn, thread_id, comment_id, content, mentions)
+  }
+
+  /// Delete the comment. Deleting the last comment of a thread deletes the thread.
+  pub fn delete_comment(&mut self, thread_id: &str, comment_id: &str) -> Result<(), DocumentError> {
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+    self
+      .body
+      .comment_operation
+      .delete_comment_with_txn(&mut txn, thread_id, comment_id)
+  }
+
+  pub fn delete_comment_thread(&mut self, thread_id: &str) -> Result<(), DocumentError> {
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+    self
+      .body
+      .comment_operation
+      .delete_thread_with_txn(&mut txn, thread_id)
+  }
+
+  pub fn resolve_comment_thread(&mut self, thread_id: &str, uid: i64) -> Result<(), DocumentError> {
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+    self
+      .body
+      .comment_operation
+      .resolve_thread_with_txn(&mut txn, thread_id, uid)
+  }
+
+  pub fn reopen_comment_thread(&mut self, thread_id: &str) -> Result<(), DocumentError> {
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+    self
+      .body
+      .comment_operation
+      .reopen_thread_with_txn(&mut txn, thread_id)
+  }
+
+  pub fn add_comment_reaction(
+    &mut self,
+    thread_id: &str,
+    comment_id: &str,
+    uid: i64,
+    emoji: &str,
+  ) -> Result<(), DocumentError> {
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+    self
+      .body
+      .comment_operation
+      .add_reaction_with_txn(&mut txn, thread_id, comment_id, uid, emoji)
+  }
+
+  pub fn remove_comment_reaction(
+    &mut self,
+    thread_id: &str,
+    comment_id: &str,
+    uid: i64,
+    emoji: &str,
+  ) -> Result<(), DocumentError> {
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+    self
+      .body
+      .comment_operation
+      .remove_reaction_with_txn(&mut txn, thread_id, comment_id, uid, emoji)
+  }
+
+  pub fn get_comment_thread(&self, thread_id: &str) -> Option<CommentThread> {
+    let txn = self.collab.transact();
+    self
+      .body
+      .comment_operation
+      .get_thread_with_txn(&txn, thread_id)
+  }
+
+  /// Get all the comment threads, ordered by creation time.
+  pub fn get_comment_threads(&self) -> Vec<CommentThread> {
+    let txn = self.collab.transact();
+    self.body.comment_operation.get_all_threads_with_txn(&txn)
+  }
+
+  pub fn get_comment_threads_for_block(&self, block_id: &str) -> Vec<CommentThread> {
+    self
+      .get_comment_threads()
+      .into_iter()
+      .filter(|thread| thread.block_id() == block_id)
+      .collect()
+  }
+
+  pub fn get_unresolved_comment_threads(&self) -> Vec<CommentThread> {
+    self
+      .get_comment_threads()
+      .into_iter()
+      .filter(|thread| !thread.resolved)
+      .collect()
+  }
+
+  /// Get the comment threads in which the user is mentioned.
+  pub fn get_comment_threads_mentioning(&self, uid: i64) -> Vec<CommentThread> {
+    self
+      .get_comment_threads()
+      .into_iter()
+      .filter(|thread| thread.mentions(uid))
+      .collect()
+  }
+
+  /// Resolve the range that the thread is anchored to against the current state of the document.
+  pub fn resolve_comment_range(&self, thread: &CommentThread) -> Option<CommentRange> {
+    let txn = self.collab.transact();
+    self.body.resolve_comment_range(&txn, &thread.anchor)
+  }
+
+  /// Subscribe to the changes of the comment threads. The callback receives the threads that
+  /// were created, updated or deleted in a transaction, and whether the change is remote.
+  pub fn subscribe_comment_changed<K, F>(&mut self, key: K, callback: F)
+  where
+    K: Into<Origin>,
+    F: Fn(&[CommentThreadChange], bool) + Send + Sync + 'static,
+  {
+    let container = {
+      let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+      self
+        .body
+        .comment_operation
+        .get_or_create_container(&mut txn)
+    };
+    let self_origin = self.origin().clone();
+    container.observe_deep_with(key, move |txn, events| {
+      let origin = CollabOrigin::from(txn);
+      let events = events.iter().collect::<Vec<_>>();
+      let changes = parse_comment_events(txn, &events);
+      if !changes.is_empty() {
+        callback(&changes, self_origin != origin);
+      }
+    });
+  }
+
   /// Get the plain text of the document.
   ///
   /// This function will call the `to_plain_text` function to get the plain text of the document.
@@ -431,13 +1087,99 @@ impl Document {
     self.body.to_plain_text(txn)
   }
 
-  /// Get the markdown text of the document.
-  ///
-  /// This function will return the markdown text of the document, it will include the formatting.
-  /// For example, for the linked text, it will return the markdown text of the linked text, the link will be included.
-  pub fn to_markdown_text(&self) -> Vec<String> {
-    let txn = self.collab.transact();
-    self.body.to_markdown_text(txn)
+  /// Get the heading hierarchy of the document, see [build_outline].
+  pub fn outline(&self) -> Result<Vec<OutlineItem>, DocumentError> {
+    let data = self.get_document_data()?;
+    let parser = DocumentParser::with_default_parsers();
+    let context = ParseContext::new(&data, &parser, OutputFormat::PlainText);
+    Ok(build_outline(&context, MAX_HEADING_LEVEL))
+  }
+
+  /// Get the markdown text of the document.
+  ///
+  /// This function will return the markdown text of the document, it will include the formatting.
+  /// For example, for the linked text, it will return the markdown text of the linked text, the link will be included.
+  pub fn to_markdown_text(&self) -> Vec<String> {
+    let txn = self.collab.transact();
+    self.body.to_markdown_text(txn)
+  }
+
+  /// Get the plain text of the document, rendering the pending suggestions according to the
+  /// options.
+  pub fn to_plain_text_with_options(&self, options: &DocumentRenderOptions) -> Vec<String> {
+    let txn = self.collab.transact();
+    self.body.to_plain_text_with_options(txn, options)
+  }
+
+  /// Get the markdown text of the document, rendering the pending suggestions according to the
+  /// options.
+  pub fn to_markdown_text_with_options(&self, options: &DocumentRenderOptions) -> Vec<String> {
+    let txn = self.collab.transact();
+    self.body.to_markdown_text_with_options(txn, options)
+  }
+
+  /// Enter suggestion mode. The following edits made through [Document::apply_text_delta] and
+  /// [Document::apply_action] are recorded as suggestions of the `author` instead of being
+  /// applied. Other users can then accept or reject them.
+  pub fn enable_suggestion_mode(&mut self, author: i64) {
+    self.suggestion_author = Some(author);
+  }
+
+  pub fn disable_suggestion_mode(&mut self) {
+    self.suggestion_author = None;
+  }
+
+  /// Returns the author of the suggestions if the document is in suggestion mode.
+  pub fn suggestion_author(&self) -> Option<i64> {
+    self.suggestion_author
+  }
+
+  /// Get the pending suggestions, ordered by time.
+  pub fn get_suggestions(&self) -> Vec<Suggestion> {
+    let txn = self.collab.transact();
+    self.body.get_suggestions(&txn)
+  }
+
+  pub fn accept_suggestion(&mut self, suggestion_id: &str) -> Result<(), DocumentError> {
+    self.resolve_suggestion(suggestion_id, true)
+  }
+
+  pub fn reject_suggestion(&mut self, suggestion_id: &str) -> Result<(), DocumentError> {
+    self.resolve_suggestion(suggestion_id, false)
+  }
+
+  fn resolve_suggestion(&mut self, suggestion_id: &str, accept: bool) -> Result<(), DocumentError> {
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+    let resolved =
+      self
+        .body
+        .resolve_suggestions(&mut txn, &|mark| mark.id == suggestion_id, accept);
+    if resolved == 0 {
+      return Err(DocumentError::SuggestionNotFound);
+    }
+    Ok(())
+  }
+
+  /// Accept the pending suggestions. When `author` is set, only the suggestions of this author are
+  /// accepted. Returns the number of accepted suggestions.
+  pub fn accept_all_suggestions(&mut self, author: Option<i64>) -> usize {
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+    self.body.resolve_suggestions(
+      &mut txn,
+      &|mark| author.is_none_or(|author| mark.author == author),
+      true,
+    )
+  }
+
+  /// Reject the pending suggestions. When `author` is set, only the suggestions of this author are
+  /// rejected. Returns the number of rejected suggestions.
+  pub fn reject_all_suggestions(&mut self, author: Option<i64>) -> usize {
+    let mut txn = transact_mut(&mut self.collab, self.actor_origin.as_ref());
+    self.body.resolve_suggestions(
+      &mut txn,
+      &|mark| author.is_none_or(|author| mark.author == author),
+      false,
+    )
   }
 }
 
@@ -484,6 +1226,11 @@ pub struct DocumentBody {
   pub children_operation: ChildrenOperation,
   pub block_operation: BlockOperation,
   pub text_operation: TextOperation,
+  pub comment_operation: CommentOperation,
+  pub schema_registry: BlockSchemaRegistry,
+  /// The ids of the blocks that own the texts, keyed by text id. It's only a cache: an entry is
+  /// checked against the block before it's used and the cache is rebuilt when it's stale.
+  text_owners: Mutex<HashMap<String, String>>,
 }
 
 impl DocumentBody {
@@ -511,6 +1258,8 @@ impl DocumentBody {
     let text_operation = TextOperation::new(text_map);
     let block_operation = BlockOperation::new(blocks, children_operation.clone());
 
+    let schema_registry = BlockSchemaRegistry::default();
+
     // If the data is not None, insert the data to the document.
     if let Some(data) = data {
       Self::write_from_document_data(
@@ -520,6 +1269,7 @@ impl DocumentBody {
         &children_operation,
         &text_operation,
         &block_operation,
+        &schema_registry,
       )?;
     }
     drop(txn);
@@ -529,6 +1279,9 @@ impl DocumentBody {
       block_operation,
       children_operation,
       text_operation,
+      comment_operation: CommentOperation::new(),
+      schema_registry,
+      text_owners: Mutex::new(HashMap::new()),
     })
   }
 
@@ -539,7 +1292,11 @@ impl DocumentBody {
     children_operation: &ChildrenOperation,
     text_operation: &TextOperation,
     block_operation: &BlockOperation,
+    schema_registry: &BlockSchemaRegistry,
   ) -> Result<(), DocumentError> {
+    for block in data.blocks.values() {
+      validate_block_data(schema_registry, &block.id, &block.ty, &block.data)?;
+    }
     root.insert(txn, PAGE_ID, data.page_id);
 
     for (_, block) in data.blocks {
@@ -561,6 +1318,24 @@ impl DocumentBody {
     Ok(())
   }
 
+  /// The maps that hold the blocks, the children and the texts of the document.
+  pub(crate) fn container_branches<T: ReadTxn>(&self, txn: &T) -> HashSet<BranchPtr> {
+    let mut branches = HashSet::from([BranchPtr::from(self.root.as_ref())]);
+    for key in [BLOCKS, META] {
+      if let Some(map) = self.root.get_with_txn::<T, MapRef>(txn, key) {
+        branches.insert(BranchPtr::from(map.as_ref()));
+        if key == META {
+          for key in [CHILDREN_MAP, TEXT_MAP] {
+            if let Some(map) = map.get_with_txn::<T, MapRef>(txn, key) {
+              branches.insert(BranchPtr::from(map.as_ref()));
+            }
+          }
+        }
+      }
+    }
+    branches
+  }
+
   /// Creates a [Document] body from the given [Collab] instance. If the required fields are not
   /// present, it will return `None`.
   pub fn from_collab(collab: &Collab) -> Option<Self> {
@@ -585,6 +1360,9 @@ impl DocumentBody {
       block_operation,
       children_operation,
       text_operation,
+      comment_operation: CommentOperation::new(),
+      schema_registry: BlockSchemaRegistry::default(),
+      text_owners: Mutex::new(HashMap::new()),
     })
   }
 
@@ -613,18 +1391,133 @@ impl DocumentBody {
         &self.children_operation,
         &self.text_operation,
         &self.block_operation,
+        &self.schema_registry,
       )
     } else {
       Ok(())
     }
   }
 
+  fn get_block_text_ref<T: ReadTxn>(&self, txn: &T, block_id: &str) -> Option<TextRef> {
+    let block = self.block_operation.get_block_with_txn(txn, block_id)?;
+    let text_id = block.external_id?;
+    self.text_operation.get_text_ref_with_txn(txn, &text_id)
+  }
+
+  pub fn sticky_position<T: ReadTxn>(
+    &self,
+    txn: &T,
+    position: &BlockTextPosition,
+  ) -> Option<DocumentAwarenessStickyPosition> {
+    let text_ref = self.get_block_text_ref(txn, &position.block_id)?;
+    let branch = text_branch(&text_ref);
+    // Stick to the character after the cursor. There is no character after the end of the text,
+    // so stick to the one before it instead.
+    let index = StickyIndex::at(txn, branch, position.offset, Assoc::After)
+      .or_else(|| StickyIndex::at(txn, branch, position.offset, Assoc::Before))?;
+    Some(DocumentAwarenessStickyPosition {
+      block_id: position.block_id.clone(),
+      index,
+    })
+  }
+
+  pub fn resolve_sticky_position<T: ReadTxn>(
+    &self,
+    txn: &T,
+    position: &DocumentAwarenessStickyPosition,
+  ) -> Option<BlockTextPosition> {
+    let offset = self.resolve_sticky_index(txn, &position.block_id, &position.index)?;
+    Some(BlockTextPosition::new(&position.block_id, offset))
+  }
+
+  fn resolve_sticky_index<T: ReadTxn>(
+    &self,
+    txn: &T,
+    block_id: &str,
+    index: &StickyIndex,
+  ) -> Option<u32> {
+    let text_ref = self.get_block_text_ref(txn, block_id)?;
+    let offset = index.get_offset(txn)?;
+    // The index may point to a text that was replaced, e.g. by [TextOperation::set_delta].
+    if offset.branch != text_branch(&text_ref) {
+      return None;
+    }
+    Some(offset.index)
+  }
+
+  pub fn create_comment_thread_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    block_id: &str,
+    start: u32,
+    end: u32,
+    comment: NewComment,
+  ) -> Result<CommentThread, DocumentError> {
+    let block = self
+      .block_operation
+      .get_block_with_txn(txn, block_id)
+      .ok_or(DocumentError::BlockIsNotFound)?;
+    let text_ref = block
+      .external_id
+      .and_then(|text_id| self.text_operation.get_text_ref_with_txn(txn, &text_id))
+      .ok_or(DocumentError::ExternalIdIsNotFound)?;
+    let text = text_ref.get_string(txn).encode_utf16().collect::<Vec<_>>();
+    if start >= end || end as usize > text.len() {
+      return Err(DocumentError::InvalidCommentRange(format!(
+        "{}..{} in a text of length {}",
+        start,
+        end,
+        text.len()
+      )));
+    }
+
+    let branch = text_branch(&text_ref);
+    let invalid_range = || DocumentError::InvalidCommentRange(format!("{}..{}", start, end));
+    let anchor = CommentAnchor {
+      block_id: block_id.to_string(),
+      start: StickyIndex::at(txn, branch, start, Assoc::After).ok_or_else(invalid_range)?,
+      end: StickyIndex::at(txn, branch, end, Assoc::Before).ok_or_else(invalid_range)?,
+    };
+    let quote = String::from_utf16_lossy(&text[start as usize..end as usize]);
+    Ok(
+      self
+        .comment_operation
+        .create_thread_with_txn(txn, anchor, quote, comment),
+    )
+  }
+
+  /// Resolve the commented range against the current state of the document.
+  ///
+  /// Returns None if the block or the text that the thread is anchored to was deleted.
+  pub fn resolve_comment_range<T: ReadTxn>(
+    &self,
+    txn: &T,
+    anchor: &CommentAnchor,
+  ) -> Option<CommentRange> {
+    let start = self.resolve_sticky_index(txn, &anchor.block_id, &anchor.start)?;
+    let end = self.resolve_sticky_index(txn, &anchor.block_id, &anchor.end)?;
+    Some(CommentRange {
+      block_id: anchor.block_id.clone(),
+      start,
+      end: end.max(start),
+    })
+  }
+
   /// Get the plain text of the document.
   pub fn to_plain_text<T: ReadTxn>(&self, txn: T) -> Vec<String> {
+    self.to_plain_text_with_options(txn, &DocumentRenderOptions::default())
+  }
+
+  pub fn to_plain_text_with_options<T: ReadTxn>(
+    &self,
+    txn: T,
+    options: &DocumentRenderOptions,
+  ) -> Vec<String> {
     // use DocumentParser to parse the document
     let document_parser = DocumentParser::with_default_parsers();
     let document_data = self.get_document_data(&txn);
-    if let Ok(document_data) = document_data {
+    if let Ok(mut document_data) = document_data {
+      render_suggestions(&mut document_data, options.suggestions);
       let plain_text = document_parser
         .parse_document(&document_data, OutputFormat::PlainText)
         .unwrap_or_default();
@@ -639,9 +1532,18 @@ impl DocumentBody {
 
   /// Get the markdown text of the document.
   pub fn to_markdown_text<T: ReadTxn>(&self, txn: T) -> Vec<String> {
+    self.to_markdown_text_with_options(txn, &DocumentRenderOptions::default())
+  }
+
+  pub fn to_markdown_text_with_options<T: ReadTxn>(
+    &self,
+    txn: T,
+    options: &DocumentRenderOptions,
+  ) -> Vec<String> {
     let document_parser = DocumentParser::with_default_parsers();
     let document_data = self.get_document_data(&txn);
-    if let Ok(document_data) = document_data {
+    if let Ok(mut document_data) = document_data {
+      render_suggestions(&mut document_data, options.suggestions);
       let markdown_text = document_parser
         .parse_document(&document_data, OutputFormat::Markdown)
         .unwrap_or_default();
@@ -659,6 +1561,7 @@ impl DocumentBody {
     block: Block,
     prev_id: Option<String>,
   ) -> Result<Block, DocumentError> {
+    self.validate_block_data(&block.id, &block.ty, &block.data)?;
     let block = self.block_operation.create_block_with_txn(txn, block)?;
     self.insert_block_to_parent(txn, &block, prev_id)
   }
@@ -771,6 +1674,7 @@ impl DocumentBody {
       Some(block) => block,
       None => return Err(DocumentError::BlockIsNotFound),
     };
+    self.validate_block_data(&block.id, &block.ty, &data)?;
     self.block_operation.set_block_with_txn(
       txn,
       &block.id,
@@ -781,6 +1685,229 @@ impl DocumentBody {
     )
   }
 
+  fn validate_block_data(
+    &self,
+    block_id: &str,
+    block_type: &str,
+    data: &HashMap<String, Value>,
+  ) -> Result<(), DocumentError> {
+    validate_block_data(&self.schema_registry, block_id, block_type, data)
+  }
+
+  pub fn validate_blocks<T: ReadTxn>(&self, txn: &T) -> BlockValidationReport {
+    let mut issues = vec![];
+    let blocks = self.block_operation.get_all_blocks(txn);
+    let children_map = self.children_operation.get_all_children(txn);
+
+    let mut block_ids = blocks.keys().collect::<Vec<_>>();
+    block_ids.sort();
+    for block_id in &block_ids {
+      let block = &blocks[*block_id];
+      if let Err(error) = self.schema_registry.validate_block(block) {
+        issues.push(BlockValidationIssue::InvalidData {
+          block_id: block.id.clone(),
+          block_type: block.ty.clone(),
+          error,
+        });
+      }
+      if !children_map.contains_key(&block.children) {
+        issues.push(BlockValidationIssue::MissingChildren {
+          block_id: block.id.clone(),
+          children_id: block.children.clone(),
+        });
+      }
+    }
+
+    // Walk the tree from the page block. A block that is reached twice is either listed by more
+    // than one parent or is its own ancestor.
+    let page_id = self
+      .root
+      .get(txn, PAGE_ID)
+      .and_then(|v| v.cast::<String>().ok())
+      .unwrap_or_default();
+    let mut visited = HashSet::new();
+    if blocks.contains_key(&page_id) {
+      let mut ancestors = vec![];
+      walk_block_tree(
+        &page_id,
+        &blocks,
+        &children_map,
+        &mut ancestors,
+        &mut visited,
+        &mut issues,
+      );
+    } else {
+      issues.push(BlockValidationIssue::MissingPage { page_id });
+    }
+
+    for block_id in block_ids {
+      if !visited.contains(block_id.as_str()) {
+        issues.push(BlockValidationIssue::Orphan {
+          block_id: block_id.clone(),
+        });
+      }
+    }
+    BlockValidationReport { issues }
+  }
+
+  pub fn extract_fragment<T: ReadTxn, S: AsRef<str>>(
+    &self,
+    txn: &T,
+    block_ids: &[S],
+  ) -> Result<DocumentData, DocumentError> {
+    let blocks = block_ids
+      .iter()
+      .map(|id| {
+        self
+          .block_operation
+          .get_block_with_txn(txn, id.as_ref())
+          .ok_or(DocumentError::BlockIsNotFound)
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    // Skip the blocks that are copied along with one of their ancestors
+    let selected = blocks
+      .iter()
+      .map(|block| block.id.clone())
+      .collect::<HashSet<_>>();
+    let has_selected_ancestor = |block: &Block| {
+      let mut parent_id = block.parent.clone();
+      let mut visited = HashSet::new();
+      while !parent_id.is_empty() && visited.insert(parent_id.clone()) {
+        if selected.contains(&parent_id) {
+          return true;
+        }
+        parent_id = match self.block_operation.get_block_with_txn(txn, &parent_id) {
+          Some(parent) => parent.parent,
+          None => break,
+        };
+      }
+      false
+    };
+
+    let page_id = generate_id();
+    let page_children_id = generate_id();
+    let mut fragment = DocumentData {
+      page_id: page_id.clone(),
+      blocks: HashMap::new(),
+      meta: DocumentMeta {
+        children_map: HashMap::new(),
+        text_map: Some(HashMap::new()),
+      },
+    };
+    let mut top_ids = vec![];
+    for mut block in blocks {
+      if has_selected_ancestor(&block) || fragment.blocks.contains_key(&block.id) {
+        continue;
+      }
+      top_ids.push(block.id.clone());
+      block.parent = page_id.clone();
+      self.copy_block_to_fragment(txn, block, &mut fragment);
+    }
+
+    fragment.blocks.insert(
+      page_id.clone(),
+      Block {
+        id: page_id.clone(),
+        ty: BlockType::Page.as_str().to_string(),
+        parent: "".to_string(),
+        children: page_children_id.clone(),
+        external_id: None,
+        external_type: None,
+        data: HashMap::new(),
+      },
+    );
+    fragment.meta.children_map.insert(page_children_id, top_ids);
+    Ok(fragment)
+  }
+
+  fn copy_block_to_fragment<T: ReadTxn>(&self, txn: &T, block: Block, fragment: &mut DocumentData) {
+    if fragment.blocks.contains_key(&block.id) {
+      return;
+    }
+    let child_ids = self
+      .children_operation
+      .get_children(txn, &block.children)
+      .into_iter()
+      .map(|child| child.to_string(txn))
+      .collect::<Vec<_>>();
+    if let Some(external_id) = &block.external_id {
+      if let Some(delta) = self.text_operation.get_delta_with_txn(txn, external_id) {
+        if let (Some(text_map), Ok(delta)) = (
+          fragment.meta.text_map.as_mut(),
+          serde_json::to_string(&delta),
+        ) {
+          text_map.insert(external_id.clone(), delta);
+        }
+      }
+    }
+    let mut children = vec![];
+    for child in child_ids
+      .iter()
+      .filter_map(|id| self.block_operation.get_block_with_txn(txn, id))
+    {
+      children.push(child.id.clone());
+      self.copy_block_to_fragment(txn, child, fragment);
+    }
+    fragment
+      .meta
+      .children_map
+      .insert(block.children.clone(), children);
+    fragment.blocks.insert(block.id.clone(), block);
+  }
+
+  pub fn insert_fragment_with_txn(
+    &self,
+    txn: &mut TransactionMut,
+    fragment: DocumentData,
+    parent_id: &str,
+    prev_id: Option<String>,
+    suggestion_author: Option<i64>,
+  ) -> Result<Vec<String>, DocumentError> {
+    if self
+      .block_operation
+      .get_block_with_txn(txn, parent_id)
+      .is_none()
+    {
+      return Err(DocumentError::ParentIsNotFound);
+    }
+    let page = fragment
+      .blocks
+      .get(&fragment.page_id)
+      .ok_or(DocumentError::PageBlockNotFound)?;
+    let top_ids = fragment
+      .meta
+      .children_map
+      .get(&page.children)
+      .cloned()
+      .unwrap_or_default();
+    // Check the data before writing anything, a transaction can't be rolled back
+    for block in fragment.blocks.values() {
+      self.validate_block_data(&block.id, &block.ty, &block.data)?;
+    }
+
+    let mut inserter = FragmentInserter {
+      body: self,
+      fragment: &fragment,
+      inserted: HashSet::new(),
+    };
+    let mut new_ids = vec![];
+    let mut prev_id = prev_id;
+    for block_id in &top_ids {
+      let suggestion = suggestion_author.map(|author| BlockSuggestion {
+        mark: SuggestionMark::new(author),
+        ty: BlockSuggestionType::Insert,
+      });
+      if let Some(new_id) =
+        inserter.insert(txn, block_id, parent_id, prev_id.clone(), suggestion)?
+      {
+        prev_id = Some(new_id.clone());
+        new_ids.push(new_id);
+      }
+    }
+    Ok(new_ids)
+  }
+
   pub fn get_document_data<T: ReadTxn>(&self, txn: &T) -> Result<DocumentData, DocumentError> {
     let page_id = self
       .root
@@ -889,10 +2016,28 @@ impl DocumentBody {
     payload: BlockActionPayload,
   ) -> Result<(), DocumentError> {
     if let Some(block) = payload.block {
-      let data = &block.data;
+      let mut data = block.data;
+      match data.get(BLOCK_SUGGESTION_KEY) {
+        // A null suggestion removes the pending suggestion of the block.
+        Some(Value::Null) => {
+          data.remove(BLOCK_SUGGESTION_KEY);
+        },
+        Some(_) => {},
+        // Keep the pending suggestion of the block, the clients that don't know about
+        // suggestions send the data without it.
+        None => {
+          if let Some(suggestion) = self
+            .block_operation
+            .get_block_with_txn(txn, &block.id)
+            .and_then(|block| block.data.get(BLOCK_SUGGESTION_KEY).cloned())
+          {
+            data.insert(BLOCK_SUGGESTION_KEY.to_string(), suggestion);
+          }
+        },
+      }
       let external_id = block.external_id;
       let external_type = block.external_type;
-      self.update_block_data(txn, &block.id, data.to_owned(), external_id, external_type)
+      self.update_block_data(txn, &block.id, data, external_id, external_type)
     } else {
       Err(DocumentError::BlockIsNotFound)
     }
@@ -941,6 +2086,210 @@ impl DocumentBody {
   }
 }
 
+impl DocumentBody {
+  fn handle_suggest_insert_action(
+    &self,
+    txn: &mut TransactionMut,
+    mut payload: BlockActionPayload,
+    author: i64,
+  ) -> Result<(), DocumentError> {
+    if let Some(block) = payload.block.as_mut() {
+      let suggestion = BlockSuggestion {
+        mark: SuggestionMark::new(author),
+        ty: BlockSuggestionType::Insert,
+      };
+      block.data.insert(
+        BLOCK_SUGGESTION_KEY.to_string(),
+        serde_json::to_value(suggestion).map_err(|_| DocumentError::ConvertDataError)?,
+      );
+    }
+    self.handle_insert_action(txn, payload)
+  }
+
+  /// Marks the block as suggested to be deleted. A block that the author suggested to insert is
+  /// deleted right away.
+  fn handle_suggest_delete_action(
+    &self,
+    txn: &mut TransactionMut,
+    payload: BlockActionPayload,
+    author: i64,
+  ) -> Result<(), DocumentError> {
+    let block_id = payload.block.ok_or(DocumentError::BlockIsNotFound)?.id;
+    let mut block = self
+      .block_operation
+      .get_block_with_txn(txn, &block_id)
+      .ok_or(DocumentError::BlockIsNotFound)?;
+    let is_own_insert = BlockSuggestion::from_block_data(&block.data).is_some_and(|suggestion| {
+      suggestion.ty == BlockSuggestionType::Insert && suggestion.mark.author == author
+    });
+    if is_own_insert {
+      return self.delete_block(txn, &block_id);
+    }
+
+    let suggestion = BlockSuggestion {
+      mark: SuggestionMark::new(author),
+      ty: BlockSuggestionType::Delete,
+    };
+    block.data.insert(
+      BLOCK_SUGGESTION_KEY.to_string(),
+      serde_json::to_value(suggestion).map_err(|_| DocumentError::ConvertDataError)?,
+    );
+    self.update_block_data(txn, &block_id, block.data, None, None)
+  }
+
+  fn handle_suggest_text_delta_action(
+    &self,
+    txn: &mut TransactionMut,
+    payload: BlockActionPayload,
+    author: i64,
+  ) -> Result<(), DocumentError> {
+    match (payload.text_id, payload.delta) {
+      (Some(text_id), Some(delta)) => {
+        let delta = deserialize_text_delta(&delta).ok().unwrap_or_default();
+        self.apply_suggested_text_delta(txn, &text_id, delta, author);
+        Ok(())
+      },
+      _ => Err(DocumentError::TextActionParamsError),
+    }
+  }
+
+  /// Applies the delta as suggestions of the `author`. A new text, or the text of a block that is
+  /// itself a pending insert suggestion, is edited directly.
+  pub fn apply_suggested_text_delta(
+    &self,
+    txn: &mut TransactionMut,
+    text_id: &str,
+    delta: Vec<TextDelta>,
+    author: i64,
+  ) {
+    match self.text_operation.get_text_ref_with_txn(txn, text_id) {
+      Some(text_ref) => {
+        let in_suggested_block = self
+          .get_text_owner(txn, text_id)
+          .and_then(|block| BlockSuggestion::from_block_data(&block.data))
+          .is_some_and(|suggestion| suggestion.ty == BlockSuggestionType::Insert);
+        if in_suggested_block {
+          self.text_operation.apply_delta(txn, text_id, delta)
+        } else {
+          apply_suggested_delta(txn, &text_ref, delta, author)
+        }
+      },
+      // Creating a new text is not a suggestion
+      None => self.text_operation.apply_delta(txn, text_id, delta),
+    }
+  }
+
+  /// Returns the block whose text is `text_id`. The blocks are only scanned when the owner of the
+  /// text isn't cached or the cached block no longer owns the text.
+  fn get_text_owner<T: ReadTxn>(&self, txn: &T, text_id: &str) -> Option<Block> {
+    let mut text_owners = match self.text_owners.lock() {
+      Ok(text_owners) => text_owners,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    let cached = text_owners
+      .get(text_id)
+      .and_then(|block_id| self.block_operation.get_block_with_txn(txn, block_id))
+      .filter(|block| block.external_id.as_deref() == Some(text_id));
+    if cached.is_some() {
+      return cached;
+    }
+
+    let mut blocks = self.block_operation.get_all_blocks(txn);
+    *text_owners = blocks
+      .values()
+      .filter_map(|block| Some((block.external_id.clone()?, block.id.clone())))
+      .collect();
+    let block_id = text_owners.get(text_id)?;
+    blocks.remove(block_id)
+  }
+
+  pub fn get_suggestions<T: ReadTxn>(&self, txn: &T) -> Vec<Suggestion> {
+    let blocks = self.block_operation.get_all_blocks(txn);
+    let mut suggestions = vec![];
+    for block in blocks.values() {
+      if let Some(suggestion) = BlockSuggestion::from_block_data(&block.data) {
+        suggestions.push(Suggestion {
+          id: suggestion.mark.id,
+          author: suggestion.mark.author,
+          timestamp: suggestion.mark.timestamp,
+          block_id: block.id.clone(),
+          kind: match suggestion.ty {
+            BlockSuggestionType::Insert => SuggestionKind::InsertBlock,
+            BlockSuggestionType::Delete => SuggestionKind::DeleteBlock,
+          },
+          text: String::new(),
+        });
+      }
+      if let Some(text_ref) = block
+        .external_id
+        .as_ref()
+        .and_then(|text_id| self.text_operation.get_text_ref_with_txn(txn, text_id))
+      {
+        suggestions.extend(text_suggestions(txn, &text_ref, &block.id));
+      }
+    }
+    suggestions.sort_by(|a, b| (a.timestamp, &a.id).cmp(&(b.timestamp, &b.id)));
+    suggestions
+  }
+
+  /// Accepts or rejects the suggestions that match the filter. Returns the number of resolved
+  /// suggestions.
+  pub fn resolve_suggestions<F>(&self, txn: &mut TransactionMut, filter: &F, accept: bool) -> usize
+  where
+    F: Fn(&SuggestionMark) -> bool,
+  {
+    let suggestions = self
+      .get_suggestions(txn)
+      .into_iter()
+      .filter(|suggestion| {
+        filter(&SuggestionMark {
+          id: suggestion.id.clone(),
+          author: suggestion.author,
+          timestamp: suggestion.timestamp,
+        })
+      })
+      .collect::<Vec<_>>();
+    let ids = suggestions
+      .iter()
+      .map(|suggestion| suggestion.id.as_str())
+      .collect::<HashSet<_>>();
+    let filter = |mark: &SuggestionMark| ids.contains(mark.id.as_str());
+
+    // Resolve the text suggestions first, deleting a block deletes its text.
+    for block in self.block_operation.get_all_blocks(txn).values() {
+      if let Some(text_ref) = block
+        .external_id
+        .as_ref()
+        .and_then(|text_id| self.text_operation.get_text_ref_with_txn(txn, text_id))
+      {
+        resolve_text_suggestions(txn, &text_ref, &filter, accept);
+      }
+    }
+
+    for suggestion in suggestions.iter() {
+      let is_block_insert = match suggestion.kind {
+        SuggestionKind::InsertBlock => true,
+        SuggestionKind::DeleteBlock => false,
+        _ => continue,
+      };
+      // The block may have been deleted along with its parent
+      let Some(mut block) = self
+        .block_operation
+        .get_block_with_txn(txn, &suggestion.block_id)
+      else {
+        continue;
+      };
+      if accept == is_block_insert {
+        block.data.remove(BLOCK_SUGGESTION_KEY);
+        let _ = self.update_block_data(txn, &block.id, block.data, None, None);
+      } else {
+        let _ = self.delete_block(txn, &block.id);
+      }
+    }
+    ids.len()
+  }
+}
+
 /// Represents a the index content of a document.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct DocumentIndexContent {
@@ -994,3 +2343,183 @@ impl From<&Document> for DocumentIndexContent {
 pub fn gen_document_id() -> String {
   uuid::Uuid::new_v4().to_string()
 }
+
+/// Restores the previous actor origin when it's dropped, even if the changes made with
+/// [Document::with_origin] panic.
+struct OriginGuard<'a> {
+  document: &'a mut Document,
+  previous: Option<CollabOrigin>,
+}
+
+impl Deref for OriginGuard<'_> {
+  type Target = Document;
+
+  fn deref(&self) -> &Self::Target {
+    self.document
+  }
+}
+
+impl DerefMut for OriginGuard<'_> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    self.document
+  }
+}
+
+impl Drop for OriginGuard<'_> {
+  fn drop(&mut self) {
+    self.document.actor_origin = self.previous.take();
+  }
+}
+
+/// A transaction made with the origin of [Document::with_origin], or with the origin of the
+/// document outside of it.
+fn transact_mut<'a>(
+  collab: &'a mut Collab,
+  actor_origin: Option<&CollabOrigin>,
+) -> TransactionMut<'a> {
+  match actor_origin {
+    Some(origin) => collab.context.transact_mut_with(origin.clone()),
+    None => collab.context.transact_mut(),
+  }
+}
+
+fn validate_block_data(
+  schema_registry: &BlockSchemaRegistry,
+  block_id: &str,
+  block_type: &str,
+  data: &HashMap<String, Value>,
+) -> Result<(), DocumentError> {
+  match schema_registry.get(block_type) {
+    Some(schema) => schema
+      .validate(data)
+      .map_err(|error| DocumentError::InvalidBlockData {
+        block_id: block_id.to_string(),
+        block_type: block_type.to_string(),
+        error,
+      }),
+    None => Ok(()),
+  }
+}
+
+fn text_branch(text_ref: &TextRef) -> BranchPtr {
+  let branch: &Branch = text_ref.as_ref();
+  BranchPtr::from(branch)
+}
+
+/// Walks the block tree depth first, reporting the children that are missing, already visited
+/// or an ancestor of their parent.
+fn walk_block_tree<'a>(
+  block_id: &'a str,
+  blocks: &'a HashMap<String, Block>,
+  children_map: &'a HashMap<String, Vec<String>>,
+  ancestors: &mut Vec<&'a str>,
+  visited: &mut HashSet<&'a str>,
+  issues: &mut Vec<BlockValidationIssue>,
+) {
+  visited.insert(block_id);
+  ancestors.push(block_id);
+  let block = &blocks[block_id];
+  for child_id in children_map.get(&block.children).into_iter().flatten() {
+    let Some(child) = blocks.get(child_id) else {
+      issues.push(BlockValidationIssue::MissingBlock {
+        parent_id: block_id.to_string(),
+        block_id: child_id.clone(),
+      });
+      continue;
+    };
+    if ancestors.contains(&child_id.as_str()) {
+      issues.push(BlockValidationIssue::Cycle {
+        block_id: child_id.clone(),
+      });
+      continue;
+    }
+    if visited.contains(child_id.as_str()) {
+      issues.push(BlockValidationIssue::DuplicateChild {
+        block_id: child_id.clone(),
+      });
+      continue;
+    }
+    if child.parent != block_id {
+      issues.push(BlockValidationIssue::ParentMismatch {
+        block_id: child_id.clone(),
+        parent_id: child.parent.clone(),
+        actual_parent_id: block_id.to_string(),
+      });
+    }
+    walk_block_tree(child_id, blocks, children_map, ancestors, visited, issues);
+  }
+  ancestors.pop();
+}
+
+/// Inserts the blocks of a fragment with new ids.
+struct FragmentInserter<'a> {
+  body: &'a DocumentBody,
+  fragment: &'a DocumentData,
+  inserted: HashSet<&'a str>,
+}
+
+impl<'a> FragmentInserter<'a> {
+  fn insert(
+    &mut self,
+    txn: &mut TransactionMut,
+    block_id: &'a str,
+    parent_id: &str,
+    prev_id: Option<String>,
+    suggestion: Option<BlockSuggestion>,
+  ) -> Result<Option<String>, DocumentError> {
+    // A block listed twice, or a cycle in a malformed fragment
+    if !self.inserted.insert(block_id) {
+      return Ok(None);
+    }
+    let Some(block) = self.fragment.blocks.get(block_id) else {
+      return Ok(None);
+    };
+
+    let mut data = block.data.clone();
+    if let Some(suggestion) = suggestion {
+      data.insert(
+        BLOCK_SUGGESTION_KEY.to_string(),
+        serde_json::to_value(suggestion).map_err(|_| DocumentError::ConvertDataError)?,
+      );
+    }
+    let delta = block.external_id.as_ref().and_then(|external_id| {
+      self
+        .fragment
+        .meta
+        .text_map
+        .as_ref()
+        .and_then(|text_map| text_map.get(external_id))
+    });
+    // The text is not shared with the source block even if the fragment doesn't contain it
+    let external_id = block.external_id.as_ref().map(|_| generate_id());
+    let new_block = Block {
+      id: generate_id(),
+      ty: block.ty.clone(),
+      parent: parent_id.to_string(),
+      children: generate_id(),
+      external_id: external_id.clone(),
+      external_type: block.external_type.clone(),
+      data,
+    };
+    let new_block = self.body.insert_block(txn, new_block, prev_id)?;
+    if let (Some(external_id), Some(delta)) = (&external_id, delta) {
+      let delta = serde_json::from_str(delta).unwrap_or_else(|_| vec![]);
+      self
+        .body
+        .text_operation
+        .apply_delta(txn, external_id, delta);
+    }
+
+    let mut prev_child_id = None;
+    if let Some(child_ids) = self.fragment.meta.children_map.get(&block.children) {
+      for child_id in child_ids {
+        if let Some(new_child_id) =
+          self.insert(txn, child_id, &new_block.id, prev_child_id.clone(), None)?
+        {
+          prev_child_id = Some(new_child_id);
+        }
+      }
+    }
+    Ok(Some(new_block.id))
+  }
+}
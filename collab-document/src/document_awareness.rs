@@ -1,3 +1,4 @@
+use collab::preclude::StickyIndex;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -6,6 +7,11 @@ pub struct DocumentAwarenessState {
   pub version: i64,
   pub user: DocumentAwarenessUser,
   pub selection: Option<DocumentAwarenessSelection>,
+  // The selection anchored to the text of the blocks. Unlike `selection`, it keeps pointing at the
+  // same characters when other users edit the text before it.
+  // Use [crate::document::Document::sticky_selection] to create it.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub sticky_selection: Option<DocumentAwarenessStickySelection>,
   // The `metadata` field is an optional field (json string) that can be used to store additional information.
   // For example, the user can store the color of the selection in this field
   pub metadata: Option<String>,
@@ -18,6 +24,7 @@ impl DocumentAwarenessState {
       version,
       user,
       selection: None,
+      sticky_selection: None,
       metadata: None,
       timestamp: 0,
     }
@@ -41,3 +48,44 @@ pub struct DocumentAwarenessPosition {
   pub path: Vec<u64>,
   pub offset: u64,
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DocumentAwarenessStickySelection {
+  pub start: DocumentAwarenessStickyPosition,
+  pub end: DocumentAwarenessStickyPosition,
+}
+
+/// A position inside the text of a block, encoded as a Yrs [StickyIndex].
+///
+/// The index is serialized in the same JSON format as the Yjs relative position, so it can be
+/// shared with the web clients.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DocumentAwarenessStickyPosition {
+  pub block_id: String,
+  pub index: StickyIndex,
+}
+
+/// A position inside the text of a block. The offset is counted in UTF-16 code units.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BlockTextPosition {
+  pub block_id: String,
+  pub offset: u32,
+}
+
+impl BlockTextPosition {
+  pub fn new<T: ToString>(block_id: T, offset: u32) -> Self {
+    Self {
+      block_id: block_id.to_string(),
+      offset,
+    }
+  }
+}
+
+/// The selection of a remote user resolved against the current state of the document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedAwarenessSelection {
+  pub user: DocumentAwarenessUser,
+  pub metadata: Option<String>,
+  pub start: BlockTextPosition,
+  pub end: BlockTextPosition,
+}
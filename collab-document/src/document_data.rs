@@ -1,5 +1,6 @@
 use collab::preclude::ClientID;
 use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::blocks::{Block, DocumentData, DocumentMeta};
 use crate::document::Document;
@@ -97,6 +98,14 @@ pub fn generate_id() -> String {
   nanoid!(10)
 }
 
+/// The current unix timestamp, in seconds.
+pub(crate) fn timestamp() -> i64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_secs() as i64)
+    .unwrap_or_default()
+}
+
 pub fn page_id_from_document_id(document_id: &str) -> Option<String> {
   let document_uuid = Uuid::parse_str(document_id).ok()?;
   Some(Uuid::new_v5(&document_uuid, PAGE.as_bytes()).to_string())
@@ -0,0 +1,937 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+use crate::blocks::{Block, BlockAction, BlockActionPayload, BlockActionType, DocumentData};
+use crate::document_data::generate_id;
+
+const INSERT_KEY: &str = "insert";
+const ATTRIBUTES_KEY: &str = "attributes";
+const EMPTY_DELTA: &str = "[]";
+
+/// The minimum similarity of two blocks with different ids to be considered the same block.
+const MATCH_THRESHOLD: f64 = 0.6;
+/// Above this number of edits, the changed part of a text is replaced as a whole.
+const MAX_EDIT_DISTANCE: usize = 1000;
+
+/// The result of [merge_document_data].
+#[derive(Debug, Clone, Default)]
+pub struct DocumentMerge {
+  /// The actions that apply the changes of theirs to ours, see
+  /// [crate::document::Document::apply_action].
+  pub actions: Vec<BlockAction>,
+  pub conflicts: Vec<MergeConflict>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeConflict {
+  /// The id of the block in ours, or in theirs for [MergeConflictKind::DeletedByOurs].
+  pub block_id: String,
+  pub kind: MergeConflictKind,
+}
+
+/// The conflicting changes are resolved in favor of ours.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeConflictKind {
+  /// Both sides changed the value of the data key.
+  Data { key: String },
+  /// Both sides changed the same part of the text.
+  Text,
+  /// Theirs changed the type of the block, the actions can't change the type of a block.
+  Type,
+  /// Both sides moved the block to different places, or theirs moved it to a place that doesn't
+  /// exist in ours.
+  Move,
+  /// Theirs deleted the block that ours changed, the block is kept.
+  DeletedByTheirs,
+  /// Ours deleted the block that theirs changed, the block stays deleted.
+  DeletedByOurs,
+}
+
+/// Merges the changes of `theirs` into `ours`, two versions of the document edited separately
+/// from the `base` version, like the same page imported twice or a snapshot restored side by side.
+///
+/// The blocks of the versions are matched by id, then by type and content similarity for the
+/// blocks that were recreated with new ids under the same parent. The texts are merged at character level and the data
+/// key by key. The returned actions turn `ours` into the merged document, the conflicting changes
+/// of theirs are reported and skipped.
+pub fn merge_document_data(
+  base: &DocumentData,
+  ours: &DocumentData,
+  theirs: &DocumentData,
+) -> DocumentMerge {
+  DocumentMerger::new(base, ours, theirs).merge()
+}
+
+/// The position of a block in base ids: its parent and its previous sibling that exists in the
+/// three versions. `None` for a parent that is not in base.
+#[derive(Debug, PartialEq, Eq)]
+struct Position<'a> {
+  parent: Option<&'a str>,
+  prev: Option<&'a str>,
+}
+
+struct DocumentMerger<'a> {
+  base: &'a DocumentData,
+  ours: &'a DocumentData,
+  theirs: &'a DocumentData,
+  ours_to_base: HashMap<&'a str, &'a str>,
+  base_to_ours: HashMap<&'a str, &'a str>,
+  theirs_to_base: HashMap<&'a str, &'a str>,
+  base_to_theirs: HashMap<&'a str, &'a str>,
+  /// theirs id -> id in the merged document
+  theirs_to_merged: HashMap<&'a str, String>,
+  /// block id -> parent id in the merged document
+  merged_parents: HashMap<String, String>,
+  actions: Vec<BlockAction>,
+  conflicts: Vec<MergeConflict>,
+}
+
+impl<'a> DocumentMerger<'a> {
+  fn new(base: &'a DocumentData, ours: &'a DocumentData, theirs: &'a DocumentData) -> Self {
+    let ours_to_base = match_blocks(base, ours);
+    let theirs_to_base = match_blocks(base, theirs);
+    let reverse = |map: &HashMap<&'a str, &'a str>| {
+      map
+        .iter()
+        .map(|(side_id, base_id)| (*base_id, *side_id))
+        .collect::<HashMap<_, _>>()
+    };
+    let base_to_ours = reverse(&ours_to_base);
+    let base_to_theirs = reverse(&theirs_to_base);
+    let mut theirs_to_merged = theirs_to_base
+      .iter()
+      .filter_map(|(theirs_id, base_id)| {
+        base_to_ours
+          .get(base_id)
+          .map(|ours_id| (*theirs_id, ours_id.to_string()))
+      })
+      .collect::<HashMap<_, _>>();
+    // The blocks added by both sides, like the blocks of theirs already merged into ours
+    for theirs_id in theirs.blocks.keys() {
+      if !theirs_to_base.contains_key(theirs_id.as_str())
+        && ours.blocks.contains_key(theirs_id)
+        && !ours_to_base.contains_key(theirs_id.as_str())
+      {
+        theirs_to_merged.insert(theirs_id.as_str(), theirs_id.clone());
+      }
+    }
+    let merged_parents = ours
+      .blocks
+      .values()
+      .map(|block| (block.id.clone(), block.parent.clone()))
+      .collect();
+
+    Self {
+      base,
+      ours,
+      theirs,
+      ours_to_base,
+      base_to_ours,
+      theirs_to_base,
+      base_to_theirs,
+      theirs_to_merged,
+      merged_parents,
+      actions: vec![],
+      conflicts: vec![],
+    }
+  }
+
+  fn merge(mut self) -> DocumentMerge {
+    for ours_id in block_order(self.ours) {
+      let Some(base_id) = self.ours_to_base.get(ours_id).copied() else {
+        continue;
+      };
+      if let Some(theirs_id) = self.base_to_theirs.get(base_id).copied() {
+        self.merge_content(ours_id, base_id, theirs_id);
+      }
+    }
+
+    for theirs_id in block_order(self.theirs) {
+      if theirs_id == self.theirs.page_id {
+        continue;
+      }
+      match self.theirs_to_base.get(theirs_id).copied() {
+        Some(base_id) => match self.base_to_ours.get(base_id).copied() {
+          Some(ours_id) => self.merge_position(ours_id, base_id, theirs_id),
+          None if self.is_changed_by_theirs(base_id, theirs_id) => {
+            self.add_conflict(theirs_id, MergeConflictKind::DeletedByOurs)
+          },
+          None => {},
+        },
+        None if self.theirs_to_merged.contains_key(theirs_id) => {},
+        None => self.insert_block(theirs_id),
+      }
+    }
+
+    self.delete_blocks();
+    DocumentMerge {
+      actions: self.actions,
+      conflicts: self.conflicts,
+    }
+  }
+
+  fn merge_content(&mut self, ours_id: &'a str, base_id: &'a str, theirs_id: &'a str) {
+    let (base, ours, theirs) = (self.base, self.ours, self.theirs);
+    let ours_block = &ours.blocks[ours_id];
+    let base_block = &base.blocks[base_id];
+    let theirs_block = &theirs.blocks[theirs_id];
+    if theirs_block.ty != base_block.ty && theirs_block.ty != ours_block.ty {
+      self.add_conflict(ours_id, MergeConflictKind::Type);
+    }
+
+    let mut data = HashMap::new();
+    let keys = base_block
+      .data
+      .keys()
+      .chain(ours_block.data.keys())
+      .chain(theirs_block.data.keys())
+      .collect::<BTreeSet<_>>();
+    for key in keys {
+      let base_value = base_block.data.get(key);
+      let ours_value = ours_block.data.get(key);
+      let theirs_value = theirs_block.data.get(key);
+      let value = if ours_value == theirs_value || base_value == theirs_value {
+        ours_value
+      } else if base_value == ours_value {
+        theirs_value
+      } else {
+        self.add_conflict(ours_id, MergeConflictKind::Data { key: key.clone() });
+        ours_value
+      };
+      if let Some(value) = value {
+        data.insert(key.clone(), value.clone());
+      }
+    }
+
+    let base_text = text_units(base, base_block).unwrap_or_default();
+    let theirs_text = text_units(theirs, theirs_block);
+    let mut external_id = None;
+    match &ours_block.external_id {
+      Some(text_id) => {
+        let ours_text = text_units(ours, ours_block).unwrap_or_default();
+        // A block without text in theirs keeps its text
+        let (text, conflict) = merge_text(
+          &base_text,
+          &ours_text,
+          theirs_text.as_deref().unwrap_or(&base_text),
+        );
+        if conflict {
+          self.add_conflict(ours_id, MergeConflictKind::Text);
+        }
+        if text != ours_text {
+          self.push_text_action(
+            BlockActionType::ApplyTextDelta,
+            text_id,
+            text_delta(&ours_text, &text),
+          );
+        }
+      },
+      // Theirs added a text to the block
+      None => {
+        if let Some(delta) = theirs_block
+          .external_id
+          .as_ref()
+          .and_then(|text_id| text_map_delta(theirs, text_id))
+          .filter(|_| theirs_text.is_some_and(|text| text != base_text))
+        {
+          let text_id = generate_id();
+          self.push_text_action(BlockActionType::InsertText, &text_id, delta.to_string());
+          external_id = Some(text_id);
+        }
+      },
+    }
+
+    if data != ours_block.data || external_id.is_some() {
+      let block = Block {
+        data,
+        external_type: external_id
+          .as_ref()
+          .and(theirs_block.external_type.clone())
+          .or_else(|| ours_block.external_type.clone()),
+        external_id: external_id.or_else(|| ours_block.external_id.clone()),
+        ..ours_block.clone()
+      };
+      self.push_block_action(BlockActionType::Update, block, None, None);
+    }
+  }
+
+  fn merge_position(&mut self, ours_id: &'a str, base_id: &'a str, theirs_id: &'a str) {
+    let base_position = self.position(self.base, base_id, Some);
+    let theirs_position = self.position(self.theirs, theirs_id, |id| {
+      self.theirs_to_base.get(id).copied()
+    });
+    if theirs_position == base_position {
+      return;
+    }
+    let ours_position = self.position(self.ours, ours_id, |id| self.ours_to_base.get(id).copied());
+    if ours_position == theirs_position {
+      return;
+    }
+    if ours_position != base_position {
+      self.add_conflict(ours_id, MergeConflictKind::Move);
+      return;
+    }
+
+    let theirs = self.theirs;
+    let theirs_block = &theirs.blocks[theirs_id];
+    let Some(parent_id) = self
+      .theirs_to_merged
+      .get(theirs_block.parent.as_str())
+      .cloned()
+    else {
+      self.add_conflict(ours_id, MergeConflictKind::Move);
+      return;
+    };
+    if self.is_merged_ancestor(ours_id, &parent_id) {
+      self.add_conflict(ours_id, MergeConflictKind::Move);
+      return;
+    }
+    let prev_id = self.merged_prev_sibling(theirs_block, &parent_id);
+    let block = self.ours.blocks[ours_id].clone();
+    self.push_block_action(
+      BlockActionType::Move,
+      block,
+      Some(parent_id.clone()),
+      prev_id,
+    );
+    self.merged_parents.insert(ours_id.to_string(), parent_id);
+  }
+
+  fn insert_block(&mut self, theirs_id: &'a str) {
+    let theirs = self.theirs;
+    let theirs_block = &theirs.blocks[theirs_id];
+    // The parent was deleted by ours
+    let Some(parent_id) = self
+      .theirs_to_merged
+      .get(theirs_block.parent.as_str())
+      .cloned()
+    else {
+      return;
+    };
+    let id = if self.ours.blocks.contains_key(theirs_id)
+      || self.base.blocks.contains_key(theirs_id)
+      || self.merged_parents.contains_key(theirs_id)
+    {
+      generate_id()
+    } else {
+      theirs_id.to_string()
+    };
+
+    let external_id = theirs_block.external_id.as_ref().map(|text_id| {
+      let delta = text_map_delta(theirs, text_id).unwrap_or(EMPTY_DELTA);
+      let new_text_id = generate_id();
+      self.push_text_action(BlockActionType::InsertText, &new_text_id, delta.to_string());
+      new_text_id
+    });
+    let prev_id = self.merged_prev_sibling(theirs_block, &parent_id);
+    let block = Block {
+      id: id.clone(),
+      ty: theirs_block.ty.clone(),
+      parent: parent_id.clone(),
+      children: generate_id(),
+      external_id,
+      external_type: theirs_block.external_type.clone(),
+      data: theirs_block.data.clone(),
+    };
+    self.push_block_action(
+      BlockActionType::Insert,
+      block,
+      Some(parent_id.clone()),
+      prev_id,
+    );
+    self.theirs_to_merged.insert(theirs_id, id.clone());
+    self.merged_parents.insert(id, parent_id);
+  }
+
+  /// Deletes the blocks that theirs deleted. A block that ours changed, or that still has
+  /// children in the merged document, is kept.
+  fn delete_blocks(&mut self) {
+    let mut deleted = HashSet::new();
+    for ours_id in block_order(self.ours) {
+      let Some(base_id) = self.ours_to_base.get(ours_id).copied() else {
+        continue;
+      };
+      if ours_id == self.ours.page_id || self.base_to_theirs.contains_key(base_id) {
+        continue;
+      }
+      if self.is_changed_by_ours(base_id, ours_id) {
+        self.add_conflict(ours_id, MergeConflictKind::DeletedByTheirs);
+      } else {
+        deleted.insert(ours_id.to_string());
+      }
+    }
+
+    // A block is kept if one of its children is kept
+    loop {
+      let kept = self
+        .merged_parents
+        .iter()
+        .filter(|(id, parent)| deleted.contains(*parent) && !deleted.contains(*id))
+        .map(|(_, parent)| parent.clone())
+        .collect::<HashSet<_>>();
+      if kept.is_empty() {
+        break;
+      }
+      for id in kept {
+        deleted.remove(&id);
+        self.add_conflict(&id, MergeConflictKind::DeletedByTheirs);
+      }
+    }
+
+    // Deleting a block deletes its children
+    for ours_id in block_order(self.ours) {
+      if deleted.contains(ours_id) && !deleted.contains(&self.merged_parents[ours_id]) {
+        let block = self.ours.blocks[ours_id].clone();
+        self.push_block_action(BlockActionType::Delete, block, None, None);
+      }
+    }
+  }
+
+  fn position(
+    &self,
+    data: &'a DocumentData,
+    block_id: &str,
+    to_base: impl Fn(&'a str) -> Option<&'a str>,
+  ) -> Position<'a> {
+    let block = &data.blocks[block_id];
+    let siblings = children_ids(data, &block.parent);
+    let index = siblings
+      .iter()
+      .position(|id| id == block_id)
+      .unwrap_or_default();
+    let prev = siblings[..index].iter().rev().find_map(|id| {
+      to_base(id).filter(|base_id| {
+        self.base_to_ours.contains_key(base_id) && self.base_to_theirs.contains_key(base_id)
+      })
+    });
+    Position {
+      parent: data
+        .blocks
+        .get(&block.parent)
+        .and_then(|parent| to_base(&parent.id)),
+      prev,
+    }
+  }
+
+  /// The closest previous sibling of the theirs block that is under the same parent in the
+  /// merged document.
+  fn merged_prev_sibling(&self, theirs_block: &Block, parent_id: &str) -> Option<String> {
+    let siblings = children_ids(self.theirs, &theirs_block.parent);
+    let index = siblings
+      .iter()
+      .position(|id| *id == theirs_block.id)
+      .unwrap_or_default();
+    siblings[..index].iter().rev().find_map(|id| {
+      self
+        .theirs_to_merged
+        .get(id.as_str())
+        .filter(|merged_id| {
+          self.merged_parents.get(*merged_id).map(String::as_str) == Some(parent_id)
+        })
+        .cloned()
+    })
+  }
+
+  fn is_merged_ancestor(&self, ancestor_id: &str, block_id: &str) -> bool {
+    let mut current = Some(block_id);
+    let mut visited = HashSet::new();
+    while let Some(id) = current {
+      if id == ancestor_id {
+        return true;
+      }
+      if !visited.insert(id) {
+        return false;
+      }
+      current = self.merged_parents.get(id).map(String::as_str);
+    }
+    false
+  }
+
+  fn is_changed_by_ours(&self, base_id: &str, ours_id: &str) -> bool {
+    is_block_changed(self.base, base_id, self.ours, ours_id, &self.ours_to_base)
+  }
+
+  fn is_changed_by_theirs(&self, base_id: &str, theirs_id: &str) -> bool {
+    is_block_changed(
+      self.base,
+      base_id,
+      self.theirs,
+      theirs_id,
+      &self.theirs_to_base,
+    )
+  }
+
+  fn add_conflict(&mut self, block_id: &str, kind: MergeConflictKind) {
+    let conflict = MergeConflict {
+      block_id: block_id.to_string(),
+      kind,
+    };
+    if !self.conflicts.contains(&conflict) {
+      self.conflicts.push(conflict);
+    }
+  }
+
+  fn push_block_action(
+    &mut self,
+    action: BlockActionType,
+    block: Block,
+    parent_id: Option<String>,
+    prev_id: Option<String>,
+  ) {
+    self.actions.push(BlockAction {
+      action,
+      payload: BlockActionPayload {
+        block: Some(block),
+        prev_id,
+        parent_id,
+        delta: None,
+        text_id: None,
+      },
+    });
+  }
+
+  fn push_text_action(&mut self, action: BlockActionType, text_id: &str, delta: String) {
+    self.actions.push(BlockAction {
+      action,
+      payload: BlockActionPayload {
+        block: None,
+        prev_id: None,
+        parent_id: None,
+        delta: Some(delta),
+        text_id: Some(text_id.to_string()),
+      },
+    });
+  }
+}
+
+/// Whether the block has a different type, data or text than in base, or has new children.
+fn is_block_changed(
+  base: &DocumentData,
+  base_id: &str,
+  side: &DocumentData,
+  side_id: &str,
+  side_to_base: &HashMap<&str, &str>,
+) -> bool {
+  let base_block = &base.blocks[base_id];
+  let side_block = &side.blocks[side_id];
+  base_block.ty != side_block.ty
+    || base_block.data != side_block.data
+    || text_units(base, base_block).unwrap_or_default()
+      != text_units(side, side_block).unwrap_or_default()
+    || children_ids(side, side_id)
+      .iter()
+      .any(|child_id| !side_to_base.contains_key(child_id.as_str()))
+}
+
+/// Matches the blocks of a version of the document with the blocks of base: the page blocks, the
+/// blocks with the same id, then the blocks with the same type and a similar content. Only the
+/// unmatched blocks of the same parent are compared, the parents are matched first.
+fn match_blocks<'a>(base: &'a DocumentData, side: &'a DocumentData) -> HashMap<&'a str, &'a str> {
+  let mut matched = HashMap::from([(side.page_id.as_str(), base.page_id.as_str())]);
+  let mut used = HashSet::from([base.page_id.as_str()]);
+  let side_order = block_order(side);
+  for id in &side_order {
+    if *id == side.page_id {
+      continue;
+    }
+    if let Some((base_id, _)) = base.blocks.get_key_value(*id) {
+      if used.insert(base_id.as_str()) {
+        matched.insert(*id, base_id.as_str());
+      }
+    }
+  }
+
+  // base parent id -> unmatched children
+  let mut candidates: HashMap<&str, Vec<(&Block, Vec<char>)>> = HashMap::new();
+  for id in block_order(base) {
+    if used.contains(id) {
+      continue;
+    }
+    let block = &base.blocks[id];
+    candidates
+      .entry(block.parent.as_str())
+      .or_default()
+      .push((block, plain_text(base, block)));
+  }
+  for id in side_order {
+    if matched.contains_key(id) {
+      continue;
+    }
+    let block = &side.blocks[id];
+    let Some(siblings) = matched
+      .get(block.parent.as_str())
+      .and_then(|parent| candidates.get(parent))
+    else {
+      continue;
+    };
+    let text = plain_text(side, block);
+    let best = siblings
+      .iter()
+      .filter(|(candidate, _)| candidate.ty == block.ty && !used.contains(candidate.id.as_str()))
+      .map(|(candidate, candidate_text)| {
+        // The candidates have the same parent
+        let score = 0.2
+          + 0.6 * similarity(candidate_text, &text)
+          + 0.2 * f64::from(u8::from(candidate.data == block.data));
+        (candidate, score)
+      })
+      .filter(|(_, score)| *score >= MATCH_THRESHOLD)
+      .max_by(|(_, a), (_, b)| a.total_cmp(b));
+    if let Some((candidate, _)) = best {
+      used.insert(candidate.id.as_str());
+      matched.insert(id, candidate.id.as_str());
+    }
+  }
+  matched
+}
+
+/// The ids of the blocks reachable from the page, parents before children.
+fn block_order(data: &DocumentData) -> Vec<&str> {
+  let mut order = vec![];
+  let mut visited = HashSet::new();
+  let mut stack = vec![data.page_id.as_str()];
+  while let Some(id) = stack.pop() {
+    if !data.blocks.contains_key(id) || !visited.insert(id) {
+      continue;
+    }
+    order.push(id);
+    stack.extend(children_ids(data, id).iter().rev().map(String::as_str));
+  }
+  order
+}
+
+fn children_ids<'a>(data: &'a DocumentData, block_id: &str) -> &'a [String] {
+  data
+    .blocks
+    .get(block_id)
+    .and_then(|block| data.meta.children_map.get(&block.children))
+    .map(Vec::as_slice)
+    .unwrap_or_default()
+}
+
+fn text_map_delta<'a>(data: &'a DocumentData, text_id: &str) -> Option<&'a str> {
+  data
+    .meta
+    .text_map
+    .as_ref()
+    .and_then(|text_map| text_map.get(text_id))
+    .map(String::as_str)
+}
+
+/// A character of a text with its attributes.
+#[derive(Debug, Clone, PartialEq)]
+struct TextUnit {
+  ch: char,
+  attributes: Option<Map<String, Value>>,
+}
+
+fn text_units(data: &DocumentData, block: &Block) -> Option<Vec<TextUnit>> {
+  let delta = text_map_delta(data, block.external_id.as_ref()?)?;
+  let ops = serde_json::from_str::<Vec<Value>>(delta).ok()?;
+  let mut units = vec![];
+  for op in ops {
+    let Some(text) = op.get(INSERT_KEY).and_then(Value::as_str) else {
+      continue;
+    };
+    let attributes = op
+      .get(ATTRIBUTES_KEY)
+      .and_then(Value::as_object)
+      .filter(|attributes| !attributes.is_empty())
+      .cloned();
+    units.extend(text.chars().map(|ch| TextUnit {
+      ch,
+      attributes: attributes.clone(),
+    }));
+  }
+  Some(units)
+}
+
+fn plain_text(data: &DocumentData, block: &Block) -> Vec<char> {
+  text_units(data, block)
+    .unwrap_or_default()
+    .into_iter()
+    .map(|unit| unit.ch)
+    .collect()
+}
+
+/// The ratio of the characters the texts have in common, between 0 and 1.
+fn similarity(a: &[char], b: &[char]) -> f64 {
+  if a.is_empty() && b.is_empty() {
+    return 1.0;
+  }
+  let common = diff(a, b)
+    .into_iter()
+    .filter_map(|op| match op {
+      DiffOp::Equal(len) => Some(len),
+      _ => None,
+    })
+    .sum::<usize>();
+  (2 * common) as f64 / (a.len() + b.len()) as f64
+}
+
+/// A replacement of `base[start..end]`.
+#[derive(Debug, Clone, PartialEq)]
+struct TextEdit {
+  start: usize,
+  end: usize,
+  replacement: Vec<TextUnit>,
+}
+
+fn text_edits(base: &[TextUnit], side: &[TextUnit]) -> Vec<TextEdit> {
+  let mut edits: Vec<TextEdit> = vec![];
+  let (mut base_index, mut side_index) = (0, 0);
+  let mut last_op_is_edit = false;
+  for op in diff(base, side) {
+    if let DiffOp::Equal(len) = op {
+      base_index += len;
+      side_index += len;
+      last_op_is_edit = false;
+      continue;
+    }
+    if !last_op_is_edit {
+      edits.push(TextEdit {
+        start: base_index,
+        end: base_index,
+        replacement: vec![],
+      });
+      last_op_is_edit = true;
+    }
+    let Some(edit) = edits.last_mut() else {
+      continue;
+    };
+    if let DiffOp::Delete(len) = op {
+      base_index += len;
+      edit.end = base_index;
+    } else if let DiffOp::Insert(len) = op {
+      edit
+        .replacement
+        .extend_from_slice(&side[side_index..side_index + len]);
+      side_index += len;
+    }
+  }
+  edits
+}
+
+/// Merges the edits of ours and theirs on the base text. The overlapping edits conflict unless
+/// they are identical, the edits of ours are kept. Returns the merged text and whether there was a
+/// conflict.
+fn merge_text(base: &[TextUnit], ours: &[TextUnit], theirs: &[TextUnit]) -> (Vec<TextUnit>, bool) {
+  if ours == base || ours == theirs {
+    return (theirs.to_vec(), false);
+  }
+  if theirs == base {
+    return (ours.to_vec(), false);
+  }
+
+  let mut edits = text_edits(base, ours)
+    .into_iter()
+    .map(|edit| (true, edit))
+    .chain(
+      text_edits(base, theirs)
+        .into_iter()
+        .map(|edit| (false, edit)),
+    )
+    .collect::<Vec<_>>();
+  edits.sort_by_key(|(is_ours, edit)| (edit.start, edit.end, !is_ours));
+
+  // Group the overlapping edits
+  let mut groups: Vec<Vec<(bool, TextEdit)>> = vec![];
+  let mut group_end = 0;
+  for (is_ours, edit) in edits {
+    let overlaps = groups.last().is_some_and(|group| {
+      let last_start = group.last().map(|(_, edit)| edit.start);
+      edit.start < group_end || Some(edit.start) == last_start
+    });
+    if overlaps {
+      group_end = group_end.max(edit.end);
+      groups.last_mut().unwrap().push((is_ours, edit));
+    } else {
+      group_end = edit.end;
+      groups.push(vec![(is_ours, edit)]);
+    }
+  }
+
+  let mut conflict = false;
+  let mut applied = vec![];
+  for group in groups {
+    let (ours_edits, theirs_edits): (Vec<_>, Vec<_>) =
+      group.into_iter().partition(|(is_ours, _)| *is_ours);
+    let ours_edits = ours_edits
+      .into_iter()
+      .map(|(_, edit)| edit)
+      .collect::<Vec<_>>();
+    let theirs_edits = theirs_edits
+      .into_iter()
+      .map(|(_, edit)| edit)
+      .collect::<Vec<_>>();
+    if ours_edits.is_empty() {
+      applied.extend(theirs_edits);
+    } else {
+      conflict |= !theirs_edits.is_empty() && theirs_edits != ours_edits;
+      applied.extend(ours_edits);
+    }
+  }
+
+  let mut text = vec![];
+  let mut index = 0;
+  for edit in applied {
+    text.extend_from_slice(&base[index..edit.start]);
+    text.extend(edit.replacement);
+    index = edit.end;
+  }
+  text.extend_from_slice(&base[index..]);
+  (text, conflict)
+}
+
+/// The delta that turns the `from` text into the `to` text. The lengths are in UTF-16 code units
+/// like the document texts.
+fn text_delta(from: &[TextUnit], to: &[TextUnit]) -> String {
+  let utf16_len = |units: &[TextUnit]| units.iter().map(|unit| unit.ch.len_utf16()).sum::<usize>();
+  let mut ops = vec![];
+  let (mut from_index, mut to_index) = (0, 0);
+  for op in diff(from, to) {
+    match op {
+      DiffOp::Equal(len) => {
+        ops.push(json!({ "retain": utf16_len(&from[from_index..from_index + len]) }));
+        from_index += len;
+        to_index += len;
+      },
+      DiffOp::Delete(len) => {
+        ops.push(json!({ "delete": utf16_len(&from[from_index..from_index + len]) }));
+        from_index += len;
+      },
+      DiffOp::Insert(len) => {
+        for chunk in to[to_index..to_index + len].chunk_by(|a, b| a.attributes == b.attributes) {
+          let mut op = Map::new();
+          op.insert(
+            INSERT_KEY.to_string(),
+            Value::String(chunk.iter().map(|unit| unit.ch).collect()),
+          );
+          if let Some(attributes) = &chunk[0].attributes {
+            op.insert(
+              ATTRIBUTES_KEY.to_string(),
+              Value::Object(attributes.clone()),
+            );
+          }
+          ops.push(Value::Object(op));
+        }
+        to_index += len;
+      },
+    }
+  }
+  if ops.last().is_some_and(|op| op.get("retain").is_some()) {
+    ops.pop();
+  }
+  Value::Array(ops).to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffOp {
+  Equal(usize),
+  Delete(usize),
+  Insert(usize),
+}
+
+/// The shortest edit script from `a` to `b`, computed with the Myers algorithm. The ops of the
+/// same kind are grouped.
+fn diff<T: PartialEq>(a: &[T], b: &[T]) -> Vec<DiffOp> {
+  let prefix = a.iter().zip(b).take_while(|(a, b)| a == b).count();
+  let suffix = a[prefix..]
+    .iter()
+    .rev()
+    .zip(b[prefix..].iter().rev())
+    .take_while(|(a, b)| a == b)
+    .count();
+  let middle_a = &a[prefix..a.len() - suffix];
+  let middle_b = &b[prefix..b.len() - suffix];
+
+  let mut ops = vec![DiffOp::Equal(prefix)];
+  match myers_diff(middle_a, middle_b) {
+    Some(middle_ops) => ops.extend(middle_ops),
+    None => ops.extend([
+      DiffOp::Delete(middle_a.len()),
+      DiffOp::Insert(middle_b.len()),
+    ]),
+  }
+  ops.push(DiffOp::Equal(suffix));
+
+  let mut grouped: Vec<DiffOp> = vec![];
+  for op in ops {
+    match (grouped.last_mut(), op) {
+      (_, DiffOp::Equal(0) | DiffOp::Delete(0) | DiffOp::Insert(0)) => {},
+      (Some(DiffOp::Equal(len)), DiffOp::Equal(n))
+      | (Some(DiffOp::Delete(len)), DiffOp::Delete(n))
+      | (Some(DiffOp::Insert(len)), DiffOp::Insert(n)) => *len += n,
+      _ => grouped.push(op),
+    }
+  }
+  grouped
+}
+
+/// Returns `None` if the texts need more than [MAX_EDIT_DISTANCE] edits.
+fn myers_diff<T: PartialEq>(a: &[T], b: &[T]) -> Option<Vec<DiffOp>> {
+  let (n, m) = (a.len() as isize, b.len() as isize);
+  let max = (n + m).min(MAX_EDIT_DISTANCE as isize);
+  let offset = max + 1;
+  let mut v = vec![0isize; 2 * offset as usize + 1];
+  // trace[d] is the furthest x of each diagonal k in -d..=d before the step d
+  let mut trace: Vec<Vec<isize>> = vec![];
+  let index = |k: isize| (k + offset) as usize;
+
+  for d in 0..=max {
+    trace.push(v[index(-d)..=index(d)].to_vec());
+    for k in (-d..=d).step_by(2) {
+      let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+        v[index(k + 1)]
+      } else {
+        v[index(k - 1)] + 1
+      };
+      let mut y = x - k;
+      while x < n && y < m && a[x as usize] == b[y as usize] {
+        x += 1;
+        y += 1;
+      }
+      v[index(k)] = x;
+      if x >= n && y >= m {
+        return Some(backtrack(&trace, n, m));
+      }
+    }
+  }
+  None
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<DiffOp> {
+  let mut ops = vec![];
+  let (mut x, mut y) = (n, m);
+  for (d, v) in trace.iter().enumerate().rev() {
+    let d = d as isize;
+    let at = |k: isize| v[(k + d) as usize];
+    let k = x - y;
+    let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+      k + 1
+    } else {
+      k - 1
+    };
+    let prev_x = if d == 0 { 0 } else { at(prev_k) };
+    let prev_y = if d == 0 { 0 } else { prev_x - prev_k };
+    while x > prev_x && y > prev_y {
+      ops.push(DiffOp::Equal(1));
+      x -= 1;
+      y -= 1;
+    }
+    if d > 0 {
+      if x == prev_x {
+        ops.push(DiffOp::Insert(1));
+      } else {
+        ops.push(DiffOp::Delete(1));
+      }
+    }
+    x = prev_x;
+    y = prev_y;
+  }
+  ops.reverse();
+  ops
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use collab::preclude::ReadTxn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::blocks::{
+  Block, BlockEvent, BlockOperation, BlockType, DocumentData, TextDelta, TextOperation,
+  touched_block_ids,
+};
+
+const MENTION_KEY: &str = "mention";
+const CHECKED_KEY: &str = "checked";
+const IMAGES_KEY: &str = "images";
+
+/// The average reading speed used by [DocumentStats::reading_time].
+pub const DEFAULT_WORDS_PER_MINUTE: usize = 200;
+
+/// The statistics of a document. The page block itself is not counted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentStats {
+  /// The number of words, see [count_words].
+  pub word_count: usize,
+  /// The number of characters, without the whitespaces.
+  pub character_count: usize,
+  /// The number of characters, including the whitespaces.
+  pub character_count_with_spaces: usize,
+  pub block_count: usize,
+  /// block type -> number of blocks
+  pub block_counts: HashMap<String, usize>,
+  /// The number of images, an image gallery counts each of its images.
+  pub image_count: usize,
+  pub file_count: usize,
+  pub todo_count: usize,
+  pub unchecked_todo_count: usize,
+}
+
+impl DocumentStats {
+  pub fn from_document_data(data: &DocumentData) -> Self {
+    let text_map = data.meta.text_map.as_ref();
+    let mut stats = Self::default();
+    for block in data.blocks.values() {
+      let delta = block
+        .external_id
+        .as_ref()
+        .and_then(|external_id| text_map?.get(external_id))
+        .and_then(|delta| serde_json::from_str::<Vec<TextDelta>>(delta).ok());
+      stats.add(&BlockStats::new(block, delta.as_deref()));
+    }
+    stats
+  }
+
+  /// The time to read the document at [DEFAULT_WORDS_PER_MINUTE].
+  pub fn reading_time(&self) -> Duration {
+    self.reading_time_with_speed(DEFAULT_WORDS_PER_MINUTE)
+  }
+
+  /// The time to read the document, rounded up to the second.
+  pub fn reading_time_with_speed(&self, words_per_minute: usize) -> Duration {
+    let words_per_minute = words_per_minute.max(1) as u64;
+    Duration::from_secs((self.word_count as u64 * 60).div_ceil(words_per_minute))
+  }
+
+  fn add(&mut self, block: &BlockStats) {
+    let Some(ty) = &block.ty else {
+      return;
+    };
+    self.word_count += block.word_count;
+    self.character_count += block.character_count;
+    self.character_count_with_spaces += block.character_count_with_spaces;
+    self.block_count += 1;
+    *self.block_counts.entry(ty.clone()).or_default() += 1;
+    self.image_count += block.image_count;
+    self.file_count += block.file_count;
+    self.todo_count += block.todo_count;
+    self.unchecked_todo_count += block.unchecked_todo_count;
+  }
+
+  fn remove(&mut self, block: &BlockStats) {
+    let Some(ty) = &block.ty else {
+      return;
+    };
+    self.word_count -= block.word_count;
+    self.character_count -= block.character_count;
+    self.character_count_with_spaces -= block.character_count_with_spaces;
+    self.block_count -= 1;
+    if let Some(count) = self.block_counts.get_mut(ty) {
+      *count -= 1;
+      if *count == 0 {
+        self.block_counts.remove(ty);
+      }
+    }
+    self.image_count -= block.image_count;
+    self.file_count -= block.file_count;
+    self.todo_count -= block.todo_count;
+    self.unchecked_todo_count -= block.unchecked_todo_count;
+  }
+}
+
+/// The contribution of a block to the [DocumentStats].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct BlockStats {
+  /// `None` for the page block, which is not counted.
+  ty: Option<String>,
+  word_count: usize,
+  character_count: usize,
+  character_count_with_spaces: usize,
+  image_count: usize,
+  file_count: usize,
+  todo_count: usize,
+  unchecked_todo_count: usize,
+}
+
+impl BlockStats {
+  fn new(block: &Block, delta: Option<&[TextDelta]>) -> Self {
+    let block_type = BlockType::from_block_ty(&block.ty);
+    if block_type == BlockType::Page {
+      return Self::default();
+    }
+
+    // The mentions are rendered from their attributes, the inserted text is a placeholder
+    let text = delta
+      .unwrap_or_default()
+      .iter()
+      .filter_map(|delta| match delta {
+        TextDelta::Inserted(text, attrs)
+          if !attrs
+            .as_ref()
+            .is_some_and(|attrs| attrs.contains_key(MENTION_KEY)) =>
+        {
+          Some(text.as_str())
+        },
+        _ => None,
+      })
+      .collect::<String>();
+
+    let is_todo = block_type == BlockType::TodoList;
+    let checked = block.data.get(CHECKED_KEY).and_then(Value::as_bool) == Some(true);
+    Self {
+      ty: Some(block.ty.clone()),
+      word_count: count_words(&text),
+      character_count: text.chars().filter(|c| !c.is_whitespace()).count(),
+      character_count_with_spaces: text.chars().count(),
+      image_count: match block_type {
+        BlockType::Image => 1,
+        BlockType::MultiImage => block
+          .data
+          .get(IMAGES_KEY)
+          .and_then(Value::as_array)
+          .map(|images| images.len())
+          .unwrap_or_default(),
+        _ => 0,
+      },
+      file_count: usize::from(block_type == BlockType::File),
+      todo_count: usize::from(is_todo),
+      unchecked_todo_count: usize::from(is_todo && !checked),
+    }
+  }
+}
+
+/// Counts the words of the text.
+///
+/// The languages written without spaces can't be split without a dictionary, so each Chinese
+/// character, hiragana and katakana counts as a word. Korean separates its words with spaces and
+/// is counted like the latin scripts. A word may contain an apostrophe or a hyphen, `don't` and
+/// `well-known` are one word.
+pub fn count_words(text: &str) -> usize {
+  let mut count = 0;
+  let mut in_word = false;
+  let mut chars = text.chars().peekable();
+  while let Some(c) = chars.next() {
+    if is_cjk(c) {
+      count += 1;
+      in_word = false;
+    } else if c.is_alphanumeric() {
+      if !in_word {
+        count += 1;
+        in_word = true;
+      }
+    } else if in_word
+      && matches!(c, '\'' | '’' | '-')
+      && chars
+        .peek()
+        .is_some_and(|next| next.is_alphanumeric() && !is_cjk(*next))
+    {
+      // The word goes on
+    } else {
+      in_word = false;
+    }
+  }
+  count
+}
+
+fn is_cjk(c: char) -> bool {
+  matches!(c,
+    '\u{3040}'..='\u{309F}' // Hiragana
+    | '\u{30A0}'..='\u{30FF}' // Katakana
+    | '\u{3400}'..='\u{4DBF}' // CJK Unified Ideographs Extension A
+    | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+    | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+    | '\u{FF66}'..='\u{FF9F}' // Halfwidth Katakana
+    | '\u{20000}'..='\u{2FA1F}' // CJK Unified Ideographs Extension B..F and supplement
+  )
+}
+
+/// Keeps the [DocumentStats] of a document up to date from its block events. Only the blocks
+/// touched by the events are read again.
+pub(crate) struct StatsTracker {
+  block_operation: BlockOperation,
+  text_operation: TextOperation,
+  /// text id -> block id
+  text_owners: HashMap<String, String>,
+  blocks: HashMap<String, BlockStats>,
+  stats: DocumentStats,
+}
+
+impl StatsTracker {
+  pub(crate) fn new<T: ReadTxn>(
+    txn: &T,
+    block_operation: BlockOperation,
+    text_operation: TextOperation,
+  ) -> Self {
+    let mut tracker = Self {
+      block_operation,
+      text_operation,
+      text_owners: HashMap::new(),
+      blocks: HashMap::new(),
+      stats: DocumentStats::default(),
+    };
+    for block in tracker.block_operation.get_all_blocks(txn).into_values() {
+      tracker.set_block(txn, &block);
+    }
+    tracker
+  }
+
+  /// Returns the new stats if the events changed them.
+  pub(crate) fn update<T: ReadTxn>(
+    &mut self,
+    txn: &T,
+    events: &[BlockEvent],
+  ) -> Option<DocumentStats> {
+    let old_stats = self.stats.clone();
+    for block_id in touched_block_ids(events, &self.text_owners) {
+      match self.block_operation.get_block_with_txn(txn, &block_id) {
+        Some(block) => self.set_block(txn, &block),
+        None => {
+          if let Some(old) = self.blocks.remove(&block_id) {
+            self.stats.remove(&old);
+          }
+          self.text_owners.retain(|_, owner| owner != &block_id);
+        },
+      }
+    }
+    (self.stats != old_stats).then(|| self.stats.clone())
+  }
+
+  fn set_block<T: ReadTxn>(&mut self, txn: &T, block: &Block) {
+    let delta = block.external_id.as_ref().and_then(|external_id| {
+      self
+        .text_owners
+        .insert(external_id.clone(), block.id.clone());
+      self.text_operation.get_delta_with_txn(txn, external_id)
+    });
+    let block_stats = BlockStats::new(block, delta.as_deref());
+    self.stats.add(&block_stats);
+    if let Some(old) = self.blocks.insert(block.id.clone(), block_stats) {
+      self.stats.remove(&old);
+    }
+  }
+}
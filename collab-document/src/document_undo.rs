@@ -0,0 +1,326 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use collab::core::collab::DEFAULT_UNDO_CAPTURE_TIMEOUT_MILLIS;
+use collab::core::origin::CollabOrigin;
+use collab::preclude::branch::{Branch, BranchPtr};
+use collab::preclude::undo::{Options, UndoManager};
+use collab::preclude::{Doc, MapRef, ReadTxn, Subscription, TransactionMut};
+use serde::{Deserialize, Serialize};
+
+use crate::blocks::{BlockOperation, ChildrenOperation, TextOperation};
+use crate::document_awareness::BlockTextPosition;
+
+/// The selection of the actor when an undo step was recorded. Restore it after undoing or
+/// redoing the step.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UndoSelection {
+  pub start: BlockTextPosition,
+  pub end: BlockTextPosition,
+}
+
+/// The metadata of an undo step.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UndoStackMeta {
+  /// The selection set with [crate::document::Document::set_undo_selection] before the first
+  /// change of the step.
+  pub selection: Option<UndoSelection>,
+}
+
+/// Options of an undo scope, see [crate::document::Document::add_undo_scope].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoScopeOptions {
+  /// Only the changes made with these origins are undone. When it's empty, the changes made with
+  /// the origin of the document are undone.
+  ///
+  /// Use [crate::document::Document::with_origin] to make changes on behalf of an actor.
+  pub tracked_origins: Vec<CollabOrigin>,
+  /// The changes made within this period after the previous one are undone in a single step.
+  pub capture_timeout_millis: u64,
+  /// When set, only the transactions that change this block or its descendants, and no other
+  /// block, are undone.
+  pub block_id: Option<String>,
+}
+
+impl Default for UndoScopeOptions {
+  fn default() -> Self {
+    Self {
+      tracked_origins: vec![],
+      capture_timeout_millis: DEFAULT_UNDO_CAPTURE_TIMEOUT_MILLIS,
+      block_id: None,
+    }
+  }
+}
+
+impl UndoScopeOptions {
+  pub fn new(origin: CollabOrigin) -> Self {
+    Self {
+      tracked_origins: vec![origin],
+      ..Default::default()
+    }
+  }
+
+  pub fn with_origin(mut self, origin: CollabOrigin) -> Self {
+    self.tracked_origins.push(origin);
+    self
+  }
+
+  pub fn with_capture_timeout(mut self, capture_timeout_millis: u64) -> Self {
+    self.capture_timeout_millis = capture_timeout_millis;
+    self
+  }
+
+  pub fn with_block(mut self, block_id: &str) -> Self {
+    self.block_id = Some(block_id.to_string());
+    self
+  }
+}
+
+/// A block and its descendants, used to decide which transactions an undo scope captures.
+pub(crate) struct BlockSubtree {
+  pub block_id: String,
+  pub block_operation: BlockOperation,
+  pub children_operation: ChildrenOperation,
+  pub text_operation: TextOperation,
+  /// The maps that hold the blocks, children and texts. Adding or removing a block changes them.
+  pub containers: HashSet<BranchPtr>,
+  /// The branches of the subtree, computed again after a transaction that may have changed the
+  /// shape of the subtree.
+  branches: Mutex<Option<SubtreeBranches>>,
+}
+
+struct SubtreeBranches {
+  /// The block maps and the children arrays of the subtree.
+  structure: HashSet<BranchPtr>,
+  texts: HashSet<BranchPtr>,
+}
+
+impl SubtreeBranches {
+  fn contains(&self, branch: &BranchPtr) -> bool {
+    self.structure.contains(branch) || self.texts.contains(branch)
+  }
+}
+
+impl BlockSubtree {
+  pub fn new(
+    block_id: String,
+    block_operation: BlockOperation,
+    children_operation: ChildrenOperation,
+    text_operation: TextOperation,
+    containers: HashSet<BranchPtr>,
+  ) -> Self {
+    Self {
+      block_id,
+      block_operation,
+      children_operation,
+      text_operation,
+      containers,
+      branches: Mutex::new(None),
+    }
+  }
+
+  /// The block maps, children arrays and texts of the subtree.
+  fn branches<T: ReadTxn>(&self, txn: &T) -> SubtreeBranches {
+    let mut branches = SubtreeBranches {
+      structure: HashSet::new(),
+      texts: HashSet::new(),
+    };
+    let mut visited = HashSet::new();
+    let mut stack = vec![self.block_id.clone()];
+    while let Some(block_id) = stack.pop() {
+      if !visited.insert(block_id.clone()) {
+        continue;
+      }
+      let Some(block) = self.block_operation.get_block_with_txn(txn, &block_id) else {
+        continue;
+      };
+      if let Some(map) = self.block_operation.get_block_map_with_txn(txn, &block_id) {
+        branches.structure.insert(BranchPtr::from(map.as_ref()));
+      }
+      if let Some(text_id) = &block.external_id {
+        if let Some(text) = self.text_operation.get_text_ref_with_txn(txn, text_id) {
+          branches
+            .texts
+            .insert(BranchPtr::from(AsRef::<Branch>::as_ref(&text)));
+        }
+      }
+      if let Some(children) = self
+        .children_operation
+        .get_children_ref_with_txn(txn, &block.children)
+      {
+        branches
+          .structure
+          .insert(BranchPtr::from(children.as_ref()));
+      }
+      stack.extend(
+        self
+          .children_operation
+          .get_children(txn, &block.children)
+          .into_iter()
+          .map(|child_id| child_id.to_string(txn)),
+      );
+    }
+    branches
+  }
+
+  /// Editing the texts doesn't change the shape of the subtree, the branches are only computed
+  /// again when a block map, a children array or a container was changed.
+  fn captures(&self, txn: &TransactionMut) -> bool {
+    let changed = txn.changed_parent_types();
+    let mut cached = match self.branches.lock() {
+      Ok(cached) => cached,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    let stale = cached.as_ref().is_none_or(|branches| {
+      changed
+        .iter()
+        .any(|branch| branches.structure.contains(branch) || self.containers.contains(branch))
+    });
+    if stale {
+      *cached = Some(self.branches(txn));
+    }
+    let Some(branches) = cached.as_ref() else {
+      return false;
+    };
+    changed.iter().any(|branch| branches.contains(branch))
+      && changed
+        .iter()
+        .all(|branch| branches.contains(branch) || self.containers.contains(branch))
+  }
+}
+
+/// An [UndoManager] with its own stacks, that only captures the changes of some origins.
+pub(crate) struct UndoScope {
+  manager: UndoManager<UndoStackMeta>,
+  /// The current selection of the actor, recorded in the new undo steps.
+  selection: Arc<Mutex<Option<UndoSelection>>>,
+  /// The metadata of the step being undone or redone, moved to the step pushed on the other stack.
+  pending: Arc<Mutex<Option<UndoStackMeta>>>,
+  /// The metadata of the last step that was undone or redone.
+  popped: Arc<Mutex<Option<UndoStackMeta>>>,
+  #[allow(dead_code)]
+  subscriptions: Vec<Subscription>,
+}
+
+impl UndoScope {
+  pub fn new(
+    doc: &Doc,
+    scope: &MapRef,
+    options: UndoScopeOptions,
+    document_origin: &CollabOrigin,
+    subtree: Option<BlockSubtree>,
+  ) -> Self {
+    let capture_transaction = subtree.map(|subtree| {
+      Arc::new(move |txn: &TransactionMut| subtree.captures(txn))
+        as Arc<dyn Fn(&TransactionMut) -> bool + Send + Sync>
+    });
+    let mut manager = UndoManager::with_scope_and_options(
+      doc,
+      scope,
+      Options {
+        capture_timeout_millis: options.capture_timeout_millis,
+        capture_transaction,
+        ..Default::default()
+      },
+    );
+    if options.tracked_origins.is_empty() {
+      manager.include_origin(document_origin.clone());
+    }
+    for origin in options.tracked_origins {
+      manager.include_origin(origin);
+    }
+
+    let selection = Arc::new(Mutex::new(None::<UndoSelection>));
+    let pending = Arc::new(Mutex::new(None::<UndoStackMeta>));
+    let popped = Arc::new(Mutex::new(None::<UndoStackMeta>));
+    let added = {
+      let selection = selection.clone();
+      let pending = pending.clone();
+      manager.observe_item_added(move |_, event| {
+        let pending = match pending.lock() {
+          Ok(mut pending) => pending.take(),
+          Err(_) => None,
+        };
+        let meta = pending.unwrap_or_else(|| UndoStackMeta {
+          selection: match selection.lock() {
+            Ok(selection) => selection.clone(),
+            Err(_) => None,
+          },
+        });
+        *event.meta_mut() = meta;
+      })
+    };
+    let popped_subscription = {
+      let popped = popped.clone();
+      manager.observe_item_popped(move |_, event| {
+        if let Ok(mut popped) = popped.lock() {
+          *popped = Some(event.meta().clone());
+        }
+      })
+    };
+
+    Self {
+      manager,
+      selection,
+      pending,
+      popped,
+      subscriptions: vec![added, popped_subscription],
+    }
+  }
+
+  pub fn set_selection(&self, selection: Option<UndoSelection>) {
+    if let Ok(mut current) = self.selection.lock() {
+      *current = selection;
+    }
+  }
+
+  pub fn can_undo(&self) -> bool {
+    self.manager.can_undo()
+  }
+
+  pub fn can_redo(&self) -> bool {
+    self.manager.can_redo()
+  }
+
+  /// The next change starts a new undo step, even within the capture timeout.
+  pub fn reset(&mut self) {
+    self.manager.reset();
+  }
+
+  pub fn undo(&mut self) -> Option<UndoStackMeta> {
+    let meta = self
+      .manager
+      .undo_stack()
+      .last()
+      .map(|item| item.meta.clone());
+    if let Ok(mut pending) = self.pending.lock() {
+      *pending = meta;
+    }
+    let changed = self.manager.undo_blocking();
+    self.take_popped(changed)
+  }
+
+  pub fn redo(&mut self) -> Option<UndoStackMeta> {
+    let meta = self
+      .manager
+      .redo_stack()
+      .last()
+      .map(|item| item.meta.clone());
+    if let Ok(mut pending) = self.pending.lock() {
+      *pending = meta;
+    }
+    let changed = self.manager.redo_blocking();
+    self.take_popped(changed)
+  }
+
+  fn take_popped(&self, changed: bool) -> Option<UndoStackMeta> {
+    if let Ok(mut pending) = self.pending.lock() {
+      pending.take();
+    }
+    let popped = match self.popped.lock() {
+      Ok(mut popped) => popped.take(),
+      Err(_) => None,
+    };
+    if changed { popped } else { None }
+  }
+}
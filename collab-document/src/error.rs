@@ -1,5 +1,8 @@
 use collab_entity::CollabValidateError;
 
+use crate::blocks::BlockDataError;
+use crate::template::TemplateError;
+
 #[derive(Debug, thiserror::Error)]
 pub enum DocumentError {
   #[error(transparent)]
@@ -58,6 +61,37 @@ pub enum DocumentError {
 
   #[error("Unable to find the page block")]
   PageBlockNotFound,
+
+  #[error("The comment thread is not found")]
+  CommentThreadNotFound,
+
+  #[error("The comment is not found")]
+  CommentNotFound,
+
+  #[error("Invalid comment range: {0}")]
+  InvalidCommentRange(String),
+
+  #[error("The suggestion is not found")]
+  SuggestionNotFound,
+
+  #[error("Invalid data of {block_type} block {block_id}: {error}")]
+  InvalidBlockData {
+    block_id: String,
+    block_type: String,
+    error: BlockDataError,
+  },
+
+  #[error(transparent)]
+  InvalidTemplate(#[from] TemplateError),
+
+  #[error("Unsupported nested document version: {0}")]
+  UnsupportedNestedDocumentVersion(u32),
+
+  #[error("Invalid nested document: {0}")]
+  InvalidNestedDocument(String),
+
+  #[error("The undo scope is not found: {0}")]
+  UndoScopeNotFound(String),
 }
 
 impl From<CollabValidateError> for DocumentError {
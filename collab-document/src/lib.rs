@@ -1,8 +1,16 @@
 pub mod block_parser;
 pub mod blocks;
+pub mod comment;
 pub mod document;
 pub mod document_awareness;
 pub mod document_data;
+pub mod document_merge;
 pub mod document_remapper;
+pub mod document_stats;
+pub mod document_undo;
 pub mod error;
 pub mod importer;
+pub mod link_graph;
+pub mod nested_json;
+pub mod suggestion;
+pub mod template;
@@ -0,0 +1,368 @@
+use std::collections::{HashMap, HashSet};
+
+use collab::preclude::{Any, Attrs, ReadTxn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::blocks::{
+  Block, BlockEvent, BlockOperation, BlockType, DocumentData, TextDelta, TextOperation,
+  touched_block_ids,
+};
+
+const MENTION_KEY: &str = "mention";
+const MENTION_PAGE_ID_KEY: &str = "page_id";
+const SUB_PAGE_VIEW_ID_KEY: &str = "viewId";
+const DATABASE_VIEW_ID_KEY: &str = "view_id";
+const URL_KEY: &str = "url";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum DocumentLinkKind {
+  /// A page mentioned in the text of a block.
+  PageMention,
+  /// A [BlockType::SubPage] block.
+  SubPage,
+  /// An inline grid, board or calendar.
+  InlineDatabase,
+  /// The url of a [BlockType::LinkPreview] block.
+  LinkPreview,
+}
+
+/// An outgoing reference of a block. The target is a view id, or a url for
+/// [DocumentLinkKind::LinkPreview].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DocumentLink {
+  pub block_id: String,
+  pub kind: DocumentLinkKind,
+  pub target: String,
+}
+
+impl DocumentLink {
+  pub fn view_id(&self) -> Option<&str> {
+    match self.kind {
+      DocumentLinkKind::LinkPreview => None,
+      _ => Some(&self.target),
+    }
+  }
+}
+
+/// Returns the links of the block, the ones stored in its data first, then the page mentions of
+/// its text in order.
+pub fn extract_block_links(block: &Block, delta: Option<&[TextDelta]>) -> Vec<DocumentLink> {
+  let mut links = vec![];
+  let data_link = match BlockType::from_block_ty(&block.ty) {
+    BlockType::SubPage => {
+      data_string(block, SUB_PAGE_VIEW_ID_KEY).map(|v| (DocumentLinkKind::SubPage, v))
+    },
+    BlockType::Grid | BlockType::Board | BlockType::Calendar => {
+      data_string(block, DATABASE_VIEW_ID_KEY).map(|v| (DocumentLinkKind::InlineDatabase, v))
+    },
+    BlockType::LinkPreview => {
+      data_string(block, URL_KEY).map(|v| (DocumentLinkKind::LinkPreview, v))
+    },
+    _ => None,
+  };
+  if let Some((kind, target)) = data_link {
+    links.push(DocumentLink {
+      block_id: block.id.clone(),
+      kind,
+      target,
+    });
+  }
+
+  for delta in delta.unwrap_or_default() {
+    if let Some(page_id) = mentioned_page_id(delta) {
+      links.push(DocumentLink {
+        block_id: block.id.clone(),
+        kind: DocumentLinkKind::PageMention,
+        target: page_id,
+      });
+    }
+  }
+  links
+}
+
+/// Returns the links of every block in the document, ordered by block id.
+pub fn extract_document_links(data: &DocumentData) -> Vec<DocumentLink> {
+  let text_map = data.meta.text_map.as_ref();
+  let mut blocks = data.blocks.values().collect::<Vec<_>>();
+  blocks.sort_by(|a, b| a.id.cmp(&b.id));
+  blocks
+    .into_iter()
+    .flat_map(|block| {
+      let delta = block
+        .external_id
+        .as_ref()
+        .and_then(|external_id| text_map?.get(external_id))
+        .and_then(|delta| serde_json::from_str::<Vec<TextDelta>>(delta).ok());
+      extract_block_links(block, delta.as_deref())
+    })
+    .collect()
+}
+
+fn data_string(block: &Block, key: &str) -> Option<String> {
+  block
+    .data
+    .get(key)
+    .and_then(|v| v.as_str())
+    .filter(|v| !v.is_empty())
+    .map(|v| v.to_string())
+}
+
+fn mentioned_page_id(delta: &TextDelta) -> Option<String> {
+  let TextDelta::Inserted(_, Some(attrs)) = delta else {
+    return None;
+  };
+  mentioned_page_id_from_attrs(attrs)
+}
+
+/// Returns the page id of the mention stored in the attributes of a text delta.
+///
+/// The mention attribute is a map, or a json string when it was written by
+/// [crate::document_remapper::DocumentCollabRemapper].
+pub fn mentioned_page_id_from_attrs(attrs: &Attrs) -> Option<String> {
+  let page_id = match attrs.get(MENTION_KEY)? {
+    Any::Map(mention) => match mention.get(MENTION_PAGE_ID_KEY)? {
+      Any::String(page_id) => page_id.to_string(),
+      _ => return None,
+    },
+    Any::String(mention) => serde_json::from_str::<Value>(mention)
+      .ok()?
+      .get(MENTION_PAGE_ID_KEY)?
+      .as_str()?
+      .to_string(),
+    _ => return None,
+  };
+  (!page_id.is_empty()).then_some(page_id)
+}
+
+/// The new links of the blocks whose links changed. A deleted block, or a block that lost all its
+/// links, maps to an empty list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentLinksChange {
+  pub document_id: String,
+  pub blocks: HashMap<String, Vec<DocumentLink>>,
+}
+
+/// A block that links to a view.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Backlink {
+  pub document_id: String,
+  pub block_id: String,
+  pub kind: DocumentLinkKind,
+}
+
+/// Index of the links between documents.
+///
+/// Feed it with [LinkIndex::index_document] when a document is opened, then keep it up to date
+/// with the changes of [crate::document::Document::subscribe_link_changed].
+#[derive(Debug, Clone, Default)]
+pub struct LinkIndex {
+  /// document id -> block id -> links
+  outgoing: HashMap<String, HashMap<String, Vec<DocumentLink>>>,
+  /// view id -> backlinks
+  incoming: HashMap<String, HashSet<Backlink>>,
+}
+
+impl LinkIndex {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Replaces the links of the document.
+  pub fn index_document(&mut self, document_id: &str, data: &DocumentData) {
+    self.index_links(document_id, extract_document_links(data));
+  }
+
+  /// Replaces the links of the document.
+  pub fn index_links(&mut self, document_id: &str, links: Vec<DocumentLink>) {
+    self.remove_document(document_id);
+    let mut blocks: HashMap<String, Vec<DocumentLink>> = HashMap::new();
+    for link in links {
+      blocks.entry(link.block_id.clone()).or_default().push(link);
+    }
+    for (block_id, links) in blocks {
+      self.set_block_links(document_id, &block_id, links);
+    }
+  }
+
+  pub fn remove_document(&mut self, document_id: &str) {
+    if let Some(blocks) = self.outgoing.remove(document_id) {
+      for links in blocks.values() {
+        self.remove_incoming(document_id, links);
+      }
+    }
+  }
+
+  pub fn apply_change(&mut self, change: &DocumentLinksChange) {
+    for (block_id, links) in &change.blocks {
+      self.set_block_links(&change.document_id, block_id, links.clone());
+    }
+  }
+
+  /// Replaces the links of the block. An empty list removes the block from the index.
+  pub fn set_block_links(&mut self, document_id: &str, block_id: &str, links: Vec<DocumentLink>) {
+    let blocks = self.outgoing.entry(document_id.to_string()).or_default();
+    let old_links = if links.is_empty() {
+      blocks.remove(block_id)
+    } else {
+      blocks.insert(block_id.to_string(), links.clone())
+    };
+    if blocks.is_empty() {
+      self.outgoing.remove(document_id);
+    }
+    if let Some(old_links) = old_links {
+      self.remove_incoming(document_id, &old_links);
+    }
+
+    for link in links {
+      if let Some(view_id) = link.view_id() {
+        self
+          .incoming
+          .entry(view_id.to_string())
+          .or_default()
+          .insert(Backlink {
+            document_id: document_id.to_string(),
+            block_id: link.block_id.clone(),
+            kind: link.kind,
+          });
+      }
+    }
+  }
+
+  /// Returns the blocks that link to the view, sorted by document and block id.
+  pub fn backlinks(&self, view_id: &str) -> Vec<Backlink> {
+    let mut backlinks = self
+      .incoming
+      .get(view_id)
+      .map(|backlinks| backlinks.iter().cloned().collect::<Vec<_>>())
+      .unwrap_or_default();
+    backlinks.sort();
+    backlinks
+  }
+
+  /// Returns the ids of the documents that link to the view.
+  pub fn linking_documents(&self, view_id: &str) -> Vec<String> {
+    let mut document_ids = self
+      .backlinks(view_id)
+      .into_iter()
+      .map(|backlink| backlink.document_id)
+      .collect::<Vec<_>>();
+    document_ids.dedup();
+    document_ids
+  }
+
+  pub fn outgoing_links(&self, document_id: &str) -> Vec<DocumentLink> {
+    let mut links = self
+      .outgoing
+      .get(document_id)
+      .map(|blocks| blocks.values().flatten().cloned().collect::<Vec<_>>())
+      .unwrap_or_default();
+    links.sort_by(|a, b| a.block_id.cmp(&b.block_id));
+    links
+  }
+
+  fn remove_incoming(&mut self, document_id: &str, links: &[DocumentLink]) {
+    for link in links {
+      let Some(view_id) = link.view_id() else {
+        continue;
+      };
+      if let Some(backlinks) = self.incoming.get_mut(view_id) {
+        backlinks.retain(|backlink| {
+          backlink.document_id != document_id || backlink.block_id != link.block_id
+        });
+        if backlinks.is_empty() {
+          self.incoming.remove(view_id);
+        }
+      }
+    }
+  }
+}
+
+/// Tracks the links of a document from its block events. The text events only carry the change
+/// of a text, so the touched blocks are read again from the transaction.
+pub(crate) struct LinkTracker {
+  block_operation: BlockOperation,
+  text_operation: TextOperation,
+  /// text id -> block id
+  text_owners: HashMap<String, String>,
+  links: HashMap<String, Vec<DocumentLink>>,
+}
+
+impl LinkTracker {
+  pub(crate) fn new<T: ReadTxn>(
+    txn: &T,
+    block_operation: BlockOperation,
+    text_operation: TextOperation,
+  ) -> Self {
+    let mut text_owners = HashMap::new();
+    let mut links = HashMap::new();
+    for block in block_operation.get_all_blocks(txn).into_values() {
+      let block_links = block_links_with_txn(txn, &text_operation, &block);
+      if let Some(external_id) = &block.external_id {
+        text_owners.insert(external_id.clone(), block.id.clone());
+      }
+      if !block_links.is_empty() {
+        links.insert(block.id, block_links);
+      }
+    }
+    Self {
+      block_operation,
+      text_operation,
+      text_owners,
+      links,
+    }
+  }
+
+  /// Returns the blocks whose links changed.
+  pub(crate) fn update<T: ReadTxn>(
+    &mut self,
+    txn: &T,
+    events: &[BlockEvent],
+  ) -> HashMap<String, Vec<DocumentLink>> {
+    let block_ids = touched_block_ids(events, &self.text_owners);
+    let mut changes = HashMap::new();
+    for block_id in block_ids {
+      let links = match self.block_operation.get_block_with_txn(txn, &block_id) {
+        Some(block) => {
+          if let Some(external_id) = &block.external_id {
+            self
+              .text_owners
+              .insert(external_id.clone(), block.id.clone());
+          }
+          block_links_with_txn(txn, &self.text_operation, &block)
+        },
+        None => {
+          self.text_owners.retain(|_, owner| owner != &block_id);
+          vec![]
+        },
+      };
+      let old_links = self
+        .links
+        .get(&block_id)
+        .map(|v| v.as_slice())
+        .unwrap_or_default();
+      if old_links == links.as_slice() {
+        continue;
+      }
+      if links.is_empty() {
+        self.links.remove(&block_id);
+      } else {
+        self.links.insert(block_id.clone(), links.clone());
+      }
+      changes.insert(block_id, links);
+    }
+    changes
+  }
+}
+
+fn block_links_with_txn<T: ReadTxn>(
+  txn: &T,
+  text_operation: &TextOperation,
+  block: &Block,
+) -> Vec<DocumentLink> {
+  let delta = block
+    .external_id
+    .as_ref()
+    .and_then(|external_id| text_operation.get_delta_with_txn(txn, external_id));
+  extract_block_links(block, delta.as_deref())
+}
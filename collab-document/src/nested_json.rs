@@ -0,0 +1,230 @@
+//! A nested JSON representation of a document, for the tools that don't use collab.
+//!
+//! Unlike [DocumentData], the children of a block are stored inline, and so is the text delta of
+//! the block:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "document": {
+//!     "type": "page",
+//!     "data": {},
+//!     "children": [
+//!       {
+//!         "type": "heading",
+//!         "data": { "level": 1 },
+//!         "delta": [{ "insert": "Hello", "attributes": { "bold": true } }]
+//!       }
+//!     ]
+//!   }
+//! }
+//! ```
+//!
+//! The exported blocks keep their ids, so that importing the json again gives back the same
+//! [DocumentData]. The ids are optional when importing, the missing ones are generated. The blocks
+//! that are not reachable from the page block are not exported.
+//!
+//! The format is described by the schema returned by [nested_document_schema].
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::blocks::{Block, DocumentData, DocumentMeta};
+use crate::document_data::generate_id;
+use crate::error::DocumentError;
+
+/// The version written by [DocumentData::to_nested_json]. A json with a greater version is
+/// rejected by [DocumentData::from_nested_json].
+pub const NESTED_DOCUMENT_VERSION: u32 = 1;
+
+const TEXT_EXTERNAL_TYPE: &str = "text";
+const NESTED_DOCUMENT_SCHEMA: &str = include_str!("../schemas/nested_document.schema.json");
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NestedDocument {
+  pub version: u32,
+  /// The page block.
+  pub document: NestedBlock,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NestedBlock {
+  #[serde(rename = "type")]
+  pub ty: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub id: Option<String>,
+  #[serde(default)]
+  pub data: HashMap<String, Value>,
+  /// The text of the block, a list of delta operations.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub delta: Option<Value>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub children: Vec<NestedBlock>,
+  /// The key of the children in [DocumentMeta::children_map].
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub children_id: Option<String>,
+  /// The [Block::external_id] of the block, the key of its text in [DocumentMeta::text_map].
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub text_id: Option<String>,
+  /// The [Block::external_type] of the block, when it's not `text`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub external_type: Option<String>,
+}
+
+/// Returns the JSON schema of the nested document format.
+pub fn nested_document_schema() -> Value {
+  serde_json::from_str(NESTED_DOCUMENT_SCHEMA).expect("the nested document schema is valid json")
+}
+
+impl DocumentData {
+  pub fn to_nested_document(&self) -> Result<NestedDocument, DocumentError> {
+    let page = self
+      .blocks
+      .get(&self.page_id)
+      .ok_or(DocumentError::PageBlockNotFound)?;
+    let document = self
+      .to_nested_block(page, &mut HashSet::new())
+      .ok_or(DocumentError::PageBlockNotFound)?;
+    Ok(NestedDocument {
+      version: NESTED_DOCUMENT_VERSION,
+      document,
+    })
+  }
+
+  pub fn to_nested_json(&self) -> Result<Value, DocumentError> {
+    let document = self.to_nested_document()?;
+    serde_json::to_value(document).map_err(|_| DocumentError::ConvertDataError)
+  }
+
+  pub fn from_nested_document(document: NestedDocument) -> Result<Self, DocumentError> {
+    if document.version > NESTED_DOCUMENT_VERSION {
+      return Err(DocumentError::UnsupportedNestedDocumentVersion(
+        document.version,
+      ));
+    }
+
+    let mut builder = DocumentDataBuilder::default();
+    let page_id = builder.insert(document.document, "")?;
+    Ok(DocumentData {
+      page_id,
+      blocks: builder.blocks,
+      meta: DocumentMeta {
+        children_map: builder.children_map,
+        text_map: Some(builder.text_map),
+      },
+    })
+  }
+
+  pub fn from_nested_json(json: Value) -> Result<Self, DocumentError> {
+    let document = serde_json::from_value::<NestedDocument>(json)
+      .map_err(|err| DocumentError::InvalidNestedDocument(err.to_string()))?;
+    Self::from_nested_document(document)
+  }
+
+  /// A block that appears twice in the tree is only exported the first time, returns `None` when
+  /// the block was already exported.
+  fn to_nested_block(&self, block: &Block, visited: &mut HashSet<String>) -> Option<NestedBlock> {
+    if !visited.insert(block.id.clone()) {
+      return None;
+    }
+    let children = self
+      .meta
+      .children_map
+      .get(&block.children)
+      .into_iter()
+      .flatten()
+      .filter_map(|child_id| self.blocks.get(child_id))
+      .filter_map(|child| self.to_nested_block(child, visited))
+      .collect();
+
+    let delta = block.external_id.as_ref().and_then(|text_id| {
+      let delta = self.meta.text_map.as_ref()?.get(text_id)?;
+      Some(serde_json::from_str(delta).unwrap_or_else(|_| Value::String(delta.clone())))
+    });
+    Some(NestedBlock {
+      ty: block.ty.clone(),
+      id: Some(block.id.clone()),
+      data: block.data.clone(),
+      delta,
+      children,
+      children_id: Some(block.children.clone()),
+      text_id: block.external_id.clone(),
+      external_type: block
+        .external_type
+        .clone()
+        .filter(|ty| ty != TEXT_EXTERNAL_TYPE),
+    })
+  }
+}
+
+#[derive(Default)]
+struct DocumentDataBuilder {
+  blocks: HashMap<String, Block>,
+  children_map: HashMap<String, Vec<String>>,
+  text_map: HashMap<String, String>,
+}
+
+impl DocumentDataBuilder {
+  /// Inserts the block and its children, returns the id of the block.
+  fn insert(&mut self, block: NestedBlock, parent_id: &str) -> Result<String, DocumentError> {
+    let id = block.id.unwrap_or_else(generate_id);
+    if self.blocks.contains_key(&id) {
+      return Err(DocumentError::InvalidNestedDocument(format!(
+        "duplicate block id: {}",
+        id
+      )));
+    }
+    let children_id = block.children_id.unwrap_or_else(generate_id);
+    if self.children_map.contains_key(&children_id) {
+      return Err(DocumentError::InvalidNestedDocument(format!(
+        "duplicate children id: {}",
+        children_id
+      )));
+    }
+
+    let text_id = match (block.text_id, &block.delta) {
+      (Some(text_id), _) => Some(text_id),
+      (None, Some(_)) => Some(generate_id()),
+      (None, None) => None,
+    };
+    let external_type = match (&text_id, block.external_type) {
+      (Some(_), None) => Some(TEXT_EXTERNAL_TYPE.to_string()),
+      (_, external_type) => external_type,
+    };
+    if let (Some(text_id), Some(delta)) = (&text_id, block.delta) {
+      let delta = match delta {
+        Value::String(delta) => delta,
+        Value::Array(_) => delta.to_string(),
+        _ => {
+          return Err(DocumentError::InvalidNestedDocument(format!(
+            "the delta of block {} is not a list of operations",
+            id
+          )));
+        },
+      };
+      self.text_map.insert(text_id.clone(), delta);
+    }
+
+    self.children_map.insert(children_id.clone(), vec![]);
+    self.blocks.insert(
+      id.clone(),
+      Block {
+        id: id.clone(),
+        ty: block.ty,
+        parent: parent_id.to_string(),
+        children: children_id.clone(),
+        external_id: text_id,
+        external_type,
+        data: block.data,
+      },
+    );
+
+    let mut child_ids = Vec::with_capacity(block.children.len());
+    for child in block.children {
+      child_ids.push(self.insert(child, &id)?);
+    }
+    self.children_map.insert(children_id, child_ids);
+    Ok(id)
+  }
+}
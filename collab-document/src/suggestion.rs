@@ -0,0 +1,383 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use collab::preclude::types::text::YChange;
+use collab::preclude::{Any, Attrs, Out, ReadTxn, Text, TextRef, TransactionMut};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::blocks::{DocumentData, TextDelta, deserialize_text_delta};
+use crate::document_data::{generate_id, timestamp};
+
+/// The text attribute of the text that is suggested to be inserted.
+pub const SUGGESTION_INSERT_ATTR: &str = "suggestion_insert";
+/// The text attribute of the text that is suggested to be deleted.
+pub const SUGGESTION_DELETE_ATTR: &str = "suggestion_delete";
+/// The key, in the data of a block, of the suggestion to insert or delete the block.
+pub const BLOCK_SUGGESTION_KEY: &str = "suggestion";
+
+const SUGGESTION_ID: &str = "id";
+const SUGGESTION_AUTHOR: &str = "author";
+const SUGGESTION_TIMESTAMP: &str = "timestamp";
+
+/// Who suggested a change and when. Stored as the value of the suggestion text attributes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuggestionMark {
+  pub id: String,
+  pub author: i64,
+  pub timestamp: i64,
+}
+
+impl SuggestionMark {
+  pub fn new(author: i64) -> Self {
+    Self {
+      id: generate_id(),
+      author,
+      timestamp: timestamp(),
+    }
+  }
+
+  pub fn from_any(value: &Any) -> Option<Self> {
+    let Any::Map(map) = value else {
+      return None;
+    };
+    let number = |key: &str| match map.get(key)? {
+      Any::BigInt(value) => Some(*value),
+      Any::Number(value) => Some(*value as i64),
+      _ => None,
+    };
+    match map.get(SUGGESTION_ID)? {
+      Any::String(id) => Some(Self {
+        id: id.to_string(),
+        author: number(SUGGESTION_AUTHOR)?,
+        timestamp: number(SUGGESTION_TIMESTAMP).unwrap_or_default(),
+      }),
+      _ => None,
+    }
+  }
+
+  pub fn to_any(&self) -> Any {
+    Any::from(HashMap::from([
+      (SUGGESTION_ID.to_string(), Any::from(self.id.as_str())),
+      (SUGGESTION_AUTHOR.to_string(), Any::BigInt(self.author)),
+      (
+        SUGGESTION_TIMESTAMP.to_string(),
+        Any::BigInt(self.timestamp),
+      ),
+    ]))
+  }
+}
+
+/// The suggestion stored in the data of a block under [BLOCK_SUGGESTION_KEY].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockSuggestion {
+  #[serde(flatten)]
+  pub mark: SuggestionMark,
+  #[serde(rename = "type")]
+  pub ty: BlockSuggestionType,
+}
+
+impl BlockSuggestion {
+  pub fn from_block_data(data: &HashMap<String, Value>) -> Option<Self> {
+    serde_json::from_value(data.get(BLOCK_SUGGESTION_KEY)?.clone()).ok()
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockSuggestionType {
+  Insert,
+  Delete,
+}
+
+/// A pending suggestion of the document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Suggestion {
+  pub id: String,
+  pub author: i64,
+  pub timestamp: i64,
+  pub block_id: String,
+  pub kind: SuggestionKind,
+  /// The suggested text for [SuggestionKind::InsertText] and [SuggestionKind::DeleteText]. Empty
+  /// for the block suggestions.
+  pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SuggestionKind {
+  InsertText,
+  DeleteText,
+  InsertBlock,
+  DeleteBlock,
+}
+
+/// How the pending suggestions are rendered by [crate::document::Document::to_plain_text_with_options]
+/// and [crate::document::Document::to_markdown_text_with_options].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SuggestionRenderMode {
+  /// Renders the text as stored. Both the suggested insertions and the text suggested to be
+  /// deleted are included.
+  #[default]
+  Raw,
+  /// Renders the document as if all the pending suggestions were accepted.
+  Accepted,
+  /// Renders the document as if all the pending suggestions were rejected, i.e. without them.
+  Rejected,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DocumentRenderOptions {
+  pub suggestions: SuggestionRenderMode,
+}
+
+impl DocumentRenderOptions {
+  pub fn with_suggestions(mut self, suggestions: SuggestionRenderMode) -> Self {
+    self.suggestions = suggestions;
+    self
+  }
+}
+
+/// A run of text with the same attributes.
+pub(crate) struct TextChunk {
+  pub start: u32,
+  pub len: u32,
+  pub text: String,
+  pub attrs: Option<Box<Attrs>>,
+}
+
+impl TextChunk {
+  pub fn mark(&self, attr: &str) -> Option<SuggestionMark> {
+    SuggestionMark::from_any(self.attrs.as_ref()?.get(attr)?)
+  }
+
+  fn end(&self) -> u32 {
+    self.start + self.len
+  }
+}
+
+/// Returns the runs of the text. The offsets are counted in UTF-16 code units, the unit used by
+/// the collab documents.
+pub(crate) fn text_chunks<T: ReadTxn>(txn: &T, text_ref: &TextRef) -> Vec<TextChunk> {
+  let mut start = 0;
+  text_ref
+    .diff(txn, YChange::identity)
+    .into_iter()
+    .map(|diff| {
+      let (text, len) = match diff.insert {
+        Out::Any(Any::String(s)) => (s.to_string(), s.encode_utf16().count() as u32),
+        // Embeds take a single position
+        _ => (String::new(), 1),
+      };
+      let chunk = TextChunk {
+        start,
+        len,
+        text,
+        attrs: diff.attributes,
+      };
+      start += len;
+      chunk
+    })
+    .collect()
+}
+
+/// Applies the delta to the text as suggestions of the `author`.
+///
+/// Inserted text is marked with [SUGGESTION_INSERT_ATTR] and deleted text is kept and marked with
+/// [SUGGESTION_DELETE_ATTR]. Deleting text that the author suggested to insert removes it.
+/// Formatting is applied as is. A suggestion that directly continues a suggestion of the same
+/// author reuses its mark, so typing a word produces a single suggestion.
+pub(crate) fn apply_suggested_delta(
+  txn: &mut TransactionMut,
+  text_ref: &TextRef,
+  delta: Vec<TextDelta>,
+  author: i64,
+) {
+  let mut index = 0;
+  for op in delta {
+    match op {
+      TextDelta::Retain(len, attrs) => {
+        if let Some(attrs) = attrs {
+          text_ref.format(txn, index, len, attrs);
+        }
+        index += len;
+      },
+      TextDelta::Inserted(content, attrs) => {
+        let chunks = text_chunks(txn, text_ref);
+        let mark = chunk_at(&chunks, index.checked_sub(1))
+          .and_then(|chunk| chunk.mark(SUGGESTION_INSERT_ATTR))
+          .filter(|mark| mark.author == author)
+          .unwrap_or_else(|| SuggestionMark::new(author));
+        let mut attrs = attrs.unwrap_or_default();
+        attrs.insert(Arc::from(SUGGESTION_INSERT_ATTR), mark.to_any());
+        text_ref.insert_with_attributes(txn, index, &content, attrs);
+        index += content.encode_utf16().count() as u32;
+      },
+      TextDelta::Deleted(len) => {
+        let end = index + len;
+        let chunks = text_chunks(txn, text_ref);
+        let mark = [index.checked_sub(1), Some(end)]
+          .into_iter()
+          .filter_map(|position| chunk_at(&chunks, position)?.mark(SUGGESTION_DELETE_ATTR))
+          .find(|mark| mark.author == author)
+          .unwrap_or_else(|| SuggestionMark::new(author));
+
+        let segments = chunks
+          .iter()
+          .filter(|chunk| chunk.start < end && chunk.end() > index)
+          .map(|chunk| {
+            let is_own_insert = chunk
+              .mark(SUGGESTION_INSERT_ATTR)
+              .is_some_and(|mark| mark.author == author);
+            let start = chunk.start.max(index);
+            (start, chunk.end().min(end) - start, is_own_insert)
+          })
+          .collect::<Vec<_>>();
+
+        // Go backwards so that removing a segment doesn't shift the ones left to process.
+        let mut removed = 0;
+        for (start, len, is_own_insert) in segments.into_iter().rev() {
+          if is_own_insert {
+            text_ref.remove_range(txn, start, len);
+            removed += len;
+          } else {
+            let attrs = Attrs::from([(Arc::from(SUGGESTION_DELETE_ATTR), mark.to_any())]);
+            text_ref.format(txn, start, len, attrs);
+          }
+        }
+        index = end - removed;
+      },
+    }
+  }
+}
+
+fn chunk_at(chunks: &[TextChunk], position: Option<u32>) -> Option<&TextChunk> {
+  let position = position?;
+  chunks
+    .iter()
+    .find(|chunk| chunk.start <= position && position < chunk.end())
+}
+
+/// Accepts or rejects the suggestions of the text that match the filter.
+pub(crate) fn resolve_text_suggestions<F>(
+  txn: &mut TransactionMut,
+  text_ref: &TextRef,
+  filter: &F,
+  accept: bool,
+) where
+  F: Fn(&SuggestionMark) -> bool,
+{
+  let chunks = text_chunks(txn, text_ref);
+  for chunk in chunks.iter().rev() {
+    for (attr, is_insert) in [
+      (SUGGESTION_INSERT_ATTR, true),
+      (SUGGESTION_DELETE_ATTR, false),
+    ] {
+      if !chunk.mark(attr).is_some_and(|mark| filter(&mark)) {
+        continue;
+      }
+      // Accepting an insertion or rejecting a deletion keeps the text and drops the mark.
+      if accept == is_insert {
+        let attrs = Attrs::from([(Arc::from(attr), Any::Null)]);
+        text_ref.format(txn, chunk.start, chunk.len, attrs);
+      } else {
+        text_ref.remove_range(txn, chunk.start, chunk.len);
+        break;
+      }
+    }
+  }
+}
+
+/// Returns the suggestions of the text. The consecutive runs of the same suggestion are merged.
+pub(crate) fn text_suggestions<T: ReadTxn>(
+  txn: &T,
+  text_ref: &TextRef,
+  block_id: &str,
+) -> Vec<Suggestion> {
+  let mut suggestions: Vec<Suggestion> = vec![];
+  for chunk in text_chunks(txn, text_ref) {
+    for (attr, kind) in [
+      (SUGGESTION_INSERT_ATTR, SuggestionKind::InsertText),
+      (SUGGESTION_DELETE_ATTR, SuggestionKind::DeleteText),
+    ] {
+      let Some(mark) = chunk.mark(attr) else {
+        continue;
+      };
+      match suggestions
+        .iter_mut()
+        .find(|suggestion| suggestion.id == mark.id && suggestion.kind == kind)
+      {
+        Some(suggestion) => suggestion.text.push_str(&chunk.text),
+        None => suggestions.push(Suggestion {
+          id: mark.id,
+          author: mark.author,
+          timestamp: mark.timestamp,
+          block_id: block_id.to_string(),
+          kind,
+          text: chunk.text.clone(),
+        }),
+      }
+    }
+  }
+  suggestions
+}
+
+/// Rewrites the document data as if the pending suggestions were accepted or rejected. The
+/// suggestion marks are removed, so the parsers render the data as a regular document.
+pub(crate) fn render_suggestions(data: &mut DocumentData, mode: SuggestionRenderMode) {
+  if mode == SuggestionRenderMode::Raw {
+    return;
+  }
+  let (drop_attr, keep_attr, drop_block) = match mode {
+    SuggestionRenderMode::Accepted => (
+      SUGGESTION_DELETE_ATTR,
+      SUGGESTION_INSERT_ATTR,
+      BlockSuggestionType::Delete,
+    ),
+    _ => (
+      SUGGESTION_INSERT_ATTR,
+      SUGGESTION_DELETE_ATTR,
+      BlockSuggestionType::Insert,
+    ),
+  };
+
+  if let Some(text_map) = data.meta.text_map.as_mut() {
+    for delta_json in text_map.values_mut() {
+      let Ok(delta) = deserialize_text_delta(delta_json) else {
+        continue;
+      };
+      let delta = delta
+        .into_iter()
+        .filter_map(|op| match op {
+          TextDelta::Inserted(content, mut attrs) => {
+            if attrs
+              .as_ref()
+              .is_some_and(|attrs| attrs.contains_key(drop_attr))
+            {
+              return None;
+            }
+            if let Some(attrs) = attrs.as_mut() {
+              attrs.remove(keep_attr);
+            }
+            Some(TextDelta::Inserted(content, attrs))
+          },
+          op => Some(op),
+        })
+        .collect::<Vec<_>>();
+      *delta_json = serde_json::to_string(&delta).unwrap_or_default();
+    }
+  }
+
+  let mut dropped_blocks = HashSet::new();
+  for block in data.blocks.values_mut() {
+    if let Some(suggestion) = block.data.remove(BLOCK_SUGGESTION_KEY) {
+      let is_dropped = serde_json::from_value::<BlockSuggestion>(suggestion)
+        .is_ok_and(|suggestion| suggestion.ty == drop_block);
+      if is_dropped {
+        dropped_blocks.insert(block.id.clone());
+      }
+    }
+  }
+  for children in data.meta.children_map.values_mut() {
+    children.retain(|child_id| !dropped_blocks.contains(child_id));
+  }
+}
@@ -0,0 +1,437 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Local, NaiveDate};
+use serde_json::Value;
+
+use crate::blocks::{AttrKey, Block, BlockType, DocumentData, DocumentMeta};
+use crate::document_data::{generate_id, page_id_from_document_id};
+
+const PLACEHOLDER_START: &str = "{{";
+const PLACEHOLDER_END: &str = "}}";
+const INSERT_KEY: &str = "insert";
+const ATTRIBUTES_KEY: &str = "attributes";
+/// The keys of the block data whose string values are substituted, the other values, like the
+/// urls and the ids, are copied as they are.
+const TEMPLATE_DATA_KEYS: &[&str] = &["icon", "caption", "name", "title"];
+
+/// The format of a date placeholder without an explicit format, like `{{date}}`.
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// variable name -> value
+pub type TemplateVariables = HashMap<String, TemplateValue>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateVariableType {
+  Text,
+  Number,
+  Date,
+  User,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateValue {
+  Text(String),
+  Number(f64),
+  Date(NaiveDate),
+  User { uid: i64, name: String },
+}
+
+impl TemplateValue {
+  pub fn today() -> Self {
+    Self::Date(Local::now().date_naive())
+  }
+
+  pub fn variable_type(&self) -> TemplateVariableType {
+    match self {
+      TemplateValue::Text(_) => TemplateVariableType::Text,
+      TemplateValue::Number(_) => TemplateVariableType::Number,
+      TemplateValue::Date(_) => TemplateVariableType::Date,
+      TemplateValue::User { .. } => TemplateVariableType::User,
+    }
+  }
+
+  /// Renders the value as the text that replaces the placeholder. The format only applies to the
+  /// dates, `{{date:%d/%m/%Y}}`, an invalid format falls back to [DEFAULT_DATE_FORMAT].
+  pub fn render(&self, format: Option<&str>) -> String {
+    match self {
+      TemplateValue::Text(text) => text.clone(),
+      TemplateValue::Number(number) => number.to_string(),
+      TemplateValue::Date(date) => {
+        let format = format
+          .map(str::trim)
+          .filter(|format| {
+            !format.is_empty() && !StrftimeItems::new(format).any(|item| item == Item::Error)
+          })
+          .unwrap_or(DEFAULT_DATE_FORMAT);
+        date.format(format).to_string()
+      },
+      TemplateValue::User { name, .. } => name.clone(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateVariable {
+  pub name: String,
+  pub ty: TemplateVariableType,
+  pub required: bool,
+  /// Used when the variable is not provided.
+  pub default: Option<TemplateValue>,
+}
+
+impl TemplateVariable {
+  /// A required variable.
+  pub fn new(name: &str, ty: TemplateVariableType) -> Self {
+    Self {
+      name: name.to_string(),
+      ty,
+      required: true,
+      default: None,
+    }
+  }
+
+  /// The placeholders of an optional variable without value are replaced by an empty text.
+  pub fn optional(mut self) -> Self {
+    self.required = false;
+    self
+  }
+
+  pub fn with_default(mut self, default: TemplateValue) -> Self {
+    self.default = Some(default);
+    self
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TemplateError {
+  #[error("Missing template variables: {}", .0.join(", "))]
+  MissingVariables(Vec<String>),
+
+  #[error("The template variable {name} expects a {expected:?} value")]
+  InvalidValue {
+    name: String,
+    expected: TemplateVariableType,
+  },
+
+  #[error("The page block of the template is not found")]
+  PageBlockNotFound,
+}
+
+/// A document whose texts and block data contain placeholders, like `{{title}}` or
+/// `{{date:%B %d, %Y}}`, that are substituted when a document is created from it.
+///
+/// The placeholders are looked up in the inserted texts of the deltas and in the string values of
+/// the [TEMPLATE_DATA_KEYS] of the block data. The code blocks and the inline code are copied as
+/// they are. A placeholder must be written with the same attributes, `{{ti` in bold followed by
+/// `tle}}` is not a placeholder. The placeholders without a declared variable are
+/// required variables that accept a value of any type, `{{date}}` can be filled with
+/// [TemplateValue::today].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentTemplate {
+  pub data: DocumentData,
+  /// variable name -> variable
+  pub variables: BTreeMap<String, TemplateVariable>,
+}
+
+impl DocumentTemplate {
+  pub fn new(data: DocumentData) -> Self {
+    Self {
+      data,
+      variables: BTreeMap::new(),
+    }
+  }
+
+  pub fn with_variable(mut self, variable: TemplateVariable) -> Self {
+    self.variables.insert(variable.name.clone(), variable);
+    self
+  }
+
+  /// The names of the placeholders used in the template, sorted.
+  pub fn placeholders(&self) -> Vec<String> {
+    let mut names = BTreeSet::new();
+    let mut collect = |text: &str| {
+      substitute_placeholders(text, |name, _| {
+        names.insert(name.to_string());
+        None
+      });
+    };
+    for block in self.data.blocks.values() {
+      template_data_strings(&block.data).for_each(&mut collect);
+      if is_code_block(block) {
+        continue;
+      }
+      let delta = block.external_id.as_ref().and_then(|external_id| {
+        let text_map = self.data.meta.text_map.as_ref()?;
+        text_map.get(external_id)
+      });
+      if let Some(delta) = delta {
+        match serde_json::from_str::<Value>(delta) {
+          Ok(delta) => visit_delta_inserts(&delta, &mut collect),
+          Err(_) => collect(delta),
+        }
+      }
+    }
+    names.into_iter().collect()
+  }
+
+  /// The declared variables and the implicit variables of the undeclared placeholders. The
+  /// implicit variables are listed as text variables but accept a value of any type.
+  pub fn all_variables(&self) -> Vec<TemplateVariable> {
+    let mut variables = self.variables.clone();
+    for name in self.placeholders() {
+      variables
+        .entry(name.clone())
+        .or_insert_with(|| TemplateVariable::new(&name, TemplateVariableType::Text));
+    }
+    variables.into_values().collect()
+  }
+
+  /// Checks that the values have the type of their variables and that every required variable
+  /// has a value or a default.
+  pub fn validate(&self, values: &TemplateVariables) -> Result<(), TemplateError> {
+    self.resolve_values(values).map(|_| ())
+  }
+
+  /// Returns a copy of the template data with the placeholders substituted. Every block, children
+  /// list and text gets a new id. The page id is derived from the document id like
+  /// [crate::document_data::default_document_data] does.
+  pub fn instantiate(
+    &self,
+    document_id: &str,
+    values: &TemplateVariables,
+  ) -> Result<DocumentData, TemplateError> {
+    let values = self.resolve_values(values)?;
+    let page = self
+      .data
+      .blocks
+      .get(&self.data.page_id)
+      .ok_or(TemplateError::PageBlockNotFound)?;
+
+    let page_id = page_id_from_document_id(document_id).unwrap_or_else(generate_id);
+    let mut copier = TemplateCopier {
+      template: &self.data,
+      values: &values,
+      visited: HashSet::new(),
+      output: DocumentData {
+        page_id: page_id.clone(),
+        blocks: HashMap::new(),
+        meta: DocumentMeta {
+          children_map: HashMap::new(),
+          text_map: Some(HashMap::new()),
+        },
+      },
+    };
+    copier.copy_block(page, page_id.clone(), String::new(), page_id);
+    Ok(copier.output)
+  }
+
+  fn resolve_values(
+    &self,
+    values: &TemplateVariables,
+  ) -> Result<HashMap<String, TemplateValue>, TemplateError> {
+    let mut resolved = HashMap::new();
+    let mut missing = vec![];
+    for variable in self.all_variables() {
+      let declared = self.variables.contains_key(&variable.name);
+      match values.get(&variable.name).or(variable.default.as_ref()) {
+        Some(value) if declared && value.variable_type() != variable.ty => {
+          return Err(TemplateError::InvalidValue {
+            name: variable.name,
+            expected: variable.ty,
+          });
+        },
+        Some(value) => {
+          resolved.insert(variable.name, value.clone());
+        },
+        None if variable.required => missing.push(variable.name),
+        None => {},
+      }
+    }
+    if !missing.is_empty() {
+      return Err(TemplateError::MissingVariables(missing));
+    }
+    Ok(resolved)
+  }
+}
+
+struct TemplateCopier<'a> {
+  template: &'a DocumentData,
+  values: &'a HashMap<String, TemplateValue>,
+  visited: HashSet<&'a str>,
+  output: DocumentData,
+}
+
+impl<'a> TemplateCopier<'a> {
+  fn copy_block(&mut self, block: &'a Block, id: String, parent: String, children: String) {
+    // A cycle in a malformed template
+    if !self.visited.insert(&block.id) {
+      return;
+    }
+
+    let external_id = block.external_id.as_ref().map(|external_id| {
+      let new_external_id = generate_id();
+      if let Some(delta) = self
+        .template
+        .meta
+        .text_map
+        .as_ref()
+        .and_then(|text_map| text_map.get(external_id))
+      {
+        let delta = if is_code_block(block) {
+          delta.clone()
+        } else {
+          self.substitute_delta(delta)
+        };
+        if let Some(text_map) = self.output.meta.text_map.as_mut() {
+          text_map.insert(new_external_id.clone(), delta);
+        }
+      }
+      new_external_id
+    });
+    let data = block
+      .data
+      .iter()
+      .map(|(key, value)| match value {
+        Value::String(text) if TEMPLATE_DATA_KEYS.contains(&key.as_str()) => {
+          (key.clone(), Value::String(self.substitute_text(text)))
+        },
+        _ => (key.clone(), value.clone()),
+      })
+      .collect();
+
+    let mut child_ids = vec![];
+    for child in self
+      .template
+      .meta
+      .children_map
+      .get(&block.children)
+      .into_iter()
+      .flatten()
+      .filter_map(|child_id| self.template.blocks.get(child_id))
+    {
+      let child_id = generate_id();
+      self.copy_block(child, child_id.clone(), id.clone(), generate_id());
+      if self.output.blocks.contains_key(&child_id) {
+        child_ids.push(child_id);
+      }
+    }
+    self
+      .output
+      .meta
+      .children_map
+      .insert(children.clone(), child_ids);
+
+    self.output.blocks.insert(
+      id.clone(),
+      Block {
+        id,
+        ty: block.ty.clone(),
+        parent,
+        children,
+        external_id,
+        external_type: block.external_type.clone(),
+        data,
+      },
+    );
+  }
+
+  fn substitute_text(&self, text: &str) -> String {
+    substitute_placeholders(text, |name, format| {
+      Some(
+        self
+          .values
+          .get(name)
+          .map(|value| value.render(format))
+          .unwrap_or_default(),
+      )
+    })
+  }
+
+  fn substitute_delta(&self, delta: &str) -> String {
+    let Ok(Value::Array(mut ops)) = serde_json::from_str::<Value>(delta) else {
+      return delta.to_string();
+    };
+    for op in ops.iter_mut().filter(|op| !is_inline_code(op)) {
+      if let Some(Value::String(insert)) = op.get_mut(INSERT_KEY) {
+        *insert = self.substitute_text(insert);
+      }
+    }
+    Value::Array(ops).to_string()
+  }
+}
+
+fn template_data_strings(data: &HashMap<String, Value>) -> impl Iterator<Item = &str> {
+  TEMPLATE_DATA_KEYS
+    .iter()
+    .filter_map(|key| data.get(*key).and_then(Value::as_str))
+}
+
+fn is_code_block(block: &Block) -> bool {
+  block.ty == BlockType::Code.as_str()
+}
+
+fn is_inline_code(op: &Value) -> bool {
+  op.get(ATTRIBUTES_KEY)
+    .and_then(|attributes| attributes.get(AttrKey::Code.as_str()))
+    .and_then(Value::as_bool)
+    .unwrap_or(false)
+}
+
+fn visit_delta_inserts<F: FnMut(&str)>(delta: &Value, f: &mut F) {
+  for op in delta
+    .as_array()
+    .into_iter()
+    .flatten()
+    .filter(|op| !is_inline_code(op))
+  {
+    if let Some(insert) = op.get(INSERT_KEY).and_then(Value::as_str) {
+      f(insert);
+    }
+  }
+}
+
+/// Replaces the `{{name}}` and `{{name:format}}` placeholders of the text with the value returned
+/// by `replace`. The placeholders for which it returns `None` are kept.
+fn substitute_placeholders<F>(text: &str, mut replace: F) -> String
+where
+  F: FnMut(&str, Option<&str>) -> Option<String>,
+{
+  let mut output = String::with_capacity(text.len());
+  let mut rest = text;
+  while let Some(start) = rest.find(PLACEHOLDER_START) {
+    output.push_str(&rest[..start]);
+    let after_start = &rest[start + PLACEHOLDER_START.len()..];
+    let Some(end) = after_start.find(PLACEHOLDER_END) else {
+      rest = &rest[start..];
+      break;
+    };
+    let content = &after_start[..end];
+    let (name, format) = match content.split_once(':') {
+      Some((name, format)) => (name.trim(), Some(format)),
+      None => (content.trim(), None),
+    };
+    match is_variable_name(name)
+      .then(|| replace(name, format))
+      .flatten()
+    {
+      Some(value) => {
+        output.push_str(&value);
+        rest = &after_start[end + PLACEHOLDER_END.len()..];
+      },
+      None => {
+        output.push_str(PLACEHOLDER_START);
+        rest = after_start;
+      },
+    }
+  }
+  output.push_str(rest);
+  output
+}
+
+fn is_variable_name(name: &str) -> bool {
+  !name.is_empty()
+    && name
+      .chars()
+      .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
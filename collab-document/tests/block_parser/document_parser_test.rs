@@ -81,3 +81,56 @@ fn test_document_parser_with_mention_delegate() {
     .unwrap();
   assert_eq!(result_md.trim(), expected);
 }
+
+#[derive(Debug)]
+struct GridDelegate;
+
+impl DocumentParserDelegate for GridDelegate {
+  fn handle_block(&self, block: &Block, context: &ParseContext) -> Option<String> {
+    if block.ty != BlockType::Grid.as_str() {
+      return None;
+    }
+    let view_id = block.data.get("view_id")?.as_str()?;
+    Some(format!("{}<grid {}>", context.get_indent(), view_id))
+  }
+}
+
+#[test]
+fn test_document_parser_with_block_delegate() {
+  let mut test = BlockTestCore::new();
+  let page_id = test.get_page().id;
+  let paragraph_id = test.get_block_children(&page_id)[0].id.clone();
+  for (ty, view_id) in [
+    (BlockType::Grid, "grid_view"),
+    (BlockType::Board, "board_view"),
+  ] {
+    let block = Block {
+      id: generate_id(),
+      ty: ty.as_str().to_string(),
+      parent: page_id.clone(),
+      children: generate_id(),
+      external_id: None,
+      external_type: None,
+      data: HashMap::from([("view_id".to_string(), json!(view_id))]),
+    };
+    test
+      .document
+      .insert_block(block, Some(paragraph_id.clone()))
+      .unwrap();
+  }
+  let document_data = test.get_document_data();
+
+  // The database blocks are skipped by the default parsers
+  let parser = DocumentParser::with_default_parsers();
+  let result = parser
+    .parse_document(&document_data, OutputFormat::PlainText)
+    .unwrap();
+  assert_eq!(result.trim(), "");
+
+  // The blocks not handled by the delegate fall back to the default parsers
+  let parser = parser.with_delegate(Arc::new(GridDelegate));
+  let result = parser
+    .parse_document(&document_data, OutputFormat::Markdown)
+    .unwrap();
+  assert_eq!(result.trim(), "<grid grid_view>");
+}
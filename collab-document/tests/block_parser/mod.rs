@@ -9,6 +9,7 @@ mod image_test;
 mod link_preview_test;
 mod math_equation_test;
 mod numbered_list_test;
+mod outline_test;
 mod paragraph_test;
 mod parser_test;
 mod quote_list_test;
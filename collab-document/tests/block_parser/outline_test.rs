@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use collab_document::block_parser::parsers::outline::OutlineParser;
+use collab_document::block_parser::{
+  BlockParser, DocumentParser, MAX_HEADING_LEVEL, OutlineItem, OutputFormat, ParseContext,
+  build_outline,
+};
+use collab_document::blocks::{Block, BlockType};
+use serde_json::{Value, json};
+
+use crate::blocks::block_test_core::{BlockTestCore, generate_id};
+
+fn append_block(
+  test: &mut BlockTestCore,
+  ty: BlockType,
+  text: Option<&str>,
+  data: HashMap<String, Value>,
+  parent_id: &str,
+) -> Block {
+  let external_id = text.map(|text| test.create_text(json!([{ "insert": text }]).to_string()));
+  let prev_id = test
+    .get_block_children(parent_id)
+    .last()
+    .map(|block| block.id.clone());
+  let block = Block {
+    id: generate_id(),
+    ty: ty.as_str().to_string(),
+    parent: parent_id.to_string(),
+    children: generate_id(),
+    external_type: external_id.as_ref().map(|_| "text".to_string()),
+    external_id,
+    data,
+  };
+  test.document.insert_block(block, prev_id).unwrap()
+}
+
+fn append_heading(test: &mut BlockTestCore, level: u8, text: &str, parent_id: &str) -> Block {
+  let data = HashMap::from([("level".to_string(), json!(level))]);
+  append_block(test, BlockType::Heading, Some(text), data, parent_id)
+}
+
+fn outline_item(
+  block: &Block,
+  level: usize,
+  text: &str,
+  children: Vec<OutlineItem>,
+) -> OutlineItem {
+  OutlineItem {
+    block_id: block.id.clone(),
+    level,
+    text: text.to_string(),
+    children,
+  }
+}
+
+/// Intro (h1)
+///   toggle
+///     Details (h2)
+///   columns
+///     column
+///       Side (h2)
+///       Deep (h3)
+/// (empty h2)
+/// End (h1)
+fn create_outline_document(test: &mut BlockTestCore) -> Vec<OutlineItem> {
+  let page_id = test.get_page().id;
+  let intro = append_heading(test, 1, "Intro", &page_id);
+  let toggle = append_block(
+    test,
+    BlockType::ToggleList,
+    Some("Toggle"),
+    HashMap::new(),
+    &page_id,
+  );
+  let details = append_heading(test, 2, "Details", &toggle.id);
+  let columns = append_block(
+    test,
+    BlockType::SimpleColumns,
+    None,
+    HashMap::new(),
+    &page_id,
+  );
+  let column = append_block(
+    test,
+    BlockType::SimpleColumn,
+    None,
+    HashMap::new(),
+    &columns.id,
+  );
+  let side = append_heading(test, 2, "Side", &column.id);
+  let deep = append_heading(test, 3, "Deep", &column.id);
+  append_heading(test, 2, " ", &page_id);
+  let end = append_heading(test, 1, "End", &page_id);
+
+  vec![
+    outline_item(
+      &intro,
+      1,
+      "Intro",
+      vec![
+        outline_item(&details, 2, "Details", vec![]),
+        outline_item(
+          &side,
+          2,
+          "Side",
+          vec![outline_item(&deep, 3, "Deep", vec![])],
+        ),
+      ],
+    ),
+    outline_item(&end, 1, "End", vec![]),
+  ]
+}
+
+#[test]
+fn test_document_outline() {
+  let mut test = BlockTestCore::new();
+  let expected = create_outline_document(&mut test);
+  assert_eq!(test.document.outline().unwrap(), expected);
+}
+
+#[test]
+fn test_document_outline_starts_with_deeper_heading() {
+  let mut test = BlockTestCore::new();
+  let page_id = test.get_page().id;
+  let h3 = append_heading(&mut test, 3, "Note", &page_id);
+  let h1 = append_heading(&mut test, 1, "Title", &page_id);
+  let h2 = append_heading(&mut test, 2, "Section", &page_id);
+
+  assert_eq!(
+    test.document.outline().unwrap(),
+    vec![
+      outline_item(&h3, 3, "Note", vec![]),
+      outline_item(
+        &h1,
+        1,
+        "Title",
+        vec![outline_item(&h2, 2, "Section", vec![])]
+      ),
+    ]
+  );
+}
+
+#[test]
+fn test_outline_parser_markdown_format() {
+  let mut test = BlockTestCore::new();
+  create_outline_document(&mut test);
+  let page_id = test.get_page().id;
+  let data = HashMap::from([("depth".to_string(), json!(2))]);
+  let block = append_block(&mut test, BlockType::Outline, None, data, &page_id);
+
+  let document_data = test.get_document_data();
+  let document_parser = DocumentParser::with_default_parsers();
+  let context = ParseContext::new(&document_data, &document_parser, OutputFormat::Markdown);
+  let result = OutlineParser.parse(&block, &context).unwrap();
+  assert_eq!(result.content, "- Intro\n  - Details\n  - Side\n- End");
+
+  // The outline is part of the exported document
+  let markdown = document_parser
+    .parse_document(&document_data, OutputFormat::Markdown)
+    .unwrap();
+  assert!(markdown.ends_with("- Intro\n  - Details\n  - Side\n- End"));
+}
+
+#[test]
+fn test_outline_parser_plain_text_format() {
+  let mut test = BlockTestCore::new();
+  create_outline_document(&mut test);
+  let page_id = test.get_page().id;
+  let block = append_block(
+    &mut test,
+    BlockType::Outline,
+    None,
+    HashMap::new(),
+    &page_id,
+  );
+
+  let document_data = test.get_document_data();
+  let document_parser = DocumentParser::with_default_parsers();
+  let context = ParseContext::new(&document_data, &document_parser, OutputFormat::PlainText);
+  let result = OutlineParser.parse(&block, &context).unwrap();
+  assert_eq!(result.content, "Intro\n  Details\n  Side\n    Deep\nEnd");
+}
+
+#[test]
+fn test_outline_parser_without_headings() {
+  let mut test = BlockTestCore::new();
+  let page_id = test.get_page().id;
+  let block = append_block(
+    &mut test,
+    BlockType::Outline,
+    None,
+    HashMap::new(),
+    &page_id,
+  );
+
+  let document_data = test.get_document_data();
+  let document_parser = DocumentParser::with_default_parsers();
+  let context = ParseContext::new(&document_data, &document_parser, OutputFormat::Markdown);
+  let result = OutlineParser.parse(&block, &context).unwrap();
+  assert!(result.content.is_empty());
+}
+
+#[test]
+fn test_outline_with_cycle_and_duplicate_children() {
+  let mut test = BlockTestCore::new();
+  let page_id = test.get_page().id;
+  let title = append_heading(&mut test, 1, "Title", &page_id);
+
+  // The heading is listed twice and contains the page
+  let mut document_data = test.get_document_data();
+  let page_children = document_data.blocks[&page_id].children.clone();
+  let children_map = &mut document_data.meta.children_map;
+  children_map
+    .get_mut(&page_children)
+    .unwrap()
+    .push(title.id.clone());
+  children_map.insert(title.children.clone(), vec![page_id]);
+
+  let document_parser = DocumentParser::with_default_parsers();
+  let context = ParseContext::new(&document_data, &document_parser, OutputFormat::Markdown);
+  assert_eq!(
+    build_outline(&context, MAX_HEADING_LEVEL),
+    vec![outline_item(&title, 1, "Title", vec![])]
+  );
+}
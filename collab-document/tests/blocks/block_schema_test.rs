@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use collab::core::collab::{CollabOptions, default_client_id};
+use collab::core::origin::CollabOrigin;
+use collab::preclude::Collab;
+use collab_document::blocks::{
+  Block, BlockAction, BlockActionPayload, BlockActionType, BlockDataError, BlockDataFieldType,
+  BlockDataSchema, BlockType, BlockValidationIssue,
+};
+use collab_document::document::{Document, DocumentBody};
+use collab_document::error::DocumentError;
+use serde_json::{Value, json};
+
+use crate::blocks::block_test_core::{BlockTestCore, TEXT_BLOCK_TYPE, generate_id};
+
+fn block_with_data(ty: &str, parent_id: &str, data: HashMap<String, Value>) -> Block {
+  Block {
+    id: generate_id(),
+    ty: ty.to_string(),
+    parent: parent_id.to_string(),
+    children: generate_id(),
+    external_id: None,
+    external_type: None,
+    data,
+  }
+}
+
+#[test]
+fn insert_block_with_invalid_builtin_data_test() {
+  let mut test = BlockTestCore::new();
+  let page_id = test.get_page().id;
+
+  let data = HashMap::from([("level".to_string(), json!(9))]);
+  let block = block_with_data(BlockType::Heading.as_str(), &page_id, data);
+  let block_id = block.id.clone();
+  let err = test.document.insert_block(block, None).unwrap_err();
+  match err {
+    DocumentError::InvalidBlockData {
+      block_id: id,
+      block_type,
+      error: BlockDataError::InvalidField { key, .. },
+    } => {
+      assert_eq!(id, block_id);
+      assert_eq!(block_type, "heading");
+      assert_eq!(key, "level");
+    },
+    err => panic!("unexpected error: {:?}", err),
+  }
+  assert!(test.document.get_block(&block_id).is_none());
+
+  // Older clients write the level as a string
+  let data = HashMap::from([("level".to_string(), json!("3"))]);
+  let block = block_with_data(BlockType::Heading.as_str(), &page_id, data);
+  test.document.insert_block(block, None).unwrap();
+
+  let data = HashMap::from([("checked".to_string(), json!("yes"))]);
+  let block = block_with_data(BlockType::TodoList.as_str(), &page_id, data);
+  assert!(matches!(
+    test.document.insert_block(block, None),
+    Err(DocumentError::InvalidBlockData { .. })
+  ));
+}
+
+#[test]
+fn image_url_is_optional_test() {
+  let mut test = BlockTestCore::new();
+  let page_id = test.get_page().id;
+
+  let block = block_with_data(BlockType::Image.as_str(), &page_id, HashMap::new());
+  assert!(test.document.insert_block(block, None).is_ok());
+  let data = HashMap::from([("url".to_string(), json!(1))]);
+  let block = block_with_data(BlockType::Image.as_str(), &page_id, data);
+  assert!(matches!(
+    test.document.insert_block(block, None),
+    Err(DocumentError::InvalidBlockData {
+      error: BlockDataError::InvalidField { .. },
+      ..
+    })
+  ));
+
+  // Creating a document from data
+  let mut data = BlockTestCore::get_default_data();
+  let page_id = data.page_id.clone();
+  let image_data = HashMap::from([("url".to_string(), json!(1))]);
+  let image = block_with_data(BlockType::Image.as_str(), &page_id, image_data);
+  data
+    .meta
+    .children_map
+    .get_mut(&data.blocks[&page_id].children)
+    .unwrap()
+    .push(image.id.clone());
+  data.blocks.insert(image.id.clone(), image);
+  let options = CollabOptions::new(generate_id(), default_client_id());
+  let collab = Collab::new_with_options(CollabOrigin::Empty, options).unwrap();
+  assert!(matches!(
+    Document::create_with_data(collab, data),
+    Err(DocumentError::InvalidBlockData {
+      error: BlockDataError::InvalidField { .. },
+      ..
+    })
+  ));
+}
+
+#[test]
+fn move_block_keeps_unchanged_data_test() {
+  let mut test = BlockTestCore::new();
+  let page_id = test.get_page().id;
+  let parent = test.insert_text_block("parent".to_string(), &page_id, None);
+  let custom_type = BlockType::Custom("kanban_card".to_string());
+  let block = block_with_data(custom_type.as_str(), &page_id, HashMap::new());
+  let block = test.document.insert_block(block, None).unwrap();
+
+  // The data is not checked again when the block is moved
+  test.document.register_block_schema(
+    custom_type,
+    BlockDataSchema::new().required("title", BlockDataFieldType::String),
+  );
+  test
+    .document
+    .move_block(&block.id, Some(parent.id.clone()), None)
+    .unwrap();
+  assert_eq!(
+    test.document.get_block(&block.id).unwrap().parent,
+    parent.id
+  );
+}
+
+#[test]
+fn custom_block_schema_test() {
+  let mut test = BlockTestCore::new();
+  let page_id = test.get_page().id;
+  let custom_type = BlockType::Custom("kanban_card".to_string());
+  test.document.register_block_schema(
+    custom_type.clone(),
+    BlockDataSchema::new()
+      .required("title", BlockDataFieldType::NonEmptyString)
+      .optional("priority", BlockDataFieldType::integer_between(0, 3)),
+  );
+
+  let block = block_with_data(custom_type.as_str(), &page_id, HashMap::new());
+  assert!(matches!(
+    test.document.insert_block(block, None),
+    Err(DocumentError::InvalidBlockData {
+      error: BlockDataError::MissingField(_),
+      ..
+    })
+  ));
+
+  let data = HashMap::from([("title".to_string(), json!("Card"))]);
+  let block = block_with_data(custom_type.as_str(), &page_id, data);
+  let block = test.document.insert_block(block, None).unwrap();
+
+  // Update the data with the document api
+  let data = HashMap::from([
+    ("title".to_string(), json!("Card")),
+    ("priority".to_string(), json!(5)),
+  ]);
+  assert!(test.document.update_block(&block.id, data).is_err());
+
+  // Update the data with an action
+  let mut invalid = block.clone();
+  invalid.data = HashMap::from([("title".to_string(), json!(""))]);
+  let action = BlockAction {
+    action: BlockActionType::Update,
+    payload: BlockActionPayload {
+      block: Some(invalid),
+      delta: None,
+      prev_id: None,
+      parent_id: Some(page_id.clone()),
+      text_id: None,
+    },
+  };
+  assert!(!test.apply_action(vec![action]));
+  assert_eq!(
+    test.document.get_block(&block.id).unwrap().data["title"],
+    json!("Card")
+  );
+
+  // Without the schema any data is accepted
+  test.document.unregister_block_schema(&custom_type);
+  test
+    .document
+    .update_block(&block.id, HashMap::new())
+    .unwrap();
+  assert!(test.document.validate_blocks().is_valid());
+}
+
+#[test]
+fn validate_blocks_test() {
+  let mut test = BlockTestCore::new();
+  let page_id = test.get_page().id;
+  let block = test.insert_text_block("hello".to_string(), &page_id, None);
+  test.insert_text_block("world".to_string(), &block.id, None);
+  assert!(test.document.validate_blocks().is_valid());
+
+  // A block written without a schema is reported once the schema is registered
+  let custom_type = BlockType::Custom("kanban_card".to_string());
+  let custom = block_with_data(custom_type.as_str(), &page_id, HashMap::new());
+  let custom = test.document.insert_block(custom, None).unwrap();
+  test.document.register_block_schema(
+    custom_type,
+    BlockDataSchema::new().required("title", BlockDataFieldType::String),
+  );
+  let report = test.document.validate_blocks();
+  assert_eq!(
+    report.issues,
+    vec![BlockValidationIssue::InvalidData {
+      block_id: custom.id,
+      block_type: "kanban_card".to_string(),
+      error: BlockDataError::MissingField("title".to_string()),
+    }]
+  );
+}
+
+#[test]
+fn validate_blocks_tree_invariants_test() {
+  let mut data = BlockTestCore::get_default_data();
+  let page_id = data.page_id.clone();
+  let page_children = data.blocks[&page_id].children.clone();
+
+  // a -> b -> a
+  let mut a = block_with_data(TEXT_BLOCK_TYPE, &page_id, HashMap::new());
+  let b = block_with_data(TEXT_BLOCK_TYPE, &a.id, HashMap::new());
+  a.parent = b.id.clone();
+  // The children of the orphan are not in the children map
+  let orphan = block_with_data(TEXT_BLOCK_TYPE, &page_id, HashMap::new());
+  let missing_id = generate_id();
+  data
+    .meta
+    .children_map
+    .get_mut(&page_children)
+    .unwrap()
+    .extend([a.id.clone(), missing_id.clone()]);
+  data
+    .meta
+    .children_map
+    .insert(a.children.clone(), vec![b.id.clone()]);
+  data
+    .meta
+    .children_map
+    .insert(b.children.clone(), vec![a.id.clone()]);
+  for block in [a.clone(), b.clone(), orphan.clone()] {
+    data.blocks.insert(block.id.clone(), block);
+  }
+
+  let options = CollabOptions::new(generate_id(), default_client_id());
+  let collab = Collab::new_with_options(CollabOrigin::Empty, options).unwrap();
+  let mut document = Document::create_with_data(collab, data).unwrap();
+  let body = DocumentBody::from_collab(&document).unwrap();
+  {
+    let mut txn = document.transact_mut();
+    body
+      .children_operation
+      .delete_children_with_txn(&mut txn, &orphan.children);
+  }
+  let issues = document.validate_blocks().issues;
+
+  assert!(issues.contains(&BlockValidationIssue::MissingChildren {
+    block_id: orphan.id.clone(),
+    children_id: orphan.children.clone(),
+  }));
+  assert!(issues.contains(&BlockValidationIssue::Orphan {
+    block_id: orphan.id.clone(),
+  }));
+  assert!(issues.contains(&BlockValidationIssue::MissingBlock {
+    parent_id: page_id.clone(),
+    block_id: missing_id,
+  }));
+  assert!(issues.contains(&BlockValidationIssue::ParentMismatch {
+    block_id: a.id.clone(),
+    parent_id: b.id.clone(),
+    actual_parent_id: page_id,
+  }));
+  assert!(issues.contains(&BlockValidationIssue::Cycle { block_id: a.id }));
+  assert_eq!(issues.len(), 5);
+}
@@ -1,3 +1,4 @@
+mod block_schema_test;
 mod block_test;
 pub mod block_test_core;
 mod text_test;
@@ -1,9 +1,15 @@
 use crate::util::DocumentTest;
 
 use collab::core::awareness::AwarenessUpdate;
+use collab::core::collab::DataSource;
+use collab::core::origin::CollabOrigin;
 use collab::preclude::block::ClientID;
 use collab::preclude::updates::decoder::{Decode, Decoder};
-use collab_document::document_awareness::{DocumentAwarenessState, DocumentAwarenessUser};
+use collab::preclude::{ReadTxn, StateVector, Update};
+use collab_document::document::Document;
+use collab_document::document_awareness::{
+  BlockTextPosition, DocumentAwarenessState, DocumentAwarenessUser,
+};
 
 use arc_swap::ArcSwapOption;
 use serde_json::Value;
@@ -24,6 +30,7 @@ fn document_awareness_test() {
       device_id: "fake_device".to_string(),
     },
     selection: None,
+    sticky_selection: None,
     metadata: None,
     timestamp: 123,
   };
@@ -71,6 +78,7 @@ fn document_awareness_serde_test() {
       device_id: "fake_device".to_string(),
     },
     selection: None,
+    sticky_selection: None,
     metadata: None,
     timestamp: 123,
   };
@@ -116,6 +124,7 @@ fn document_awareness_serde_test2() {
       device_id: "fake_device".to_string(),
     },
     selection: None,
+    sticky_selection: None,
     metadata: None,
     timestamp: 123,
   };
@@ -193,6 +202,7 @@ fn document_awareness_incoming_update() {
       device_id: "device_1".to_string(),
     },
     selection: None,
+    sticky_selection: None,
     metadata: Some("meta1".into()),
     timestamp: 1111,
   });
@@ -203,6 +213,7 @@ fn document_awareness_incoming_update() {
       device_id: "device_2".to_string(),
     },
     selection: None,
+    sticky_selection: None,
     metadata: Some("meta2".into()),
     timestamp: 2222,
   });
@@ -219,6 +230,94 @@ fn document_awareness_incoming_update() {
   );
 }
 
+#[test]
+fn document_awareness_sticky_selection_test() {
+  let mut d1 = DocumentTest::new(1, "1");
+  let page_id = d1.get_page_id().unwrap();
+  let block_id = d1.get_block_children_ids(&page_id)[0].clone();
+  let text_id = d1.get_block(&block_id).unwrap().external_id.unwrap();
+  d1.document
+    .apply_text_delta(&text_id, r#"[{"insert": "Hello world"}]"#.to_string());
+
+  let doc_state = d1.encode_collab().unwrap().doc_state.to_vec();
+  let d2 = Document::open_with_options(
+    CollabOrigin::Empty,
+    DataSource::DocStateV1(doc_state),
+    "1",
+    2,
+  )
+  .unwrap();
+
+  // 1. the second client selects "world"
+  let selection = d2
+    .sticky_selection(
+      &BlockTextPosition::new(&block_id, 6),
+      &BlockTextPosition::new(&block_id, 11),
+    )
+    .unwrap();
+  let mut state = DocumentAwarenessState::new(
+    1,
+    DocumentAwarenessUser {
+      uid: 2,
+      device_id: "device_2".to_string(),
+    },
+  );
+  state.sticky_selection = Some(selection);
+  d2.set_awareness_local_state(state);
+  let update = d2.get_awareness().update().unwrap();
+  d1.get_awareness().apply_update(update).unwrap();
+
+  // 2. the first client inserts text before the selection
+  d1.document
+    .apply_text_delta(&text_id, r#"[{"insert": "Oh, "}]"#.to_string());
+
+  // 3. the selection still covers "world"
+  let selections = d1.resolve_remote_selections();
+  assert_eq!(selections.len(), 1);
+  let resolved = selections.get(&d2.client_id()).unwrap();
+  assert_eq!(resolved.user.uid, 2);
+  assert_eq!(resolved.start, BlockTextPosition::new(&block_id, 10));
+  assert_eq!(resolved.end, BlockTextPosition::new(&block_id, 15));
+  assert_eq!(
+    d1.get_plain_text_from_block(&block_id).unwrap(),
+    "Oh, Hello world"
+  );
+
+  // 4. the local selection of the first client is not included
+  let position = d1
+    .sticky_position(&BlockTextPosition::new(&block_id, 15))
+    .unwrap();
+  let state = DocumentAwarenessState {
+    sticky_selection: d1.sticky_selection(
+      &BlockTextPosition::new(&block_id, 0),
+      &BlockTextPosition::new(&block_id, 2),
+    ),
+    ..DocumentAwarenessState::new(
+      1,
+      DocumentAwarenessUser {
+        uid: 1,
+        device_id: "device_1".to_string(),
+      },
+    )
+  };
+  d1.set_awareness_local_state(state);
+  assert_eq!(d1.resolve_remote_selections().len(), 1);
+
+  // 5. a position at the end of the text sticks to the last character, so text appended by
+  // another client goes after it
+  let update = d1
+    .transact()
+    .encode_state_as_update_v1(&StateVector::default());
+  let mut d2 = d2;
+  d2.apply_update(Update::decode_v1(&update).unwrap())
+    .unwrap();
+  d2.apply_text_delta(&text_id, r#"[{"retain": 15}, {"insert": "!"}]"#.to_string());
+  assert_eq!(
+    d2.resolve_sticky_position(&position).unwrap(),
+    BlockTextPosition::new(&block_id, 15)
+  );
+}
+
 /// the [OldAwarenessUpdate] is the object used before the [AwarenessUpdate] is introduced. In here,
 /// we use the [OldAwarenessUpdate] to simulate the old awareness update object. Try to reproduce
 /// serde issue when decoding the [OldAwarenessUpdate] object with the [AwarenessUpdate] decoder.
@@ -0,0 +1,306 @@
+use std::sync::{Arc, Mutex};
+
+use collab::core::collab::DataSource;
+use collab::core::origin::CollabOrigin;
+use collab_document::comment::{CommentThreadChange, CommentThreadChangeKind, NewComment};
+use collab_document::document::Document;
+use collab_document::error::DocumentError;
+
+use crate::util::DocumentTest;
+
+fn create_document_with_text(text: &str) -> (DocumentTest, String, String) {
+  let mut test = DocumentTest::new(1, "1");
+  let page_id = test.get_page_id().unwrap();
+  let block_id = test.get_block_children_ids(&page_id)[0].clone();
+  let text_id = test.get_block(&block_id).unwrap().external_id.unwrap();
+  test
+    .document
+    .apply_text_delta(&text_id, format!(r#"[{{"insert": "{}"}}]"#, text));
+  (test, block_id, text_id)
+}
+
+#[test]
+fn create_comment_thread_test() {
+  let (mut test, block_id, _) = create_document_with_text("Hello world");
+  let thread = test
+    .document
+    .create_comment_thread(&block_id, 6, 11, NewComment::new(1, "Typo?"))
+    .unwrap();
+  assert_eq!(thread.quote, "world");
+  assert_eq!(thread.block_id(), block_id);
+  assert_eq!(thread.created_by, 1);
+  assert!(!thread.resolved);
+
+  let reply = test
+    .document
+    .reply_comment_thread(
+      &thread.id,
+      NewComment::new(2, "Looks fine to me @3").with_mentions(vec![3]),
+    )
+    .unwrap();
+
+  let saved = test.get_comment_thread(&thread.id).unwrap();
+  assert_eq!(saved, {
+    let mut thread = thread.clone();
+    thread.comments.push(reply.clone());
+    thread
+  });
+  assert_eq!(saved.comments[1].mentions, vec![3]);
+  assert_eq!(test.get_comment_threads_mentioning(3).len(), 1);
+  assert!(test.get_comment_threads_mentioning(1).is_empty());
+
+  test
+    .document
+    .update_comment(&thread.id, &reply.id, "Fixed".to_string(), vec![])
+    .unwrap();
+  let saved = test.get_comment_thread(&thread.id).unwrap();
+  assert_eq!(saved.comments[1].content, "Fixed");
+  assert!(saved.comments[1].updated_at.is_some());
+  assert!(test.get_comment_threads_mentioning(3).is_empty());
+}
+
+#[test]
+fn create_comment_thread_with_invalid_range_test() {
+  let (mut test, block_id, _) = create_document_with_text("Hello");
+  for (start, end) in [(2, 2), (3, 1), (0, 6)] {
+    let result =
+      test
+        .document
+        .create_comment_thread(&block_id, start, end, NewComment::new(1, "a"));
+    assert!(matches!(result, Err(DocumentError::InvalidCommentRange(_))));
+  }
+  let result = test
+    .document
+    .create_comment_thread("unknown", 0, 1, NewComment::new(1, "a"));
+  assert!(matches!(result, Err(DocumentError::BlockIsNotFound)));
+  assert!(test.get_comment_threads().is_empty());
+}
+
+#[test]
+fn comment_range_follows_text_edits_test() {
+  let (mut test, block_id, text_id) = create_document_with_text("Hello world");
+  let thread = test
+    .document
+    .create_comment_thread(&block_id, 6, 11, NewComment::new(1, "a"))
+    .unwrap();
+
+  // Text inserted before the range shifts it
+  test
+    .document
+    .apply_text_delta(&text_id, r#"[{"insert": "Oh, "}]"#.to_string());
+  let range = test.resolve_comment_range(&thread).unwrap();
+  assert_eq!((range.start, range.end), (10, 15));
+
+  // Text typed right before or right after the range is not part of it
+  test.document.apply_text_delta(
+    &text_id,
+    r#"[{"retain": 10}, {"insert": "big "}, {"retain": 5}, {"insert": "!"}]"#.to_string(),
+  );
+  let range = test.resolve_comment_range(&thread).unwrap();
+  assert_eq!((range.start, range.end), (14, 19));
+  assert_eq!(
+    test.get_plain_text_from_block(&block_id).unwrap(),
+    "Oh, Hello big world!"
+  );
+
+  // Deleting the commented text collapses the range
+  test
+    .document
+    .apply_text_delta(&text_id, r#"[{"retain": 14}, {"delete": 5}]"#.to_string());
+  let range = test.resolve_comment_range(&thread).unwrap();
+  assert!(range.is_collapsed());
+
+  // The thread is orphaned once its block is deleted
+  test.document.delete_block(&block_id).unwrap();
+  assert!(test.resolve_comment_range(&thread).is_none());
+  assert_eq!(test.get_comment_threads_for_block(&block_id).len(), 1);
+}
+
+#[test]
+fn resolve_and_reopen_comment_thread_test() {
+  let (mut test, block_id, _) = create_document_with_text("Hello world");
+  let thread_1 = test
+    .document
+    .create_comment_thread(&block_id, 0, 5, NewComment::new(1, "a"))
+    .unwrap();
+  let thread_2 = test
+    .document
+    .create_comment_thread(&block_id, 6, 11, NewComment::new(1, "b"))
+    .unwrap();
+  assert_eq!(test.get_comment_threads_for_block(&block_id).len(), 2);
+  assert!(test.get_comment_threads_for_block("other").is_empty());
+
+  test
+    .document
+    .resolve_comment_thread(&thread_1.id, 2)
+    .unwrap();
+  let resolved = test.get_comment_thread(&thread_1.id).unwrap();
+  assert!(resolved.resolved);
+  assert_eq!(resolved.resolved_by, Some(2));
+  assert!(resolved.resolved_at.is_some());
+  let unresolved = test.get_unresolved_comment_threads();
+  assert_eq!(unresolved.len(), 1);
+  assert_eq!(unresolved[0].id, thread_2.id);
+
+  test.document.reopen_comment_thread(&thread_1.id).unwrap();
+  let reopened = test.get_comment_thread(&thread_1.id).unwrap();
+  assert!(!reopened.resolved);
+  assert!(reopened.resolved_by.is_none());
+  assert_eq!(test.get_unresolved_comment_threads().len(), 2);
+
+  assert!(matches!(
+    test.document.resolve_comment_thread("unknown", 1),
+    Err(DocumentError::CommentThreadNotFound)
+  ));
+}
+
+#[test]
+fn comment_reaction_test() {
+  let (mut test, block_id, _) = create_document_with_text("Hello world");
+  let thread = test
+    .document
+    .create_comment_thread(&block_id, 0, 5, NewComment::new(1, "a"))
+    .unwrap();
+  let comment_id = thread.comments[0].id.clone();
+
+  test
+    .document
+    .add_comment_reaction(&thread.id, &comment_id, 1, "👍")
+    .unwrap();
+  test
+    .document
+    .add_comment_reaction(&thread.id, &comment_id, 2, "👍")
+    .unwrap();
+  test
+    .document
+    .add_comment_reaction(&thread.id, &comment_id, 2, ":tada:")
+    .unwrap();
+  // Reacting twice with the same emoji is a no-op
+  test
+    .document
+    .add_comment_reaction(&thread.id, &comment_id, 1, "👍")
+    .unwrap();
+
+  let comment = test.get_comment_thread(&thread.id).unwrap().comments[0].clone();
+  assert_eq!(comment.reactions.len(), 2);
+  let thumbs_up = comment
+    .reactions
+    .iter()
+    .find(|reaction| reaction.emoji == "👍")
+    .unwrap();
+  assert_eq!(thumbs_up.uids.len(), 2);
+  let tada = comment
+    .reactions
+    .iter()
+    .find(|reaction| reaction.emoji == ":tada:")
+    .unwrap();
+  assert_eq!(tada.uids, vec![2]);
+
+  test
+    .document
+    .remove_comment_reaction(&thread.id, &comment_id, 2, ":tada:")
+    .unwrap();
+  let comment = test.get_comment_thread(&thread.id).unwrap().comments[0].clone();
+  assert_eq!(comment.reactions.len(), 1);
+  assert!(matches!(
+    test
+      .document
+      .add_comment_reaction(&thread.id, "unknown", 1, "👍"),
+    Err(DocumentError::CommentNotFound)
+  ));
+}
+
+#[test]
+fn delete_comment_test() {
+  let (mut test, block_id, _) = create_document_with_text("Hello world");
+  let thread = test
+    .document
+    .create_comment_thread(&block_id, 0, 5, NewComment::new(1, "a"))
+    .unwrap();
+  let reply = test
+    .document
+    .reply_comment_thread(&thread.id, NewComment::new(2, "b"))
+    .unwrap();
+
+  test.document.delete_comment(&thread.id, &reply.id).unwrap();
+  assert_eq!(
+    test.get_comment_thread(&thread.id).unwrap().comments.len(),
+    1
+  );
+
+  // Deleting the last comment deletes the thread
+  test
+    .document
+    .delete_comment(&thread.id, &thread.comments[0].id)
+    .unwrap();
+  assert!(test.get_comment_thread(&thread.id).is_none());
+  assert!(test.get_comment_threads().is_empty());
+}
+
+#[test]
+fn comment_threads_sync_and_undo_test() {
+  let (mut test, block_id, _) = create_document_with_text("Hello world");
+  let thread = test
+    .document
+    .create_comment_thread(&block_id, 6, 11, NewComment::new(1, "a"))
+    .unwrap();
+
+  // Comments are not part of the undo history of the document
+  while test.document.undo() {}
+  assert!(test.get_comment_thread(&thread.id).is_some());
+
+  let doc_state = test.encode_collab().unwrap().doc_state.to_vec();
+  let document = Document::open_with_options(
+    CollabOrigin::Empty,
+    DataSource::DocStateV1(doc_state),
+    "1",
+    2,
+  )
+  .unwrap();
+  assert_eq!(document.get_comment_threads(), vec![thread]);
+}
+
+#[test]
+fn subscribe_comment_changed_test() {
+  let (mut test, block_id, _) = create_document_with_text("Hello world");
+  let received = Arc::new(Mutex::new(Vec::<(Vec<CommentThreadChange>, bool)>::new()));
+  let cloned_received = received.clone();
+  test
+    .document
+    .subscribe_comment_changed("test", move |changes, is_remote| {
+      cloned_received
+        .lock()
+        .unwrap()
+        .push((changes.to_vec(), is_remote));
+    });
+
+  let thread = test
+    .document
+    .create_comment_thread(&block_id, 0, 5, NewComment::new(1, "a"))
+    .unwrap();
+  test
+    .document
+    .reply_comment_thread(&thread.id, NewComment::new(2, "b"))
+    .unwrap();
+  test.document.delete_comment_thread(&thread.id).unwrap();
+
+  let kinds = received
+    .lock()
+    .unwrap()
+    .iter()
+    .map(|(changes, is_remote)| {
+      assert!(!is_remote);
+      assert_eq!(changes.len(), 1);
+      assert_eq!(changes[0].thread_id, thread.id);
+      changes[0].kind
+    })
+    .collect::<Vec<_>>();
+  assert_eq!(
+    kinds,
+    vec![
+      CommentThreadChangeKind::Created,
+      CommentThreadChangeKind::Updated,
+      CommentThreadChangeKind::Deleted,
+    ]
+  );
+}
@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+
+use collab_document::blocks::BlockType;
+use collab_document::error::DocumentError;
+use serde_json::json;
+
+use crate::blocks::block_test_core::BlockTestCore;
+
+fn block_texts(test: &BlockTestCore, parent_id: &str) -> Vec<String> {
+  test
+    .get_block_children(parent_id)
+    .into_iter()
+    .map(|block| {
+      let text_id = block.external_id.unwrap();
+      test.get_text_delta_with_text_id(&text_id)
+    })
+    .collect()
+}
+
+#[test]
+fn extract_fragment_test() {
+  let mut test = BlockTestCore::new();
+  let page_id = test.get_page().id;
+  let first = test.insert_text_block("first".to_string(), &page_id, None);
+  let nested = test.insert_text_block("nested".to_string(), &first.id, None);
+  let second = test.insert_text_block("second".to_string(), &page_id, Some(first.id.clone()));
+
+  // The nested block is copied along with its parent
+  let fragment = test
+    .document
+    .extract_fragment(&[second.id.clone(), nested.id.clone(), first.id.clone()])
+    .unwrap();
+  let page = &fragment.blocks[&fragment.page_id];
+  assert_eq!(page.ty, BlockType::Page.as_str());
+  assert_eq!(
+    fragment.meta.children_map[&page.children],
+    vec![second.id.clone(), first.id.clone()]
+  );
+  assert_eq!(fragment.blocks.len(), 4);
+  assert_eq!(fragment.blocks[&first.id].parent, fragment.page_id);
+  assert_eq!(fragment.blocks[&nested.id].parent, first.id);
+  assert_eq!(
+    fragment.meta.children_map[&first.children],
+    vec![nested.id.clone()]
+  );
+  assert!(fragment.meta.children_map[&nested.children].is_empty());
+
+  let text_map = fragment.meta.text_map.as_ref().unwrap();
+  assert_eq!(text_map.len(), 3);
+  assert_eq!(
+    text_map[nested.external_id.as_ref().unwrap()],
+    json!([{ "insert": "nested" }]).to_string()
+  );
+
+  assert!(matches!(
+    test.document.extract_fragment(&["unknown"]),
+    Err(DocumentError::BlockIsNotFound)
+  ));
+}
+
+#[test]
+fn insert_fragment_test() {
+  let mut source = BlockTestCore::new();
+  let source_page_id = source.get_page().id;
+  let first = source.insert_text_block("first".to_string(), &source_page_id, None);
+  source.insert_text_block("nested".to_string(), &first.id, None);
+  let second = source.insert_text_block(
+    "second".to_string(),
+    &source_page_id,
+    Some(first.id.clone()),
+  );
+  let fragment = source
+    .document
+    .extract_fragment(&[first.id.clone(), second.id.clone()])
+    .unwrap();
+
+  let mut target = BlockTestCore::new();
+  let page = target.get_page();
+  let existing = target.get_block_children(&page.id)[0].clone();
+  let new_ids = target
+    .document
+    .insert_fragment(fragment.clone(), &page.id, Some(existing.id.clone()))
+    .unwrap();
+  assert_eq!(new_ids.len(), 2);
+  assert!(!new_ids.contains(&first.id));
+
+  let children = target.get_block_children(&page.id);
+  let child_ids = children.iter().map(|b| b.id.clone()).collect::<Vec<_>>();
+  assert_eq!(
+    child_ids,
+    vec![existing.id, new_ids[0].clone(), new_ids[1].clone()]
+  );
+  assert_eq!(
+    block_texts(&target, &new_ids[0]),
+    vec![json!([{ "insert": "nested" }]).to_string()]
+  );
+  let pasted = target.get_block(&new_ids[0]);
+  assert_ne!(pasted.children, first.children);
+  assert_ne!(pasted.external_id, first.external_id);
+
+  // The same fragment can be pasted again
+  let again = target
+    .document
+    .insert_fragment(fragment, &page.id, None)
+    .unwrap();
+  let all_ids = new_ids.iter().chain(again.iter()).collect::<HashSet<_>>();
+  assert_eq!(all_ids.len(), 4);
+  assert_eq!(
+    block_texts(&target, &page.id)[0],
+    block_texts(&target, &page.id)[3]
+  );
+  assert!(target.document.validate_blocks().is_valid());
+}
+
+#[test]
+fn insert_fragment_undo_test() {
+  let mut source = BlockTestCore::new();
+  let source_page_id = source.get_page().id;
+  let first = source.insert_text_block("first".to_string(), &source_page_id, None);
+  source.insert_text_block("nested".to_string(), &first.id, None);
+  let fragment = source.document.extract_fragment(&[first.id]).unwrap();
+
+  let mut target = BlockTestCore::new();
+  let page_id = target.get_page().id;
+  let before = target.document.get_all_block_ids().len();
+  target
+    .document
+    .insert_fragment(fragment, &page_id, None)
+    .unwrap();
+  assert_eq!(target.document.get_all_block_ids().len(), before + 2);
+
+  assert!(target.document.undo());
+  assert_eq!(target.document.get_all_block_ids().len(), before);
+  assert!(!target.document.can_undo());
+}
+
+#[test]
+fn insert_fragment_in_suggestion_mode_test() {
+  let mut source = BlockTestCore::new();
+  let source_page_id = source.get_page().id;
+  let first = source.insert_text_block("first".to_string(), &source_page_id, None);
+  source.insert_text_block("nested".to_string(), &first.id, None);
+  let fragment = source.document.extract_fragment(&[first.id]).unwrap();
+
+  let mut target = BlockTestCore::new();
+  let page_id = target.get_page().id;
+  let before = target.document.get_all_block_ids().len();
+  target.document.enable_suggestion_mode(2);
+  let new_ids = target
+    .document
+    .insert_fragment(fragment, &page_id, None)
+    .unwrap();
+
+  // Only the top level block is suggested, the nested one comes along with it
+  let suggestions = target.document.get_suggestions();
+  assert_eq!(suggestions.len(), 1);
+  assert_eq!(suggestions[0].block_id, new_ids[0]);
+
+  assert!(
+    target
+      .document
+      .reject_suggestion(&suggestions[0].id)
+      .is_ok()
+  );
+  assert_eq!(target.document.get_all_block_ids().len(), before);
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use collab_document::blocks::{Block, BlockType};
+use collab_document::link_graph::{
+  Backlink, DocumentLink, DocumentLinkKind, LinkIndex, extract_document_links,
+};
+use serde_json::json;
+
+use crate::blocks::block_test_core::{BlockTestCore, generate_id};
+
+fn insert_data_block(test: &mut BlockTestCore, ty: BlockType, key: &str, value: &str) -> Block {
+  let page_id = test.get_page().id;
+  let block = Block {
+    id: generate_id(),
+    ty: ty.as_str().to_string(),
+    parent: page_id,
+    children: generate_id(),
+    external_id: None,
+    external_type: None,
+    data: HashMap::from([(key.to_string(), json!(value))]),
+  };
+  test.document.insert_block(block, None).unwrap()
+}
+
+fn insert_mention_block(test: &mut BlockTestCore, page_ids: &[&str]) -> Block {
+  let mut delta = vec![json!({ "insert": "see " })];
+  for page_id in page_ids {
+    delta.push(json!({
+      "insert": "$",
+      "attributes": { "mention": { "type": "page", "page_id": page_id } }
+    }));
+  }
+  let external_id = test.create_text(json!(delta).to_string());
+  let mut block = test.get_text_block("".to_string(), &test.get_page().id);
+  block.external_id = Some(external_id);
+  test.document.insert_block(block, None).unwrap()
+}
+
+#[test]
+fn extract_document_links_test() {
+  let mut test = BlockTestCore::new();
+  let sub_page = insert_data_block(&mut test, BlockType::SubPage, "viewId", "v_sub");
+  let grid = insert_data_block(&mut test, BlockType::Grid, "view_id", "v_grid");
+  let preview = insert_data_block(
+    &mut test,
+    BlockType::LinkPreview,
+    "url",
+    "https://appflowy.io",
+  );
+  let mention = insert_mention_block(&mut test, &["v_a", "v_b"]);
+
+  let mut links = test.document.get_links();
+  links.sort_by(|a, b| a.target.cmp(&b.target));
+  assert_eq!(
+    links,
+    vec![
+      DocumentLink {
+        block_id: preview.id.clone(),
+        kind: DocumentLinkKind::LinkPreview,
+        target: "https://appflowy.io".to_string(),
+      },
+      DocumentLink {
+        block_id: mention.id.clone(),
+        kind: DocumentLinkKind::PageMention,
+        target: "v_a".to_string(),
+      },
+      DocumentLink {
+        block_id: mention.id.clone(),
+        kind: DocumentLinkKind::PageMention,
+        target: "v_b".to_string(),
+      },
+      DocumentLink {
+        block_id: grid.id,
+        kind: DocumentLinkKind::InlineDatabase,
+        target: "v_grid".to_string(),
+      },
+      DocumentLink {
+        block_id: sub_page.id,
+        kind: DocumentLinkKind::SubPage,
+        target: "v_sub".to_string(),
+      },
+    ]
+  );
+  assert_eq!(links[0].view_id(), None);
+  assert_eq!(
+    extract_document_links(&test.get_document_data()).len(),
+    links.len()
+  );
+}
+
+#[test]
+fn link_index_backlinks_test() {
+  let mut doc_a = BlockTestCore::new();
+  let mention_a = insert_mention_block(&mut doc_a, &["target"]);
+  let mut doc_b = BlockTestCore::new();
+  let sub_page_b = insert_data_block(&mut doc_b, BlockType::SubPage, "viewId", "target");
+  insert_data_block(&mut doc_b, BlockType::Board, "view_id", "other");
+
+  let mut index = LinkIndex::new();
+  index.index_document("a", &doc_a.get_document_data());
+  index.index_document("b", &doc_b.get_document_data());
+
+  assert_eq!(
+    index.backlinks("target"),
+    vec![
+      Backlink {
+        document_id: "a".to_string(),
+        block_id: mention_a.id,
+        kind: DocumentLinkKind::PageMention,
+      },
+      Backlink {
+        document_id: "b".to_string(),
+        block_id: sub_page_b.id,
+        kind: DocumentLinkKind::SubPage,
+      },
+    ]
+  );
+  assert_eq!(index.linking_documents("other"), vec!["b".to_string()]);
+  assert_eq!(index.outgoing_links("b").len(), 2);
+
+  // Indexing a document again replaces its links
+  index.index_document("a", &BlockTestCore::new().get_document_data());
+  assert_eq!(index.linking_documents("target"), vec!["b".to_string()]);
+
+  index.remove_document("b");
+  assert!(index.backlinks("target").is_empty());
+  assert!(index.backlinks("other").is_empty());
+  assert!(index.outgoing_links("b").is_empty());
+}
+
+#[test]
+fn link_index_incremental_update_test() {
+  let mut test = BlockTestCore::new();
+  let document_id = test.document.object_id().to_string();
+  let index = Arc::new(Mutex::new(LinkIndex::new()));
+  index
+    .lock()
+    .unwrap()
+    .index_document(&document_id, &test.get_document_data());
+  let change_count = Arc::new(Mutex::new(0));
+
+  let cloned_index = index.clone();
+  let cloned_count = change_count.clone();
+  test
+    .document
+    .subscribe_link_changed("links", move |change| {
+      *cloned_count.lock().unwrap() += 1;
+      cloned_index.lock().unwrap().apply_change(change);
+    });
+
+  let block = insert_mention_block(&mut test, &["v1"]);
+  assert_eq!(index.lock().unwrap().backlinks("v1").len(), 1);
+
+  // Typing doesn't change the links
+  let text_id = block.external_id.clone().unwrap();
+  let count = *change_count.lock().unwrap();
+  test.document.apply_text_delta(
+    &text_id,
+    json!([{ "retain": 4 }, { "insert": "page " }]).to_string(),
+  );
+  assert_eq!(*change_count.lock().unwrap(), count);
+
+  // Replace the mention
+  test.document.apply_text_delta(
+    &text_id,
+    json!([
+      { "retain": 9 },
+      { "delete": 1 },
+      { "insert": "$", "attributes": { "mention": { "type": "page", "page_id": "v2" } } }
+    ])
+    .to_string(),
+  );
+  assert!(index.lock().unwrap().backlinks("v1").is_empty());
+  assert_eq!(index.lock().unwrap().backlinks("v2")[0].block_id, block.id);
+
+  // Update the data of a block
+  let grid = insert_data_block(&mut test, BlockType::Grid, "view_id", "grid_1");
+  test
+    .document
+    .update_block(
+      &grid.id,
+      HashMap::from([("view_id".to_string(), json!("grid_2"))]),
+    )
+    .unwrap();
+  assert!(index.lock().unwrap().backlinks("grid_1").is_empty());
+  assert_eq!(index.lock().unwrap().backlinks("grid_2").len(), 1);
+
+  // Delete the blocks
+  test.delete_block(&block.id);
+  test.delete_block(&grid.id);
+  assert!(index.lock().unwrap().backlinks("v2").is_empty());
+  assert!(index.lock().unwrap().backlinks("grid_2").is_empty());
+  assert!(
+    index
+      .lock()
+      .unwrap()
+      .outgoing_links(&document_id)
+      .is_empty()
+  );
+}
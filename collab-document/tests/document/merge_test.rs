@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+
+use collab_document::blocks::{
+  Block, BlockDataFieldType, BlockDataSchema, BlockType, DocumentData, DocumentMeta,
+};
+use collab_document::document::Document;
+use collab_document::document_merge::{MergeConflict, MergeConflictKind, merge_document_data};
+use collab_document::error::DocumentError;
+use serde_json::json;
+
+use crate::blocks::block_test_core::{BlockTestCore, generate_id};
+
+fn page_texts(document: &Document) -> Vec<String> {
+  let page_id = document.get_page_id().unwrap();
+  document
+    .get_block_children_ids(&page_id)
+    .iter()
+    .map(|block_id| document.get_plain_text_from_block(block_id).unwrap())
+    .collect()
+}
+
+fn insert_paragraph(document: &mut Document, text: &str, prev_id: Option<String>) -> Block {
+  let text_id = generate_id();
+  document.apply_text_delta(&text_id, json!([{ "insert": text }]).to_string());
+  let block = Block {
+    id: generate_id(),
+    ty: "paragraph".to_string(),
+    parent: document.get_page_id().unwrap(),
+    children: generate_id(),
+    external_id: Some(text_id),
+    external_type: Some("text".to_string()),
+    data: HashMap::new(),
+  };
+  document.insert_block(block, prev_id).unwrap()
+}
+
+/// Appends paragraphs after the empty paragraph of the default document.
+fn create_base(test: &mut BlockTestCore, texts: &[&str]) -> Vec<Block> {
+  let page_id = test.get_page().id;
+  let mut prev_id = test.get_block_children(&page_id)[0].id.clone();
+  let mut blocks = vec![];
+  for text in texts {
+    let block = insert_paragraph(&mut test.document, text, Some(prev_id));
+    prev_id = block.id.clone();
+    blocks.push(block);
+  }
+  blocks
+}
+
+fn text_id(block: &Block) -> &str {
+  block.external_id.as_deref().unwrap()
+}
+
+/// The same document with new block, children and text ids, like a page imported again.
+fn with_new_ids(data: &DocumentData) -> DocumentData {
+  let mut ids = HashMap::new();
+  let mut new_id = |id: &str| {
+    ids
+      .entry(id.to_string())
+      .or_insert_with(generate_id)
+      .clone()
+  };
+  let page_id = new_id(&data.page_id);
+  let blocks = data
+    .blocks
+    .values()
+    .map(|block| {
+      let block = Block {
+        id: new_id(&block.id),
+        parent: if block.parent.is_empty() {
+          String::new()
+        } else {
+          new_id(&block.parent)
+        },
+        children: new_id(&block.children),
+        external_id: block.external_id.as_deref().map(&mut new_id),
+        ..block.clone()
+      };
+      (block.id.clone(), block)
+    })
+    .collect();
+  let children_map = data
+    .meta
+    .children_map
+    .iter()
+    .map(|(id, children)| (new_id(id), children.iter().map(|id| new_id(id)).collect()))
+    .collect();
+  let text_map = data.meta.text_map.as_ref().map(|text_map| {
+    text_map
+      .iter()
+      .map(|(id, delta)| (new_id(id), delta.clone()))
+      .collect()
+  });
+  DocumentData {
+    page_id,
+    blocks,
+    meta: DocumentMeta {
+      children_map,
+      text_map,
+    },
+  }
+}
+
+#[test]
+fn merge_document_without_conflicts_test() {
+  let mut test = BlockTestCore::new();
+  let base_blocks = create_base(&mut test, &["Hello 🌍 world", "Second", "Third"]);
+  let (a, b, c) = (&base_blocks[0], &base_blocks[1], &base_blocks[2]);
+  test.update_block_data(&b.id, HashMap::from([("k".to_string(), json!(1))]));
+  let base = test.get_document_data();
+
+  let mut theirs = Document::create("theirs", base.clone(), 1).unwrap();
+  // The emoji is two UTF-16 code units
+  theirs.apply_text_delta(
+    text_id(a),
+    json!([{ "retain": 14 }, { "insert": "!" }]).to_string(),
+  );
+  theirs
+    .update_block(&b.id, HashMap::from([("k".to_string(), json!(2))]))
+    .unwrap();
+  insert_paragraph(&mut theirs, "New", Some(b.id.clone()));
+  theirs.delete_block(&c.id).unwrap();
+  let theirs = theirs.get_document_data().unwrap();
+
+  test.document.apply_text_delta(
+    text_id(a),
+    json!([{ "retain": 6 }, { "insert": "brave " }]).to_string(),
+  );
+  insert_paragraph(&mut test.document, "Mine", Some(c.id.clone()));
+
+  let conflicts = test.document.merge_document(&base, &theirs).unwrap();
+  assert!(conflicts.is_empty());
+  assert_eq!(
+    page_texts(&test.document),
+    vec!["", "Hello brave 🌍 world!", "Second", "New", "Mine"]
+  );
+  assert_eq!(test.get_block(&b.id).data["k"], json!(2));
+  assert!(test.document.get_block(&c.id).is_none());
+
+  // Merging again changes nothing
+  let ours = test.get_document_data();
+  let merged = merge_document_data(&base, &ours, &theirs);
+  assert!(merged.actions.is_empty());
+}
+
+#[test]
+fn merge_document_conflicts_test() {
+  let mut test = BlockTestCore::new();
+  let base_blocks = create_base(&mut test, &["Hello world", "b", "c"]);
+  let (a, b, c) = (&base_blocks[0], &base_blocks[1], &base_blocks[2]);
+  test.update_block_data(&b.id, HashMap::from([("k".to_string(), json!(1))]));
+  let base = test.get_document_data();
+
+  let mut theirs = Document::create("theirs", base.clone(), 1).unwrap();
+  theirs.apply_text_delta(
+    text_id(a),
+    json!([{ "retain": 6 }, { "delete": 5 }, { "insert": "there" }]).to_string(),
+  );
+  theirs
+    .update_block(&b.id, HashMap::from([("k".to_string(), json!(3))]))
+    .unwrap();
+  theirs.delete_block(&c.id).unwrap();
+  let theirs = theirs.get_document_data().unwrap();
+
+  test.document.apply_text_delta(
+    text_id(a),
+    json!([{ "retain": 6 }, { "delete": 5 }, { "insert": "folks" }]).to_string(),
+  );
+  test.update_block_data(&b.id, HashMap::from([("k".to_string(), json!(2))]));
+  test.document.apply_text_delta(
+    text_id(c),
+    json!([{ "retain": 1 }, { "insert": "!" }]).to_string(),
+  );
+
+  let conflicts = test.document.merge_document(&base, &theirs).unwrap();
+  assert_eq!(
+    conflicts,
+    vec![
+      MergeConflict {
+        block_id: a.id.clone(),
+        kind: MergeConflictKind::Text,
+      },
+      MergeConflict {
+        block_id: b.id.clone(),
+        kind: MergeConflictKind::Data {
+          key: "k".to_string()
+        },
+      },
+      MergeConflict {
+        block_id: c.id.clone(),
+        kind: MergeConflictKind::DeletedByTheirs,
+      },
+    ]
+  );
+  // The conflicts are resolved in favor of ours
+  assert_eq!(
+    page_texts(&test.document),
+    vec!["", "Hello folks", "b", "c!"]
+  );
+  assert_eq!(test.get_block(&b.id).data["k"], json!(2));
+}
+
+#[test]
+fn merge_document_with_new_ids_test() {
+  let mut test = BlockTestCore::new();
+  create_base(
+    &mut test,
+    &["Alpha paragraph", "Beta paragraph", "Gamma paragraph"],
+  );
+  let base = test.get_document_data();
+  let block_count = base.blocks.len();
+
+  // The page imported again, then edited
+  let theirs_data = with_new_ids(&base);
+  let theirs_id = theirs_data.page_id.clone();
+  let mut theirs = Document::create("theirs", theirs_data, 1).unwrap();
+  let children = theirs.get_block_children_ids(&theirs_id);
+  let (beta, gamma) = (&children[2], &children[3]);
+  let beta_text_id = theirs.get_block(beta).unwrap().external_id.unwrap();
+  theirs.apply_text_delta(
+    &beta_text_id,
+    json!([{ "retain": 14 }, { "insert": " edited" }]).to_string(),
+  );
+  theirs
+    .move_block(gamma, Some(theirs_id.clone()), Some(children[0].clone()))
+    .unwrap();
+  let theirs = theirs.get_document_data().unwrap();
+
+  let conflicts = test.document.merge_document(&base, &theirs).unwrap();
+  assert!(conflicts.is_empty());
+  assert_eq!(
+    page_texts(&test.document),
+    vec![
+      "",
+      "Gamma paragraph",
+      "Alpha paragraph",
+      "Beta paragraph edited"
+    ]
+  );
+  // The blocks are matched, not duplicated
+  assert_eq!(test.get_document_data().blocks.len(), block_count);
+}
+
+#[test]
+fn merge_document_matches_new_ids_under_the_same_parent_test() {
+  let mut test = BlockTestCore::new();
+  create_base(&mut test, &["Alpha paragraph", "Beta paragraph"]);
+  let base = test.get_document_data();
+
+  // Theirs recreated the first paragraph as a child of the second one
+  let theirs_data = with_new_ids(&base);
+  let theirs_id = theirs_data.page_id.clone();
+  let mut theirs = Document::create("theirs", theirs_data, 1).unwrap();
+  let children = theirs.get_block_children_ids(&theirs_id);
+  let (alpha, beta) = (children[1].clone(), children[2].clone());
+  theirs.move_block(&alpha, Some(beta), None).unwrap();
+  let theirs = theirs.get_document_data().unwrap();
+
+  let ours = test.get_document_data();
+  let alpha_id = test.get_block_children(&ours.page_id)[1].id.clone();
+  test.document.merge_document(&base, &theirs).unwrap();
+  // The block under another parent is not matched, it is inserted and the old one deleted
+  assert!(test.document.get_block(&alpha_id).is_none());
+  assert_eq!(page_texts(&test.document), vec!["", "Beta paragraph"]);
+  let beta_id = test.get_block_children(&ours.page_id)[1].id.clone();
+  let beta_children = test.document.get_block_children_ids(&beta_id);
+  assert_eq!(beta_children.len(), 1);
+  assert_eq!(
+    test
+      .document
+      .get_plain_text_from_block(&beta_children[0])
+      .unwrap(),
+    "Alpha paragraph"
+  );
+}
+
+#[test]
+fn merge_document_writes_nothing_on_error_test() {
+  let mut test = BlockTestCore::new();
+  let base_blocks = create_base(&mut test, &["Hello"]);
+  let base = test.get_document_data();
+
+  let mut theirs = Document::create("theirs", base.clone(), 1).unwrap();
+  theirs.apply_text_delta(
+    text_id(&base_blocks[0]),
+    json!([{ "retain": 5 }, { "insert": " world" }]).to_string(),
+  );
+  let card = Block {
+    id: generate_id(),
+    ty: "kanban_card".to_string(),
+    parent: base.page_id.clone(),
+    children: generate_id(),
+    external_id: None,
+    external_type: None,
+    data: HashMap::new(),
+  };
+  theirs
+    .insert_block(card, Some(base_blocks[0].id.clone()))
+    .unwrap();
+  let theirs = theirs.get_document_data().unwrap();
+
+  test.document.register_block_schema(
+    BlockType::Custom("kanban_card".to_string()),
+    BlockDataSchema::new().required("title", BlockDataFieldType::String),
+  );
+  assert!(matches!(
+    test.document.merge_document(&base, &theirs),
+    Err(DocumentError::InvalidBlockData { .. })
+  ));
+  assert_eq!(test.get_document_data(), base);
+}
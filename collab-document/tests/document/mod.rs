@@ -1,5 +1,14 @@
 mod awareness_test;
+mod comment_test;
 mod document_data_test;
 mod document_test;
+mod fragment_test;
+mod link_graph_test;
+mod merge_test;
+mod nested_json_test;
 mod redo_undo_test;
 mod restore_test;
+mod stats_test;
+mod suggestion_test;
+mod template_test;
+mod undo_scope_test;
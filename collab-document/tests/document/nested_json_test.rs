@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+
+use collab_document::blocks::DocumentData;
+use collab_document::blocks::{Block, BlockType};
+use collab_document::document::Document;
+use collab_document::error::DocumentError;
+use collab_document::nested_json::{NESTED_DOCUMENT_VERSION, nested_document_schema};
+use serde_json::{Value, json};
+
+use crate::blocks::block_test_core::{BlockTestCore, generate_id};
+
+const TEXT_BLOCK_TYPES: [BlockType; 9] = [
+  BlockType::Paragraph,
+  BlockType::TodoList,
+  BlockType::BulletedList,
+  BlockType::NumberedList,
+  BlockType::Quote,
+  BlockType::Heading,
+  BlockType::Callout,
+  BlockType::Code,
+  BlockType::ToggleList,
+];
+
+const DATA_BLOCK_TYPES: [BlockType; 21] = [
+  BlockType::Image,
+  BlockType::Divider,
+  BlockType::MultiImage,
+  BlockType::Grid,
+  BlockType::Board,
+  BlockType::Calendar,
+  BlockType::MathEquation,
+  BlockType::AiWriter,
+  BlockType::Outline,
+  BlockType::LinkPreview,
+  BlockType::Video,
+  BlockType::File,
+  BlockType::SubPage,
+  BlockType::Error,
+  BlockType::SimpleTable,
+  BlockType::SimpleTableRow,
+  BlockType::SimpleTableCell,
+  BlockType::SimpleColumns,
+  BlockType::SimpleColumn,
+  BlockType::Table,
+  BlockType::TableCell,
+];
+
+fn append_block(
+  test: &mut BlockTestCore,
+  ty: &BlockType,
+  delta: Option<Value>,
+  parent_id: &str,
+) -> Block {
+  let external_id = delta.map(|delta| test.create_text(delta.to_string()));
+  let prev_id = test
+    .get_block_children(parent_id)
+    .last()
+    .map(|block| block.id.clone());
+  let block = Block {
+    id: generate_id(),
+    ty: ty.as_str().to_string(),
+    parent: parent_id.to_string(),
+    children: generate_id(),
+    external_type: external_id.as_ref().map(|_| "text".to_string()),
+    external_id,
+    data: HashMap::from([
+      ("type".to_string(), json!(ty.as_str())),
+      (
+        "nested".to_string(),
+        json!({ "list": [1, 2.5, null], "flag": true }),
+      ),
+    ]),
+  };
+  test.document.insert_block(block, prev_id).unwrap()
+}
+
+/// A block of every built-in type, the text blocks have a formatted text and a nested paragraph.
+fn create_document() -> BlockTestCore {
+  let mut test = BlockTestCore::new();
+  let page_id = test.get_page().id;
+  for ty in TEXT_BLOCK_TYPES.iter() {
+    let delta = json!([
+      { "insert": "Hello " },
+      { "insert": "world 🌍", "attributes": { "bold": true, "href": "https://example.com" } },
+    ]);
+    let block = append_block(&mut test, ty, Some(delta), &page_id);
+    append_block(
+      &mut test,
+      &BlockType::Paragraph,
+      Some(json!([{ "insert": "child" }])),
+      &block.id,
+    );
+  }
+  for ty in DATA_BLOCK_TYPES.iter() {
+    append_block(&mut test, ty, None, &page_id);
+  }
+  test
+}
+
+#[test]
+fn nested_json_round_trip_test() {
+  let test = create_document();
+  let data = test.get_document_data();
+  let json = test.document.to_nested_json().unwrap();
+
+  assert_eq!(json["version"], json!(NESTED_DOCUMENT_VERSION));
+  assert_eq!(json["document"]["type"], json!("page"));
+  let children = json["document"]["children"].as_array().unwrap();
+  // The empty paragraph of the default document comes first
+  assert_eq!(
+    children.len(),
+    1 + TEXT_BLOCK_TYPES.len() + DATA_BLOCK_TYPES.len()
+  );
+  let heading = &children[6];
+  assert_eq!(heading["type"], json!("heading"));
+  assert_eq!(heading["delta"][1]["attributes"]["bold"], json!(true));
+  assert_eq!(
+    heading["children"][0]["delta"],
+    json!([{ "insert": "child" }])
+  );
+
+  let imported = DocumentData::from_nested_json(json.clone()).unwrap();
+  assert_same_document(&imported, &data);
+  let document = Document::create(&uuid::Uuid::new_v4().to_string(), imported, 1).unwrap();
+  assert_same_document(&document.get_document_data().unwrap(), &data);
+  assert_eq!(document.to_nested_json().unwrap(), json);
+
+  // The json can be serialized and parsed again
+  let json = serde_json::from_str::<Value>(&json.to_string()).unwrap();
+  let imported = DocumentData::from_nested_json(json).unwrap();
+  assert_same_document(&imported, &data);
+}
+
+/// The order of the keys of the delta attributes may change.
+fn assert_same_document(left: &DocumentData, right: &DocumentData) {
+  let text_map = |data: &DocumentData| {
+    data
+      .meta
+      .text_map
+      .iter()
+      .flatten()
+      .map(|(id, delta)| (id.clone(), serde_json::from_str::<Value>(delta).unwrap()))
+      .collect::<HashMap<_, _>>()
+  };
+  assert_eq!(left.page_id, right.page_id);
+  assert_eq!(left.blocks, right.blocks);
+  assert_eq!(left.meta.children_map, right.meta.children_map);
+  assert_eq!(text_map(left), text_map(right));
+}
+
+#[test]
+fn import_nested_json_without_ids_test() {
+  let json = json!({
+    "version": 1,
+    "document": {
+      "type": "page",
+      "children": [
+        {
+          "type": "heading",
+          "data": { "level": 1 },
+          "delta": [{ "insert": "Title" }]
+        },
+        {
+          "type": "bulleted_list",
+          "delta": [{ "insert": "Item" }],
+          "children": [{ "type": "paragraph", "delta": [{ "insert": "Nested" }] }]
+        },
+        { "type": "divider" }
+      ]
+    }
+  });
+  let data = DocumentData::from_nested_json(json).unwrap();
+  assert_eq!(data.blocks.len(), 5);
+  let divider = data
+    .blocks
+    .values()
+    .find(|block| block.ty == "divider")
+    .unwrap();
+  assert_eq!(divider.parent, data.page_id);
+  assert!(divider.external_id.is_none());
+
+  let document = Document::create(&uuid::Uuid::new_v4().to_string(), data, 1).unwrap();
+  assert_eq!(document.to_plain_text()[..3], ["Title", "Item", "Nested"]);
+}
+
+#[test]
+fn export_repeated_children_once_test() {
+  let mut test = BlockTestCore::new();
+  let page_id = test.get_page().id;
+  let block = test.insert_text_block("hello".to_string(), &page_id, None);
+
+  // The block is listed twice and contains the page
+  let mut data = test.get_document_data();
+  let page_children = data.blocks[&page_id].children.clone();
+  let children_map = &mut data.meta.children_map;
+  children_map
+    .get_mut(&page_children)
+    .unwrap()
+    .push(block.id.clone());
+  children_map.insert(block.children.clone(), vec![page_id]);
+
+  let document = data.to_nested_document().unwrap();
+  let ids = document
+    .document
+    .children
+    .iter()
+    .map(|child| child.id.clone().unwrap())
+    .collect::<Vec<_>>();
+  assert_eq!(ids.len(), 2);
+  assert_eq!(ids.iter().filter(|id| **id == block.id).count(), 1);
+  let exported = document
+    .document
+    .children
+    .iter()
+    .find(|child| child.id.as_ref() == Some(&block.id))
+    .unwrap();
+  assert!(exported.children.is_empty());
+}
+
+#[test]
+fn import_invalid_nested_json_test() {
+  let result = DocumentData::from_nested_json(json!({
+    "version": NESTED_DOCUMENT_VERSION + 1,
+    "document": { "type": "page" }
+  }));
+  assert!(matches!(
+    result,
+    Err(DocumentError::UnsupportedNestedDocumentVersion(version)) if version == NESTED_DOCUMENT_VERSION + 1
+  ));
+
+  let result = DocumentData::from_nested_json(json!({
+    "version": 1,
+    "document": {
+      "type": "page",
+      "children": [{ "type": "paragraph", "id": "a" }, { "type": "paragraph", "id": "a" }]
+    }
+  }));
+  assert!(matches!(
+    result,
+    Err(DocumentError::InvalidNestedDocument(_))
+  ));
+
+  let result = DocumentData::from_nested_json(json!({ "document": { "type": "page" } }));
+  assert!(matches!(
+    result,
+    Err(DocumentError::InvalidNestedDocument(_))
+  ));
+}
+
+#[test]
+fn nested_document_schema_test() {
+  let schema = nested_document_schema();
+  assert_eq!(
+    schema["properties"]["version"]["const"],
+    json!(NESTED_DOCUMENT_VERSION)
+  );
+  assert_eq!(
+    schema["$defs"]["block"]["properties"]["type"]["examples"]
+      .as_array()
+      .unwrap()
+      .len(),
+    TEXT_BLOCK_TYPES.len() + DATA_BLOCK_TYPES.len() + 1
+  );
+}
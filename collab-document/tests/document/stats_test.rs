@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use collab_document::blocks::{Block, BlockType};
+use collab_document::document_stats::{DocumentStats, count_words};
+use serde_json::{Value, json};
+
+use crate::blocks::block_test_core::{BlockTestCore, generate_id};
+
+fn insert_block(
+  test: &mut BlockTestCore,
+  ty: BlockType,
+  delta: Option<Value>,
+  data: HashMap<String, Value>,
+) -> Block {
+  let external_id = delta.map(|delta| test.create_text(delta.to_string()));
+  let block = Block {
+    id: generate_id(),
+    ty: ty.as_str().to_string(),
+    parent: test.get_page().id,
+    children: generate_id(),
+    external_type: external_id.as_ref().map(|_| "text".to_string()),
+    external_id,
+    data,
+  };
+  test.document.insert_block(block, None).unwrap()
+}
+
+fn insert_todo(test: &mut BlockTestCore, text: &str, checked: bool) -> Block {
+  insert_block(
+    test,
+    BlockType::TodoList,
+    Some(json!([{ "insert": text }])),
+    HashMap::from([("checked".to_string(), json!(checked))]),
+  )
+}
+
+#[test]
+fn count_words_test() {
+  assert_eq!(count_words(""), 0);
+  assert_eq!(count_words("  Hello,   world!  "), 2);
+  assert_eq!(count_words("don't stop the well-known show - ok"), 6);
+  assert_eq!(count_words("你好世界"), 4);
+  assert_eq!(count_words("Hello 世界!"), 3);
+  assert_eq!(count_words("こんにちは、AppFlowy"), 6);
+  assert_eq!(count_words("안녕하세요 세계"), 2);
+}
+
+#[test]
+fn document_stats_test() {
+  let mut test = BlockTestCore::new();
+  insert_block(
+    &mut test,
+    BlockType::Paragraph,
+    Some(json!([
+      { "insert": "Hello " },
+      { "insert": "$", "attributes": { "mention": { "type": "page", "page_id": "v1" } } },
+      { "insert": " 世界" }
+    ])),
+    HashMap::new(),
+  );
+  insert_todo(&mut test, "buy milk", false);
+  insert_todo(&mut test, "walk", true);
+  insert_block(
+    &mut test,
+    BlockType::Image,
+    None,
+    HashMap::from([("url".to_string(), json!("https://appflowy.io/1.png"))]),
+  );
+  insert_block(
+    &mut test,
+    BlockType::MultiImage,
+    None,
+    HashMap::from([(
+      "images".to_string(),
+      json!([{ "url": "a" }, { "url": "b" }]),
+    )]),
+  );
+  insert_block(
+    &mut test,
+    BlockType::File,
+    None,
+    HashMap::from([("url".to_string(), json!("https://appflowy.io/a.pdf"))]),
+  );
+
+  let stats = test.document.stats().unwrap();
+  // "Hello  世界", "buy milk" and "walk", the mention is not counted
+  assert_eq!(stats.word_count, 6);
+  assert_eq!(stats.character_count, 18);
+  assert_eq!(stats.character_count_with_spaces, 21);
+  // The empty paragraph of the default document is counted
+  assert_eq!(stats.block_count, 7);
+  assert_eq!(stats.block_counts["paragraph"], 2);
+  assert_eq!(stats.block_counts["todo_list"], 2);
+  assert!(!stats.block_counts.contains_key("page"));
+  assert_eq!(stats.image_count, 3);
+  assert_eq!(stats.file_count, 1);
+  assert_eq!(stats.todo_count, 2);
+  assert_eq!(stats.unchecked_todo_count, 1);
+
+  assert_eq!(stats.reading_time(), Duration::from_secs(2));
+  assert_eq!(stats.reading_time_with_speed(6), Duration::from_secs(60));
+  assert_eq!(
+    DocumentStats::from_document_data(&test.get_document_data()),
+    stats
+  );
+}
+
+#[test]
+fn document_stats_incremental_update_test() {
+  let mut test = BlockTestCore::new();
+  let latest = Arc::new(Mutex::new(None::<DocumentStats>));
+  let cloned_latest = latest.clone();
+  test
+    .document
+    .subscribe_stats_changed("stats", move |stats| {
+      *cloned_latest.lock().unwrap() = Some(stats.clone());
+    });
+  let latest_stats = || latest.lock().unwrap().clone().unwrap();
+
+  let todo = insert_todo(&mut test, "buy milk", false);
+  assert_eq!(latest_stats(), test.document.stats().unwrap());
+  assert_eq!(latest_stats().word_count, 2);
+  assert_eq!(latest_stats().unchecked_todo_count, 1);
+
+  // Edit the text
+  let text_id = todo.external_id.clone().unwrap();
+  test.document.apply_text_delta(
+    &text_id,
+    json!([{ "retain": 8 }, { "insert": " and 牛奶" }]).to_string(),
+  );
+  assert_eq!(latest_stats().word_count, 5);
+  assert_eq!(latest_stats(), test.document.stats().unwrap());
+
+  // Check the todo
+  test
+    .document
+    .update_block(
+      &todo.id,
+      HashMap::from([("checked".to_string(), json!(true))]),
+    )
+    .unwrap();
+  assert_eq!(latest_stats().unchecked_todo_count, 0);
+  assert_eq!(latest_stats().todo_count, 1);
+
+  // Delete the todo
+  test.delete_block(&todo.id);
+  let stats = latest_stats();
+  assert_eq!(stats, test.document.stats().unwrap());
+  assert_eq!(stats.word_count, 0);
+  assert_eq!(stats.todo_count, 0);
+  assert!(!stats.block_counts.contains_key("todo_list"));
+}
@@ -0,0 +1,215 @@
+use collab_document::document::Document;
+use collab_document::error::DocumentError;
+use collab_document::suggestion::{DocumentRenderOptions, SuggestionKind, SuggestionRenderMode};
+use serde_json::{Value, json};
+
+use crate::blocks::block_test_core::BlockTestCore;
+
+const REVIEWER: i64 = 2;
+const OTHER_REVIEWER: i64 = 3;
+
+fn plain_text(document: &Document, mode: SuggestionRenderMode) -> Vec<String> {
+  document
+    .to_plain_text_with_options(&DocumentRenderOptions::default().with_suggestions(mode))
+    .into_iter()
+    .filter(|line| !line.is_empty())
+    .collect()
+}
+
+fn create_test_with_text(text: &str) -> (BlockTestCore, String, String) {
+  let mut test = BlockTestCore::new();
+  let page = test.get_page();
+  let block = test.insert_text_block(text.to_string(), &page.id, None);
+  let text_id = block.external_id.clone().unwrap();
+  (test, block.id, text_id)
+}
+
+#[test]
+fn suggest_text_insert_and_delete_test() {
+  let (mut test, _, text_id) = create_test_with_text("Hello world");
+  test.document.enable_suggestion_mode(REVIEWER);
+  assert_eq!(test.document.suggestion_author(), Some(REVIEWER));
+
+  test.document.apply_text_delta(
+    &text_id,
+    json!([{ "retain": 6 }, { "insert": "big " }]).to_string(),
+  );
+  test
+    .document
+    .apply_text_delta(&text_id, json!([{ "delete": 6 }]).to_string());
+
+  assert_eq!(
+    plain_text(&test.document, SuggestionRenderMode::Raw),
+    vec!["Hello big world"]
+  );
+  assert_eq!(
+    plain_text(&test.document, SuggestionRenderMode::Accepted),
+    vec!["big world"]
+  );
+  assert_eq!(
+    plain_text(&test.document, SuggestionRenderMode::Rejected),
+    vec!["Hello world"]
+  );
+  // Without options the text is rendered as stored
+  assert_eq!(
+    test
+      .document
+      .to_plain_text()
+      .into_iter()
+      .filter(|line| !line.is_empty())
+      .collect::<Vec<_>>(),
+    vec!["Hello big world"]
+  );
+
+  let suggestions = test.document.get_suggestions();
+  assert_eq!(suggestions.len(), 2);
+  let insert = suggestions
+    .iter()
+    .find(|suggestion| suggestion.kind == SuggestionKind::InsertText)
+    .unwrap();
+  assert_eq!(insert.text, "big ");
+  assert_eq!(insert.author, REVIEWER);
+  let delete = suggestions
+    .iter()
+    .find(|suggestion| suggestion.kind == SuggestionKind::DeleteText)
+    .unwrap();
+  assert_eq!(delete.text, "Hello ");
+
+  test.document.disable_suggestion_mode();
+  test.document.accept_suggestion(&insert.id).unwrap();
+  test.document.reject_suggestion(&delete.id).unwrap();
+  assert!(test.document.get_suggestions().is_empty());
+  assert_eq!(
+    plain_text(&test.document, SuggestionRenderMode::Raw),
+    vec!["Hello big world"]
+  );
+
+  assert!(matches!(
+    test.document.accept_suggestion(&insert.id),
+    Err(DocumentError::SuggestionNotFound)
+  ));
+}
+
+#[test]
+fn continuous_suggestions_are_merged_test() {
+  let (mut test, _, text_id) = create_test_with_text("ab");
+  test.document.enable_suggestion_mode(REVIEWER);
+  for (index, c) in ["x", "y", "z"].iter().enumerate() {
+    test.document.apply_text_delta(
+      &text_id,
+      json!([{ "retain": 1 + index }, { "insert": c }]).to_string(),
+    );
+  }
+  let suggestions = test.document.get_suggestions();
+  assert_eq!(suggestions.len(), 1);
+  assert_eq!(suggestions[0].text, "xyz");
+
+  // Deleting the own suggested text removes it right away
+  test.document.apply_text_delta(
+    &text_id,
+    json!([{ "retain": 2 }, { "delete": 2 }]).to_string(),
+  );
+  assert_eq!(
+    plain_text(&test.document, SuggestionRenderMode::Raw),
+    vec!["axb"]
+  );
+
+  // Deleting the text of another author is a suggestion
+  test.document.enable_suggestion_mode(OTHER_REVIEWER);
+  test.document.apply_text_delta(
+    &text_id,
+    json!([{ "retain": 1 }, { "delete": 2 }]).to_string(),
+  );
+  assert_eq!(
+    plain_text(&test.document, SuggestionRenderMode::Raw),
+    vec!["axb"]
+  );
+  assert_eq!(
+    plain_text(&test.document, SuggestionRenderMode::Accepted),
+    vec!["a"]
+  );
+}
+
+#[test]
+fn suggest_block_insert_and_delete_test() {
+  let (mut test, block_id, _) = create_test_with_text("Keep me");
+  let page = test.get_page();
+  let insert_action =
+    test.get_insert_action("New block".to_string(), &page.id, Some(block_id.clone()));
+  let delete_action = test.get_delete_action(&block_id);
+
+  test.document.enable_suggestion_mode(REVIEWER);
+  assert!(test.apply_action(vec![insert_action, delete_action]));
+  assert_eq!(test.get_block(&block_id).id, block_id);
+  assert_eq!(
+    plain_text(&test.document, SuggestionRenderMode::Raw),
+    vec!["Keep me", "New block"]
+  );
+  assert_eq!(
+    plain_text(&test.document, SuggestionRenderMode::Accepted),
+    vec!["New block"]
+  );
+  assert_eq!(
+    plain_text(&test.document, SuggestionRenderMode::Rejected),
+    vec!["Keep me"]
+  );
+
+  let mut kinds = test
+    .document
+    .get_suggestions()
+    .into_iter()
+    .map(|suggestion| suggestion.kind)
+    .collect::<Vec<_>>();
+  kinds.sort_by_key(|kind| *kind as u8);
+  assert_eq!(
+    kinds,
+    vec![SuggestionKind::InsertBlock, SuggestionKind::DeleteBlock]
+  );
+
+  // Updating the block keeps its suggestion
+  let update_action = test.get_update_action("Keep me".to_string(), &block_id);
+  assert!(test.apply_action(vec![update_action]));
+  assert_eq!(test.document.get_suggestions().len(), 2);
+
+  // A null suggestion clears it
+  let mut update_action = test.get_update_action("Keep me".to_string(), &block_id);
+  if let Some(block) = update_action.payload.block.as_mut() {
+    block.data.insert("suggestion".to_string(), Value::Null);
+  }
+  assert!(test.apply_action(vec![update_action]));
+  let suggestions = test.document.get_suggestions();
+  assert_eq!(suggestions.len(), 1);
+  assert_eq!(suggestions[0].kind, SuggestionKind::InsertBlock);
+
+  assert_eq!(test.document.reject_all_suggestions(None), 1);
+  assert!(test.document.get_suggestions().is_empty());
+  assert_eq!(
+    plain_text(&test.document, SuggestionRenderMode::Raw),
+    vec!["Keep me"]
+  );
+}
+
+#[test]
+fn accept_all_suggestions_of_author_test() {
+  let (mut test, block_id, text_id) = create_test_with_text("Hello");
+  test.document.enable_suggestion_mode(REVIEWER);
+  test.document.apply_text_delta(
+    &text_id,
+    json!([{ "retain": 5 }, { "insert": " world" }]).to_string(),
+  );
+  test.document.enable_suggestion_mode(OTHER_REVIEWER);
+  let delete_action = test.get_delete_action(&block_id);
+  assert!(test.apply_action(vec![delete_action]));
+
+  assert_eq!(test.document.accept_all_suggestions(Some(REVIEWER)), 1);
+  let suggestions = test.document.get_suggestions();
+  assert_eq!(suggestions.len(), 1);
+  assert_eq!(suggestions[0].author, OTHER_REVIEWER);
+  assert_eq!(
+    plain_text(&test.document, SuggestionRenderMode::Rejected),
+    vec!["Hello world"]
+  );
+
+  assert_eq!(test.document.accept_all_suggestions(None), 1);
+  assert!(test.document.get_block(&block_id).is_none());
+}
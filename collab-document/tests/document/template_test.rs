@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use collab_document::blocks::{Block, BlockType, DocumentData};
+use collab_document::document::Document;
+use collab_document::error::DocumentError;
+use collab_document::template::{
+  DocumentTemplate, TemplateError, TemplateValue, TemplateVariable, TemplateVariableType,
+  TemplateVariables,
+};
+use serde_json::{Value, json};
+
+use crate::blocks::block_test_core::{BlockTestCore, generate_id};
+
+fn append_block(
+  test: &mut BlockTestCore,
+  ty: BlockType,
+  delta: Option<Value>,
+  data: HashMap<String, Value>,
+  parent_id: &str,
+) -> Block {
+  let external_id = delta.map(|delta| test.create_text(delta.to_string()));
+  let prev_id = test
+    .get_block_children(parent_id)
+    .last()
+    .map(|block| block.id.clone());
+  let block = Block {
+    id: generate_id(),
+    ty: ty.as_str().to_string(),
+    parent: parent_id.to_string(),
+    children: generate_id(),
+    external_type: external_id.as_ref().map(|_| "text".to_string()),
+    external_id,
+    data,
+  };
+  test.document.insert_block(block, prev_id).unwrap()
+}
+
+/// heading: {{title}}
+/// paragraph: Created by {{user}} on {{date:%d/%m/%Y}}
+/// callout ({{icon}}): Due {{ due }}
+/// simple table
+///   row
+///     cell
+///       paragraph: {{count}} items
+fn create_template() -> (DocumentTemplate, DocumentData) {
+  let mut test = BlockTestCore::new();
+  let page_id = test.get_page().id;
+  append_block(
+    &mut test,
+    BlockType::Heading,
+    Some(json!([{ "insert": "{{title}}", "attributes": { "bold": true } }])),
+    HashMap::from([("level".to_string(), json!(1))]),
+    &page_id,
+  );
+  append_block(
+    &mut test,
+    BlockType::Paragraph,
+    Some(json!([{ "insert": "Created by {{user}} on {{date:%d/%m/%Y}}" }])),
+    HashMap::new(),
+    &page_id,
+  );
+  append_block(
+    &mut test,
+    BlockType::Callout,
+    Some(json!([{ "insert": "Due {{ due }}" }])),
+    HashMap::from([("icon".to_string(), json!("{{icon}}"))]),
+    &page_id,
+  );
+  let table = append_block(
+    &mut test,
+    BlockType::SimpleTable,
+    None,
+    HashMap::new(),
+    &page_id,
+  );
+  let row = append_block(
+    &mut test,
+    BlockType::SimpleTableRow,
+    None,
+    HashMap::new(),
+    &table.id,
+  );
+  let cell = append_block(
+    &mut test,
+    BlockType::SimpleTableCell,
+    None,
+    HashMap::new(),
+    &row.id,
+  );
+  append_block(
+    &mut test,
+    BlockType::Paragraph,
+    Some(json!([{ "insert": "{{count}} items" }])),
+    HashMap::new(),
+    &cell.id,
+  );
+
+  let data = test.get_document_data();
+  let template = DocumentTemplate::new(data.clone())
+    .with_variable(TemplateVariable::new("title", TemplateVariableType::Text))
+    .with_variable(TemplateVariable::new("user", TemplateVariableType::User))
+    .with_variable(TemplateVariable::new("date", TemplateVariableType::Date))
+    .with_variable(TemplateVariable::new("count", TemplateVariableType::Number))
+    .with_variable(TemplateVariable::new("due", TemplateVariableType::Text).optional())
+    .with_variable(
+      TemplateVariable::new("icon", TemplateVariableType::Text)
+        .with_default(TemplateValue::Text("📌".to_string())),
+    );
+  (template, data)
+}
+
+fn template_variables() -> TemplateVariables {
+  HashMap::from([
+    (
+      "title".to_string(),
+      TemplateValue::Text("Weekly sync".to_string()),
+    ),
+    (
+      "user".to_string(),
+      TemplateValue::User {
+        uid: 1,
+        name: "Lucas".to_string(),
+      },
+    ),
+    (
+      "date".to_string(),
+      TemplateValue::Date(NaiveDate::from_ymd_opt(2024, 3, 9).unwrap()),
+    ),
+    ("count".to_string(), TemplateValue::Number(3.0)),
+  ])
+}
+
+#[test]
+fn template_placeholders_test() {
+  let (template, _) = create_template();
+  assert_eq!(
+    template.placeholders(),
+    vec!["count", "date", "due", "icon", "title", "user"]
+  );
+
+  // The undeclared placeholders are required variables that accept any value
+  let template = DocumentTemplate::new(template.data);
+  assert!(
+    template
+      .all_variables()
+      .iter()
+      .all(|variable| variable.required && variable.ty == TemplateVariableType::Text)
+  );
+  let mut variables = template_variables();
+  variables.insert("date".to_string(), TemplateValue::today());
+  variables.insert("due".to_string(), TemplateValue::Number(1.0));
+  variables.insert("icon".to_string(), TemplateValue::Text("📌".to_string()));
+  assert_eq!(template.validate(&variables), Ok(()));
+  variables.remove("icon");
+  assert_eq!(
+    template.validate(&variables),
+    Err(TemplateError::MissingVariables(vec!["icon".to_string()]))
+  );
+}
+
+#[test]
+fn create_document_from_template_test() {
+  let (template, template_data) = create_template();
+  let document_id = uuid::Uuid::new_v4().to_string();
+  let document =
+    Document::create_from_template(&document_id, &template, &template_variables(), 1).unwrap();
+  let data = document.get_document_data().unwrap();
+
+  assert_eq!(data.blocks.len(), template_data.blocks.len());
+  assert_eq!(Some(data.page_id.clone()), document.get_page_id());
+  // Every id is new
+  for block in data.blocks.values() {
+    assert!(!template_data.blocks.contains_key(&block.id));
+    assert!(
+      !template_data
+        .meta
+        .children_map
+        .contains_key(&block.children)
+    );
+  }
+  assert_ne!(data.page_id, template_data.page_id);
+
+  let texts = document.to_plain_text();
+  assert_eq!(
+    texts,
+    vec![
+      "Weekly sync".to_string(),
+      "Created by Lucas on 09/03/2024".to_string(),
+      "📌 Due ".to_string(),
+      "3 items".to_string(),
+    ]
+  );
+
+  // The attributes are kept, the first block is the empty paragraph of the default document
+  let heading_id = document.get_block_children_ids(&data.page_id)[1].clone();
+  assert_eq!(
+    document.get_block_delta_json(&heading_id).unwrap(),
+    json!([{ "insert": "Weekly sync", "attributes": { "bold": true } }])
+  );
+
+  // The template is not changed
+  assert_eq!(template.data, template_data);
+}
+
+#[test]
+fn template_validation_test() {
+  let (template, _) = create_template();
+
+  let mut variables = template_variables();
+  variables.remove("title");
+  variables.remove("count");
+  assert_eq!(
+    template.validate(&variables),
+    Err(TemplateError::MissingVariables(vec![
+      "count".to_string(),
+      "title".to_string()
+    ]))
+  );
+  assert!(matches!(
+    Document::create_from_template("1", &template, &variables, 1),
+    Err(DocumentError::InvalidTemplate(
+      TemplateError::MissingVariables(_)
+    ))
+  ));
+
+  let mut variables = template_variables();
+  variables.insert(
+    "date".to_string(),
+    TemplateValue::Text("tomorrow".to_string()),
+  );
+  assert_eq!(
+    template.validate(&variables),
+    Err(TemplateError::InvalidValue {
+      name: "date".to_string(),
+      expected: TemplateVariableType::Date,
+    })
+  );
+
+  assert_eq!(template.validate(&template_variables()), Ok(()));
+}
+
+#[test]
+fn template_value_render_test() {
+  let date = TemplateValue::Date(NaiveDate::from_ymd_opt(2024, 12, 25).unwrap());
+  assert_eq!(date.render(None), "2024-12-25");
+  assert_eq!(date.render(Some("%B %d, %Y")), "December 25, 2024");
+  // An invalid format falls back to the default one
+  assert_eq!(date.render(Some("%Q")), "2024-12-25");
+  assert_eq!(TemplateValue::Number(2.5).render(None), "2.5");
+}
+
+#[test]
+fn template_skips_code_and_non_text_data_test() {
+  let mut test = BlockTestCore::new();
+  let page_id = test.get_page().id;
+  append_block(
+    &mut test,
+    BlockType::Code,
+    Some(json!([{ "insert": "let name = \"{{name}}\";" }])),
+    HashMap::from([("language".to_string(), json!("rust"))]),
+    &page_id,
+  );
+  append_block(
+    &mut test,
+    BlockType::Paragraph,
+    Some(json!([
+      { "insert": "Render " },
+      { "insert": "{{value}}", "attributes": { "code": true } },
+      { "insert": " for {{title}}" }
+    ])),
+    HashMap::new(),
+    &page_id,
+  );
+  append_block(
+    &mut test,
+    BlockType::Image,
+    None,
+    HashMap::from([
+      (
+        "url".to_string(),
+        json!("https://appflowy.io/{{title}}.png"),
+      ),
+      ("caption".to_string(), json!("{{title}}")),
+    ]),
+    &page_id,
+  );
+
+  let template = DocumentTemplate::new(test.get_document_data());
+  assert_eq!(template.placeholders(), vec!["title"]);
+
+  let variables = HashMap::from([(
+    "title".to_string(),
+    TemplateValue::Text("Guide".to_string()),
+  )]);
+  let document_id = uuid::Uuid::new_v4().to_string();
+  let document = Document::create_from_template(&document_id, &template, &variables, 1).unwrap();
+  let data = document.get_document_data().unwrap();
+  let children = document.get_block_children_ids(&data.page_id);
+
+  assert_eq!(
+    document.get_block_delta_json(&children[1]).unwrap(),
+    json!([{ "insert": "let name = \"{{name}}\";" }])
+  );
+  assert_eq!(
+    document.get_block_delta_json(&children[2]).unwrap(),
+    json!([
+      { "insert": "Render " },
+      { "insert": "{{value}}", "attributes": { "code": true } },
+      { "insert": " for Guide" }
+    ])
+  );
+  let image = &data.blocks[&children[3]];
+  assert_eq!(
+    image.data["url"],
+    json!("https://appflowy.io/{{title}}.png")
+  );
+  assert_eq!(image.data["caption"], json!("Guide"));
+}
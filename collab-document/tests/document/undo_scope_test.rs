@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use collab::core::collab::UndoOptions;
+use collab::core::collab_plugin::{CollabPlugin, CollabPluginType};
+use collab::core::origin::{CollabClient, CollabOrigin};
+use collab_document::blocks::Block;
+use collab_document::document::Document;
+use collab_document::document_awareness::BlockTextPosition;
+use collab_document::document_undo::{UndoScopeOptions, UndoSelection};
+use collab_document::error::DocumentError;
+use nanoid::nanoid;
+use serde_json::to_value;
+
+use crate::util::{DocumentTest, insert_block_for_page};
+
+fn actor(uid: i64) -> CollabOrigin {
+  CollabOrigin::Client(CollabClient::new(uid, "actor"))
+}
+
+/// Records the origins of the local updates, like a cloud plugin that pushes them.
+#[derive(Clone, Default)]
+struct LocalUpdatePlugin(Arc<Mutex<Vec<CollabOrigin>>>);
+
+impl CollabPlugin for LocalUpdatePlugin {
+  fn receive_local_update(&self, origin: &CollabOrigin, _object_id: &str, _update: &[u8]) {
+    self.0.lock().unwrap().push(origin.clone());
+  }
+
+  fn plugin_type(&self) -> CollabPluginType {
+    CollabPluginType::Other("LocalUpdatePlugin".to_string())
+  }
+}
+
+fn insert_child_block(document: &mut Document, parent_id: &str) -> Block {
+  let block = Block {
+    id: nanoid!(10),
+    ty: "paragraph".to_string(),
+    parent: parent_id.to_string(),
+    children: nanoid!(10),
+    external_id: None,
+    external_type: None,
+    data: Default::default(),
+  };
+  document.insert_block(block, None).unwrap()
+}
+
+fn text_data(text: &str) -> HashMap<String, serde_json::Value> {
+  HashMap::from([("text".to_string(), to_value(text).unwrap())])
+}
+
+#[test]
+fn undo_scope_per_origin_test() {
+  let mut document = DocumentTest::new(1, "1").document;
+  let origin = document.origin().clone();
+  let (alice, bob) = (actor(2), actor(3));
+  document
+    .add_undo_scope("alice", UndoScopeOptions::new(alice.clone()))
+    .unwrap();
+  document
+    .add_undo_scope("bob", UndoScopeOptions::new(bob.clone()))
+    .unwrap();
+
+  let alice_block = document.with_origin(alice, |document| {
+    insert_block_for_page(document, nanoid!(10))
+  });
+  let bob_block =
+    document.with_origin(bob, |document| insert_block_for_page(document, nanoid!(10)));
+
+  // the changes of other actors are not undone by the default undo manager.
+  assert!(!document.can_undo());
+  assert!(document.can_undo_scope("alice"));
+  assert!(document.can_undo_scope("bob"));
+
+  document.undo_scope("alice").unwrap();
+  assert!(document.get_block(&alice_block.id).is_none());
+  assert!(document.get_block(&bob_block.id).is_some());
+  assert!(!document.can_undo_scope("alice"));
+  assert!(document.can_undo_scope("bob"));
+
+  document.redo_scope("alice").unwrap();
+  assert!(document.get_block(&alice_block.id).is_some());
+
+  document.undo_scope("bob").unwrap();
+  assert!(document.get_block(&alice_block.id).is_some());
+  assert!(document.get_block(&bob_block.id).is_none());
+
+  // the origin of the document is restored.
+  assert_eq!(document.origin(), &origin);
+
+  // even when the changes panic.
+  let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+    document.with_origin(actor(4), |_| panic!("failed to apply the changes"))
+  }));
+  assert!(result.is_err());
+  assert_eq!(document.origin(), &origin);
+}
+
+#[test]
+fn undo_scope_capture_timeout_test() {
+  let mut document = DocumentTest::new(1, "1").document;
+  document
+    .add_undo_scope("merged", UndoScopeOptions::default())
+    .unwrap();
+  document
+    .add_undo_scope(
+      "separated",
+      UndoScopeOptions::default().with_capture_timeout(0),
+    )
+    .unwrap();
+
+  let first = insert_block_for_page(&mut document, nanoid!(10));
+  let second = insert_block_for_page(&mut document, nanoid!(10));
+
+  // without capture timeout, every transaction is a step.
+  document.undo_scope("separated").unwrap();
+  assert!(document.get_block(&first.id).is_some());
+  assert!(document.get_block(&second.id).is_none());
+  document.redo_scope("separated").unwrap();
+
+  // within the capture timeout, the transactions are undone together.
+  document.undo_scope("merged").unwrap();
+  assert!(document.get_block(&first.id).is_none());
+  assert!(document.get_block(&second.id).is_none());
+  assert!(!document.can_undo_scope("merged"));
+}
+
+#[test]
+fn undo_scope_of_block_subtree_test() {
+  let mut document = DocumentTest::new(1, "1").document;
+  let page_id = document.get_page_id().unwrap();
+  let parent = insert_child_block(&mut document, &page_id);
+  let sibling = insert_child_block(&mut document, &page_id);
+  document
+    .add_undo_scope(
+      "parent",
+      UndoScopeOptions::default()
+        .with_block(&parent.id)
+        .with_capture_timeout(0),
+    )
+    .unwrap();
+
+  let child = insert_child_block(&mut document, &parent.id);
+  document
+    .update_block(&parent.id, text_data("parent"))
+    .unwrap();
+  document
+    .update_block(&sibling.id, text_data("sibling"))
+    .unwrap();
+
+  // the change of the sibling is not in the scope.
+  document.undo_scope("parent").unwrap();
+  assert_eq!(document.get_block(&parent.id).unwrap().data, HashMap::new());
+  assert_eq!(
+    document.get_block(&sibling.id).unwrap().data,
+    text_data("sibling")
+  );
+
+  // inserting a child changes the subtree.
+  document.undo_scope("parent").unwrap();
+  assert!(document.get_block(&child.id).is_none());
+  assert!(!document.can_undo_scope("parent"));
+
+  // a block moved into the subtree is in the scope.
+  let child = insert_child_block(&mut document, &page_id);
+  document
+    .move_block(&child.id, Some(parent.id.clone()), None)
+    .unwrap();
+  assert!(!document.can_undo_scope("parent"));
+  document
+    .update_block(&child.id, text_data("child"))
+    .unwrap();
+  assert!(document.can_undo_scope("parent"));
+
+  let result = document.add_undo_scope("missing", UndoScopeOptions::default().with_block("x"));
+  assert!(matches!(result, Err(DocumentError::BlockIsNotFound)));
+}
+
+#[test]
+fn undo_scope_selection_meta_test() {
+  let mut document = DocumentTest::new(1, "1").document;
+  document
+    .add_undo_scope("local", UndoScopeOptions::default())
+    .unwrap();
+  let block = insert_block_for_page(&mut document, nanoid!(10));
+  document.stop_undo_scope_capturing("local").unwrap();
+
+  let selection = UndoSelection {
+    start: BlockTextPosition::new(&block.id, 1),
+    end: BlockTextPosition::new(&block.id, 3),
+  };
+  document
+    .set_undo_selection("local", Some(selection.clone()))
+    .unwrap();
+  document
+    .update_block(&block.id, text_data("hello"))
+    .unwrap();
+  document.set_undo_selection("local", None).unwrap();
+
+  let meta = document.undo_scope("local").unwrap().unwrap();
+  assert_eq!(meta.selection, Some(selection.clone()));
+  assert_eq!(document.get_block(&block.id).unwrap().data, HashMap::new());
+
+  // the selection is kept when the step is redone.
+  let meta = document.redo_scope("local").unwrap().unwrap();
+  assert_eq!(meta.selection, Some(selection));
+
+  // the first step was recorded without selection.
+  document.undo_scope("local").unwrap();
+  let meta = document.undo_scope("local").unwrap().unwrap();
+  assert_eq!(meta.selection, None);
+  assert_eq!(document.undo_scope("local").unwrap(), None);
+
+  assert!(matches!(
+    document.undo_scope("unknown"),
+    Err(DocumentError::UndoScopeNotFound(_))
+  ));
+}
+
+#[test]
+fn default_undo_options_test() {
+  let mut document = DocumentTest::new(1, "1").document;
+  let importer = actor(2);
+  document.set_undo_options(UndoOptions {
+    capture_timeout_millis: 0,
+    tracked_origins: vec![importer.clone()],
+  });
+
+  let local = insert_block_for_page(&mut document, nanoid!(10));
+  assert!(!document.can_undo());
+
+  let first = document.with_origin(importer.clone(), |document| {
+    insert_block_for_page(document, nanoid!(10))
+  });
+  let second = document.with_origin(importer, |document| {
+    insert_block_for_page(document, nanoid!(10))
+  });
+  assert!(document.undo());
+  assert!(document.get_block(&first.id).is_some());
+  assert!(document.get_block(&second.id).is_none());
+  assert!(document.undo());
+  assert!(document.get_block(&first.id).is_none());
+  assert!(document.get_block(&local.id).is_some());
+  assert!(!document.undo());
+}
+
+#[test]
+fn plugin_receives_changes_made_with_origin_test() {
+  let mut document = DocumentTest::new(1, "1").document;
+  let plugin = LocalUpdatePlugin::default();
+  document.add_plugin(Box::new(plugin.clone()));
+  let origin = document.origin().clone();
+  let importer = actor(5);
+
+  insert_block_for_page(&mut document, nanoid!(10));
+  document.with_origin(importer.clone(), |document| {
+    insert_block_for_page(document, nanoid!(10))
+  });
+
+  let origins = plugin.0.lock().unwrap().clone();
+  assert!(origins.contains(&origin));
+  assert_eq!(origins.last(), Some(&importer));
+  assert_eq!(document.origin(), &origin);
+}
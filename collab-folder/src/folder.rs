@@ -18,8 +18,9 @@ use crate::hierarchy_builder::{FlattedViews, ParentChildViews};
 use crate::section::{Section, SectionItem, SectionMap};
 use crate::view::view_from_map_ref;
 use crate::{
-  FolderData, ParentChildRelations, SectionChangeSender, SpacePermission, TrashInfo, View,
-  ViewChangeReceiver, ViewUpdate, ViewsMap, Workspace, impl_section_op,
+  EffectivePermission, FolderData, ParentChildRelations, SectionChangeSender, SpacePermission,
+  TrashInfo, View, ViewAcl, ViewChangeReceiver, ViewPermissionMap, ViewRole, ViewUpdate, ViewsMap,
+  Workspace, impl_section_op,
 };
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
@@ -54,6 +55,7 @@ const VIEWS: &str = "views";
 const PARENT_CHILD_VIEW_RELATION: &str = "relation";
 const CURRENT_VIEW: &str = "current_view";
 const CURRENT_VIEW_FOR_USER: &str = "current_view_for_user";
+const VIEW_PERMISSIONS: &str = "permissions";
 
 pub(crate) const FAVORITES_V1: &str = "favorites";
 const SECTION: &str = "section";
@@ -243,9 +245,53 @@ impl Folder {
 
   pub fn delete_views<T: AsRef<str>>(&mut self, views: Vec<T>) {
     let mut txn = self.collab.transact_mut();
+    self.body.permissions.remove_with_txn(&mut txn, &views);
     self.body.views.delete_views(&mut txn, views);
   }
 
+  /// Grants the `role` to the user on the view. The grant is inherited by the child views unless
+  /// they have their own grant for the user.
+  pub fn grant_view_permission(&mut self, view_id: &str, uid: i64, role: ViewRole) {
+    let mut txn = self.collab.transact_mut();
+    self
+      .body
+      .permissions
+      .grant_with_txn(&mut txn, view_id, uid, role);
+  }
+
+  pub fn revoke_view_permission(&mut self, view_id: &str, uid: i64) {
+    let mut txn = self.collab.transact_mut();
+    self
+      .body
+      .permissions
+      .revoke_with_txn(&mut txn, view_id, uid);
+  }
+
+  /// Set whether the view inherits the grants of its parent views.
+  pub fn set_view_permission_inherit(&mut self, view_id: &str, inherit: bool) {
+    let mut txn = self.collab.transact_mut();
+    self
+      .body
+      .permissions
+      .set_inherit_with_txn(&mut txn, view_id, inherit);
+  }
+
+  /// Returns the grants that are set on the view itself, not including the inherited ones.
+  pub fn get_view_acl(&self, view_id: &str) -> Option<ViewAcl> {
+    let txn = self.collab.transact();
+    self.body.permissions.get_view_acl_with_txn(&txn, view_id)
+  }
+
+  /// Returns the permission of the user on the view, resolved through the view hierarchy.
+  /// Returns None if the user has no access to the view.
+  pub fn effective_permission(&self, view_id: &str, uid: i64) -> Option<EffectivePermission> {
+    let txn = self.collab.transact();
+    self
+      .body
+      .permissions
+      .effective_permission_with_txn(&txn, &self.body.views, view_id, uid)
+  }
+
   // Section operations
   // Favorites
   impl_section_op!(
@@ -467,6 +513,7 @@ pub struct FolderBody {
   pub root: MapRef,
   pub views: Arc<ViewsMap>,
   pub section: Arc<SectionMap>,
+  pub permissions: ViewPermissionMap,
   pub meta: MapRef,
   #[allow(dead_code)]
   notifier: Option<FolderNotify>,
@@ -491,6 +538,7 @@ impl FolderBody {
     let views: MapRef = root.get_or_init(&mut txn, VIEWS);
     let section: MapRef = root.get_or_init(&mut txn, SECTION);
     let meta: MapRef = root.get_or_init(&mut txn, FOLDER_META);
+    let permissions: MapRef = root.get_or_init(&mut txn, VIEW_PERMISSIONS);
     let parent_child_relations = Arc::new(ParentChildRelations::new(
       root.get_or_init(&mut txn, PARENT_CHILD_VIEW_RELATION),
     ));
@@ -546,10 +594,12 @@ impl FolderBody {
         }
       }
     }
+    let permissions = ViewPermissionMap::new(permissions);
     Self {
       root,
       views,
       section,
+      permissions,
       meta,
       notifier,
     }
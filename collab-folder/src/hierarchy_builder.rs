@@ -1,11 +1,9 @@
 use crate::space_info::SpacePermission;
 use crate::{
-  IconType, RepeatedViewIdentifier, SPACE_CREATED_AT_KEY, SPACE_ICON_COLOR_KEY, SPACE_ICON_KEY,
-  SPACE_IS_SPACE_KEY, SPACE_PERMISSION_KEY, SpaceInfo, View, ViewIcon, ViewIdentifier, ViewLayout,
-  timestamp,
+  IconType, RepeatedViewIdentifier, SpaceInfo, TextLayoutSize, View, ViewCover, ViewExtra,
+  ViewIcon, ViewIdentifier, ViewLayout, timestamp,
 };
 
-use serde_json::json;
 use std::fmt::{Display, Formatter};
 use std::future::Future;
 use std::ops::{Deref, DerefMut};
@@ -231,7 +229,7 @@ impl NestedChildViewBuilder {
   }
 }
 
-pub struct ViewExtraBuilder(serde_json::Value);
+pub struct ViewExtraBuilder(ViewExtra);
 impl Default for ViewExtraBuilder {
   fn default() -> Self {
     Self::new()
@@ -240,52 +238,73 @@ impl Default for ViewExtraBuilder {
 
 impl ViewExtraBuilder {
   pub fn new() -> Self {
-    Self(json!({}))
+    Self(ViewExtra::default())
   }
 
   pub fn is_space(mut self, is_space: bool) -> Self {
-    self.0[SPACE_IS_SPACE_KEY] = json!(is_space);
+    let space_info = self.space_info_mut();
+    space_info.is_space = is_space;
     if is_space {
-      self.0[SPACE_CREATED_AT_KEY] = json!(timestamp());
+      space_info.space_created_at = timestamp();
     }
     self
   }
 
   pub fn with_space_icon(mut self, icon: Option<&str>) -> Self {
     if let Some(icon) = icon {
-      self.0[SPACE_ICON_KEY] = json!(icon);
+      self.space_info_mut().space_icon = Some(icon.to_string());
     }
     self
   }
 
   pub fn with_space_icon_color(mut self, icon_color: Option<&str>) -> Self {
     if let Some(icon_color) = icon_color {
-      self.0[SPACE_ICON_COLOR_KEY] = json!(icon_color);
+      self.space_info_mut().space_icon_color = Some(icon_color.to_string());
     }
     self
   }
 
   pub fn with_space_permission(mut self, permission: SpacePermission) -> Self {
-    self.0[SPACE_PERMISSION_KEY] = json!(permission as u8);
+    self.space_info_mut().space_permission = permission;
     self
   }
 
   pub fn with_space_info(mut self, space_info: SpaceInfo) -> Self {
-    self.0[SPACE_IS_SPACE_KEY] = json!(space_info.is_space);
-    self.0[SPACE_PERMISSION_KEY] = json!(space_info.space_permission as u8);
-    if let Some(icon) = space_info.space_icon {
-      self.0[SPACE_ICON_KEY] = json!(icon);
-    }
-    if let Some(icon_color) = space_info.space_icon_color {
-      self.0[SPACE_ICON_COLOR_KEY] = json!(icon_color);
-    }
-    self.0[SPACE_CREATED_AT_KEY] = json!(space_info.space_created_at);
+    self.0.space_info = Some(space_info);
     self
   }
 
-  pub fn build(self) -> serde_json::Value {
+  pub fn with_cover(mut self, cover: ViewCover) -> Self {
+    self.0.cover = Some(cover);
+    self
+  }
+
+  pub fn with_line_height_layout(mut self, layout: TextLayoutSize) -> Self {
+    self.0.line_height_layout = Some(layout);
+    self
+  }
+
+  pub fn with_font_layout(mut self, layout: TextLayoutSize) -> Self {
+    self.0.font_layout = Some(layout);
+    self
+  }
+
+  /// Returns the space info, creating one that doesn't mark the view as a space if the view has
+  /// none yet.
+  fn space_info_mut(&mut self) -> &mut SpaceInfo {
+    self.0.space_info.get_or_insert_with(|| SpaceInfo {
+      is_space: false,
+      ..Default::default()
+    })
+  }
+
+  pub fn build_extra(self) -> ViewExtra {
     self.0
   }
+
+  pub fn build(self) -> serde_json::Value {
+    serde_json::Value::Object(self.0.to_json_map())
+  }
 }
 
 #[derive(Debug, Clone)]
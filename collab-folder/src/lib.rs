@@ -2,19 +2,23 @@ pub use entities::*;
 pub use folder::*;
 pub use folder_migration::*;
 pub use folder_observe::*;
+pub use permission::*;
 pub use relation::*;
 pub use section::*;
 // pub use trash::*;
 pub use space_info::*;
 pub use view::*;
+pub use view_extra::*;
 pub use workspace::*;
 
 mod entities;
 mod folder;
+mod permission;
 mod relation;
 mod section;
 // mod trash;
 mod view;
+mod view_extra;
 mod workspace;
 
 #[macro_use]
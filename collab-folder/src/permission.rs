@@ -0,0 +1,239 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::bail;
+use collab::preclude::{Any, Map, MapExt, MapRef, ReadTxn, TransactionMut, YrsValue};
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::ViewsMap;
+
+const VIEW_PERMISSION_INHERIT: &str = "inherit";
+
+/// Stores the access control list of the views.
+///
+/// Each grant is a key of the map made of the view id and the user id, and its value is the
+/// [ViewRole] granted to that user. The keys are flat so that two clients granting on a view
+/// concurrently for the first time keep both grants. A view without grants inherits the grants
+/// of its parent view.
+/// permissions {
+///   view_id:uid: role,
+///   view_id:inherit: bool,
+/// }
+///
+pub struct ViewPermissionMap {
+  container: MapRef,
+}
+
+impl ViewPermissionMap {
+  pub fn new(container: MapRef) -> Self {
+    Self { container }
+  }
+
+  /// Grants the `role` to the user on the view and all of its descendants that don't override it.
+  pub fn grant_with_txn(&self, txn: &mut TransactionMut, view_id: &str, uid: i64, role: ViewRole) {
+    self
+      .container
+      .insert(txn, permission_key(view_id, &uid.to_string()), role);
+  }
+
+  /// Removes the explicit grant of the user on the view. The user falls back to the grant
+  /// inherited from the parent views, if any.
+  pub fn revoke_with_txn(&self, txn: &mut TransactionMut, view_id: &str, uid: i64) {
+    self
+      .container
+      .remove(txn, &permission_key(view_id, &uid.to_string()));
+  }
+
+  /// When `inherit` is false, the view stops inheriting the grants of its parent views. Only the
+  /// users granted on the view itself can access it, and the descendants that inherit from it
+  /// only get the grants of the view and of the views between them.
+  pub fn set_inherit_with_txn(&self, txn: &mut TransactionMut, view_id: &str, inherit: bool) {
+    self.container.insert(
+      txn,
+      permission_key(view_id, VIEW_PERMISSION_INHERIT),
+      inherit,
+    );
+  }
+
+  /// Removes all the grants of the given views.
+  pub fn remove_with_txn<T: AsRef<str>>(&self, txn: &mut TransactionMut, view_ids: &[T]) {
+    let view_ids = view_ids
+      .iter()
+      .map(|view_id| view_id.as_ref())
+      .collect::<HashSet<_>>();
+    let keys = self
+      .container
+      .keys(txn)
+      .filter(|key| {
+        split_permission_key(key).is_some_and(|(view_id, _)| view_ids.contains(view_id))
+      })
+      .map(|key| key.to_string())
+      .collect::<Vec<_>>();
+    for key in keys {
+      self.container.remove(txn, &key);
+    }
+  }
+
+  pub fn get_view_acl_with_txn<T: ReadTxn>(&self, txn: &T, view_id: &str) -> Option<ViewAcl> {
+    let mut acl = None;
+    for (key, value) in self.container.iter(txn) {
+      let Some((key_view_id, key)) = split_permission_key(key) else {
+        continue;
+      };
+      if key_view_id != view_id {
+        continue;
+      }
+      let acl = acl.get_or_insert_with(ViewAcl::default);
+      if key == VIEW_PERMISSION_INHERIT {
+        if let YrsValue::Any(Any::Bool(inherit)) = value {
+          acl.inherit = inherit;
+        }
+      } else if let (Ok(uid), Ok(role)) = (key.parse::<i64>(), value.cast::<i64>()) {
+        if let Ok(role) = ViewRole::try_from(role) {
+          acl.grants.insert(uid, role);
+        }
+      }
+    }
+    acl
+  }
+
+  fn get_role_with_txn<T: ReadTxn>(&self, txn: &T, view_id: &str, uid: i64) -> Option<ViewRole> {
+    let role = self
+      .container
+      .get_with_txn::<_, i64>(txn, &permission_key(view_id, &uid.to_string()))?;
+    ViewRole::try_from(role).ok()
+  }
+
+  fn get_inherit_with_txn<T: ReadTxn>(&self, txn: &T, view_id: &str) -> bool {
+    self
+      .container
+      .get_with_txn(txn, &permission_key(view_id, VIEW_PERMISSION_INHERIT))
+      .unwrap_or(true)
+  }
+
+  /// Resolves the permission of the user on the view.
+  ///
+  /// Starting from the view, walks up the view hierarchy until it finds a view that grants a role
+  /// to the user. A grant on a view overrides the grants of its parent views, so a child view can
+  /// lower or raise the role inherited from its parents. The walk stops at a view that doesn't
+  /// inherit from its parent.
+  pub fn effective_permission_with_txn<T: ReadTxn>(
+    &self,
+    txn: &T,
+    views: &ViewsMap,
+    view_id: &str,
+    uid: i64,
+  ) -> Option<EffectivePermission> {
+    let mut visited = HashSet::new();
+    let mut current_view_id = view_id.to_string();
+    loop {
+      if !visited.insert(current_view_id.clone()) {
+        return None;
+      }
+
+      if let Some(role) = self.get_role_with_txn(txn, &current_view_id, uid) {
+        return Some(EffectivePermission {
+          role,
+          is_inherited: current_view_id != view_id,
+          source_view_id: current_view_id,
+        });
+      }
+      if !self.get_inherit_with_txn(txn, &current_view_id) {
+        return None;
+      }
+
+      let parent_view_id = views.get_parent_view_id_with_txn(txn, &current_view_id)?;
+      if parent_view_id.is_empty() || parent_view_id == current_view_id {
+        return None;
+      }
+      current_view_id = parent_view_id;
+    }
+  }
+}
+
+/// The grants of a single view.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ViewAcl {
+  /// Whether the view inherits the grants of its parent view. Default is true.
+  pub inherit: bool,
+  pub grants: HashMap<i64, ViewRole>,
+}
+
+impl Default for ViewAcl {
+  fn default() -> Self {
+    Self {
+      inherit: true,
+      grants: HashMap::new(),
+    }
+  }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EffectivePermission {
+  pub role: ViewRole,
+  /// The view that the role is granted on. Equal to the requested view id when the role is not
+  /// inherited.
+  pub source_view_id: String,
+  pub is_inherited: bool,
+}
+
+/// The role of a user on a view. The roles are ordered, a higher role includes all the
+/// capabilities of the lower ones.
+#[derive(
+  Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Clone, Copy, Serialize_repr, Deserialize_repr,
+)]
+#[repr(u8)]
+pub enum ViewRole {
+  Viewer = 0,
+  Commenter = 1,
+  Editor = 2,
+  Owner = 3,
+}
+
+impl ViewRole {
+  pub fn can_read(&self) -> bool {
+    *self >= ViewRole::Viewer
+  }
+
+  pub fn can_comment(&self) -> bool {
+    *self >= ViewRole::Commenter
+  }
+
+  pub fn can_edit(&self) -> bool {
+    *self >= ViewRole::Editor
+  }
+
+  /// Only the owner can change the grants of the view.
+  pub fn can_manage(&self) -> bool {
+    *self >= ViewRole::Owner
+  }
+}
+
+impl TryFrom<i64> for ViewRole {
+  type Error = anyhow::Error;
+
+  fn try_from(value: i64) -> Result<Self, Self::Error> {
+    match value {
+      0 => Ok(ViewRole::Viewer),
+      1 => Ok(ViewRole::Commenter),
+      2 => Ok(ViewRole::Editor),
+      3 => Ok(ViewRole::Owner),
+      _ => bail!("Unknown view role {}", value),
+    }
+  }
+}
+
+fn permission_key(view_id: &str, key: &str) -> String {
+  format!("{}:{}", view_id, key)
+}
+
+/// Returns the view id and the user id, or the inherit key, of a permission key.
+fn split_permission_key(key: &str) -> Option<(&str, &str)> {
+  key.rsplit_once(':')
+}
+
+impl From<ViewRole> for Any {
+  fn from(role: ViewRole) -> Self {
+    Any::BigInt(role as i64)
+  }
+}
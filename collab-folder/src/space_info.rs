@@ -16,7 +16,7 @@ pub const SPACE_CREATED_AT_KEY: &str = "space_created_at";
 ///   Child views inherit the space's permissions.
 ///
 /// - Normal view: Cannot contain space views and has no direct permission controls.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct SpaceInfo {
   /// Whether the view is a space view.
   pub is_space: bool,
@@ -33,12 +33,14 @@ pub struct SpaceInfo {
   /// The space icon.
   ///
   /// If the space_icon is none, the space view will use the default icon.
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub space_icon: Option<String>,
 
   /// The space icon color.
   ///
   /// If the space_icon_color is none, the space view will use the default icon color.
   /// The value should be a valid hex color code: 0xFFA34AFD
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub space_icon_color: Option<String>,
 }
 
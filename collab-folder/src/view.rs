@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use anyhow::bail;
@@ -15,6 +15,7 @@ use crate::folder_observe::ViewChangeSender;
 
 use crate::section::{Section, SectionItem, SectionMap};
 use crate::space_info::SpaceInfo;
+use crate::view_extra::{ViewCover, ViewExtra};
 use crate::{ParentChildRelations, RepeatedViewIdentifier, ViewIdentifier, subscribe_view_change};
 use crate::{UserId, impl_any_update, impl_i64_update, impl_option_i64_update, impl_str_update};
 
@@ -30,6 +31,9 @@ const VIEW_LAST_EDITED_TIME: &str = "last_edited_time";
 const VIEW_LAST_EDITED_BY: &str = "last_edited_by";
 const VIEW_IS_LOCKED: &str = "is_locked";
 const VIEW_EXTRA: &str = "extra";
+/// The changes of the top-level keys of the extra, see [ViewUpdate::update_extra]. The map is
+/// created with the view, two clients creating it lazily and concurrently would drop one of them.
+const VIEW_EXTRA_CHANGES: &str = "extra_changes";
 // const VIEW_LAST_VIEWED_TIME: &str = "last_viewed_time";
 
 pub fn timestamp() -> i64 {
@@ -266,6 +270,11 @@ impl ViewsMap {
     self.get_view_with_txn(txn, view_id, uid)
   }
 
+  pub fn get_parent_view_id_with_txn<T: ReadTxn>(&self, txn: &T, view_id: &str) -> Option<String> {
+    let map_ref: MapRef = self.container.get_with_txn(txn, view_id)?;
+    map_ref.get_with_txn(txn, VIEW_PARENT_ID)
+  }
+
   pub fn get_view_name_with_txn<T: ReadTxn>(&self, txn: &T, view_id: &str) -> Option<String> {
     let map_ref: MapRef = self.container.get_with_txn(txn, view_id)?;
     map_ref.get_with_txn(txn, FOLDER_VIEW_NAME)
@@ -472,7 +481,7 @@ pub(crate) fn view_from_map_ref<T: ReadTxn>(
     .unwrap_or(timestamp());
   let last_edited_by = map_ref.get_with_txn(txn, VIEW_LAST_EDITED_BY);
   let is_locked = map_ref.get_with_txn(txn, VIEW_IS_LOCKED);
-  let extra = map_ref.get_with_txn(txn, VIEW_EXTRA);
+  let extra = get_extra_from_view_map(map_ref, txn);
 
   Some(View {
     id,
@@ -496,6 +505,71 @@ pub fn get_icon_from_view_map<T: ReadTxn>(map_ref: &MapRef, txn: &T) -> Option<V
   serde_json::from_str::<ViewIcon>(&icon_str).ok()
 }
 
+/// Returns the extra of the view.
+///
+/// A change written by [ViewUpdate::update_extra] replaces its key of the extra string as long as
+/// the string still has the value the change was made from. The change survives a concurrent
+/// write of the string, and is ignored once the string is written again with another value, e.g.
+/// by a client that only knows the string.
+pub fn get_extra_from_view_map<T: ReadTxn>(map_ref: &MapRef, txn: &T) -> Option<String> {
+  let extra: Option<String> = map_ref.get_with_txn(txn, VIEW_EXTRA);
+  let Some(changes) = map_ref.get_with_txn::<_, MapRef>(txn, VIEW_EXTRA_CHANGES) else {
+    return extra;
+  };
+  if changes.len(txn) == 0 {
+    return extra;
+  }
+
+  let mut json = extra_json_map(extra.as_deref());
+  let mut is_changed = false;
+  for (key, change) in extra_changes(&changes, txn) {
+    if change.applies_to(json.get(&key)) {
+      match change.new {
+        Some(value) => json.insert(key, value),
+        None => json.remove(&key),
+      };
+      is_changed = true;
+    }
+  }
+  if is_changed {
+    Some(serde_json::Value::Object(json).to_string())
+  } else {
+    extra
+  }
+}
+
+/// A change of a top-level key of the extra, `None` if the key is absent.
+#[derive(Debug, Serialize, Deserialize)]
+struct ExtraChange {
+  old: Option<serde_json::Value>,
+  new: Option<serde_json::Value>,
+}
+
+impl ExtraChange {
+  /// Returns true if the change is not in the extra string yet and the string still has the value
+  /// the change was made from.
+  fn applies_to(&self, current: Option<&serde_json::Value>) -> bool {
+    current == self.old.as_ref() && current != self.new.as_ref()
+  }
+}
+
+fn extra_json_map(extra: Option<&str>) -> serde_json::Map<String, serde_json::Value> {
+  extra
+    .and_then(|extra| serde_json::from_str(extra).ok())
+    .unwrap_or_default()
+}
+
+fn extra_changes<T: ReadTxn>(changes: &MapRef, txn: &T) -> Vec<(String, ExtraChange)> {
+  changes
+    .iter(txn)
+    .filter_map(|(key, change)| {
+      let change = change.cast::<String>().ok()?;
+      let change = serde_json::from_str::<ExtraChange>(&change).ok()?;
+      Some((key.to_string(), change))
+    })
+    .collect()
+}
+
 pub struct ViewBuilder<'a, 'b> {
   view_id: &'a str,
   map_ref: MapRef,
@@ -514,6 +588,7 @@ impl<'a, 'b> ViewBuilder<'a, 'b> {
     section_map: &'a SectionMap,
   ) -> Self {
     map_ref.insert(txn, FOLDER_VIEW_ID, view_id);
+    map_ref.insert(txn, VIEW_EXTRA_CHANGES, MapPrelim::default());
     Self {
       view_id,
       map_ref,
@@ -567,7 +642,6 @@ impl<'a, 'b, 'c> ViewUpdate<'a, 'b, 'c> {
     VIEW_LAST_EDITED_TIME
   );
   impl_option_i64_update!(set_last_edited_by, VIEW_LAST_EDITED_BY);
-  impl_str_update!(set_extra, set_extra_if_not_none, VIEW_EXTRA);
 
   pub fn new(
     uid: UserId,
@@ -587,6 +661,83 @@ impl<'a, 'b, 'c> ViewUpdate<'a, 'b, 'c> {
     }
   }
 
+  /// Replaces the whole extra of the view.
+  pub fn set_extra<T: AsRef<str>>(self, value: T) -> Self {
+    if let Some(changes) = self
+      .map_ref
+      .get_with_txn::<_, MapRef>(self.txn, VIEW_EXTRA_CHANGES)
+    {
+      changes.clear(self.txn);
+    }
+    self.map_ref.insert(self.txn, VIEW_EXTRA, value.as_ref());
+    self
+  }
+
+  pub fn set_extra_if_not_none<T: AsRef<str>>(self, value: Option<T>) -> Self {
+    if let Some(value) = value {
+      self.set_extra(value)
+    } else {
+      self
+    }
+  }
+
+  /// Updates the extra of the view in place.
+  ///
+  /// The whole extra string is written, and each top-level key changed by `f` is also recorded
+  /// as a change of the view, so two clients editing different keys concurrently (e.g. one sets
+  /// the cover while the other changes the font layout) keep both changes after merging. The
+  /// views created before the changes were recorded only get the string.
+  pub fn update_extra<F>(self, f: F) -> Self
+  where
+    F: FnOnce(&mut ViewExtra),
+  {
+    let extra: Option<String> = self.map_ref.get_with_txn(self.txn, VIEW_EXTRA);
+    let old = get_extra_from_view_map(self.map_ref, self.txn)
+      .and_then(|extra| ViewExtra::from_json_str(&extra).ok())
+      .unwrap_or_default();
+    let mut new = old.clone();
+    f(&mut new);
+
+    if let Some(changes) = self
+      .map_ref
+      .get_with_txn::<_, MapRef>(self.txn, VIEW_EXTRA_CHANGES)
+    {
+      // the changes replaced by a later write of the string are never applied again.
+      let json = extra_json_map(extra.as_deref());
+      for (key, change) in extra_changes(&changes, self.txn) {
+        let current = json.get(&key);
+        if current != change.old.as_ref() && current != change.new.as_ref() {
+          changes.remove(self.txn, &key);
+        }
+      }
+
+      let old_map = old.to_json_map();
+      let new_map = new.to_json_map();
+      let keys = old_map.keys().chain(new_map.keys()).collect::<HashSet<_>>();
+      for key in keys {
+        let change = ExtraChange {
+          old: old_map.get(key).cloned(),
+          new: new_map.get(key).cloned(),
+        };
+        if change.old != change.new {
+          if let Ok(change) = serde_json::to_string(&change) {
+            changes.insert(self.txn, key.as_str(), change);
+          }
+        }
+      }
+    }
+
+    self
+      .map_ref
+      .insert(self.txn, VIEW_EXTRA, new.to_json_string());
+    self
+  }
+
+  /// Merges the keys set in `extra` into the extra of the view and keeps every other key.
+  pub fn merge_extra(self, extra: ViewExtra) -> Self {
+    self.update_extra(|current| current.merge(extra))
+  }
+
   pub fn set_children(self, children: RepeatedViewIdentifier) -> Self {
     let array = self
       .children_map
@@ -722,6 +873,8 @@ pub struct View {
   ///           "5" represents unsplash image
   /// - line_height_layout: "small" or "normal" or "large"
   /// - font_layout: "small", or "normal", or "large"
+  ///
+  /// Use [View::view_extra] to read it as a [ViewExtra].
   pub extra: Option<String>,
 }
 
@@ -767,10 +920,21 @@ impl View {
       extra: None,
     }
   }
+
   pub fn space_info(&self) -> Option<SpaceInfo> {
     let extra = self.extra.as_ref()?;
     serde_json::from_str::<SpaceInfo>(extra).ok()
   }
+
+  /// Returns the typed extra of the view, or None if the extra is empty or not a JSON object.
+  pub fn view_extra(&self) -> Option<ViewExtra> {
+    let extra = self.extra.as_ref()?;
+    ViewExtra::from_json_str(extra).ok()
+  }
+
+  pub fn cover(&self) -> Option<ViewCover> {
+    self.view_extra()?.cover
+  }
 }
 
 #[derive(Eq, PartialEq, Debug, Hash, Clone, Serialize_repr, Deserialize_repr)]
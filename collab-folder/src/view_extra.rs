@@ -0,0 +1,206 @@
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::space_info::{
+  SPACE_CREATED_AT_KEY, SPACE_ICON_COLOR_KEY, SPACE_ICON_KEY, SPACE_IS_SPACE_KEY,
+  SPACE_PERMISSION_KEY, SpaceInfo,
+};
+
+pub const VIEW_EXTRA_COVER_KEY: &str = "cover";
+pub const VIEW_EXTRA_LINE_HEIGHT_LAYOUT_KEY: &str = "line_height_layout";
+pub const VIEW_EXTRA_FONT_LAYOUT_KEY: &str = "font_layout";
+
+const SPACE_INFO_KEYS: [&str; 5] = [
+  SPACE_IS_SPACE_KEY,
+  SPACE_PERMISSION_KEY,
+  SPACE_CREATED_AT_KEY,
+  SPACE_ICON_KEY,
+  SPACE_ICON_COLOR_KEY,
+];
+
+/// Typed representation of the JSON stored in [crate::View::extra].
+///
+/// Known keys are parsed into their typed fields. Keys that are unknown to this version, or
+/// known keys whose value can't be parsed, are kept in `unknown` so that writing the extra back
+/// never drops data written by a newer client.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ViewExtra {
+  pub cover: Option<ViewCover>,
+  pub line_height_layout: Option<TextLayoutSize>,
+  pub font_layout: Option<TextLayoutSize>,
+  /// Present when the view is a space. The space info is stored flattened into the top level
+  /// of the extra JSON.
+  pub space_info: Option<SpaceInfo>,
+  pub unknown: Map<String, Value>,
+}
+
+impl ViewExtra {
+  pub fn from_json_str(s: &str) -> Result<Self, serde_json::Error> {
+    let map = serde_json::from_str::<Map<String, Value>>(s)?;
+    Ok(Self::from_json_map(map))
+  }
+
+  pub fn to_json_string(&self) -> String {
+    Value::Object(self.to_json_map()).to_string()
+  }
+
+  pub fn from_json_map(mut map: Map<String, Value>) -> Self {
+    let cover = take_typed(&mut map, VIEW_EXTRA_COVER_KEY);
+    let line_height_layout = take_typed(&mut map, VIEW_EXTRA_LINE_HEIGHT_LAYOUT_KEY);
+    let font_layout = take_typed(&mut map, VIEW_EXTRA_FONT_LAYOUT_KEY);
+
+    let space_map = SPACE_INFO_KEYS
+      .iter()
+      .filter_map(|key| map.get(*key).map(|value| (key.to_string(), value.clone())))
+      .collect::<Map<String, Value>>();
+    let space_info = serde_json::from_value::<SpaceInfo>(Value::Object(space_map)).ok();
+    if space_info.is_some() {
+      for key in SPACE_INFO_KEYS {
+        map.remove(key);
+      }
+    }
+
+    Self {
+      cover,
+      line_height_layout,
+      font_layout,
+      space_info,
+      unknown: map,
+    }
+  }
+
+  pub fn to_json_map(&self) -> Map<String, Value> {
+    let mut map = self.unknown.clone();
+    if let Some(Value::Object(space_map)) = self
+      .space_info
+      .as_ref()
+      .and_then(|info| serde_json::to_value(info).ok())
+    {
+      map.extend(space_map);
+    }
+    insert_typed(&mut map, VIEW_EXTRA_COVER_KEY, &self.cover);
+    insert_typed(
+      &mut map,
+      VIEW_EXTRA_LINE_HEIGHT_LAYOUT_KEY,
+      &self.line_height_layout,
+    );
+    insert_typed(&mut map, VIEW_EXTRA_FONT_LAYOUT_KEY, &self.font_layout);
+    map
+  }
+
+  /// Overwrites the keys that are set in `other` and keeps every other key as is.
+  pub fn merge(&mut self, other: ViewExtra) {
+    if other.cover.is_some() {
+      self.cover = other.cover;
+    }
+    if other.line_height_layout.is_some() {
+      self.line_height_layout = other.line_height_layout;
+    }
+    if other.font_layout.is_some() {
+      self.font_layout = other.font_layout;
+    }
+    if other.space_info.is_some() {
+      self.space_info = other.space_info;
+    }
+    self.unknown.extend(other.unknown);
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.to_json_map().is_empty()
+  }
+}
+
+impl Serialize for ViewExtra {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.to_json_map().serialize(serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for ViewExtra {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let map = Map::<String, Value>::deserialize(deserializer)?;
+    Ok(Self::from_json_map(map))
+  }
+}
+
+fn take_typed<T: for<'de> Deserialize<'de>>(map: &mut Map<String, Value>, key: &str) -> Option<T> {
+  let value = serde_json::from_value::<T>(map.get(key)?.clone()).ok()?;
+  map.remove(key);
+  Some(value)
+}
+
+fn insert_typed<T: Serialize>(map: &mut Map<String, Value>, key: &str, value: &Option<T>) {
+  if let Some(value) = value.as_ref().and_then(|v| serde_json::to_value(v).ok()) {
+    map.insert(key.to_string(), value);
+  }
+}
+
+/// The cover of a document view.
+///
+/// Stored as `{ "type": "0", "value": "..." }`, the meaning of `value` depends on the type.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ViewCover {
+  #[serde(rename = "type")]
+  pub ty: CoverType,
+  pub value: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[repr(u8)]
+pub enum CoverType {
+  /// A solid color, the value is the color code.
+  Color = 0,
+  Gradient = 1,
+  BuiltInImage = 2,
+  CustomImage = 3,
+  LocalImage = 4,
+  UnsplashImage = 5,
+}
+
+impl TryFrom<u8> for CoverType {
+  type Error = String;
+
+  fn try_from(value: u8) -> Result<Self, Self::Error> {
+    match value {
+      0 => Ok(CoverType::Color),
+      1 => Ok(CoverType::Gradient),
+      2 => Ok(CoverType::BuiltInImage),
+      3 => Ok(CoverType::CustomImage),
+      4 => Ok(CoverType::LocalImage),
+      5 => Ok(CoverType::UnsplashImage),
+      _ => Err(format!("Unknown cover type {}", value)),
+    }
+  }
+}
+
+/// The cover type is written as a string, e.g. "0", but older data may contain a number.
+impl Serialize for CoverType {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&(*self as u8).to_string())
+  }
+}
+
+impl<'de> Deserialize<'de> for CoverType {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let value = match Value::deserialize(deserializer)? {
+      Value::String(s) => s.parse::<u8>().map_err(D::Error::custom)?,
+      Value::Number(n) => n
+        .as_u64()
+        .and_then(|n| u8::try_from(n).ok())
+        .ok_or_else(|| D::Error::custom(format!("Invalid cover type {}", n)))?,
+      other => return Err(D::Error::custom(format!("Invalid cover type {}", other))),
+    };
+    CoverType::try_from(value).map_err(D::Error::custom)
+  }
+}
+
+/// Used by `line_height_layout` and `font_layout`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TextLayoutSize {
+  Small,
+  #[default]
+  Normal,
+  Large,
+}
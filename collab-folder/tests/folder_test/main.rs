@@ -2,10 +2,12 @@ mod child_views_test;
 mod custom_section;
 mod favorite_test;
 mod load_disk;
+mod permission_test;
 // mod recent_views_test;
 mod serde_test;
 mod space_info_test;
 mod trash_test;
 mod util;
+mod view_extra_test;
 mod view_test;
 mod workspace_test;
@@ -0,0 +1,179 @@
+use collab::core::collab::DataSource;
+use collab::core::origin::CollabOrigin;
+use collab::preclude::updates::decoder::Decode;
+use collab::preclude::{ReadTxn, StateVector, Update};
+use collab_folder::{Folder, UserId, ViewRole};
+
+use crate::util::{create_folder_with_workspace, make_test_view};
+
+const OWNER: i64 = 1;
+const MEMBER: i64 = 2;
+const GUEST: i64 = 3;
+
+fn create_folder_with_nested_views() -> crate::util::FolderTest {
+  // w1
+  // └── v1
+  //     └── v1_1
+  //         └── v1_1_1
+  let uid = UserId::from(OWNER);
+  let mut folder_test = create_folder_with_workspace(uid.clone(), "w1");
+  folder_test.insert_views(
+    vec![
+      make_test_view("v1", "w1", vec![]),
+      make_test_view("v1_1", "v1", vec![]),
+      make_test_view("v1_1_1", "v1_1", vec![]),
+    ],
+    uid.as_i64(),
+  );
+  folder_test
+}
+
+#[test]
+fn grant_view_permission_test() {
+  let mut folder_test = create_folder_with_nested_views();
+  folder_test.grant_view_permission("v1", OWNER, ViewRole::Owner);
+  folder_test.grant_view_permission("v1", MEMBER, ViewRole::Editor);
+
+  let acl = folder_test.get_view_acl("v1").unwrap();
+  assert!(acl.inherit);
+  assert_eq!(acl.grants.len(), 2);
+  assert_eq!(acl.grants[&MEMBER], ViewRole::Editor);
+  assert!(folder_test.get_view_acl("v1_1").is_none());
+
+  let permission = folder_test.effective_permission("v1", MEMBER).unwrap();
+  assert_eq!(permission.role, ViewRole::Editor);
+  assert!(!permission.is_inherited);
+  assert!(permission.role.can_edit());
+  assert!(!permission.role.can_manage());
+  assert!(folder_test.effective_permission("v1", GUEST).is_none());
+}
+
+#[test]
+fn inherit_view_permission_test() {
+  let mut folder_test = create_folder_with_nested_views();
+  folder_test.grant_view_permission("v1", MEMBER, ViewRole::Commenter);
+
+  let permission = folder_test.effective_permission("v1_1_1", MEMBER).unwrap();
+  assert_eq!(permission.role, ViewRole::Commenter);
+  assert!(permission.is_inherited);
+  assert_eq!(permission.source_view_id, "v1");
+
+  // Grant on the workspace applies to every view
+  folder_test.grant_view_permission("w1", GUEST, ViewRole::Viewer);
+  let permission = folder_test.effective_permission("v1_1", GUEST).unwrap();
+  assert_eq!(permission.role, ViewRole::Viewer);
+  assert_eq!(permission.source_view_id, "w1");
+}
+
+#[test]
+fn override_inherited_view_permission_test() {
+  let mut folder_test = create_folder_with_nested_views();
+  folder_test.grant_view_permission("v1", MEMBER, ViewRole::Viewer);
+  folder_test.grant_view_permission("v1_1", MEMBER, ViewRole::Editor);
+
+  assert_eq!(
+    folder_test.effective_permission("v1", MEMBER).unwrap().role,
+    ViewRole::Viewer
+  );
+  let permission = folder_test.effective_permission("v1_1_1", MEMBER).unwrap();
+  assert_eq!(permission.role, ViewRole::Editor);
+  assert_eq!(permission.source_view_id, "v1_1");
+
+  // Revoking the override falls back to the inherited grant
+  folder_test.revoke_view_permission("v1_1", MEMBER);
+  let permission = folder_test.effective_permission("v1_1_1", MEMBER).unwrap();
+  assert_eq!(permission.role, ViewRole::Viewer);
+  assert_eq!(permission.source_view_id, "v1");
+}
+
+#[test]
+fn stop_inheriting_view_permission_test() {
+  let mut folder_test = create_folder_with_nested_views();
+  folder_test.grant_view_permission("v1", MEMBER, ViewRole::Editor);
+  folder_test.grant_view_permission("v1", GUEST, ViewRole::Viewer);
+  folder_test.set_view_permission_inherit("v1_1", false);
+  folder_test.grant_view_permission("v1_1", GUEST, ViewRole::Commenter);
+
+  assert!(folder_test.effective_permission("v1_1", MEMBER).is_none());
+  assert!(folder_test.effective_permission("v1_1_1", MEMBER).is_none());
+  assert_eq!(
+    folder_test
+      .effective_permission("v1_1_1", GUEST)
+      .unwrap()
+      .role,
+    ViewRole::Commenter
+  );
+
+  folder_test.set_view_permission_inherit("v1_1", true);
+  assert_eq!(
+    folder_test
+      .effective_permission("v1_1_1", MEMBER)
+      .unwrap()
+      .role,
+    ViewRole::Editor
+  );
+}
+
+#[test]
+fn move_view_changes_inherited_permission_test() {
+  let mut folder_test = create_folder_with_nested_views();
+  folder_test.insert_view(make_test_view("v2", "w1", vec![]), None, OWNER);
+  folder_test.grant_view_permission("v1", MEMBER, ViewRole::Editor);
+  folder_test.grant_view_permission("v2", MEMBER, ViewRole::Viewer);
+
+  folder_test.move_nested_view("v1_1", "v2", None, OWNER);
+  let permission = folder_test.effective_permission("v1_1_1", MEMBER).unwrap();
+  assert_eq!(permission.role, ViewRole::Viewer);
+  assert_eq!(permission.source_view_id, "v2");
+}
+
+#[test]
+fn delete_view_removes_permission_test() {
+  let mut folder_test = create_folder_with_nested_views();
+  folder_test.grant_view_permission("v1_1", MEMBER, ViewRole::Editor);
+  folder_test.delete_views(vec!["v1_1"]);
+  assert!(folder_test.get_view_acl("v1_1").is_none());
+}
+
+#[test]
+fn concurrent_grant_view_permission_test() {
+  let mut folder_1 = create_folder_with_nested_views();
+  let doc_state = folder_1.encode_collab().unwrap().doc_state.to_vec();
+  let mut folder_2 = Folder::from_collab_doc_state(
+    CollabOrigin::Empty,
+    DataSource::DocStateV1(doc_state),
+    "w1",
+    2,
+  )
+  .unwrap();
+
+  // both clients grant on the view for the first time
+  folder_1.grant_view_permission("v1_1", MEMBER, ViewRole::Editor);
+  folder_2.grant_view_permission("v1_1", GUEST, ViewRole::Viewer);
+  folder_2.set_view_permission_inherit("v1_1", false);
+
+  let update_1 = folder_1
+    .collab
+    .transact()
+    .encode_state_as_update_v1(&StateVector::default());
+  let update_2 = folder_2
+    .collab
+    .transact()
+    .encode_state_as_update_v1(&StateVector::default());
+  folder_1
+    .collab
+    .apply_update(Update::decode_v1(&update_2).unwrap())
+    .unwrap();
+  folder_2
+    .collab
+    .apply_update(Update::decode_v1(&update_1).unwrap())
+    .unwrap();
+
+  for folder in [&folder_1.folder, &folder_2] {
+    let acl = folder.get_view_acl("v1_1").unwrap();
+    assert!(!acl.inherit);
+    assert_eq!(acl.grants.len(), 2);
+    assert_eq!(acl.grants[&MEMBER], ViewRole::Editor);
+    assert_eq!(acl.grants[&GUEST], ViewRole::Viewer);
+  }
+}
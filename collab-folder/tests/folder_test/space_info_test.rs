@@ -76,3 +76,32 @@ fn create_non_space_test() {
   let space_info_json = serde_json::to_value(space_info).unwrap();
   assert_json_diff::assert_json_eq!(space_info_json, json!({}),);
 }
+
+#[test]
+fn space_icon_does_not_make_view_a_space_test() {
+  let extra = ViewExtraBuilder::new()
+    .with_space_icon(Some("interface_essential/home-3"))
+    .with_space_permission(SpacePermission::Private)
+    .build_extra();
+  let space_info = extra.space_info.unwrap();
+  assert!(!space_info.is_space);
+  assert_eq!(
+    space_info.space_icon.as_deref(),
+    Some("interface_essential/home-3")
+  );
+
+  // unsetting the space keeps the icon and the permission
+  let extra = ViewExtraBuilder::new()
+    .is_space(true)
+    .with_space_icon(Some("interface_essential/lock"))
+    .with_space_permission(SpacePermission::Private)
+    .is_space(false)
+    .build_extra();
+  let space_info = extra.space_info.unwrap();
+  assert!(!space_info.is_space);
+  assert_eq!(
+    space_info.space_icon.as_deref(),
+    Some("interface_essential/lock")
+  );
+  assert_eq!(space_info.space_permission, SpacePermission::Private);
+}
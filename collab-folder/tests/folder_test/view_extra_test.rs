@@ -0,0 +1,267 @@
+use collab::core::collab::DataSource;
+use collab::core::origin::CollabOrigin;
+use collab::preclude::updates::decoder::Decode;
+use collab::preclude::{Map, MapExt, MapRef, ReadTxn, StateVector, Update};
+use collab_entity::define::FOLDER;
+use collab_folder::hierarchy_builder::ViewExtraBuilder;
+use collab_folder::{
+  CoverType, Folder, SpacePermission, TextLayoutSize, UserId, ViewCover, ViewExtra,
+};
+use serde_json::json;
+
+use crate::util::{create_folder_with_workspace, make_test_view};
+
+#[test]
+fn parse_view_extra_test() {
+  let extra = json!({
+    "cover": { "type": "5", "value": "https://unsplash.com/photo" },
+    "line_height_layout": "small",
+    "font_layout": "large",
+    "is_space": true,
+    "space_permission": 1,
+    "space_created_at": 100,
+    "custom_key": { "a": 1 }
+  })
+  .to_string();
+
+  let view_extra = ViewExtra::from_json_str(&extra).unwrap();
+  assert_eq!(
+    view_extra.cover,
+    Some(ViewCover {
+      ty: CoverType::UnsplashImage,
+      value: "https://unsplash.com/photo".to_string(),
+    })
+  );
+  assert_eq!(view_extra.line_height_layout, Some(TextLayoutSize::Small));
+  assert_eq!(view_extra.font_layout, Some(TextLayoutSize::Large));
+  let space_info = view_extra.space_info.clone().unwrap();
+  assert_eq!(space_info.space_permission, SpacePermission::Private);
+  assert_eq!(space_info.space_created_at, 100);
+  assert_eq!(view_extra.unknown.len(), 1);
+  assert_eq!(view_extra.unknown["custom_key"], json!({ "a": 1 }));
+
+  // Round trip keeps every key
+  let value: serde_json::Value = serde_json::from_str(&view_extra.to_json_string()).unwrap();
+  let expected: serde_json::Value = serde_json::from_str(&extra).unwrap();
+  assert_json_diff::assert_json_eq!(value, expected);
+}
+
+#[test]
+fn view_extra_keeps_unparsable_known_keys_test() {
+  let extra = json!({
+    "cover": { "type": "9", "value": "future cover" },
+    "font_layout": "huge",
+    "is_space": false,
+  })
+  .to_string();
+
+  let view_extra = ViewExtra::from_json_str(&extra).unwrap();
+  assert!(view_extra.cover.is_none());
+  assert!(view_extra.font_layout.is_none());
+  assert!(view_extra.space_info.is_none());
+  assert_eq!(view_extra.unknown.len(), 3);
+
+  let value: serde_json::Value = serde_json::from_str(&view_extra.to_json_string()).unwrap();
+  let expected: serde_json::Value = serde_json::from_str(&extra).unwrap();
+  assert_json_diff::assert_json_eq!(value, expected);
+}
+
+#[test]
+fn cover_type_accepts_number_test() {
+  let cover: ViewCover = serde_json::from_value(json!({ "type": 2, "value": "b" })).unwrap();
+  assert_eq!(cover.ty, CoverType::BuiltInImage);
+  assert_eq!(
+    serde_json::to_value(&cover).unwrap(),
+    json!({ "type": "2", "value": "b" })
+  );
+}
+
+#[test]
+fn build_view_extra_with_cover_test() {
+  let extra = ViewExtraBuilder::new()
+    .with_cover(ViewCover {
+      ty: CoverType::Color,
+      value: "0xFFA34AFD".to_string(),
+    })
+    .with_font_layout(TextLayoutSize::Small)
+    .build();
+  assert_json_diff::assert_json_eq!(
+    extra,
+    json!({
+      "cover": { "type": "0", "value": "0xFFA34AFD" },
+      "font_layout": "small"
+    })
+  );
+}
+
+#[test]
+fn merge_view_extra_test() {
+  let uid = UserId::from(1);
+  let mut folder_test = create_folder_with_workspace(uid.clone(), "w1");
+  let mut view = make_test_view("v1", "w1", vec![]);
+  view.extra = Some(json!({ "font_layout": "small", "custom_key": 1 }).to_string());
+  folder_test.insert_view(view, None, uid.as_i64());
+
+  let view = folder_test
+    .update_view(
+      "v1",
+      |update| {
+        update
+          .merge_extra(
+            ViewExtraBuilder::new()
+              .with_line_height_layout(TextLayoutSize::Large)
+              .build_extra(),
+          )
+          .done()
+      },
+      uid.as_i64(),
+    )
+    .unwrap();
+
+  let view_extra = view.view_extra().unwrap();
+  assert_eq!(view_extra.font_layout, Some(TextLayoutSize::Small));
+  assert_eq!(view_extra.line_height_layout, Some(TextLayoutSize::Large));
+  assert_eq!(view_extra.unknown["custom_key"], json!(1));
+
+  // Removing a key through update_extra
+  let view = folder_test
+    .update_view(
+      "v1",
+      |update| {
+        update
+          .update_extra(|extra| {
+            extra.font_layout = None;
+          })
+          .done()
+      },
+      uid.as_i64(),
+    )
+    .unwrap();
+  let view_extra = view.view_extra().unwrap();
+  assert!(view_extra.font_layout.is_none());
+  assert_eq!(view_extra.line_height_layout, Some(TextLayoutSize::Large));
+
+  // set_extra replaces everything
+  let view = folder_test
+    .update_view(
+      "v1",
+      |update| update.set_extra(json!({ "a": 1 }).to_string()).done(),
+      uid.as_i64(),
+    )
+    .unwrap();
+  let value: serde_json::Value = serde_json::from_str(view.extra.as_ref().unwrap()).unwrap();
+  assert_json_diff::assert_json_eq!(value, json!({ "a": 1 }));
+}
+
+#[test]
+fn concurrent_merge_view_extra_test() {
+  let uid = UserId::from(1);
+  let mut folder_1 = create_folder_with_workspace(uid.clone(), "w1");
+  let view = make_test_view("v1", "w1", vec![]);
+  folder_1.insert_view(view, None, uid.as_i64());
+
+  let doc_state = folder_1.encode_collab().unwrap().doc_state.to_vec();
+  let mut folder_2 = Folder::from_collab_doc_state(
+    CollabOrigin::Empty,
+    DataSource::DocStateV1(doc_state),
+    "w1",
+    2,
+  )
+  .unwrap();
+
+  folder_1.update_view(
+    "v1",
+    |update| {
+      update
+        .merge_extra(
+          ViewExtraBuilder::new()
+            .with_cover(ViewCover {
+              ty: CoverType::Gradient,
+              value: "appflowy".to_string(),
+            })
+            .build_extra(),
+        )
+        .done()
+    },
+    uid.as_i64(),
+  );
+  folder_2.update_view(
+    "v1",
+    |update| {
+      update
+        .merge_extra(
+          ViewExtraBuilder::new()
+            .with_font_layout(TextLayoutSize::Large)
+            .build_extra(),
+        )
+        .done()
+    },
+    uid.as_i64(),
+  );
+
+  let update_1 = folder_1
+    .collab
+    .transact()
+    .encode_state_as_update_v1(&StateVector::default());
+  let update_2 = folder_2
+    .collab
+    .transact()
+    .encode_state_as_update_v1(&StateVector::default());
+  folder_1
+    .collab
+    .apply_update(Update::decode_v1(&update_2).unwrap())
+    .unwrap();
+  folder_2
+    .collab
+    .apply_update(Update::decode_v1(&update_1).unwrap())
+    .unwrap();
+
+  for folder in [&folder_1.folder, &folder_2] {
+    let view_extra = folder
+      .get_view("v1", uid.as_i64())
+      .unwrap()
+      .view_extra()
+      .unwrap();
+    assert_eq!(view_extra.cover.unwrap().ty, CoverType::Gradient);
+    assert_eq!(view_extra.font_layout, Some(TextLayoutSize::Large));
+  }
+}
+
+#[test]
+fn set_extra_string_after_merge_test() {
+  let uid = UserId::from(1);
+  let mut folder_test = create_folder_with_workspace(uid.clone(), "w1");
+  let view = make_test_view("v1", "w1", vec![]);
+  folder_test.insert_view(view, None, uid.as_i64());
+  folder_test.update_view(
+    "v1",
+    |update| {
+      update
+        .merge_extra(
+          ViewExtraBuilder::new()
+            .with_font_layout(TextLayoutSize::Large)
+            .build_extra(),
+        )
+        .done()
+    },
+    uid.as_i64(),
+  );
+
+  // a client that only knows the extra string replaces it.
+  {
+    let collab = &mut folder_test.folder.collab;
+    let mut txn = collab.context.transact_mut();
+    let view_map: MapRef = collab
+      .data
+      .get_with_path(&txn, [FOLDER, "views", "v1"])
+      .unwrap();
+    view_map.insert(
+      &mut txn,
+      "extra",
+      json!({ "font_layout": "small" }).to_string(),
+    );
+  }
+  let view = folder_test.get_view("v1", uid.as_i64()).unwrap();
+  let view_extra = view.view_extra().unwrap();
+  assert_eq!(view_extra.font_layout, Some(TextLayoutSize::Small));
+}
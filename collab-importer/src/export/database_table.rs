@@ -0,0 +1,161 @@
+use collab_database::database::Database;
+use collab_database::error::DatabaseError;
+use collab_database::fields::{FieldVisibility, field_visibility_in_view};
+use collab_database::rows::Row;
+use collab_database::views::FieldSettingsMap;
+use collab_document::block_parser::OutputFormat;
+use futures::TryStreamExt;
+
+use crate::error::ImporterError;
+
+const ROW_CHUNK_SIZE: usize = 20;
+
+/// The cells of a database view as text, ready to be rendered in an exported document.
+///
+/// The columns follow the field order of the view. Hidden fields are skipped, and so are the
+/// fields that are hidden when empty if none of the rows has a value for them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseViewTable {
+  pub view_id: String,
+  pub name: String,
+  pub columns: Vec<String>,
+  pub rows: Vec<Vec<String>>,
+}
+
+impl DatabaseViewTable {
+  pub async fn from_database(database: &Database, view_id: &str) -> Result<Self, ImporterError> {
+    let view = database
+      .get_view(view_id)
+      .ok_or(DatabaseError::DatabaseViewNotExist)?;
+    let field_settings = database.get_field_settings::<FieldSettingsMap>(view_id, None);
+    let fields = database
+      .get_fields_in_view(view_id, None)
+      .into_iter()
+      .filter_map(|field| {
+        let visibility =
+          field_visibility_in_view(&field, view.layout, field_settings.get(&field.id));
+        if visibility == FieldVisibility::AlwaysHidden {
+          return None;
+        }
+        let reader = database.get_cell_reader(&field.id);
+        Some((field, visibility, reader))
+      })
+      .collect::<Vec<_>>();
+
+    let rows: Vec<Row> = database
+      .get_rows_for_view(view_id, ROW_CHUNK_SIZE, None, false)
+      .await
+      .try_collect()
+      .await?;
+    let mut cells = rows
+      .iter()
+      .map(|row| {
+        fields
+          .iter()
+          .map(|(field, _, reader)| {
+            let cell = row.cells.get(&field.id);
+            cell
+              .zip(reader.as_ref())
+              .map(|(cell, reader)| reader.stringify_cell(cell))
+              .unwrap_or_default()
+          })
+          .collect::<Vec<_>>()
+      })
+      .collect::<Vec<_>>();
+
+    let mut columns = vec![];
+    for (index, (field, visibility, _)) in fields.iter().enumerate().rev() {
+      let is_empty = cells.iter().all(|row| row[index].is_empty());
+      if *visibility == FieldVisibility::HideWhenEmpty && is_empty {
+        cells.iter_mut().for_each(|row| {
+          row.remove(index);
+        });
+      } else {
+        columns.insert(0, field.name.clone());
+      }
+    }
+
+    Ok(Self {
+      view_id: view_id.to_string(),
+      name: view.name,
+      columns,
+      rows: cells,
+    })
+  }
+
+  /// Renders the table, every line starts with the given indent.
+  ///
+  /// For [OutputFormat::Markdown], it's a pipe table. For [OutputFormat::PlainText], the columns
+  /// are padded to the width of their longest cell.
+  pub fn render(&self, format: OutputFormat, indent: &str) -> String {
+    if self.columns.is_empty() {
+      return String::new();
+    }
+
+    let lines = match format {
+      OutputFormat::Markdown => self.markdown_lines(),
+      OutputFormat::PlainText => self.plain_text_lines(),
+    };
+    lines
+      .into_iter()
+      .map(|line| format!("{}{}", indent, line))
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+
+  fn markdown_lines(&self) -> Vec<String> {
+    let markdown_row = |cells: &[String]| {
+      let cells = cells
+        .iter()
+        .map(|cell| escape_markdown_cell(cell))
+        .collect::<Vec<_>>();
+      format!("| {} |", cells.join(" | "))
+    };
+
+    let mut lines = vec![
+      markdown_row(&self.columns),
+      format!("|{}", " --- |".repeat(self.columns.len())),
+    ];
+    lines.extend(self.rows.iter().map(|row| markdown_row(row)));
+    lines
+  }
+
+  fn plain_text_lines(&self) -> Vec<String> {
+    let cell_text = |cell: &str| cell.replace(['\r', '\n'], " ");
+    let mut widths = self
+      .columns
+      .iter()
+      .map(|column| column.chars().count())
+      .collect::<Vec<_>>();
+    for row in &self.rows {
+      for (width, cell) in widths.iter_mut().zip(row) {
+        *width = (*width).max(cell_text(cell).chars().count());
+      }
+    }
+
+    let plain_text_row = |cells: &[String]| {
+      let cells = cells
+        .iter()
+        .zip(&widths)
+        .map(|(cell, width)| format!("{:<width$}", cell_text(cell), width = *width))
+        .collect::<Vec<_>>();
+      cells.join(" | ").trim_end().to_string()
+    };
+
+    let separator = widths
+      .iter()
+      .map(|width| "-".repeat(*width))
+      .collect::<Vec<_>>()
+      .join("-+-");
+    let mut lines = vec![plain_text_row(&self.columns), separator];
+    lines.extend(self.rows.iter().map(|row| plain_text_row(row)));
+    lines
+  }
+}
+
+fn escape_markdown_cell(cell: &str) -> String {
+  cell
+    .replace('|', "\\|")
+    .replace("\r\n", "<br>")
+    .replace('\n', "<br>")
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use collab::preclude::Attrs;
+use collab_database::database::Database;
+use collab_document::block_parser::{DocumentParserDelegate, ParseContext};
+use collab_document::blocks::{Block, BlockType, DocumentData};
+use collab_document::link_graph::{
+  DocumentLinkKind, extract_document_links, mentioned_page_id_from_attrs,
+};
+use collab_folder::Folder;
+
+use crate::error::ImporterError;
+use crate::export::DatabaseViewTable;
+
+// do not change the key value, it comes from the flutter code.
+const DATABASE_VIEW_ID_KEY: &str = "view_id";
+
+/// Resolves the references of a document when it's exported with a
+/// [collab_document::block_parser::DocumentParser].
+///
+/// The parser is synchronous, so the databases and page titles are loaded before parsing:
+/// - the inline grid, board and calendar blocks are rendered as tables of their database view.
+///   Use [linked_database_view_ids] to find the views to load.
+/// - the mentioned pages are replaced by their titles.
+///
+/// A reference that was not loaded is rendered by the default parsers.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentExportDelegate {
+  tables: HashMap<String, DatabaseViewTable>,
+  page_titles: HashMap<String, String>,
+}
+
+impl DocumentExportDelegate {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn add_table(&mut self, table: DatabaseViewTable) {
+    self.tables.insert(table.view_id.clone(), table);
+  }
+
+  pub async fn add_database_view(
+    &mut self,
+    database: &Database,
+    view_id: &str,
+  ) -> Result<(), ImporterError> {
+    let table = DatabaseViewTable::from_database(database, view_id).await?;
+    self.add_table(table);
+    Ok(())
+  }
+
+  pub fn add_page_title(&mut self, view_id: &str, title: &str) {
+    self
+      .page_titles
+      .insert(view_id.to_string(), title.to_string());
+  }
+
+  /// Adds the names of all the views of the folder that are visible to the given user.
+  pub fn add_page_titles_from_folder(&mut self, folder: &Folder, uid: i64) {
+    for view in folder.get_all_views(uid) {
+      self.page_titles.insert(view.id.clone(), view.name.clone());
+    }
+  }
+
+  pub fn get_table(&self, view_id: &str) -> Option<&DatabaseViewTable> {
+    self.tables.get(view_id)
+  }
+
+  pub fn get_page_title(&self, view_id: &str) -> Option<&str> {
+    self.page_titles.get(view_id).map(|title| title.as_str())
+  }
+}
+
+impl DocumentParserDelegate for DocumentExportDelegate {
+  fn handle_text_delta(
+    &self,
+    _text: &str,
+    attributes: Option<&Attrs>,
+    _context: &ParseContext,
+  ) -> Option<String> {
+    let page_id = mentioned_page_id_from_attrs(attributes?)?;
+    self.page_titles.get(&page_id).cloned()
+  }
+
+  fn handle_block(&self, block: &Block, context: &ParseContext) -> Option<String> {
+    match BlockType::from_block_ty(&block.ty) {
+      BlockType::Grid | BlockType::Board | BlockType::Calendar => {
+        let view_id = block.data.get(DATABASE_VIEW_ID_KEY)?.as_str()?;
+        let table = self.tables.get(view_id)?;
+        Some(table.render(context.format, &context.get_indent()))
+      },
+      _ => None,
+    }
+  }
+}
+
+/// Returns the ids of the database views referenced by the inline grid, board and calendar
+/// blocks of the document, without duplicates.
+pub fn linked_database_view_ids(data: &DocumentData) -> Vec<String> {
+  let mut view_ids = vec![];
+  for link in extract_document_links(data) {
+    if link.kind == DocumentLinkKind::InlineDatabase && !view_ids.contains(&link.target) {
+      view_ids.push(link.target);
+    }
+  }
+  view_ids
+}
@@ -0,0 +1,5 @@
+pub mod database_table;
+pub mod document_export_delegate;
+
+pub use database_table::*;
+pub use document_export_delegate::*;
@@ -1,4 +1,5 @@
 pub mod error;
+pub mod export;
 pub mod imported_collab;
 pub mod notion;
 mod space_view;
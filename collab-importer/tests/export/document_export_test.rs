@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use collab::core::collab::default_client_id;
+use collab_database::database::Database;
+use collab_database::database_trait::NoPersistenceDatabaseCollabService;
+use collab_database::fields::{FieldSettingsBuilder, FieldVisibility};
+use collab_database::template::csv::CSVTemplate;
+use collab_document::block_parser::{DocumentParser, OutputFormat};
+use collab_document::blocks::{Block, BlockType, mention_block_delta};
+use collab_document::document::Document;
+use collab_document::document_data::default_document_data;
+use collab_importer::export::{DocumentExportDelegate, linked_database_view_ids};
+use serde_json::json;
+
+const VIEW_ID: &str = "grid_view";
+
+async fn create_database() -> Database {
+  let content = "Name,Status,Notes,Secret\nBuy milk,Done,,1\nWrite a|b,Todo,,2\n";
+  let mut csv_template = CSVTemplate::try_from_reader(content.as_bytes(), false, None).unwrap();
+  csv_template.reset_view_id(VIEW_ID.to_string());
+  let template = csv_template.try_into_database_template(None).await.unwrap();
+  let service = Arc::new(NoPersistenceDatabaseCollabService::new(default_client_id()));
+  let mut database = Database::create_with_template(template, service.clone(), service)
+    .await
+    .unwrap();
+
+  let fields = database.get_fields_in_view(VIEW_ID, None);
+  for (name, visibility) in [
+    ("Notes", FieldVisibility::HideWhenEmpty),
+    ("Secret", FieldVisibility::AlwaysHidden),
+  ] {
+    let field = fields.iter().find(|field| field.name == name).unwrap();
+    let settings = FieldSettingsBuilder::new(&field.id)
+      .visibility(visibility)
+      .build();
+    database.update_field_settings(VIEW_ID, Some(vec![field.id.clone()]), settings);
+  }
+  database
+}
+
+fn create_document() -> Document {
+  let document_id = uuid::Uuid::new_v4().to_string();
+  let mut document =
+    Document::create(&document_id, default_document_data(&document_id), 1).unwrap();
+  let page_id = document.get_page_id().unwrap();
+  let paragraph_id = document.get_block_children_ids(&page_id)[0].clone();
+
+  let text_id = document
+    .get_block(&paragraph_id)
+    .unwrap()
+    .external_id
+    .unwrap();
+  document.apply_text_delta(
+    &text_id,
+    json!([{ "insert": "See " }, mention_block_delta("roadmap_view")]).to_string(),
+  );
+
+  let grid = Block {
+    id: nanoid::nanoid!(10),
+    ty: BlockType::Grid.as_str().to_string(),
+    parent: page_id.clone(),
+    children: nanoid::nanoid!(10),
+    external_id: None,
+    external_type: None,
+    data: HashMap::from([("view_id".to_string(), json!(VIEW_ID))]),
+  };
+  document.insert_block(grid, Some(paragraph_id)).unwrap();
+  document
+}
+
+#[tokio::test]
+async fn export_document_with_database_and_mention_test() {
+  let database = create_database().await;
+  let document_data = create_document().get_document_data().unwrap();
+  assert_eq!(linked_database_view_ids(&document_data), vec![VIEW_ID]);
+
+  let mut delegate = DocumentExportDelegate::new();
+  for view_id in linked_database_view_ids(&document_data) {
+    delegate
+      .add_database_view(&database, &view_id)
+      .await
+      .unwrap();
+  }
+  delegate.add_page_title("roadmap_view", "Roadmap");
+  let table = delegate.get_table(VIEW_ID).unwrap();
+  assert_eq!(table.columns, vec!["Name", "Status"]);
+
+  let parser = DocumentParser::with_default_parsers().with_delegate(Arc::new(delegate));
+  let markdown = parser
+    .parse_document(&document_data, OutputFormat::Markdown)
+    .unwrap();
+  assert_eq!(
+    markdown,
+    "See Roadmap\n\
+     | Name | Status |\n\
+     | --- | --- |\n\
+     | Buy milk | Done |\n\
+     | Write a\\|b | Todo |"
+  );
+
+  let plain_text = parser
+    .parse_document(&document_data, OutputFormat::PlainText)
+    .unwrap();
+  assert_eq!(
+    plain_text,
+    "See Roadmap\n\
+     Name      | Status\n\
+     ----------+-------\n\
+     Buy milk  | Done\n\
+     Write a|b | Todo"
+  );
+}
+
+#[tokio::test]
+async fn export_document_without_loaded_database_test() {
+  let document_data = create_document().get_document_data().unwrap();
+  let parser =
+    DocumentParser::with_default_parsers().with_delegate(Arc::new(DocumentExportDelegate::new()));
+  let plain_text = parser
+    .parse_document(&document_data, OutputFormat::PlainText)
+    .unwrap();
+  // The unknown mention and the database block are rendered by the default parsers
+  assert_eq!(plain_text.trim(), "See $");
+}
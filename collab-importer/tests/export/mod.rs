@@ -0,0 +1 @@
+mod document_export_test;
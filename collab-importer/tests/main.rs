@@ -1,3 +1,4 @@
+mod export;
 mod notion_test;
 mod util;
 mod workspace;
@@ -4,7 +4,7 @@ use std::ops::{Deref, DerefMut};
 use std::panic;
 use std::panic::AssertUnwindSafe;
 
-use arc_swap::ArcSwapOption;
+use arc_swap::{ArcSwap, ArcSwapOption};
 use std::sync::Arc;
 use std::vec::IntoIter;
 
@@ -82,6 +82,10 @@ pub struct CollabContext {
   /// is disabled. To enable it, call [Collab::enable_undo_manager].
   undo_manager: Option<UndoManager>,
 
+  /// The origins of the local changes besides [CollabContext::origin], see
+  /// [CollabContext::add_local_origin].
+  local_origins: Arc<ArcSwap<Vec<CollabOrigin>>>,
+
   /// The current transaction that is being executed.
   current_txn: Option<TransactionMut<'static>>,
 }
@@ -95,6 +99,7 @@ impl CollabContext {
       origin,
       awareness,
       undo_manager: None,
+      local_origins: Default::default(),
       current_txn: None,
     }
   }
@@ -160,6 +165,30 @@ impl CollabContext {
     self.doc().transact_mut_with(self.origin.clone())
   }
 
+  /// Creates a transaction with the given origin instead of the origin of this context.
+  ///
+  /// It's used to make changes on behalf of another local actor, like an importer or an AI
+  /// writer, so that they can be told apart by the [UndoManager]s and the observers. Register the
+  /// origin with [CollabContext::add_local_origin] so that the plugins receive the changes.
+  pub fn transact_mut_with(&mut self, origin: CollabOrigin) -> TransactionMut {
+    self.doc().transact_mut_with(origin)
+  }
+
+  /// Registers the origin of another local actor. The plugins receive the updates made with it as
+  /// local updates, like the ones made with the origin of this context.
+  pub fn add_local_origin(&self, origin: CollabOrigin) {
+    if origin == self.origin || self.local_origins.load().contains(&origin) {
+      return;
+    }
+    self.local_origins.rcu(|origins| {
+      let mut origins = origins.as_ref().clone();
+      if !origins.contains(&origin) {
+        origins.push(origin.clone());
+      }
+      origins
+    });
+  }
+
   pub fn undo(&mut self) -> Result<bool, CollabError> {
     let undo_manager = self.undo_manager_mut()?;
     Ok(undo_manager.undo_blocking())
@@ -215,6 +244,27 @@ impl CollabContext {
   }
 }
 
+pub const DEFAULT_UNDO_CAPTURE_TIMEOUT_MILLIS: u64 = 500;
+
+/// Options of the [UndoManager] of a [Collab], see [Collab::enable_undo_redo_with_options].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoOptions {
+  /// The changes made within this period after the previous one are undone in a single step.
+  pub capture_timeout_millis: u64,
+  /// Only the changes made with these origins are undone. When it's empty, the changes made with
+  /// the origin of the [Collab] are undone.
+  pub tracked_origins: Vec<CollabOrigin>,
+}
+
+impl Default for UndoOptions {
+  fn default() -> Self {
+    Self {
+      capture_timeout_millis: DEFAULT_UNDO_CAPTURE_TIMEOUT_MILLIS,
+      tracked_origins: vec![],
+    }
+  }
+}
+
 pub fn default_client_id() -> ClientID {
   let mut rng = fastrand::Rng::new();
   let client_id: u32 = rng.u32(0..u32::MAX);
@@ -404,6 +454,7 @@ impl Collab {
       self.object_id.clone(),
       self.plugins.clone(),
       self.origin().clone(),
+      self.context.local_origins.clone(),
     );
 
     let awareness_subscription = observe_awareness(
@@ -503,15 +554,29 @@ impl Collab {
     if self.context.undo_manager.is_some() {
       return;
     }
+    self.enable_undo_redo_with_options(UndoOptions::default());
+  }
+
+  /// Creates the [UndoManager] with the given options. The existing one is replaced, so its undo
+  /// and redo stacks are lost.
+  pub fn enable_undo_redo_with_options(&mut self, options: UndoOptions) {
     // a frequent case includes establishing a new transaction for every user key stroke. Meanwhile
     // we may decide to use different granularity of undo/redo actions. These are grouped together
-    // on time-based ranges (configurable in undo::Options, which is 500ms by default).
+    // on time-based ranges, see [UndoOptions::capture_timeout_millis].
     let mut undo_manager = UndoManager::with_scope_and_options(
       self.context.doc(),
       &self.data,
-      yrs::undo::Options::default(),
+      yrs::undo::Options {
+        capture_timeout_millis: options.capture_timeout_millis,
+        ..Default::default()
+      },
     );
-    undo_manager.include_origin(self.origin().clone());
+    if options.tracked_origins.is_empty() {
+      undo_manager.include_origin(self.origin().clone());
+    }
+    for origin in options.tracked_origins {
+      undo_manager.include_origin(origin);
+    }
     self.context.undo_manager = Some(undo_manager);
   }
 
@@ -571,12 +636,14 @@ fn observe_awareness(
 
 /// Observe a document for updates.
 /// Use the uid and the device_id to verify that the update is local or remote.
-/// If the update is local, the plugins will be notified.
+/// If the update is local, the plugins will be notified. The updates made with one of the
+/// `local_origins` are local too.
 fn observe_doc(
   doc: &Doc,
   oid: String,
   plugins: Plugins,
   local_origin: CollabOrigin,
+  local_origins: Arc<ArcSwap<Vec<CollabOrigin>>>,
 ) -> (Subscription, Option<AfterTransactionSubscription>) {
   let cloned_oid = oid.clone();
   let cloned_plugins = plugins.clone();
@@ -595,8 +662,8 @@ fn observe_doc(
 
         plugin.receive_update(&cloned_oid, txn, &event.update);
         let remote_origin = CollabOrigin::from(txn);
-        if remote_origin == local_origin {
-          plugin.receive_local_update(&local_origin, &cloned_oid, &event.update);
+        if remote_origin == local_origin || local_origins.load().contains(&remote_origin) {
+          plugin.receive_local_update(&remote_origin, &cloned_oid, &event.update);
         } else {
           #[cfg(feature = "verbose_log")]
           tracing::trace!("{} did apply remote {} update", local_origin, remote_origin);